@@ -1,14 +1,21 @@
 use std::cell::RefCell;
+use std::net::IpAddr;
 use std::rc::Rc;
 
 use anyhow::{anyhow, Context, Result};
-use deno_core::{error::custom_error, extension, op2, OpState};
+use deno_core::{
+    error::{custom_error, type_error},
+    extension, op2, v8, OpState, ToJsBuffer,
+};
 use ethabi::ethereum_types::Address;
 use lit_actions_grpc::proto::*;
+use serde::Serialize;
 use serde_json::json;
 use tracing::instrument;
 
+use crate::fetch::{self, FetchGrant, PendingFetchGrants};
 use crate::validation::*;
+use crate::wasm::{self, PendingWasmModules, WasmMemoryLimit};
 
 // Macro to simplify implementing synchronous ops over gRPC.
 macro_rules! remote_op {
@@ -128,12 +135,98 @@ fn op_set_response(state: &mut OpState, #[string] response: String) -> Result<()
 
 #[instrument(skip_all, ret)]
 #[op2(async, reentrant)]
-async fn op_increment_fetch_count(state: Rc<RefCell<OpState>>) -> Result<u32> {
-    remote_op_async!(op_increment_fetch_count,
-        state,
-        IncrementFetchCountRequest {},
-        UnionRequest::IncrementFetchCount(resp) => Ok(resp.fetch_count)
-    )
+async fn op_increment_fetch_count(
+    state: Rc<RefCell<OpState>>,
+    #[string] url: String,
+) -> Result<u32> {
+    let remote = state.clone();
+    let resp = remote_op_async!(op_increment_fetch_count,
+        remote,
+        IncrementFetchCountRequest { url: url.clone() },
+        UnionRequest::IncrementFetchCount(resp) => Ok(resp)
+    )?;
+
+    let addrs = resp
+        .addrs
+        .iter()
+        .map(|addr| addr.parse::<IpAddr>())
+        .collect::<Result<Vec<_>, _>>()
+        .context("op_increment_fetch_count: invalid address")?;
+    // Only requests leaving the actions server need a grant
+    if !addrs.is_empty() {
+        let mut state = state.borrow_mut();
+        if !state.has::<PendingFetchGrants>() {
+            state.put(PendingFetchGrants::default());
+        }
+        state.borrow_mut::<PendingFetchGrants>().insert(
+            url,
+            FetchGrant {
+                addrs,
+                max_response_bytes: resp.max_response_bytes,
+                timeout_ms: resp.timeout_ms,
+                remaining_bytes: resp.remaining_bytes,
+            },
+        );
+    }
+
+    Ok(resp.fetch_count)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchResponse {
+    status: u16,
+    status_text: String,
+    headers: Vec<(String, String)>,
+    body: ToJsBuffer,
+}
+
+// Performs an HTTP(S) request granted by op_increment_fetch_count and reports
+// what was read to lit-node, which rejects it if it violates the egress policy.
+#[instrument(skip_all, ret)]
+#[op2(async, reentrant)]
+#[serde]
+async fn op_fetch(
+    state: Rc<RefCell<OpState>>,
+    #[string] url: String,
+    #[string] method: String,
+    #[serde] headers: Vec<(String, String)>,
+    #[buffer(copy)] body: Vec<u8>,
+) -> Result<FetchResponse> {
+    let grant = state
+        .borrow_mut()
+        .try_borrow_mut::<PendingFetchGrants>()
+        .and_then(|grants| grants.take(&url))
+        .ok_or_else(|| {
+            custom_error(
+                "PermissionDenied",
+                format!("Fetching {url} has not been allowed by the node"),
+            )
+        })?;
+
+    let result = fetch::fetch(&grant, &url, &method, headers, body).await?;
+
+    let remote = state.clone();
+    remote_op_async!(op_fetch,
+        remote,
+        RecordFetchRequest {
+            url,
+            bytes: result.bytes,
+            violation: result.violation.to_string(),
+        },
+        UnionRequest::RecordFetch(_) => Ok::<(), anyhow::Error>(())
+    )?;
+
+    if let Some(error) = result.error {
+        return Err(type_error(error));
+    }
+
+    Ok(FetchResponse {
+        status: result.status,
+        status_text: result.status_text,
+        headers: result.headers,
+        body: result.body.into(),
+    })
 }
 
 #[instrument(skip_all, ret)]
@@ -467,6 +560,72 @@ async fn op_sign_and_combine_ecdsa(
     )
 }
 
+#[instrument(skip_all, ret)]
+#[op2(async, reentrant)]
+#[string]
+async fn op_send_transaction(
+    state: Rc<RefCell<OpState>>,
+    #[string] chain: String,
+    #[string] public_key: String,
+    #[serde] tx: serde_json::Value,
+) -> Result<String> {
+    ensure_not_blank!(chain);
+
+    remote_op_async!(op_send_transaction,
+        state,
+        SendTransactionRequest {
+            chain,
+            public_key,
+            tx: serde_json::to_vec(&tx)?,
+        },
+        UnionRequest::SendTransaction(resp) => Ok(resp.tx_hash)
+    )
+}
+
+#[instrument(skip_all, ret)]
+#[op2(async, reentrant)]
+#[serde]
+async fn op_get_randomness(
+    state: Rc<RefCell<OpState>>,
+    #[number] round: u64,
+) -> Result<serde_json::Value> {
+    remote_op_async!(op_get_randomness,
+        state,
+        GetRandomnessRequest { round },
+        UnionRequest::GetRandomness(resp) => Ok(json!({
+            "round": resp.round,
+            "signature": resp.signature,
+            "randomness": resp.randomness,
+        }))
+    )
+}
+
+#[instrument(skip_all, ret)]
+#[op2(async, reentrant)]
+#[serde]
+async fn op_storage(
+    state: Rc<RefCell<OpState>>,
+    #[string] method: String,
+    #[string] public_key: String,
+    #[string] key: String,
+    #[serde] value: Option<String>,
+    #[serde] expected_version: Option<u64>,
+) -> Result<serde_json::Value> {
+    ensure_not_blank!(public_key, "publicKey");
+
+    remote_op_async!(op_storage,
+        state,
+        StorageRequest {
+            method,
+            public_key,
+            key,
+            value,
+            expected_version,
+        },
+        UnionRequest::Storage(resp) => serde_json::from_slice(&resp.result).map_err(Into::into)
+    )
+}
+
 #[instrument(skip_all, ret)]
 #[op2(async, reentrant)]
 #[string]
@@ -553,6 +712,82 @@ async fn op_decrypt_to_single_node(
     )
 }
 
+#[instrument(skip_all, ret)]
+#[op2(async, reentrant)]
+async fn op_load_wasm(state: Rc<RefCell<OpState>>, #[string] ipfs_id: String) -> Result<()> {
+    ensure_not_blank!(ipfs_id, "ipfsId");
+
+    let code = remote_op_async!(op_load_wasm,
+        state,
+        LoadWasmRequest { cid: ipfs_id.clone() },
+        UnionRequest::LoadWasm(resp) => Ok(resp.code)
+    )?;
+
+    // Keep the verified bytes on the Rust side, JS only ever refers to the CID
+    let mut state = state.borrow_mut();
+    if !state.has::<PendingWasmModules>() {
+        state.put(PendingWasmModules::default());
+    }
+    state
+        .borrow_mut::<PendingWasmModules>()
+        .0
+        .insert(ipfs_id, code);
+    Ok(())
+}
+
+// Returns the compiled module for the given CID, or undefined if it must be loaded first.
+#[instrument(skip_all)]
+#[op2]
+fn op_wasm_module<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    state: &mut OpState,
+    #[string] ipfs_id: String,
+) -> Result<v8::Local<'s, v8::Value>> {
+    let (module, memory_bytes) = match wasm::cached_module(scope, &ipfs_id) {
+        Some(cached) => cached,
+        None => {
+            let Some(code) = state
+                .try_borrow_mut::<PendingWasmModules>()
+                .and_then(|pending| pending.0.remove(&ipfs_id))
+            else {
+                return Ok(v8::undefined(scope).into());
+            };
+
+            let memory_bytes = wasm::memory_bytes(&code)
+                .map_err(|e| type_error(format!("Invalid WebAssembly module {ipfs_id}: {e}")))?;
+
+            let tc = &mut v8::TryCatch::new(scope);
+            let Some(module) = v8::WasmModuleObject::compile(tc, &code) else {
+                let msg = tc
+                    .exception()
+                    .map(|e| e.to_rust_string_lossy(tc))
+                    .unwrap_or_default();
+                tc.reset();
+                return Err(type_error(format!(
+                    "Invalid WebAssembly module {ipfs_id}: {msg}"
+                )));
+            };
+            wasm::cache_module(&ipfs_id, &module, memory_bytes);
+            (module, memory_bytes)
+        }
+    };
+
+    // Every instantiation goes through here, so all instances of the execution are counted
+    if let Some(limit) = state.try_borrow_mut::<WasmMemoryLimit>() {
+        if let Err(used_bytes) = limit.charge(memory_bytes) {
+            // Reported as OutOfMemory by the runtime, like exceeding the heap limit
+            (limit.on_exceeded)(used_bytes);
+            scope.terminate_execution();
+            return Err(custom_error(
+                "RangeError",
+                format!("WebAssembly module {ipfs_id} requires more memory than allowed"),
+            ));
+        }
+    }
+
+    Ok(module.into())
+}
+
 // Build a deno_core::Extension providing custom ops
 extension!(
     lit_actions,
@@ -565,6 +800,7 @@ extension!(
         op_claim_key_identifier,
         op_get_latest_nonce,
         op_increment_fetch_count,
+        op_fetch,
         op_pkp_permissions_get_permitted_auth_method_scopes,
         op_pkp_permissions_get_permitted,
         op_pkp_permissions_is_permitted_auth_method,
@@ -576,12 +812,17 @@ extension!(
         op_broadcast_and_collect,
         op_decrypt_and_combine,
         op_sign_and_combine_ecdsa,
+        op_send_transaction,
+        op_storage,
+        op_get_randomness,
         op_get_rpc_url,
         op_p2p_broadcast,
         op_p2p_collect_from_leader,
         op_is_leader,
         op_encrypt_bls,
         op_decrypt_to_single_node,
+        op_load_wasm,
+        op_wasm_module,
     ],
     esm_entry_point = "ext:lit_actions/99_patches.js",
     esm = [
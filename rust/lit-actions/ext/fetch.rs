@@ -0,0 +1,163 @@
+//! HTTP(S) requests made by `fetch` in Lit Actions.
+//!
+//! Deno's own network access is denied by the permission layer (which covers
+//! fetch, WebSocket, Deno.connect and Deno.connectTls alike), so the only way
+//! out is this client. It only fetches URLs lit-node granted via the
+//! `IncrementFetchCount` op, connects to the addresses lit-node checked against
+//! its egress policy (without resolving the host again) and enforces the
+//! response limits handed out with the grant.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use reqwest::{redirect, Method, Url};
+
+const USER_AGENT: &str = "lit_protocol_node";
+
+/// A URL lit-node allowed to be fetched once, with the limits of the response.
+#[derive(Debug, Clone)]
+pub(crate) struct FetchGrant {
+    pub addrs: Vec<IpAddr>,
+    pub max_response_bytes: u64,
+    pub timeout_ms: u64,
+    pub remaining_bytes: u64,
+}
+
+/// Grants of the current execution by URL, each consumed by a single request.
+#[derive(Default)]
+pub(crate) struct PendingFetchGrants(HashMap<String, VecDeque<FetchGrant>>);
+
+impl PendingFetchGrants {
+    pub fn insert(&mut self, url: String, grant: FetchGrant) {
+        self.0.entry(url).or_default().push_back(grant);
+    }
+
+    pub fn take(&mut self, url: &str) -> Option<FetchGrant> {
+        let grants = self.0.get_mut(url)?;
+        let grant = grants.pop_front();
+        if grants.is_empty() {
+            self.0.remove(url);
+        }
+        grant
+    }
+}
+
+/// The response of a single request (redirects are not followed). Everything
+/// read is accounted for, even if the request fails or times out.
+#[derive(Debug, Default)]
+pub(crate) struct FetchResult {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Size of the response body read
+    pub bytes: u64,
+    /// response_too_large, total_bytes_exceeded, timeout or empty
+    pub violation: &'static str,
+    /// Set if the request failed (after `bytes` were read)
+    pub error: Option<String>,
+}
+
+pub(crate) async fn fetch(
+    grant: &FetchGrant,
+    url: &str,
+    method: &str,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+) -> Result<FetchResult> {
+    let parsed = Url::parse(url).with_context(|| format!("Invalid URL {url}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("Fetching {}: URLs is not supported", parsed.scheme());
+    }
+    if grant.addrs.is_empty() {
+        bail!("No checked address to connect to for {url}");
+    }
+
+    let mut client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .redirect(redirect::Policy::none()) // followed by the JS wrapper, checking each hop
+        .no_proxy();
+    if let Some(domain) = parsed.domain() {
+        // The port is taken from the URL
+        let addrs: Vec<SocketAddr> = grant
+            .addrs
+            .iter()
+            .map(|ip| SocketAddr::new(*ip, 0))
+            .collect();
+        client = client.resolve_to_addrs(domain, &addrs);
+    } else if !parsed
+        .host_str()
+        .and_then(|host| host.trim_matches(['[', ']']).parse::<IpAddr>().ok())
+        .is_some_and(|ip| grant.addrs.contains(&ip))
+    {
+        bail!("Address of {url} was not checked");
+    }
+    let client = client.build().context("Could not build HTTP client")?;
+
+    let mut request = client.request(
+        Method::from_bytes(method.as_bytes())
+            .with_context(|| format!("Invalid method {method}"))?,
+        parsed,
+    );
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    if !body.is_empty() {
+        request = request.body(body);
+    }
+
+    let limit = grant.max_response_bytes.min(grant.remaining_bytes);
+    let mut result = FetchResult::default();
+    let timeout = Duration::from_millis(grant.timeout_ms);
+    let completed = tokio::time::timeout(timeout, async {
+        let mut response = request.send().await?;
+        result.status = response.status().as_u16();
+        result.status_text = response
+            .status()
+            .canonical_reason()
+            .unwrap_or_default()
+            .to_string();
+        result.headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+
+        while let Some(chunk) = response.chunk().await? {
+            result.bytes += chunk.len() as u64;
+            if result.bytes > limit {
+                result.violation = if result.bytes > grant.max_response_bytes {
+                    "response_too_large"
+                } else {
+                    "total_bytes_exceeded"
+                };
+                break;
+            }
+            result.body.extend_from_slice(&chunk);
+        }
+        Ok::<_, reqwest::Error>(())
+    })
+    .await;
+
+    match completed {
+        Ok(Ok(())) => Ok(result),
+        Ok(Err(e)) => {
+            result.error = Some(format!(
+                "{:#}",
+                anyhow!(e).context(format!("Error fetching {url}"))
+            ));
+            Ok(result)
+        }
+        Err(_) => {
+            result.violation = "timeout";
+            Ok(result)
+        }
+    }
+}
@@ -1,6 +1,10 @@
 import * as ops from 'ext:core/ops';
 import { Uint8arrays } from 'ext:lit_actions/01_uint8arrays.js';
 
+// WebAssembly is removed from the global scope at runtime so that only modules
+// loaded by CID via instantiateWasm can be compiled
+const WasmInstance = globalThis.WebAssembly.Instance;
+
 /**
  * Check if a given IPFS ID is permitted to sign using a given PKP tokenId
  * @function isPermittedAction
@@ -255,6 +259,119 @@ function broadcastAndCollect({ name, value }) {
   return ops.op_broadcast_and_collect(name, value);
 }
 
+let consensusFetchCount = 0;
+
+// JSON with object keys sorted, so that equal values compare equal whatever their key order
+function canonicalJson(value) {
+  if (Array.isArray(value)) {
+    return `[${value.map(canonicalJson).join(',')}]`;
+  }
+  if (value !== null && typeof value === 'object') {
+    return `{${Object.keys(value)
+      .sort()
+      .filter((key) => value[key] !== undefined)
+      .map((key) => `${JSON.stringify(key)}:${canonicalJson(value[key])}`)
+      .join(',')}}`;
+  }
+  return JSON.stringify(value);
+}
+
+/**
+ * Fetch a URL on every node and agree on the extracted value. Each node's value is exchanged with the other nodes, and every node then reduces the values the leader collected, so the call either returns the same agreed value on every node or throws if the nodes disagree.
+ * @function consensusFetch
+ * @param {Object} params
+ * @param {string} params.url The URL to fetch
+ * @param {Object} params.init Optional fetch options, e.g. method and headers
+ * @param {function} params.extract Optional function that receives the response body (parsed as JSON if possible) and returns the value to agree on. Defaults to the whole body
+ * @param {string} params.mode How to agree on a value: "majority" (the default) requires more than half of the nodes to return the same value, "median" returns the median of the numeric values returned by more than half of the nodes, and "threshold" requires at least `threshold` nodes to return the same value
+ * @param {number} params.threshold The number of nodes that must agree in "threshold" mode, or the minimum number of values in "median" mode
+ * @returns {Promise<any>} The agreed value
+ */
+async function consensusFetch({ url, init, extract, mode, threshold }) {
+  mode = mode || 'majority';
+  if (!['majority', 'median', 'threshold'].includes(mode)) {
+    throw new Error(`consensusFetch: unknown mode "${mode}"`);
+  }
+  if (mode === 'threshold' && !(threshold > 0)) {
+    throw new Error('consensusFetch: "threshold" mode requires a positive threshold');
+  }
+
+  // Failures are shared too, so that every node gets a value from every other node
+  let local;
+  try {
+    const response = await fetch(url, init);
+    if (!response.ok) {
+      throw new Error(`HTTP status ${response.status}`);
+    }
+    const text = await response.text();
+    let body;
+    try {
+      body = JSON.parse(text);
+    } catch (e) {
+      body = text;
+    }
+    const value = extract ? await extract(body) : body;
+    if (mode === 'median' && (typeof value !== 'number' || !Number.isFinite(value))) {
+      throw new Error(`extracted value is not a number: ${value}`);
+    }
+    local = { value };
+  } catch (e) {
+    local = { error: e.toString() };
+  }
+
+  // Nodes may collect different sets of values (i.e. as peers time out), so all of them
+  // reduce the set the leader collected
+  const name = `consensusFetch_${consensusFetchCount++}`;
+  let collected = await ops.op_broadcast_and_collect(name, JSON.stringify(local));
+  if (await ops.op_is_leader()) {
+    await ops.op_p2p_broadcast(`${name}_leader`, JSON.stringify(collected));
+  } else {
+    collected = JSON.parse(await ops.op_p2p_collect_from_leader(`${name}_leader`));
+  }
+  collected = collected.map((v) => JSON.parse(v));
+  const nodes = collected.length;
+  const values = collected.filter((v) => !('error' in v)).map((v) => v.value);
+  const errors = collected.filter((v) => 'error' in v).map((v) => v.error);
+  const failures = errors.length
+    ? ` (${errors.length} node(s) failed, e.g. ${errors[0]})`
+    : '';
+
+  if (mode === 'median') {
+    const required = threshold || Math.floor(nodes / 2) + 1;
+    if (values.length < required) {
+      throw new Error(
+        `consensusFetch: only ${values.length} of ${nodes} nodes returned a value, ${required} required${failures}`
+      );
+    }
+    values.sort((a, b) => a - b);
+    // Lower median, so that the result is a value some node actually observed
+    return values[Math.floor((values.length - 1) / 2)];
+  }
+
+  const counts = new Map();
+  for (const value of values) {
+    const key = canonicalJson(value);
+    const entry = counts.get(key) || { value, count: 0 };
+    entry.count++;
+    counts.set(key, entry);
+  }
+  const ranked = [...counts.values()].sort((a, b) => b.count - a.count);
+  const required = mode === 'threshold' ? threshold : Math.floor(nodes / 2) + 1;
+  const best = ranked[0];
+
+  if (!best || best.count < required) {
+    throw new Error(
+      `consensusFetch: nodes disagree, at most ${best ? best.count : 0} of ${nodes} returned the same value, ${required} required${failures}`
+    );
+  }
+  if (ranked.length > 1 && ranked[1].count === best.count) {
+    throw new Error(
+      `consensusFetch: nodes disagree, ${best.count} of ${nodes} nodes returned each of several different values`
+    );
+  }
+  return best.value;
+}
+
 /**
  * Decrypt and combine the provided
  * @param {string} accessControlConditions The access control conditions
@@ -319,6 +436,114 @@ function signAndCombineEcdsa({ toSign, publicKey, sigName }) {
   );
 }
 
+function toQuantity(value) {
+  if (value === undefined || value === null) {
+    return undefined;
+  }
+  if (typeof value === 'object' && typeof value.toHexString === 'function') {
+    return value.toHexString(); // ethers BigNumber
+  }
+  return '0x' + BigInt(value).toString(16);
+}
+
+/**
+ * Sign a transaction with a PKP and broadcast it. Missing nonce, gas and EIP-1559 fees are filled in by the nodes, the transaction hash is signed with the PKP and a single node broadcasts the signed transaction.
+ * @function sendTransaction
+ * @param {Object} params
+ * @param {string} params.chain The chain to send the transaction on
+ * @param {string} params.publicKey The public key of the PKP that signs the transaction
+ * @param {Object} params.tx The transaction: to, value, data and optionally nonce, gas (or gasLimit), maxFeePerGas, maxPriorityFeePerGas and accessList.  Pass the nonce of a pending transaction to replace it
+ * @returns {Promise<string>} The transaction hash
+ */
+function sendTransaction({ chain, publicKey, tx }) {
+  const { gasLimit, ...rest } = tx;
+  const normalized = {
+    ...rest,
+    gas: toQuantity(rest.gas ?? gasLimit),
+    value: toQuantity(rest.value),
+    nonce: toQuantity(rest.nonce),
+    maxFeePerGas: toQuantity(rest.maxFeePerGas),
+    maxPriorityFeePerGas: toQuantity(rest.maxPriorityFeePerGas),
+    chainId: toQuantity(rest.chainId),
+  };
+  for (const key of Object.keys(normalized)) {
+    if (normalized[key] === undefined) {
+      delete normalized[key];
+    }
+  }
+  return ops.op_send_transaction(chain, publicKey, normalized);
+}
+
+/**
+ * Get a round of the network's randomness beacon. Every node signs the round with its share of the BLS root key and the shares are combined, so the randomness can't be predicted or biased by any subset of nodes below the threshold. The signature can be verified against the network public key returned by the handshake.
+ * @function getRandomness
+ * @param {Object} params
+ * @param {number} params.round The beacon round, which must not be in the future.  Round n starts 30 * n seconds after 2024-01-01T00:00:00Z (Unix time 1704067200)
+ * @returns {Promise<{round: number, signature: string, randomness: string}>} The hex-encoded BLS signature of the round and the 32 bytes of randomness derived from it
+ */
+function getRandomness({ round }) {
+  return ops.op_get_randomness(round);
+}
+
+/**
+ * Key-value storage for the PKP and the action (by IPFS ID) using it. Values are encrypted at rest and replicated on every node; the action must be a permitted action of the PKP.  Every write increments the version of the key, which can be used for compare-and-swap.
+ */
+const storage = {
+  /**
+   * Get a value from storage
+   * @function storage.get
+   * @param {Object} params
+   * @param {string} params.publicKey The public key of the PKP whose storage to use
+   * @param {string} params.key The key to get
+   * @returns {Promise<{value: string, version: number} | null>} The value and its version, or null if the key doesn't exist
+   */
+  get({ publicKey, key }) {
+    return ops.op_storage('get', publicKey, key, null, null);
+  },
+
+  /**
+   * Store a value
+   * @function storage.put
+   * @param {Object} params
+   * @param {string} params.publicKey The public key of the PKP whose storage to use
+   * @param {string} params.key The key to set
+   * @param {string} params.value The value to store
+   * @param {number} params.expectedVersion Optional. Only store the value if the current version of the key matches, 0 if the key must not exist
+   * @returns {Promise<number>} The new version of the key
+   */
+  put({ publicKey, key, value, expectedVersion }) {
+    if (typeof value !== 'string') {
+      throw new TypeError('value must be a string');
+    }
+    return ops.op_storage('put', publicKey, key, value, expectedVersion ?? null);
+  },
+
+  /**
+   * Delete a value
+   * @function storage.delete
+   * @param {Object} params
+   * @param {string} params.publicKey The public key of the PKP whose storage to use
+   * @param {string} params.key The key to delete
+   * @param {number} params.expectedVersion Optional. Only delete the key if its current version matches
+   * @returns {Promise<boolean>} Whether the key existed
+   */
+  delete({ publicKey, key, expectedVersion }) {
+    return ops.op_storage('delete', publicKey, key, null, expectedVersion ?? null);
+  },
+
+  /**
+   * List keys
+   * @function storage.list
+   * @param {Object} params
+   * @param {string} params.publicKey The public key of the PKP whose storage to use
+   * @param {string} params.prefix Optional. Only list keys starting with this prefix
+   * @returns {Promise<Array<string>>} The keys, in order
+   */
+  list({ publicKey, prefix }) {
+    return ops.op_storage('list', publicKey, prefix ?? '', null, null);
+  },
+};
+
 /**
  *
  * @param {bool} waitForResponse Whether to wait for a response or not - if false, the function will return immediately.
@@ -379,6 +604,23 @@ function encrypt({ accessControlConditions, to_encrypt }) {
   return ops.op_encrypt_bls(accessControlConditions, to_encrypt);
 }
 
+/**
+ * Instantiate a WebAssembly module by its IPFS CID. The module is verified against the CID, and compiled modules are cached across executions.
+ * @function instantiateWasm
+ * @param {Object} params
+ * @param {string} params.ipfsId The IPFS ID of the WebAssembly module
+ * @param {Object} params.imports The imports object passed to the module's instance
+ * @returns {Promise<WebAssembly.Instance>} The instantiated module, e.g. call its functions via instance.exports
+ */
+async function instantiateWasm({ ipfsId, imports }) {
+  let module = ops.op_wasm_module(ipfsId);
+  if (module === undefined) {
+    await ops.op_load_wasm(ipfsId);
+    module = ops.op_wasm_module(ipfsId);
+  }
+  return new WasmInstance(module, imports ?? {});
+}
+
 globalThis.LitActions = {
   isPermittedAction,
   isPermittedAddress,
@@ -404,10 +646,15 @@ globalThis.LitActions = {
   aesDecrypt,
 
   broadcastAndCollect,
+  consensusFetch,
   decryptAndCombine,
   signAndCombineEcdsa,
+  sendTransaction,
+  getRandomness,
+  storage,
   runOnce,
   getRpcUrl,
   encrypt,
   decryptToSingleNode,
+  instantiateWasm,
 };
@@ -1,4 +1,8 @@
-import { op_increment_fetch_count, op_panic } from 'ext:core/ops';
+import {
+  op_fetch,
+  op_increment_fetch_count,
+  op_panic,
+} from 'ext:core/ops';
 
 // Import modules to suppress build error:
 // "Following modules were not evaluated; make sure they are imported from other code"
@@ -10,15 +14,107 @@ import * as _jwt from 'ext:lit_actions/03_jsonwebtoken.js';
 // this block scopes oldFetch so that nobody can ever use it after
 {
   const oldFetch = globalThis.fetch;
-  const fetch = async function () {
-    const fetchCount = await op_increment_fetch_count();
-    // console.log(
-    //   "fetchCount: " +
-    //     fetchCount +
-    //     " and arguments: " +
-    //     JSON.stringify(arguments, null, 2)
-    // );
-    return oldFetch.apply(null, arguments);
+  const MAX_REDIRECTS = 20;
+  const REDIRECT_STATUSES = [301, 302, 303, 307, 308];
+  const NULL_BODY_STATUSES = [204, 205, 304];
+
+  // Fetch a single URL (without following redirects) and read the whole body.
+  // HTTP(S) requests go through op_fetch, which connects to the addresses the
+  // node checked and enforces the limits of the grant (Deno's network access
+  // is denied, so oldFetch can only be used for URLs that never leave here).
+  const fetchOnce = async function (request) {
+    const { protocol } = new URL(request.url);
+    if (protocol === 'data:' || protocol === 'blob:') {
+      const response = await oldFetch(
+        new Request(request, { redirect: 'manual' })
+      );
+      const chunks = [];
+      if (response.body) {
+        for await (const chunk of response.body) {
+          chunks.push(chunk);
+        }
+      }
+      return { response, chunks };
+    }
+
+    const body = request.body
+      ? new Uint8Array(await request.arrayBuffer())
+      : new Uint8Array();
+    // Throws if the response violated the egress policy
+    const response = await abortable(
+      op_fetch(request.url, request.method, [...request.headers], body),
+      request.signal
+    );
+    return {
+      response: { ...response, headers: new Headers(response.headers) },
+      chunks: [response.body],
+    };
+  };
+
+  // The request keeps running until its timeout, but the caller stops waiting.
+  const abortable = function (promise, signal) {
+    if (signal.aborted) {
+      return Promise.reject(signal.reason);
+    }
+    return new Promise((resolve, reject) => {
+      const abort = () => reject(signal.reason);
+      signal.addEventListener('abort', abort, { once: true });
+      promise
+        .then(resolve, reject)
+        .finally(() => signal.removeEventListener('abort', abort));
+    });
+  };
+
+  const fetch = async function (input, init = undefined) {
+    const original = new Request(input, init);
+    const { headers, signal, redirect } = original;
+    let { method, url } = original;
+    // Buffered, so that it can be sent again when following redirects
+    let body = original.body ? await original.arrayBuffer() : null;
+
+    for (let redirects = 0; ; redirects++) {
+      // Checks the egress policy for the URL and counts every request made
+      await op_increment_fetch_count(url);
+      const request = new Request(url, { method, headers, body, signal });
+      const { response, chunks } = await fetchOnce(request);
+
+      const location = response.headers.get('location');
+      if (
+        REDIRECT_STATUSES.includes(response.status) &&
+        location !== null &&
+        redirect !== 'manual'
+      ) {
+        if (redirect === 'error') {
+          throw new TypeError(`Unexpected redirect fetching ${url}`);
+        }
+        if (redirects >= MAX_REDIRECTS) {
+          throw new TypeError(`Too many redirects fetching ${original.url}`);
+        }
+        if (
+          (response.status === 303 && method !== 'HEAD') ||
+          ([301, 302].includes(response.status) && method === 'POST')
+        ) {
+          method = 'GET';
+          body = null;
+        }
+        url = new URL(location, url).toString();
+        continue;
+      }
+
+      const result = new Response(
+        NULL_BODY_STATUSES.includes(response.status) ? null : new Blob(chunks),
+        {
+          status: response.status,
+          statusText: response.statusText,
+          headers: response.headers,
+        }
+      );
+      Object.defineProperties(result, {
+        url: { value: url },
+        redirected: { value: redirects > 0 },
+      });
+      return result;
+    }
   };
   Object.freeze(fetch);
 
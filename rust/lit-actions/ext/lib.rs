@@ -1,5 +1,8 @@
 mod bindings;
+mod fetch;
 mod validation;
+mod wasm;
 
 // Export extension
 pub use bindings::lit_actions;
+pub use wasm::{WasmMemoryLimit, WASM_MAX_MEMORY_PAGES};
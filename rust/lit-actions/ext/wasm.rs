@@ -0,0 +1,223 @@
+//! WebAssembly support for Lit Actions.
+//!
+//! Modules are fetched by IPFS CID through lit-node (which verifies the CID),
+//! compiled once and cached for the lifetime of the process, so that later
+//! executions can instantiate them without fetching or compiling again.
+//!
+//! Each instance is charged the linear memory its memories can grow to (their
+//! declared maximum, or `WASM_MAX_MEMORY_PAGES` if they have none), and the
+//! instances of an execution together may not exceed its memory limit. Running
+//! WebAssembly code is metered by the execution deadline: the runtime
+//! terminates the isolate, which also interrupts WebAssembly loops.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context as _, Result};
+use deno_core::v8;
+
+/// The most pages a single memory may grow to (256 MB), passed to V8.
+pub const WASM_MAX_MEMORY_PAGES: u64 = 4096;
+
+const MAX_CACHED_MODULES: usize = 64;
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+const SECTION_IMPORT: u8 = 2;
+const SECTION_MEMORY: u8 = 5;
+const IMPORT_KIND_FUNC: u8 = 0;
+const IMPORT_KIND_TABLE: u8 = 1;
+const IMPORT_KIND_MEMORY: u8 = 2;
+const IMPORT_KIND_GLOBAL: u8 = 3;
+const IMPORT_KIND_TAG: u8 = 4;
+
+/// The memory limit of the current execution, put into `OpState` by the runtime.
+/// `used_bytes` is the memory charged to the instances created so far.
+/// `on_exceeded` is called with the requested size when a module needs more
+/// memory than allowed, so the runtime can terminate the isolate.
+pub struct WasmMemoryLimit {
+    pub max_bytes: u64,
+    pub used_bytes: u64,
+    pub on_exceeded: Box<dyn Fn(u64)>,
+}
+
+impl WasmMemoryLimit {
+    /// Charge the memory of a new instance, returning the total charged to the
+    /// execution if it exceeds the limit (in which case nothing is charged).
+    pub(crate) fn charge(&mut self, memory_bytes: u64) -> Result<(), u64> {
+        let used_bytes = self.used_bytes.saturating_add(memory_bytes);
+        if used_bytes > self.max_bytes {
+            return Err(used_bytes);
+        }
+        self.used_bytes = used_bytes;
+        Ok(())
+    }
+}
+
+/// Verified module bytes received from lit-node, waiting to be compiled.
+#[derive(Default)]
+pub(crate) struct PendingWasmModules(pub HashMap<String, Vec<u8>>);
+
+struct CachedModule {
+    compiled: v8::CompiledWasmModule,
+    memory_bytes: u64,
+}
+
+#[derive(Default)]
+struct ModuleCache {
+    modules: HashMap<String, CachedModule>,
+    order: VecDeque<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref MODULE_CACHE: Mutex<ModuleCache> = Mutex::new(ModuleCache::default());
+}
+
+/// Look up a compiled module by CID, returning it with the memory charged per instance.
+pub(crate) fn cached_module<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    cid: &str,
+) -> Option<(v8::Local<'s, v8::WasmModuleObject>, u64)> {
+    let cache = MODULE_CACHE.lock().ok()?;
+    let cached = cache.modules.get(cid)?;
+    let module = v8::WasmModuleObject::from_compiled_module(scope, &cached.compiled)?;
+    Some((module, cached.memory_bytes))
+}
+
+pub(crate) fn cache_module(cid: &str, module: &v8::WasmModuleObject, memory_bytes: u64) {
+    let Ok(mut cache) = MODULE_CACHE.lock() else {
+        return;
+    };
+    if cache.modules.contains_key(cid) {
+        return;
+    }
+
+    while cache.order.len() >= MAX_CACHED_MODULES {
+        if let Some(evicted) = cache.order.pop_front() {
+            cache.modules.remove(&evicted);
+        }
+    }
+
+    cache.order.push_back(cid.to_string());
+    cache.modules.insert(
+        cid.to_string(),
+        CachedModule {
+            compiled: module.get_compiled_module(),
+            memory_bytes,
+        },
+    );
+}
+
+/// Returns the number of bytes the memories defined by the module can grow to,
+/// based on their declared maximum number of pages (or `WASM_MAX_MEMORY_PAGES`).
+/// Imported memories are charged to the instance that defines them.
+pub(crate) fn memory_bytes(wasm: &[u8]) -> Result<u64> {
+    let mut reader = Reader::new(wasm);
+    if reader.bytes(4)? != b"\0asm" {
+        bail!("not a WebAssembly module");
+    }
+    reader.bytes(4)?; // version
+
+    let mut pages = 0u64;
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let mut section = Reader::new(reader.bytes(size)?);
+        match id {
+            SECTION_IMPORT => {
+                for _ in 0..section.u32()? {
+                    section.name()?; // module
+                    section.name()?; // field
+                    match section.byte()? {
+                        IMPORT_KIND_FUNC => {
+                            section.u32()?;
+                        }
+                        IMPORT_KIND_TABLE => {
+                            section.byte()?; // reftype
+                            section.limits()?;
+                        }
+                        IMPORT_KIND_MEMORY => {
+                            section.limits()?;
+                        }
+                        IMPORT_KIND_GLOBAL => {
+                            section.bytes(2)?; // valtype, mutability
+                        }
+                        IMPORT_KIND_TAG => {
+                            section.byte()?;
+                            section.u32()?;
+                        }
+                        kind => bail!("unknown import kind {kind}"),
+                    }
+                }
+            }
+            SECTION_MEMORY => {
+                for _ in 0..section.u32()? {
+                    let (min, max) = section.limits()?;
+                    pages = pages.saturating_add(min.max(max.unwrap_or(WASM_MAX_MEMORY_PAGES)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(pages.saturating_mul(WASM_PAGE_SIZE))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("unexpected end of WebAssembly module");
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    // Unsigned LEB128
+    fn u64(&mut self) -> Result<u64> {
+        let mut result = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            result |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        bail!("invalid LEB128 integer in WebAssembly module")
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        u32::try_from(self.u64()?).context("integer out of range in WebAssembly module")
+    }
+
+    fn name(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    /// Returns the minimum and maximum of a limits entry.
+    fn limits(&mut self) -> Result<(u64, Option<u64>)> {
+        let flags = self.byte()?;
+        let min = self.u64()?;
+        let max = match flags & 0x01 {
+            0 => None,
+            _ => Some(self.u64()?),
+        };
+        Ok((min, max))
+    }
+}
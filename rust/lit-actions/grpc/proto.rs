@@ -107,3 +107,9 @@ decl_op!(P2pCollectFromLeader);
 decl_op!(IsLeader);
 decl_op!(EncryptBls);
 decl_op!(DecryptToSingleNode);
+decl_op!(LoadModule);
+decl_op!(LoadWasm);
+decl_op!(SendTransaction);
+decl_op!(Storage);
+decl_op!(RecordFetch);
+decl_op!(GetRandomness);
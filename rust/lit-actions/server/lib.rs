@@ -1,5 +1,7 @@
+mod module_loader;
 mod runtime;
 mod tracing;
+mod typescript;
 
 pub mod server;
 
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, bail, Context as _, Result};
+use deno_core::{
+    error::type_error, ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode,
+    ModuleSpecifier, ModuleType, RequestedModuleType, ResolutionKind,
+};
+use lit_actions_grpc::proto::*;
+use tracing::{debug, instrument};
+
+pub(crate) const IPFS_SCHEME: &str = "ipfs";
+
+/// Resolves `import ... from "ipfs://<CID>"` by asking lit-node for the module source,
+/// which fetches it through its IPFS cache, verifies the CID and enforces the allowlist.
+/// Any other specifier (files, URLs, relative paths) is rejected.
+pub(crate) struct IpfsModuleLoader {
+    outbound_tx: flume::Sender<tonic::Result<ExecuteJsResponse>>,
+    inbound_rx: flume::Receiver<ExecuteJsRequest>,
+}
+
+impl IpfsModuleLoader {
+    pub fn new(
+        outbound_tx: flume::Sender<tonic::Result<ExecuteJsResponse>>,
+        inbound_rx: flume::Receiver<ExecuteJsRequest>,
+    ) -> Self {
+        Self {
+            outbound_tx,
+            inbound_rx,
+        }
+    }
+}
+
+/// Extract the CID from an `ipfs://<CID>` specifier.
+pub(crate) fn ipfs_cid(specifier: &ModuleSpecifier) -> Result<&str> {
+    if specifier.scheme() != IPFS_SCHEME {
+        bail!(type_error(format!(
+            "Only ipfs://<CID> imports are supported, got \"{specifier}\""
+        )));
+    }
+
+    match specifier.host_str() {
+        Some(cid) if !cid.is_empty() && matches!(specifier.path(), "" | "/") => Ok(cid),
+        _ => bail!(type_error(format!(
+            "Invalid IPFS module specifier \"{specifier}\", expected ipfs://<CID>"
+        ))),
+    }
+}
+
+impl ModuleLoader for IpfsModuleLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        _kind: ResolutionKind,
+    ) -> Result<ModuleSpecifier> {
+        // Relative imports inside an IPFS module would need a directory CID, which we
+        // don't support; every import must name the CID of the module itself.
+        let resolved = ModuleSpecifier::parse(specifier).map_err(|_| {
+            type_error(format!(
+                "Relative import \"{specifier}\" from \"{referrer}\" is not supported, use ipfs://<CID>"
+            ))
+        })?;
+        ipfs_cid(&resolved)?;
+
+        Ok(resolved)
+    }
+
+    #[instrument(skip_all, fields(specifier = %module_specifier))]
+    fn load(
+        &self,
+        module_specifier: &ModuleSpecifier,
+        _maybe_referrer: Option<&ModuleSpecifier>,
+        _is_dyn_import: bool,
+        requested_module_type: RequestedModuleType,
+    ) -> ModuleLoadResponse {
+        let specifier = module_specifier.clone();
+        let tx = self.outbound_tx.clone();
+        let rx = self.inbound_rx.clone();
+
+        ModuleLoadResponse::Async(Box::pin(async move {
+            if requested_module_type != RequestedModuleType::None {
+                bail!(type_error(format!(
+                    "Unsupported module type for \"{specifier}\", only JavaScript modules can be imported"
+                )));
+            }
+
+            let cid = ipfs_cid(&specifier)?.to_string();
+            debug!("Loading module {specifier}");
+
+            tx.send_async(Ok(LoadModuleRequest { cid }.into()))
+                .await
+                .context("load_module")?;
+
+            // Must be blocking to preserve ops order
+            let resp = rx.recv().context("load_module")?;
+            let code = match resp.union {
+                Some(UnionRequest::LoadModule(LoadModuleResponse { code })) => code,
+                Some(UnionRequest::ReportError(ErrorResponse { error })) => {
+                    return Err(anyhow!(error))
+                }
+                other => {
+                    return Err(anyhow!("unexpected response: {other:?}").context("load_module"))
+                }
+            };
+
+            Ok(ModuleSource::new(
+                ModuleType::JavaScript,
+                ModuleSourceCode::String(code.into()),
+                &specifier,
+                None,
+            ))
+        }))
+    }
+}
@@ -8,7 +8,7 @@ use std::thread;
 use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context, Result};
-use deno_core::{v8, JsRuntime, NoopModuleLoader};
+use deno_core::{error::JsError, v8, JsRuntime, ModuleLoader, SourceMapGetter};
 use deno_runtime::{
     deno_fs::RealFs,
     deno_permissions::{Permissions, PermissionsContainer, PermissionsOptions},
@@ -17,16 +17,25 @@ use deno_runtime::{
     BootstrapOptions, WorkerLogLevel,
 };
 use indoc::formatdoc;
+use lit_actions_ext::{WasmMemoryLimit, WASM_MAX_MEMORY_PAGES};
 use lit_actions_grpc::proto::{ExecuteJsRequest, ExecuteJsResponse};
 use tokio::sync::{mpsc, oneshot};
 use tonic::Status;
 use tracing::{debug, info_span, instrument, warn};
 
+use crate::module_loader::IpfsModuleLoader;
+use crate::typescript::{self, TypeScriptSourceMap};
+
 // Same default limits as in lit-node's action client
 const DEFAULT_TIMEOUT_MS: u64 = 30000; // 30s
 const DEFAULT_MEMORY_LIMIT_MB: usize = 256; // 256MB
 
 const EXECUTION_TERMINATED_ERROR: &str = "Uncaught Error: execution terminated";
+const IMPORT_OUTSIDE_MODULE_ERROR: &str = "Cannot use import statement outside a module";
+
+const USER_SCRIPT_NAME: &str = "<user_provided_script>";
+const USER_MODULE_SPECIFIER: &str = "file:///$lit$user_provided_module.js";
+const USER_CODE_FILE_NAMES: &[&str] = &[USER_SCRIPT_NAME, USER_MODULE_SPECIFIER];
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum ExecutionResult {
@@ -66,12 +75,13 @@ fn build_main_worker_and_inject_sdk(
     auth_context: &Option<serde_json::Value>,
     http_headers: BTreeMap<String, String>,
     memory_limit_mb: Option<usize>,
+    module_loader: Rc<dyn ModuleLoader>,
+    source_map_getter: Option<Rc<dyn SourceMapGetter>>,
 ) -> Result<MainWorker> {
-    // Deny everything except for network access, e.g. via fetch()
-    let perms = Permissions::from_options(&PermissionsOptions {
-        allow_net: Some(vec![]),
-        ..Default::default()
-    })?;
+    // Deny everything, including network access: fetch() goes through op_fetch,
+    // which only connects to addresses checked by lit-node's egress policy, while
+    // WebSocket, Deno.connect and Deno.connectTls are denied by this permission.
+    let perms = Permissions::from_options(&PermissionsOptions::default())?;
 
     let options = WorkerOptions {
         bootstrap: BootstrapOptions {
@@ -90,12 +100,12 @@ fn build_main_worker_and_inject_sdk(
         root_cert_store_provider: None,
         seed: None,
         fs: Arc::new(RealFs),
-        module_loader: Rc::new(NoopModuleLoader), // avoid loading any modules
+        module_loader, // only ipfs://<CID> imports, resolved by lit-node
         node_resolver: None,
         npm_resolver: None,
         create_web_worker_cb: Arc::new(|_| unimplemented!("web workers are not supported")),
         format_js_error_fn: Some(Arc::new(format_js_error)),
-        source_map_getter: None,
+        source_map_getter, // maps stack traces of TypeScript actions
         maybe_inspector_server: None,
         should_break_on_first_statement: false,
         should_wait_for_inspector_session: false,
@@ -168,6 +178,7 @@ fn build_main_worker_and_inject_sdk(
             delete Deno.build;
             delete Deno.permissions;
             delete Deno.version;
+            delete globalThis.WebAssembly;
         "#};
 
         worker
@@ -206,7 +217,9 @@ pub fn init_v8() {
     let unknown_flags = &deno_core::v8_set_flags(vec![
         "UNUSED_BUT_NECESSARY_ARG0".into(), // See https://github.com/denoland/deno/blob/v1.37/cli/util/v8.rs#L17
         // "--jitless".into(),                 // Disable runtime allocation of executable memory
-        "--no-expose-wasm".into(), // Don't expose wasm interface to JavaScript
+        // WebAssembly is only reachable via Lit.Actions.instantiateWasm (see DenoNamespace.js);
+        // cap each wasm memory, instances are charged this much against the memory limit
+        format!("--wasm-max-mem-pages={WASM_MAX_MEMORY_PAGES}"),
         // "--no-use-ic".into(),      // Don't use inline caching
         "--disallow-code-generation-from-strings".into(), // Disallow eval and friends
         "--memory-protection-keys".into(), // Protect code memory with PKU if available
@@ -225,6 +238,7 @@ pub fn init_v8() {
 #[instrument(skip_all, err)]
 pub(crate) async fn execute_js(
     code: String,
+    typescript: Option<bool>,
     js_params: Option<serde_json::Value>,
     auth_context: Option<serde_json::Value>,
     http_headers: BTreeMap<String, String>,
@@ -240,27 +254,54 @@ pub(crate) async fn execute_js(
     let timeout_ms = timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
     let memory_limit_mb = memory_limit_mb.unwrap_or(DEFAULT_MEMORY_LIMIT_MB);
 
+    let module_loader = Rc::new(IpfsModuleLoader::new(
+        outbound_tx.clone(),
+        inbound_rx.clone(),
+    ));
+
+    let (code, source_map_getter) = match typescript::transpile_if_typescript(&code, typescript)? {
+        Some(transpiled) => (
+            transpiled.code.clone(),
+            Some(Rc::new(TypeScriptSourceMap {
+                transpiled,
+                file_names: USER_CODE_FILE_NAMES,
+            }) as Rc<dyn SourceMapGetter>),
+        ),
+        None => (code, None),
+    };
+
     let mut worker = build_main_worker_and_inject_sdk(
         &js_params,
         &auth_context,
         http_headers,
         Some(memory_limit_mb),
+        module_loader,
+        source_map_getter,
     )
     .context("Error building main worker")
     .map_err(|e| anyhow!("{e:#}"))?; // Ensure to keep context when downcasting JS errors later
 
+    let (halt_isolate_tx, mut halt_isolate_rx) = oneshot::channel::<ExecutionResult>();
+    let (memory_limit_tx, memory_limit_rx) = mpsc::unbounded_channel::<usize>();
+
     let op_state = worker.js_runtime.op_state();
     {
         // scope the borrow
         let mut state = op_state.borrow_mut();
         state.put(outbound_tx);
         state.put(inbound_rx);
+        // Terminate isolate when a WebAssembly module needs more memory than allowed
+        let memory_limit_tx = memory_limit_tx.clone();
+        state.put(WasmMemoryLimit {
+            max_bytes: (memory_limit_mb * 1024 * 1024) as u64,
+            used_bytes: 0,
+            on_exceeded: Box::new(move |bytes| {
+                let _ = memory_limit_tx.send(bytes as usize);
+            }),
+        });
         drop(state);
     }
 
-    let (halt_isolate_tx, mut halt_isolate_rx) = oneshot::channel::<ExecutionResult>();
-    let (memory_limit_tx, memory_limit_rx) = mpsc::unbounded_channel::<usize>();
-
     start_controller_thread(
         &mut worker.js_runtime,
         timeout_ms,
@@ -276,13 +317,27 @@ pub(crate) async fn execute_js(
             current_limit * 2
         });
 
+    // Actions are classic scripts unless they use static imports, in which case
+    // they are evaluated as an ES module (dynamic imports work in both).
+    let mut module_evaluation = None;
     if let Err(e) = worker
         .js_runtime
-        .execute_script("<user_provided_script>", code)
+        .execute_script(USER_SCRIPT_NAME, code.clone())
     {
-        // Delay error handling if the controller has terminated the isolate,
-        // in which case halt_isolate_rx will tell the reason.
-        if e.to_string() != EXECUTION_TERMINATED_ERROR {
+        if is_import_outside_module_error(&e) {
+            debug!("Evaluating code as ES module");
+
+            let specifier = deno_core::resolve_url(USER_MODULE_SPECIFIER)?;
+            let module_id = tokio::time::timeout(
+                Duration::from_millis(timeout_ms),
+                worker.js_runtime.load_main_es_module_from_code(&specifier, code),
+            )
+            .await
+            .map_err(|_| Status::deadline_exceeded(format!("Your function exceeded the maximum runtime of {timeout_ms}ms and was terminated.")))??;
+            module_evaluation = Some(worker.js_runtime.mod_evaluate(module_id));
+        } else if e.to_string() != EXECUTION_TERMINATED_ERROR {
+            // Delay error handling if the controller has terminated the isolate,
+            // in which case halt_isolate_rx will tell the reason.
             bail!(e);
         }
     };
@@ -312,9 +367,19 @@ pub(crate) async fn execute_js(
         }
     }?;
 
+    if let Some(module_evaluation) = module_evaluation {
+        module_evaluation.await?;
+    }
+
     Ok(())
 }
 
+fn is_import_outside_module_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<JsError>()
+        .and_then(|e| e.exception_message.strip_prefix("Uncaught SyntaxError: "))
+        .is_some_and(|msg| msg.starts_with(IMPORT_OUTSIDE_MODULE_ERROR))
+}
+
 fn start_controller_thread(
     js_runtime: &mut JsRuntime,
     worker_timeout_ms: u64,
@@ -69,6 +69,7 @@ impl Action for Server {
                     timeout,
                     memory_limit,
                     http_headers,
+                    typescript,
                 })) => {
                     std::thread::spawn(move || {
                         let rt = tokio::runtime::Builder::new_current_thread()
@@ -79,6 +80,7 @@ impl Action for Server {
                         rt.block_on(with_context(tracer.clone(), async move {
                             let res = crate::runtime::execute_js(
                                 code,
+                                typescript,
                                 js_params.and_then(|v| serde_json::from_slice(&v).ok()),
                                 auth_context.and_then(|v| serde_json::from_slice(&v).ok()),
                                 http_headers,
@@ -0,0 +1,158 @@
+//! TypeScript support for Lit Actions.
+//!
+//! TypeScript code is transpiled to JavaScript in-process with deno_ast. Whether
+//! code is TypeScript is either flagged in the execution request or detected by
+//! parsing it: code that isn't valid JavaScript but is valid TypeScript is
+//! transpiled. Results are cached by content hash, so actions loaded from IPFS
+//! are only parsed once per process.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context as _, Result};
+use deno_ast::{
+    EmitOptions, ImportsNotUsedAsValues, MediaType, ParseParams, SourceMapOption, TranspileOptions,
+};
+use deno_core::SourceMapGetter;
+use sha2::{Digest as _, Sha256};
+use tracing::debug;
+
+const MAX_CACHED_SOURCES: usize = 256;
+
+// Only used to name the source in parser errors and the source map
+const TYPESCRIPT_SPECIFIER: &str = "file:///$lit$user_provided_script.ts";
+
+/// Transpiled TypeScript code with the source map back to the original.
+#[derive(Debug)]
+pub(crate) struct Transpiled {
+    pub code: String,
+    source_map: Vec<u8>,
+    source: String,
+}
+
+#[derive(Default)]
+struct TranspileCache {
+    // None if the code is JavaScript
+    sources: HashMap<[u8; 32], Option<Arc<Transpiled>>>,
+    order: VecDeque<[u8; 32]>,
+}
+
+lazy_static::lazy_static! {
+    static ref TRANSPILE_CACHE: Mutex<TranspileCache> = Mutex::new(TranspileCache::default());
+}
+
+/// Transpile the code if it is TypeScript, returning `None` for JavaScript.
+/// When `typescript` is `None`, the language is detected from the code.
+pub(crate) fn transpile_if_typescript(
+    code: &str,
+    typescript: Option<bool>,
+) -> Result<Option<Arc<Transpiled>>> {
+    if typescript == Some(false) {
+        return Ok(None);
+    }
+
+    let hash: [u8; 32] = Sha256::digest(code.as_bytes()).into();
+    if let Some(cached) = TRANSPILE_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.sources.get(&hash).cloned())
+    {
+        // A cached detection result doesn't apply if the caller insists on TypeScript
+        if cached.is_some() || typescript.is_none() {
+            return Ok(cached);
+        }
+    }
+
+    let transpiled = match typescript {
+        Some(true) => Some(transpile(code)?),
+        _ if is_typescript(code) => Some(transpile(code)?),
+        // Invalid JavaScript ends up here as well, so that V8 reports the error
+        _ => None,
+    }
+    .map(Arc::new);
+
+    if let Ok(mut cache) = TRANSPILE_CACHE.lock() {
+        if !cache.sources.contains_key(&hash) {
+            while cache.order.len() >= MAX_CACHED_SOURCES {
+                if let Some(evicted) = cache.order.pop_front() {
+                    cache.sources.remove(&evicted);
+                }
+            }
+            cache.order.push_back(hash);
+        }
+        cache.sources.insert(hash, transpiled.clone());
+    }
+
+    Ok(transpiled)
+}
+
+fn parse(code: &str, media_type: MediaType) -> Result<deno_ast::ParsedSource> {
+    Ok(deno_ast::parse_program(ParseParams {
+        specifier: deno_core::resolve_url(TYPESCRIPT_SPECIFIER)?,
+        text: code.into(),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })?)
+}
+
+fn is_typescript(code: &str) -> bool {
+    parse(code, MediaType::JavaScript).is_err() && parse(code, MediaType::TypeScript).is_ok()
+}
+
+fn transpile(code: &str) -> Result<Transpiled> {
+    debug!("Transpiling TypeScript code");
+
+    let emitted = parse(code, MediaType::TypeScript)
+        .context("Invalid TypeScript code")?
+        .transpile(
+            &TranspileOptions {
+                // Drop imports only used as types, e.g. of ipfs:// modules
+                imports_not_used_as_values: ImportsNotUsedAsValues::Remove,
+                ..Default::default()
+            },
+            &EmitOptions {
+                source_map: SourceMapOption::Separate,
+                inline_sources: false,
+                ..Default::default()
+            },
+        )
+        .context("Error transpiling TypeScript code")?
+        .into_source();
+
+    Ok(Transpiled {
+        code: emitted.text,
+        source_map: emitted
+            .source_map
+            .context("Missing source map of transpiled TypeScript code")?
+            .into_bytes(),
+        source: code.to_string(),
+    })
+}
+
+/// Maps stack traces of the transpiled code (run under any of `file_names`)
+/// back to the TypeScript source.
+pub(crate) struct TypeScriptSourceMap {
+    pub transpiled: Arc<Transpiled>,
+    pub file_names: &'static [&'static str],
+}
+
+impl SourceMapGetter for TypeScriptSourceMap {
+    fn get_source_map(&self, file_name: &str) -> Option<Vec<u8>> {
+        self.file_names
+            .contains(&file_name)
+            .then(|| self.transpiled.source_map.clone())
+    }
+
+    fn get_source_line(&self, file_name: &str, line_number: usize) -> Option<String> {
+        if !self.file_names.contains(&file_name) {
+            return None;
+        }
+        self.transpiled
+            .source
+            .lines()
+            .nth(line_number)
+            .map(String::from)
+    }
+}
@@ -194,6 +194,30 @@ impl TestClient {
                 self.messages.put(req);
                 self.messages.take::<EncryptBlsResponse>().into()
             }
+            UnionResponse::LoadModule(req) => {
+                self.messages.put(req);
+                self.messages.take::<LoadModuleResponse>().into()
+            }
+            UnionResponse::LoadWasm(req) => {
+                self.messages.put(req);
+                self.messages.take::<LoadWasmResponse>().into()
+            }
+            UnionResponse::SendTransaction(req) => {
+                self.messages.put(req);
+                self.messages.take::<SendTransactionResponse>().into()
+            }
+            UnionResponse::Storage(req) => {
+                self.messages.put(req);
+                self.messages.take::<StorageResponse>().into()
+            }
+            UnionResponse::RecordFetch(req) => {
+                self.messages.put(req);
+                self.messages.take::<RecordFetchResponse>().into()
+            }
+            UnionResponse::GetRandomness(req) => {
+                self.messages.put(req);
+                self.messages.take::<GetRandomnessResponse>().into()
+            }
             UnionResponse::Result(_) => unreachable!(), // handled in main loop
         }
     }
@@ -434,18 +458,464 @@ async fn fetch(mut client: TestClient) {
     };
 
     client
-        .respond_with(IncrementFetchCountResponse { fetch_count: 1 })
+        .respond_with(IncrementFetchCountResponse {
+            fetch_count: 1,
+            max_response_bytes: 1024,
+            timeout_ms: 5000,
+            remaining_bytes: 1024,
+            addrs: vec!["127.0.0.1".to_string()],
+        })
+        .respond_with(RecordFetchResponse {})
         .execute_js(code)
         .await
         .unwrap();
 
     assert_eq!(
         client.received::<IncrementFetchCountRequest>(),
-        IncrementFetchCountRequest {}
+        IncrementFetchCountRequest {
+            url: format!("{}/", mock_server.uri())
+        }
+    );
+    assert_eq!(
+        client.received::<RecordFetchRequest>(),
+        RecordFetchRequest {
+            url: format!("{}/", mock_server.uri()),
+            bytes: 0,
+            violation: "".to_string(),
+        }
+    );
+    assert!(client.received::<ExecutionResult>().success);
+}
+
+#[rstest]
+#[tokio::test]
+async fn fetch_response_too_large(mut client: TestClient) {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("a".repeat(100)))
+        .mount(&mock_server)
+        .await;
+
+    let code = formatdoc! {r#"
+        (async () => {{
+            await fetch("{uri}")
+        }})()
+        "#,
+        uri = &mock_server.uri()
+    };
+
+    // The body is read up to the limit, lit-node decides how to handle the violation
+    client
+        .respond_with(IncrementFetchCountResponse {
+            fetch_count: 1,
+            max_response_bytes: 10,
+            timeout_ms: 5000,
+            remaining_bytes: 1024,
+            addrs: vec!["127.0.0.1".to_string()],
+        })
+        .respond_with(RecordFetchResponse {})
+        .execute_js(code)
+        .await
+        .unwrap();
+
+    let req = client.received::<RecordFetchRequest>();
+    assert_eq!(req.violation, "response_too_large");
+    assert!(req.bytes > 10);
+    assert!(client.received::<ExecutionResult>().success);
+}
+
+// The host is never resolved by the actions server, only the address checked by
+// lit-node is connected to (.invalid would not resolve, see RFC 2606)
+#[rstest]
+#[tokio::test]
+async fn fetch_connects_to_checked_address(mut client: TestClient) {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("pinned"))
+        .mount(&mock_server)
+        .await;
+    let url = format!(
+        "http://lit-egress-test.invalid:{}/",
+        mock_server.address().port()
+    );
+
+    client
+        .respond_with(IncrementFetchCountResponse {
+            fetch_count: 1,
+            max_response_bytes: 1024,
+            timeout_ms: 5000,
+            remaining_bytes: 1024,
+            addrs: vec![mock_server.address().ip().to_string()],
+        })
+        .respond_with(RecordFetchResponse {})
+        .respond_with(SetResponseResponse {})
+        .execute_js(formatdoc! {r#"
+            (async () => {{
+                const text = await fetch("{url}").then(r => r.text())
+                Lit.Actions.setResponse({{ response: text }})
+            }})()
+            "#
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.received::<IncrementFetchCountRequest>(),
+        IncrementFetchCountRequest { url: url.clone() }
+    );
+    assert_eq!(
+        client.received::<RecordFetchRequest>(),
+        RecordFetchRequest {
+            url,
+            bytes: 6,
+            violation: "".to_string(),
+        }
+    );
+    assert_eq!(client.received::<SetResponseRequest>().response, "pinned");
+    assert!(client.received::<ExecutionResult>().success);
+}
+
+// Sockets other than fetch are denied by the net permission, so they can't
+// bypass lit-node's egress policy
+#[rstest]
+#[tokio::test]
+async fn net_permission_denied(mut client: TestClient) {
+    client
+        .respond_with(SetResponseResponse {})
+        .execute_js(indoc! {r#"
+            (async () => {
+                const attempts = [
+                    () => Deno.connect({ hostname: "127.0.0.1", port: 80 }),
+                    () => Deno.connectTls({ hostname: "127.0.0.1", port: 443 }),
+                    () => new WebSocket("ws://127.0.0.1:80"),
+                ];
+                const errors = [];
+                for (const attempt of attempts) {
+                    try {
+                        await attempt();
+                        errors.push("allowed");
+                    } catch (e) {
+                        errors.push(e.name);
+                    }
+                }
+                Lit.Actions.setResponse({ response: errors.join(",") });
+            })()
+        "#})
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.received::<SetResponseRequest>().response,
+        "PermissionDenied,PermissionDenied,PermissionDenied"
+    );
+    assert!(client.received::<ExecutionResult>().success);
+}
+
+#[rstest]
+#[tokio::test]
+async fn send_transaction(mut client: TestClient) {
+    client
+        .respond_with(SendTransactionResponse {
+            tx_hash: "0xabcd".to_string(),
+        })
+        .respond_with(SetResponseResponse {})
+        .execute_js(indoc! {r#"
+            (async () => {
+                const txHash = await Lit.Actions.sendTransaction({
+                    chain: "ethereum",
+                    publicKey: "0x1234",
+                    tx: {
+                        to: "0x000000000000000000000000000000000000dead",
+                        value: ethers.utils.parseEther("1"),
+                        gasLimit: 21000,
+                        maxFeePerGas: 100n,
+                    },
+                });
+                Lit.Actions.setResponse({ response: txHash });
+            })();
+        "#})
+        .await
+        .unwrap();
+
+    let req = client.received::<SendTransactionRequest>();
+    assert_eq!(req.chain, "ethereum");
+    assert_eq!(req.public_key, "0x1234");
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&req.tx).unwrap(),
+        serde_json::json!({
+            "to": "0x000000000000000000000000000000000000dead",
+            "value": "0x0de0b6b3a7640000",
+            "gas": "0x5208",
+            "maxFeePerGas": "0x64",
+        })
+    );
+    assert_eq!(client.received::<SetResponseRequest>().response, "0xabcd");
+    assert!(client.received::<ExecutionResult>().success);
+}
+
+#[rstest]
+#[tokio::test]
+async fn get_randomness(mut client: TestClient) {
+    client
+        .respond_with(GetRandomnessResponse {
+            round: 57000000,
+            signature: "a1b2".to_string(),
+            randomness: "c3d4".to_string(),
+        })
+        .respond_with(SetResponseResponse {})
+        .execute_js(indoc! {r#"
+            (async () => {
+                const { round, randomness } = await Lit.Actions.getRandomness({ round: 57000000 });
+                Lit.Actions.setResponse({ response: `${round}:${randomness}` });
+            })();
+        "#})
+        .await
+        .unwrap();
+
+    assert_eq!(client.received::<GetRandomnessRequest>().round, 57000000);
+    assert_eq!(
+        client.received::<SetResponseRequest>().response,
+        "57000000:c3d4"
     );
     assert!(client.received::<ExecutionResult>().success);
 }
 
+#[rstest]
+#[tokio::test]
+async fn storage(mut client: TestClient) {
+    client
+        .respond_with(StorageResponse {
+            result: br#"{"value":"41","version":3}"#.to_vec(),
+        })
+        .respond_with(StorageResponse {
+            result: b"4".to_vec(),
+        })
+        .respond_with(SetResponseResponse {})
+        .execute_js(indoc! {r#"
+            (async () => {
+                const publicKey = "0x1234";
+                const { value, version } = await Lit.Actions.storage.get({ publicKey, key: "counter" });
+                const newVersion = await Lit.Actions.storage.put({
+                    publicKey,
+                    key: "counter",
+                    value: String(Number(value) + 1),
+                    expectedVersion: version,
+                });
+                Lit.Actions.setResponse({ response: String(newVersion) });
+            })();
+        "#})
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.received::<StorageRequest>(),
+        StorageRequest {
+            method: "get".to_string(),
+            public_key: "0x1234".to_string(),
+            key: "counter".to_string(),
+            value: None,
+            expected_version: None,
+        }
+    );
+    assert_eq!(
+        client.received::<StorageRequest>(),
+        StorageRequest {
+            method: "put".to_string(),
+            public_key: "0x1234".to_string(),
+            key: "counter".to_string(),
+            value: Some("42".to_string()),
+            expected_version: Some(3),
+        }
+    );
+    assert_eq!(client.received::<SetResponseRequest>().response, "4");
+    assert!(client.received::<ExecutionResult>().success);
+
+    // Errors from lit-node, e.g. a version mismatch
+    let res = client
+        .respond_with(ErrorResponse {
+            error: "Storage version mismatch for counter: expected 0, found 4".to_string(),
+        })
+        .execute_js(indoc! {r#"
+            Lit.Actions.storage.put({ publicKey: "0x1234", key: "counter", value: "1", expectedVersion: 0 });
+        "#})
+        .await;
+
+    assert_eq!(
+        client.received::<StorageRequest>().expected_version,
+        Some(0)
+    );
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .contains("Storage version mismatch for counter"));
+    assert!(!client.received::<ExecutionResult>().success);
+}
+
+#[rstest]
+#[tokio::test]
+async fn consensus_fetch(mut client: TestClient) {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"price": 101}"#))
+        .mount(&mock_server)
+        .await;
+
+    // `leader` is the set the leader collected, when this node isn't the leader
+    for (mode, collected, leader, expected) in [
+        (
+            "majority",
+            vec![r#"{"value":101}"#, r#"{"value":101}"#, r#"{"value":99}"#],
+            None,
+            Ok("101"),
+        ),
+        (
+            "median",
+            vec![
+                r#"{"value":105}"#,
+                r#"{"value":99}"#,
+                r#"{"error":"Error: HTTP status 500"}"#,
+                r#"{"value":101}"#,
+            ],
+            None,
+            Ok("101"),
+        ),
+        (
+            "majority",
+            vec![r#"{"value":101}"#, r#"{"value":99}"#, r#"{"value":100}"#],
+            None,
+            Err("consensusFetch: nodes disagree, at most 1 of 3 returned the same value, 2 required"),
+        ),
+        (
+            "median",
+            vec![
+                r#"{"value":101}"#,
+                r#"{"error":"Error: HTTP status 500"}"#,
+                r#"{"error":"Error: HTTP status 500"}"#,
+            ],
+            None,
+            Err("consensusFetch: only 1 of 3 nodes returned a value, 2 required (2 node(s) failed, e.g. Error: HTTP status 500)"),
+        ),
+        // Objects agree whatever their key order
+        (
+            "majority",
+            vec![
+                r#"{"value":{"usd":101,"eur":93}}"#,
+                r#"{"value":{"eur":93,"usd":101}}"#,
+                r#"{"value":99}"#,
+            ],
+            None,
+            Ok(r#"{"usd":101,"eur":93}"#),
+        ),
+        // Followers reduce the leader's set rather than their own
+        (
+            "median",
+            vec![r#"{"value":101}"#],
+            Some(vec![r#"{"value":105}"#, r#"{"value":99}"#, r#"{"value":103}"#]),
+            Ok("103"),
+        ),
+        (
+            "majority",
+            vec![r#"{"value":101}"#, r#"{"value":101}"#, r#"{"value":99}"#],
+            Some(vec![r#"{"value":101}"#, r#"{"value":99}"#, r#"{"value":100}"#]),
+            Err("consensusFetch: nodes disagree, at most 1 of 3 returned the same value, 2 required"),
+        ),
+    ] {
+        let collected: Vec<String> = collected.into_iter().map(String::from).collect();
+        client
+            .respond_with(IncrementFetchCountResponse {
+                fetch_count: 1,
+                max_response_bytes: 1024,
+                timeout_ms: 5000,
+                remaining_bytes: 1024,
+                addrs: vec!["127.0.0.1".to_string()],
+            })
+            .respond_with(RecordFetchResponse {})
+            .respond_with(BroadcastAndCollectResponse {
+                name: "consensusFetch_0".to_string(),
+                values: collected.clone(),
+            })
+            .respond_with(IsLeaderResponse {
+                result: leader.is_none(),
+            });
+        match &leader {
+            None => {
+                client.respond_with(P2pBroadcastResponse { result: true });
+            }
+            Some(leader) => {
+                client.respond_with(P2pCollectFromLeaderResponse {
+                    name: "consensusFetch_0_leader".to_string(),
+                    value: serde_json::to_string(leader).unwrap(),
+                });
+            }
+        }
+        if expected.is_ok() {
+            client.respond_with(SetResponseResponse {});
+        }
+
+        let res = client
+            .execute_js(formatdoc! {r#"
+                (async () => {{
+                    const price = await Lit.Actions.consensusFetch({{
+                        url: "{uri}",
+                        extract: (body) => body.price,
+                        mode: "{mode}",
+                    }});
+                    Lit.Actions.setResponse({{ response: JSON.stringify(price) }});
+                }})();
+                "#,
+                uri = &mock_server.uri()
+            })
+            .await;
+
+        assert_eq!(
+            client.received::<IncrementFetchCountRequest>(),
+            IncrementFetchCountRequest {
+                url: format!("{}/", mock_server.uri())
+            }
+        );
+        assert_eq!(
+            client.received::<BroadcastAndCollectRequest>(),
+            BroadcastAndCollectRequest {
+                name: "consensusFetch_0".to_string(),
+                value: r#"{"value":101}"#.to_string(),
+            }
+        );
+        match &leader {
+            None => assert_eq!(
+                client.received::<P2pBroadcastRequest>(),
+                P2pBroadcastRequest {
+                    name: "consensusFetch_0_leader".to_string(),
+                    value: serde_json::to_string(&collected).unwrap(),
+                }
+            ),
+            Some(_) => assert_eq!(
+                client.received::<P2pCollectFromLeaderRequest>().name,
+                "consensusFetch_0_leader"
+            ),
+        }
+        match expected {
+            Ok(response) => {
+                res.unwrap();
+                assert_eq!(client.received::<SetResponseRequest>().response, response);
+                assert!(client.received::<ExecutionResult>().success);
+            }
+            Err(error) => {
+                assert!(res.unwrap_err().to_string().contains(error));
+                assert!(!client.received::<ExecutionResult>().success);
+            }
+        }
+    }
+}
+
 #[rstest]
 #[tokio::test]
 async fn pkp_get_permitted(mut client: TestClient) {
@@ -830,6 +1300,238 @@ async fn call_child(mut client: TestClient) {
     }
 }
 
+#[rstest]
+#[tokio::test]
+async fn import_module(mut client: TestClient) {
+    const LIB: &str = "export const greet = name => `Hello, ${name}!`;";
+
+    // Static import
+    {
+        client
+            .respond_with(LoadModuleResponse {
+                code: LIB.to_string(),
+            })
+            .respond_with(SetResponseResponse {})
+            .execute_js(indoc! {r#"
+                import { greet } from "ipfs://QmGreet";
+                Lit.Actions.setResponse({ response: greet("Lit") });
+            "#})
+            .await
+            .unwrap();
+
+        assert_eq!(
+            client.received::<LoadModuleRequest>(),
+            LoadModuleRequest {
+                cid: "QmGreet".to_string(),
+            }
+        );
+        assert_eq!(
+            client.received::<SetResponseRequest>().response,
+            "Hello, Lit!"
+        );
+        assert!(client.received::<ExecutionResult>().success);
+    }
+
+    // Dynamic import from a classic script
+    {
+        client
+            .respond_with(LoadModuleResponse {
+                code: LIB.to_string(),
+            })
+            .respond_with(SetResponseResponse {})
+            .execute_js(indoc! {r#"
+                (async () => {
+                    const { greet } = await import("ipfs://QmGreet");
+                    Lit.Actions.setResponse({ response: greet("Lit") });
+                })();
+            "#})
+            .await
+            .unwrap();
+
+        assert_eq!(client.received::<LoadModuleRequest>().cid, "QmGreet");
+        assert_eq!(
+            client.received::<SetResponseRequest>().response,
+            "Hello, Lit!"
+        );
+        assert!(client.received::<ExecutionResult>().success);
+    }
+
+    // Only ipfs://<CID> specifiers are allowed
+    for specifier in [
+        "https://example.com/lib.js",
+        "./lib.js",
+        "ipfs://QmDir/lib.js",
+    ] {
+        let res = client
+            .execute_js(format!(r#"import {{ greet }} from "{specifier}";"#))
+            .await;
+
+        assert!(res.is_err(), "{specifier} should not be importable");
+        assert!(!client.received::<ExecutionResult>().success);
+    }
+
+    // Errors from lit-node, e.g. a CID that isn't allowlisted
+    {
+        let res = client
+            .respond_with(ErrorResponse {
+                error: "Module ipfs://QmGreet is not allowlisted".to_string(),
+            })
+            .execute_js(r#"import { greet } from "ipfs://QmGreet";"#)
+            .await;
+
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("Module ipfs://QmGreet is not allowlisted"));
+        assert!(!client.received::<ExecutionResult>().success);
+    }
+}
+
+#[rstest]
+#[tokio::test]
+async fn instantiate_wasm(mut client: TestClient) {
+    // (module (func (export "add") (param i32 i32) (result i32)
+    //   local.get 0 local.get 1 i32.add))
+    const ADD_WASM: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+        0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // type section
+        0x03, 0x02, 0x01, 0x00, // function section
+        0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, // export section
+        0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b, // code section
+    ];
+    let code = indoc! {r#"
+        (async () => {
+            const instance = await Lit.Actions.instantiateWasm({ ipfsId: "QmWasmAdd" });
+            Lit.Actions.setResponse({ response: String(instance.exports.add(2, 3)) });
+        })();
+    "#};
+
+    client
+        .respond_with(LoadWasmResponse {
+            code: ADD_WASM.to_vec(),
+        })
+        .respond_with(SetResponseResponse {})
+        .execute_js(code)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client.received::<LoadWasmRequest>(),
+        LoadWasmRequest {
+            cid: "QmWasmAdd".to_string(),
+        }
+    );
+    assert_eq!(client.received::<SetResponseRequest>().response, "5");
+    assert!(client.received::<ExecutionResult>().success);
+
+    // The compiled module is cached, so it's not requested again
+    client
+        .respond_with(SetResponseResponse {})
+        .execute_js(code)
+        .await
+        .unwrap();
+
+    assert_eq!(client.received::<SetResponseRequest>().response, "5");
+    assert!(client.received::<ExecutionResult>().success);
+}
+
+#[rstest]
+#[tokio::test]
+async fn wasm_oom(mut client: TestClient) {
+    // (module (memory 8192)), i.e. 512 MB
+    const BIG_MEMORY_WASM: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+        0x05, 0x04, 0x01, 0x00, 0x80, 0x40, // memory section
+    ];
+
+    let res = client
+        .respond_with(LoadWasmResponse {
+            code: BIG_MEMORY_WASM.to_vec(),
+        })
+        .execute_js(ExecutionRequest {
+            code: r#"Lit.Actions.instantiateWasm({ ipfsId: "QmWasmBigMemory" });"#.into(),
+            memory_limit: Some(100),
+            ..Default::default()
+        })
+        .await;
+    let status = res.unwrap_err().downcast::<Status>().unwrap();
+
+    assert_eq!(client.received::<LoadWasmRequest>().cid, "QmWasmBigMemory");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(
+        status.message(),
+        "Your function exceeded the maximum memory of 100 MB and was terminated."
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn wasm_memory_counted_across_instances(mut client: TestClient) {
+    // (module (memory 1 1000)), i.e. up to 62.5 MB per instance
+    const GROWABLE_MEMORY_WASM: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+        0x05, 0x05, 0x01, 0x01, 0x01, 0xe8, 0x07, // memory section
+    ];
+    let code = indoc! {r#"
+        (async () => {
+            await Lit.Actions.instantiateWasm({ ipfsId: "QmWasmGrowableMemory" });
+            await Lit.Actions.instantiateWasm({ ipfsId: "QmWasmGrowableMemory" });
+        })();
+    "#};
+
+    let res = client
+        .respond_with(LoadWasmResponse {
+            code: GROWABLE_MEMORY_WASM.to_vec(),
+        })
+        .execute_js(ExecutionRequest {
+            code: code.into(),
+            memory_limit: Some(100),
+            ..Default::default()
+        })
+        .await;
+    let status = res.unwrap_err().downcast::<Status>().unwrap();
+
+    assert_eq!(
+        client.received::<LoadWasmRequest>().cid,
+        "QmWasmGrowableMemory"
+    );
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[rstest]
+#[tokio::test]
+async fn wasm_timeout(mut client: TestClient) {
+    // (module (func (export "spin") (loop (br 0))))
+    const SPIN_WASM: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+        0x03, 0x02, 0x01, 0x00, // function section
+        0x07, 0x08, 0x01, 0x04, 0x73, 0x70, 0x69, 0x6e, 0x00, 0x00, // export section
+        0x0a, 0x09, 0x01, 0x07, 0x00, 0x03, 0x40, 0x0c, 0x00, 0x0b, 0x0b, // code section
+    ];
+    let code = indoc! {r#"
+        (async () => {
+            const instance = await Lit.Actions.instantiateWasm({ ipfsId: "QmWasmSpin" });
+            instance.exports.spin();
+        })();
+    "#};
+
+    let res = client
+        .respond_with(LoadWasmResponse {
+            code: SPIN_WASM.to_vec(),
+        })
+        .execute_js(ExecutionRequest {
+            code: code.into(),
+            timeout: Some(500),
+            ..Default::default()
+        })
+        .await;
+    let status = res.unwrap_err().downcast::<Status>().unwrap();
+
+    assert_eq!(client.received::<LoadWasmRequest>().cid, "QmWasmSpin");
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+}
+
 #[rstest]
 #[tokio::test]
 async fn call_contract(mut client: TestClient) {
@@ -1059,6 +1761,77 @@ async fn throw_error(mut client: TestClient) {
     }
 }
 
+#[rstest]
+#[tokio::test]
+async fn typescript(mut client: TestClient) {
+    // Detected from the code
+    {
+        let code = indoc! {r#"
+            interface Greeting {
+                text: string;
+            }
+            const greeting: Greeting = { text: "Hello" };
+            Lit.Actions.setResponse({ response: greeting.text as string });
+        "#};
+
+        client
+            .respond_with(SetResponseResponse {})
+            .execute_js(code)
+            .await
+            .unwrap();
+
+        assert_eq!(client.received::<SetResponseRequest>().response, "Hello");
+        assert!(client.received::<ExecutionResult>().success);
+    }
+
+    // Flagged in the request, with stack traces pointing to the TypeScript source
+    {
+        let code = indoc! {r#"
+            type Answer = number;
+
+            function fail(answer: Answer): never {
+                throw new Error(`boom ${answer}`);
+            }
+
+            fail(42);
+        "#};
+        let res = client
+            .execute_js(ExecutionRequest {
+                code: code.into(),
+                typescript: Some(true),
+                ..Default::default()
+            })
+            .await;
+
+        let err = res.unwrap_err().to_string();
+        assert!(err.starts_with("Uncaught Error: boom 42"), "{err}");
+        assert!(
+            err.contains("    throw new Error(`boom ${answer}`);"),
+            "{err}"
+        );
+        assert!(err.contains("at fail (<user_provided_script>:4:"), "{err}");
+        assert!(err.contains("at <user_provided_script>:7:"), "{err}");
+        assert_eq!(client.received::<ExecutionResult>().success, false);
+    }
+
+    // Plain JavaScript isn't transpiled
+    {
+        let res = client
+            .execute_js(ExecutionRequest {
+                code: "const x: number = 1;".into(),
+                typescript: Some(false),
+                ..Default::default()
+            })
+            .await;
+
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .starts_with("Uncaught SyntaxError"));
+        assert_eq!(client.received::<ExecutionResult>().success, false);
+    }
+}
+
 #[rstest]
 #[tokio::test]
 async fn timeout(mut client: TestClient) {
@@ -1130,7 +1903,7 @@ async fn panic_in_op(mut client: TestClient) {
 }
 
 // This includes an example for every Deno permission class except --allow-net,
-// which is the only permission allowed (see fetch test).
+// which is denied as well (see net_permission_denied test).
 // You can test what's allowed and denied by default via `deno repl --no-prompt`.
 #[rstest]
 #[tokio::test]
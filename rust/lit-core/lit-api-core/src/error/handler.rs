@@ -12,6 +12,11 @@ pub trait ApiError {
     fn handle(self) -> status::Custom<Value>;
     fn handle_with_details(self, details: impl AsRef<str>) -> status::Custom<Value>;
     fn handle_with_logs(self, logs: impl AsRef<str>) -> status::Custom<Value>;
+    /// Keep the status, `errorCode` and `message` of a response which predates the error
+    /// catalog (clients match on them); the problem document fields are still included.
+    fn handle_with_legacy_code(
+        self, status: Status, error_code: &str, message: impl AsRef<str>,
+    ) -> status::Custom<Value>;
 }
 
 impl ApiError for Error {
@@ -28,6 +33,17 @@ impl ApiError for Error {
         status.1["logs"] = logs.as_ref().into();
         status
     }
+
+    fn handle_with_legacy_code(
+        self, status: Status, error_code: &str, message: impl AsRef<str>,
+    ) -> status::Custom<Value> {
+        let mut res = handle_err(self);
+        res.0 = status;
+        res.1["status"] = status.code.into();
+        res.1["errorCode"] = error_code.into();
+        res.1["message"] = message.as_ref().into();
+        res
+    }
 }
 
 pub fn handle_err(err: Error) -> status::Custom<Value> {
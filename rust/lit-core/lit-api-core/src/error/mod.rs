@@ -33,4 +33,11 @@ pub(crate) enum EC {
     CoreApiHttpClientConnectFailed,
 }
 
+/// The catalog of codes defined by this crate (and `lit_core`).
+pub fn api_error_catalog() -> Vec<CodeCatalogEntry> {
+    let mut catalog = EC::catalog();
+    catalog.extend(core_error_catalog());
+    catalog
+}
+
 generate_pkg_constructors!(PKG_NAME);
@@ -22,6 +22,7 @@ use crate::error::handler::ApiError;
 use crate::error::Result;
 use crate::error::{validation_err_code, EC};
 use crate::http::rocket::event::{Event, EventDataKey, EventManager};
+use crate::http::rocket::problem::ProblemJson;
 use crate::http::tls::certs::{ep_certs, tls_config_load, CertManager};
 use lit_core::config::{LitConfig, ReloadableLitConfig};
 
@@ -242,6 +243,7 @@ fn build_rocket(cfg: &LitConfig, is_https: bool, is_aux_http: bool) -> Result<Ro
     }
 
     r = r.register("/", catchers![guard_failure_catcher]);
+    r = r.attach(ProblemJson);
 
     Ok(r)
 }
@@ -3,3 +3,4 @@ pub mod event;
 #[cfg(feature = "rocket-helper")]
 pub mod helper;
 pub mod launcher;
+pub mod problem;
@@ -0,0 +1,26 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::{Request, Response};
+
+use lit_core::error::PROBLEM_JSON_CONTENT_TYPE;
+
+/// Marks JSON error responses (which are `PublicError` documents) as
+/// `application/problem+json`.
+pub struct ProblemJson;
+
+#[async_trait]
+impl Fairing for ProblemJson {
+    fn info(&self) -> Info {
+        Info { name: "Problem JSON", kind: Kind::Response }
+    }
+
+    async fn on_response<'r>(&self, _req: &'r Request<'_>, res: &mut Response<'r>) {
+        if res.status().code < 400 || !res.content_type().map(|ct| ct.is_json()).unwrap_or(false) {
+            return;
+        }
+
+        if let Some(ct) = ContentType::parse_flexible(PROBLEM_JSON_CONTENT_TYPE) {
+            res.set_header(ct);
+        }
+    }
+}
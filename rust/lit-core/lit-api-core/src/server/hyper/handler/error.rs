@@ -1,8 +1,9 @@
+use hyper::header::CONTENT_TYPE;
 use hyper::{Body, Response, StatusCode};
 use log::{as_error, error};
 use serde_json::json;
 
-use crate::error::{err_to_public_error, Error};
+use crate::error::{err_to_public_error, Error, PROBLEM_JSON_CONTENT_TYPE};
 
 pub trait ApiError {
     fn handle(self) -> Response<Body>;
@@ -32,7 +33,10 @@ pub fn handle_err_with_details(err: Error, details: Option<String>) -> Response<
     let public_json = public.to_json();
     match public_json {
         Ok(json) => {
-            let err_resp = Response::builder().status(status).body(Body::from(json.to_string()));
+            let err_resp = Response::builder()
+                .status(status)
+                .header(CONTENT_TYPE, PROBLEM_JSON_CONTENT_TYPE)
+                .body(Body::from(json.to_string()));
             if let Ok(resp) = err_resp {
                 return resp;
             }
@@ -1,22 +1,44 @@
-use crate::error::Result;
+use crate::error::{config_err, Result};
+use crate::transparency::types::ReleaseLogHead;
 use std::env;
 
 use lit_core::config::{LitConfig, LitConfigBuilder};
 use std::path::PathBuf;
 
 const ATTESTATION_SERVICE_SOCK_PATH: &str = "/var/run/lit-attestation-service.sock";
+const RELEASE_LOG_HEAD_PATH: &str = "/var/lib/lit/attestation/release-log-head.json";
+
+pub const CFG_KEY_ATTESTATION_KDF_VERSION: &str = "attestation.kdf.version";
+pub const DEFAULT_ATTESTATION_KDF_VERSION: i64 = 0;
+pub const CFG_KEY_ATTESTATION_RELEASE_LOG_REQUIRE_INCLUSION: &str =
+    "attestation.release_log.require_inclusion";
+pub const CFG_KEY_ATTESTATION_RELEASE_LOG_URL: &str = "attestation.release_log.url";
+pub const CFG_KEY_ATTESTATION_RELEASE_LOG_PUBLIC_KEY: &str = "attestation.release_log.public_key";
+pub const CFG_KEY_ATTESTATION_RELEASE_LOG_HEAD_PATH: &str = "attestation.release_log.head_path";
+pub const CFG_KEY_ATTESTATION_RELEASE_LOG_TRUSTED_TREE_SIZE: &str =
+    "attestation.release_log.trusted_head.tree_size";
+pub const CFG_KEY_ATTESTATION_RELEASE_LOG_TRUSTED_ROOT_HASH: &str =
+    "attestation.release_log.trusted_head.root_hash";
 
 pub const ENV_ATTESTATION_SERVICE_SOCK_PATH: &str = "LIT_ATTESTATION_SERVICE_SOCK_PATH";
 
 pub trait LitAttestationConfig {
     fn apply_defaults(builder: LitConfigBuilder) -> Result<LitConfigBuilder>;
     fn attestation_service_socket_path(&self) -> PathBuf;
+    fn kdf_version(&self) -> Result<u32>;
+    fn release_log_require_inclusion(&self) -> bool;
+    fn release_log_url(&self) -> Result<String>;
+    fn release_log_public_key(&self) -> Result<String>;
+    fn release_log_head_path(&self) -> PathBuf;
+    fn release_log_trusted_head(&self) -> Result<ReleaseLogHead>;
 }
 
 impl LitAttestationConfig for LitConfig {
     #[inline]
-    fn apply_defaults(builder: LitConfigBuilder) -> Result<LitConfigBuilder> {
+    fn apply_defaults(mut builder: LitConfigBuilder) -> Result<LitConfigBuilder> {
         // Set defaults
+        builder =
+            builder.set_default(CFG_KEY_ATTESTATION_KDF_VERSION, DEFAULT_ATTESTATION_KDF_VERSION);
 
         Ok(builder)
     }
@@ -24,6 +46,67 @@ impl LitAttestationConfig for LitConfig {
     fn attestation_service_socket_path(&self) -> PathBuf {
         attestation_service_socket_path()
     }
+
+    /// The current KDF key version (bumped to rotate derived keys).
+    ///
+    /// Invalid values are rejected, as deriving with the wrong version gives the wrong keys.
+    fn kdf_version(&self) -> Result<u32> {
+        // Not every consumer applies our defaults (i.e. the initrd), so fall back here too.
+        if self.get_string(CFG_KEY_ATTESTATION_KDF_VERSION).is_err() {
+            return Ok(DEFAULT_ATTESTATION_KDF_VERSION as u32);
+        }
+
+        let version = self.get_int(CFG_KEY_ATTESTATION_KDF_VERSION)?;
+        u32::try_from(version).map_err(|e| {
+            config_err(e, Some(format!("invalid {CFG_KEY_ATTESTATION_KDF_VERSION}: {version}")))
+        })
+    }
+
+    /// Whether verified releases must be included in the release log (off by default).
+    fn release_log_require_inclusion(&self) -> bool {
+        self.get_bool(CFG_KEY_ATTESTATION_RELEASE_LOG_REQUIRE_INCLUSION).unwrap_or(false)
+    }
+
+    /// The base url of the release log (i.e. the prov api).
+    fn release_log_url(&self) -> Result<String> {
+        self.get_checked_string(CFG_KEY_ATTESTATION_RELEASE_LOG_URL)
+    }
+
+    /// The public key (hex) of the release log key which signs the log heads (not the prov
+    /// wallet key which issues the releases).
+    fn release_log_public_key(&self) -> Result<String> {
+        self.get_checked_string(CFG_KEY_ATTESTATION_RELEASE_LOG_PUBLIC_KEY)
+    }
+
+    /// Where the largest release log head seen is kept (so it survives a restart).
+    fn release_log_head_path(&self) -> PathBuf {
+        self.get_string(CFG_KEY_ATTESTATION_RELEASE_LOG_HEAD_PATH)
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(RELEASE_LOG_HEAD_PATH))
+    }
+
+    /// The release log head shipped with the config, which every head seen must be consistent
+    /// with until a larger one is stored (heads are not trusted on first use).
+    fn release_log_trusted_head(&self) -> Result<ReleaseLogHead> {
+        let tree_size = self.get_int(CFG_KEY_ATTESTATION_RELEASE_LOG_TRUSTED_TREE_SIZE)?;
+        let tree_size = u64::try_from(tree_size).map_err(|e| {
+            config_err(
+                e,
+                Some(format!(
+                    "invalid {CFG_KEY_ATTESTATION_RELEASE_LOG_TRUSTED_TREE_SIZE}: {tree_size}"
+                )),
+            )
+        })?;
+        let head = ReleaseLogHead {
+            tree_size,
+            root_hash: self
+                .get_checked_string(CFG_KEY_ATTESTATION_RELEASE_LOG_TRUSTED_ROOT_HASH)?,
+            signature: None,
+        };
+        head.root_hash_bytes()?;
+
+        Ok(head)
+    }
 }
 
 pub fn attestation_service_socket_path() -> PathBuf {
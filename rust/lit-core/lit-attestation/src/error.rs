@@ -44,6 +44,9 @@ pub(crate) enum EC {
     /// The auth header of a request has an invalid body hash.
     #[code(kind = Validation, http_status = 403)]
     AttestationRequestAuthBodyHashInvalid,
+    /// The verification of a release against the release log has failed.
+    #[code(kind = Validation, http_status = 403)]
+    AttestationReleaseLogVerifyFailed,
 }
 
 generate_pkg_constructors!(PKG_NAME, pub(crate), EC);
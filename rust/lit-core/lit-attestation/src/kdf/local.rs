@@ -0,0 +1,159 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::{env, fmt};
+
+use async_trait::async_trait;
+use blake3::derive_key;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+use crate::error::{io_err, validation_err, Result};
+use crate::kdf::provider::KdfProvider;
+use crate::kdf::{build_context, KdfType};
+
+pub const ENV_KDF_LOCAL_KEY_PATH: &str = "LIT_KDF_LOCAL_KEY_PATH";
+
+const KEY_FILE_MAGIC: &[u8; 8] = b"LITKDF01";
+const KEY_FILE_SEED_LEN: usize = 32;
+const KEY_FILE_LEN: usize = KEY_FILE_MAGIC.len() + KEY_FILE_SEED_LEN + blake3::OUT_LEN;
+const CHECKSUM_CONTEXT: &str = "lit-attestation kdf local seal v1";
+const LEGACY_KEY_MATERIAL: &[u8] = b"local-key-material";
+
+/// Software (TPM-less) KDF provider, only permitted in the dev env.
+///
+/// Key material is either a random seed held in a local key file (see
+/// [`LocalKdfProvider::from_key_file`]) or, when no file is configured, the fixed
+/// legacy material used before providers were pluggable.  The key file is not encrypted,
+/// only checksummed and readable by its owner, so it offers no protection beyond file
+/// permissions.
+#[derive(Clone)]
+pub struct LocalKdfProvider {
+    key_material: Vec<u8>,
+    path: Option<PathBuf>,
+}
+
+impl LocalKdfProvider {
+    /// Uses the key file at `LIT_KDF_LOCAL_KEY_PATH` when set, otherwise the legacy material.
+    pub fn from_env() -> Result<Self> {
+        match env::var(ENV_KDF_LOCAL_KEY_PATH) {
+            Ok(path) if !path.is_empty() => Self::from_key_file(path),
+            _ => Ok(Self::legacy()),
+        }
+    }
+
+    pub fn legacy() -> Self {
+        Self { key_material: LEGACY_KEY_MATERIAL.to_vec(), path: None }
+    }
+
+    /// Load the seed from a key file, creating a fresh one if it is missing.
+    pub fn from_key_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let seed =
+            match path.exists() {
+                true => decode_key_file(&fs::read(path).map_err(|e| {
+                    io_err(e, Some(format!("failed to read kdf key file: {path:?}")))
+                })?)?,
+                false => {
+                    let mut seed = vec![0u8; KEY_FILE_SEED_LEN];
+                    ChaChaRng::from_entropy().fill_bytes(&mut seed);
+
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .mode(0o600)
+                        .open(path)
+                        .map_err(|e| {
+                            io_err(e, Some(format!("failed to create kdf key file: {path:?}")))
+                        })?;
+                    file.write_all(&encode_key_file(&seed)).map_err(|e| {
+                        io_err(e, Some(format!("failed to write kdf key file: {path:?}")))
+                    })?;
+
+                    seed
+                }
+            };
+
+        Ok(Self { key_material: seed, path: Some(path.to_path_buf()) })
+    }
+
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+}
+
+impl fmt::Debug for LocalKdfProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalKdfProvider").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl KdfProvider for LocalKdfProvider {
+    fn typ(&self) -> KdfType {
+        KdfType::Local
+    }
+
+    async fn derive_key(&self, namespace: &str, version: u32, context: &str) -> Result<[u8; 32]> {
+        Ok(derive_key(&build_context(namespace, version, context)?, &self.key_material[..]))
+    }
+}
+
+// Util
+
+fn encode_key_file(seed: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(KEY_FILE_LEN);
+    encoded.extend_from_slice(KEY_FILE_MAGIC);
+    encoded.extend_from_slice(seed);
+    encoded.extend_from_slice(&derive_key(CHECKSUM_CONTEXT, seed));
+    encoded
+}
+
+fn decode_key_file(encoded: &[u8]) -> Result<Vec<u8>> {
+    if encoded.len() != KEY_FILE_LEN || !encoded.starts_with(KEY_FILE_MAGIC) {
+        return Err(validation_err("kdf key file has an invalid format", None));
+    }
+
+    let seed = &encoded[KEY_FILE_MAGIC.len()..KEY_FILE_MAGIC.len() + KEY_FILE_SEED_LEN];
+    if derive_key(CHECKSUM_CONTEXT, seed)[..] != encoded[KEY_FILE_MAGIC.len() + KEY_FILE_SEED_LEN..]
+    {
+        return Err(validation_err("kdf key file failed the checksum", None));
+    }
+
+    Ok(seed.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kdf::local::{decode_key_file, encode_key_file, LocalKdfProvider};
+    use crate::kdf::provider::KdfProvider;
+
+    #[tokio::test]
+    async fn test_key_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kdf.key");
+
+        let created = LocalKdfProvider::from_key_file(&path).expect("failed to create");
+        let loaded = LocalKdfProvider::from_key_file(&path).expect("failed to load");
+
+        assert_eq!(
+            created.derive_key("ns", 0, "ctx").await.unwrap(),
+            loaded.derive_key("ns", 0, "ctx").await.unwrap()
+        );
+        assert_ne!(
+            created.derive_key("ns", 0, "ctx").await.unwrap(),
+            LocalKdfProvider::legacy().derive_key("ns", 0, "ctx").await.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_file_rejects_tampering() {
+        let mut encoded = encode_key_file(&[7u8; 32]);
+        assert_eq!(decode_key_file(&encoded).unwrap(), vec![7u8; 32]);
+
+        encoded[10] ^= 0xff;
+        assert!(decode_key_file(&encoded).is_err());
+        assert!(decode_key_file(&encoded[..20]).is_err());
+    }
+}
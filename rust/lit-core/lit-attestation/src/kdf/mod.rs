@@ -0,0 +1,264 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::{env, fmt};
+
+use aleo_std_cpu::{get_cpu, Cpu};
+use lit_core::config::LitConfig;
+use lit_core::error::Unexpected;
+use serde::{Deserialize, Serialize};
+
+use crate::config::LitAttestationConfig;
+use crate::error::{validation_err, Result};
+use crate::kdf::local::LocalKdfProvider;
+use crate::kdf::provider::KdfProvider;
+use crate::kdf::sev_snp::SevSnpKdfProvider;
+
+pub mod local;
+pub mod provider;
+pub mod sev_snp;
+
+pub const ENV_KDF_TYPE_OVERRIDE: &str = "LIT_KDF_TYPE_OVERRIDE";
+
+pub const KDF_NS_SEPARATOR: &str = "::";
+/// Prefixes the contexts the attestation service derives for its clients, so that they can't
+/// derive the keys the guest derives for itself.
+pub const KDF_SERVICE_NS_PREFIX: &str = "AS";
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[allow(unused)]
+pub enum KdfType {
+    AmdSevSnp,
+    Local,
+}
+
+impl KdfType {
+    pub fn from_system() -> Option<KdfType> {
+        if let Ok(typ) = env::var(ENV_KDF_TYPE_OVERRIDE) {
+            return match typ.as_str() {
+                "AMD_SEV_SNP" => Some(Self::AmdSevSnp),
+                "LOCAL" => Some(Self::Local),
+                _ => None,
+            };
+        }
+
+        match get_cpu() {
+            Cpu::AMD => Some(Self::AmdSevSnp),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KdfType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KdfType::AmdSevSnp => write!(f, "AMD_SEV_SNP"),
+            KdfType::Local => write!(f, "LOCAL"),
+        }
+    }
+}
+
+impl FromStr for KdfType {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "AMD_SEV_SNP" => Ok(KdfType::AmdSevSnp),
+            "LOCAL" => Ok(KdfType::Local),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Kdf {
+    typ: KdfType,
+    namespace: String,
+    version: u32,
+    provider: Arc<dyn KdfProvider>,
+}
+
+impl Kdf {
+    pub fn try_new(cfg: &LitConfig, typ: KdfType, namespace: Option<String>) -> Result<Self> {
+        let provider: Arc<dyn KdfProvider> = match typ {
+            KdfType::AmdSevSnp => Arc::new(SevSnpKdfProvider::new()),
+            KdfType::Local => Arc::new(LocalKdfProvider::from_env()?),
+        };
+
+        Self::try_new_with_provider(cfg, provider, namespace)
+    }
+
+    /// Construct with an explicit provider (the namespace and version rules still apply).
+    pub fn try_new_with_provider(
+        cfg: &LitConfig, provider: Arc<dyn KdfProvider>, namespace: Option<String>,
+    ) -> Result<Self> {
+        let typ = provider.typ();
+        if matches!(typ, KdfType::Local) && !cfg.is_dev() {
+            return Err(validation_err("KDF type of LOCAL is only allowed for env dev", None));
+        }
+
+        let namespace = namespace.unwrap_or_else(|| cfg.key().to_string());
+        validate_namespace(&namespace)?;
+
+        Ok(Self { typ, namespace, version: cfg.kdf_version()?, provider })
+    }
+
+    /// Construct with the KDF type appropriate for this system (LOCAL outside of a guest).
+    pub fn try_new_for_cfg(cfg: &LitConfig, namespace: Option<String>) -> Result<Self> {
+        let kdf_type = match cfg.litos_guest() {
+            Ok(true) => KdfType::from_system()
+                .expect_or_err("failed to determine KDF type for this guest")?,
+            _ => KdfType::Local,
+        };
+
+        Self::try_new(cfg, kdf_type, namespace)
+    }
+
+    /// Wrapper for new then derive.
+    pub async fn try_derive<S: AsRef<str>>(cfg: &LitConfig, context: S) -> Result<[u8; 32]> {
+        Self::try_new_for_cfg(cfg, None)?.derive(context.as_ref()).await
+    }
+
+    pub fn typ(&self) -> KdfType {
+        self.typ
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// The current key version, keys are derived with this version unless asked otherwise.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Override the current key version (i.e. following a rotation).
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Derive a key for the current version.
+    pub async fn derive<S: AsRef<str>>(&self, context: S) -> Result<[u8; 32]> {
+        self.derive_version(context, self.version).await
+    }
+
+    /// Derive a key for a specific version, which may not be newer than the current version.
+    pub async fn derive_version<S: AsRef<str>>(
+        &self, context: S, version: u32,
+    ) -> Result<[u8; 32]> {
+        if version > self.version {
+            return Err(validation_err(
+                format!("KDF version {version} is newer than the current version {}", self.version),
+                None,
+            ));
+        }
+
+        // Validates the namespace and context (whichever provider is used).
+        build_context(&self.namespace, version, context.as_ref())?;
+
+        self.provider.derive_key(&self.namespace, version, context.as_ref()).await
+    }
+}
+
+/// Namespaces are a single segment, they may not contain the separator.
+pub fn validate_namespace(namespace: &str) -> Result<()> {
+    if namespace.is_empty()
+        || !namespace.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(validation_err(format!("invalid KDF namespace: '{namespace}'"), None));
+    }
+
+    Ok(())
+}
+
+/// Builds the full context passed to the provider.
+///
+/// Version 0 keeps the original (unversioned) format so existing keys remain derivable.
+pub fn build_context(namespace: &str, version: u32, context: &str) -> Result<String> {
+    validate_namespace(namespace)?;
+    if context.is_empty() {
+        return Err(validation_err("KDF context may not be empty", None));
+    }
+
+    Ok(match version {
+        0 => format!("{namespace}{KDF_NS_SEPARATOR}{context}"),
+        _ => format!("{namespace}{KDF_NS_SEPARATOR}v{version}{KDF_NS_SEPARATOR}{context}"),
+    })
+}
+
+/// The context the attestation service derives for a client's (namespaced) context.
+///
+/// This is the only place the service prefix is applied, the service then derives it with its
+/// own namespace and the version requested by the client.
+pub fn service_context(context: &str) -> Result<String> {
+    if context.is_empty() {
+        return Err(validation_err("KDF context may not be empty", None));
+    }
+
+    Ok(format!("{KDF_SERVICE_NS_PREFIX}{KDF_NS_SEPARATOR}{context}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use lit_core::config::{LitConfig, LitConfigBuilder};
+
+    use crate::kdf::local::LocalKdfProvider;
+    use crate::kdf::{build_context, service_context, Kdf};
+
+    fn dev_cfg() -> LitConfig {
+        LitConfigBuilder::default().set_override("lit.env", "dev").build().expect("failed to build")
+    }
+
+    #[test]
+    fn test_build_context() {
+        assert_eq!(build_context("ns", 0, "ctx").unwrap(), "ns::ctx");
+        assert_eq!(build_context("ns", 2, "a::b").unwrap(), "ns::v2::a::b");
+        assert!(build_context("n::s", 0, "ctx").is_err());
+        assert!(build_context("", 0, "ctx").is_err());
+        assert!(build_context("ns", 0, "").is_err());
+    }
+
+    #[test]
+    fn test_service_context() {
+        assert_eq!(service_context("ns::ctx").unwrap(), "AS::ns::ctx");
+        assert!(service_context("").is_err());
+    }
+
+    #[test]
+    fn test_invalid_version_rejected() {
+        let cfg = LitConfigBuilder::default()
+            .set_override("lit.env", "dev")
+            .set_override("attestation.kdf.version", -1)
+            .build()
+            .expect("failed to build");
+        assert!(
+            Kdf::try_new_with_provider(&cfg, Arc::new(LocalKdfProvider::legacy()), None).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_derive_versions() {
+        let kdf = Kdf::try_new_with_provider(
+            &dev_cfg(),
+            Arc::new(LocalKdfProvider::legacy()),
+            Some("test".into()),
+        )
+        .expect("failed to init kdf")
+        .with_version(2);
+
+        let current = kdf.derive("ctx").await.unwrap();
+        assert_eq!(current, kdf.derive_version("ctx", 2).await.unwrap());
+        assert_ne!(current, kdf.derive_version("ctx", 1).await.unwrap());
+        assert_ne!(current, kdf.derive_version("ctx", 0).await.unwrap());
+        assert!(kdf.derive_version("ctx", 3).await.is_err());
+
+        // Version 0 matches the legacy derivation.
+        assert_eq!(
+            kdf.derive_version("ctx", 0).await.unwrap(),
+            blake3::derive_key("test::ctx", b"local-key-material")
+        );
+    }
+}
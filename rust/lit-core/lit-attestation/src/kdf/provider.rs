@@ -0,0 +1,20 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+
+use crate::error::Result;
+use crate::kdf::KdfType;
+
+/// A source of key material for the [`crate::kdf::Kdf`].
+///
+/// `Kdf` validates the namespace, version and context before calling a provider.  Providers
+/// which derive locally combine them with [`crate::kdf::build_context`], so the namespace and
+/// versioning rules are identical no matter which provider is plugged in.
+#[async_trait]
+pub trait KdfProvider: Debug + Send + Sync {
+    /// The KDF type this provider implements.
+    fn typ(&self) -> KdfType;
+
+    /// Derive a 32 byte key for the context, within the namespace and at the version.
+    async fn derive_key(&self, namespace: &str, version: u32, context: &str) -> Result<[u8; 32]>;
+}
@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+
+#[allow(unused_imports)]
+use crate::error::{attestation_err, conversion_err, sev_snp_err, Result};
+use crate::kdf::provider::KdfProvider;
+#[allow(unused_imports)]
+use crate::kdf::{build_context, KdfType};
+#[cfg(feature = "generate-via-service")]
+use crate::service::client::{AttestationServiceClient, ATTESTATION_SERVICE_CLIENT};
+#[cfg(feature = "generate-via-service")]
+use crate::service::types::KdfReq;
+#[cfg(feature = "generate-via-system")]
+use crate::utils::sev_snp::sev_snp_derive_key;
+
+/// Derives keys from the AMD SEV-SNP firmware derived key (either directly or via the
+/// attestation service).
+#[derive(Clone, Debug, Default)]
+pub struct SevSnpKdfProvider;
+
+impl SevSnpKdfProvider {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(feature = "generate-via-system")]
+    async fn derive_via_system(
+        &self, namespace: &str, version: u32, context: &str,
+    ) -> Result<[u8; 32]> {
+        sev_snp_derive_key(&build_context(namespace, version, context)?)
+            .map_err(|e| sev_snp_err(e, Some("failed to derive_via_system".into())))
+    }
+
+    /// The service applies the version (and its own prefix, see `kdf::service_context`), so
+    /// only the namespace is applied to the context here.
+    #[cfg(feature = "generate-via-service")]
+    async fn derive_via_service(
+        &self, namespace: &str, version: u32, context: &str,
+    ) -> Result<[u8; 32]> {
+        let req = KdfReq::new(build_context(namespace, 0, context)?).with_version(version);
+        match ATTESTATION_SERVICE_CLIENT.load().kdf(&req).await {
+            Ok(resp) => {
+                if resp.version != version {
+                    return Err(attestation_err(
+                        format!(
+                            "attestation service derived kdf version {} instead of {version}",
+                            resp.version
+                        ),
+                        None,
+                    ));
+                }
+                let key: [u8; 32] =
+                    resp.key.val.try_into().map_err(|_e| {
+                        conversion_err("failed to convert kdf key to [u8; 32]", None)
+                    })?;
+                Ok(key)
+            }
+            Err(e) => Err(attestation_err(
+                e,
+                Some("failed to request kdf via attestation service".into()),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl KdfProvider for SevSnpKdfProvider {
+    fn typ(&self) -> KdfType {
+        KdfType::AmdSevSnp
+    }
+
+    #[allow(unreachable_code, unused_variables)]
+    async fn derive_key(&self, namespace: &str, version: u32, context: &str) -> Result<[u8; 32]> {
+        #[cfg(feature = "generate-via-service")]
+        return self.derive_via_service(namespace, version, context).await;
+
+        #[cfg(feature = "generate-via-system")]
+        return self.derive_via_system(namespace, version, context).await;
+
+        unimplemented!(
+            "Unexpected: SevSnpKdfProvider::derive_key() called and neither generate-via-service nor generate-via-system feature enabled"
+        )
+    }
+}
@@ -6,6 +6,7 @@ pub mod kdf;
 pub mod request;
 #[cfg(feature = "service")]
 pub mod service;
+pub mod transparency;
 pub mod utils;
 pub mod verification;
 
@@ -44,11 +44,19 @@ pub struct AttestationResp {
 #[serde(rename_all = "camelCase")]
 pub struct KdfReq {
     pub context: String,
+    /// The key version to derive (defaults to the version configured for the service).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
 }
 
 impl KdfReq {
     pub fn new<S: Into<String>>(context: S) -> Self {
-        Self { context: context.into() }
+        Self { context: context.into(), version: None }
+    }
+
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
     }
 }
 
@@ -56,4 +64,6 @@ impl KdfReq {
 #[serde(rename_all = "camelCase")]
 pub struct KdfResp {
     pub key: Bytes,
+    #[serde(default)]
+    pub version: u32,
 }
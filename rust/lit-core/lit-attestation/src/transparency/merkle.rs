@@ -0,0 +1,254 @@
+use sha2::{Digest, Sha256};
+
+// Merkle tree hashing as per RFC 6962 / RFC 9162 (leaves and nodes are domain separated).
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The root of the tree over the given leaf hashes (the empty tree hashes the empty string).
+pub fn root_hash(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&root_hash(&leaves[..k]), &root_hash(&leaves[k..]))
+        }
+    }
+}
+
+/// The audit path for the leaf at `index`.
+pub fn inclusion_path(leaves: &[Hash], index: usize) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 || index >= n {
+        return Vec::new();
+    }
+
+    let k = split_point(n);
+    if index < k {
+        let mut path = inclusion_path(&leaves[..k], index);
+        path.push(root_hash(&leaves[k..]));
+        path
+    } else {
+        let mut path = inclusion_path(&leaves[k..], index - k);
+        path.push(root_hash(&leaves[..k]));
+        path
+    }
+}
+
+/// The proof that the tree of `old_size` leaves is a prefix of the tree over `leaves`.
+pub fn consistency_path(leaves: &[Hash], old_size: usize) -> Vec<Hash> {
+    if old_size == 0 || old_size >= leaves.len() {
+        return Vec::new();
+    }
+
+    consistency_subpath(leaves, old_size, true)
+}
+
+fn consistency_subpath(leaves: &[Hash], m: usize, complete: bool) -> Vec<Hash> {
+    let n = leaves.len();
+    if m == n {
+        return if complete { Vec::new() } else { vec![root_hash(leaves)] };
+    }
+
+    let k = split_point(n);
+    if m <= k {
+        let mut path = consistency_subpath(&leaves[..k], m, complete);
+        path.push(root_hash(&leaves[k..]));
+        path
+    } else {
+        let mut path = consistency_subpath(&leaves[k..], m - k, false);
+        path.push(root_hash(&leaves[..k]));
+        path
+    }
+}
+
+pub fn verify_inclusion(
+    leaf: &Hash, index: u64, tree_size: u64, path: &[Hash], root: &Hash,
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    let mut f_n = index;
+    let mut s_n = tree_size - 1;
+    let mut r = *leaf;
+
+    for p in path {
+        if s_n == 0 {
+            return false;
+        }
+
+        if f_n & 1 == 1 || f_n == s_n {
+            r = node_hash(p, &r);
+            if f_n & 1 == 0 {
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    s_n == 0 && r.eq(root)
+}
+
+pub fn verify_consistency(
+    old_size: u64, new_size: u64, old_root: &Hash, new_root: &Hash, path: &[Hash],
+) -> bool {
+    if old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return path.is_empty() && old_root.eq(new_root);
+    }
+    if old_size == 0 {
+        return path.is_empty();
+    }
+
+    let mut path = path.to_vec();
+    if old_size.is_power_of_two() {
+        path.insert(0, *old_root);
+    }
+
+    let mut f_n = old_size - 1;
+    let mut s_n = new_size - 1;
+    while f_n & 1 == 1 {
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+    let mut f_r = *first;
+    let mut s_r = *first;
+
+    for c in rest {
+        if s_n == 0 {
+            return false;
+        }
+
+        if f_n & 1 == 1 || f_n == s_n {
+            f_r = node_hash(c, &f_r);
+            s_r = node_hash(c, &s_r);
+            if f_n & 1 == 0 {
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            }
+        } else {
+            s_r = node_hash(&s_r, c);
+        }
+
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    s_n == 0 && f_r.eq(old_root) && s_r.eq(new_root)
+}
+
+// Largest power of two smaller than n (n > 1).
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transparency::merkle::{
+        consistency_path, inclusion_path, leaf_hash, root_hash, verify_consistency,
+        verify_inclusion, Hash,
+    };
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n).map(|i| leaf_hash(format!("leaf-{i}").as_bytes())).collect()
+    }
+
+    #[test]
+    fn inclusion_test() {
+        for n in 1..=17 {
+            let leaves = leaves(n);
+            let root = root_hash(&leaves);
+
+            for i in 0..n {
+                let path = inclusion_path(&leaves, i);
+                assert!(verify_inclusion(&leaves[i], i as u64, n as u64, &path, &root));
+
+                // Wrong index, leaf or size.
+                if n > 1 {
+                    let j = (i + 1) % n;
+                    assert!(!verify_inclusion(&leaves[i], j as u64, n as u64, &path, &root));
+                    assert!(!verify_inclusion(&leaves[j], i as u64, n as u64, &path, &root));
+                }
+                assert!(!verify_inclusion(&leaves[i], i as u64, n as u64 + 1, &path, &root));
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_test() {
+        let all = leaves(17);
+
+        for n in 1..=17 {
+            let new_root = root_hash(&all[..n]);
+
+            for m in 1..=n {
+                let old_root = root_hash(&all[..m]);
+                let path = consistency_path(&all[..n], m);
+
+                assert!(
+                    verify_consistency(m as u64, n as u64, &old_root, &new_root, &path),
+                    "consistency {m} -> {n}"
+                );
+
+                // A rewritten history must not verify.
+                if m < n {
+                    let mut forked = all[..m].to_vec();
+                    forked[m - 1] = leaf_hash(b"forked");
+                    let forked_root = root_hash(&forked);
+
+                    assert!(!verify_consistency(
+                        m as u64, n as u64, &forked_root, &new_root, &path
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_tree_test() {
+        // SHA-256 of the empty string.
+        assert_eq!(
+            lit_core::utils::binary::bytes_to_hex(root_hash(&[])),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
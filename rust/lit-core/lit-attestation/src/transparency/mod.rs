@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use log::{as_serde, trace, warn};
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use lit_core::config::LitConfig;
+
+use crate::config::LitAttestationConfig;
+use crate::error::{conversion_err, http_client_err, io_err, validation_err_code, Result, EC};
+use crate::transparency::types::{
+    ReleaseConsistencyProof, ReleaseInclusionProof, ReleaseLogEntry, ReleaseLogHead,
+};
+
+pub mod merkle;
+pub mod types;
+
+const RELEASE_LOG_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The largest head seen, every later head must be consistent with it (kept on disk too, as the
+// log could otherwise be rewritten across a restart). Loaded on first use, starting from the
+// trusted head in the config.
+static LAST_SEEN_HEAD: Lazy<RwLock<Option<ReleaseLogHead>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Deserialize)]
+struct ProofResponse<T> {
+    proof: T,
+}
+
+/// Verify the release (with its on-chain manifest cid) is included in the release log.
+pub async fn verify_release_logged(
+    cfg: &LitConfig, release_id: &str, manifest_cid: &[u8],
+) -> Result<ReleaseLogEntry> {
+    let url = cfg.release_log_url()?;
+    let public_key = cfg.release_log_public_key()?;
+    let manifest_cid = bs58::encode(manifest_cid).into_string();
+
+    let proof: ReleaseInclusionProof =
+        get_proof(format!("{}/release/log/proof/{}", url.trim_end_matches('/'), release_id))
+            .await?;
+    proof.head.verify_signature(&public_key)?;
+    proof.verify()?;
+
+    if !proof.entry.release_id.eq(release_id) || !proof.entry.manifest_cid.eq(&manifest_cid) {
+        return Err(validation_err_code(
+            format!(
+                "release log entry does not match release ({}) manifest cid ({})",
+                release_id, manifest_cid
+            ),
+            EC::AttestationReleaseLogVerifyFailed,
+            None,
+        )
+        .add_field("entry", serde_json::to_value(&proof.entry).unwrap_or(Value::Null))
+        .add_detail(format!("Release Id ({release_id}) does not match the release log")));
+    }
+
+    verify_head_consistent(cfg, url.as_str(), &public_key, &proof.head).await?;
+
+    trace!(release_id = as_serde!(release_id),
+        leaf_index = as_serde!(proof.leaf_index),
+        tree_size = as_serde!(proof.head.tree_size);
+        "Release log inclusion verify OK");
+
+    Ok(proof.entry)
+}
+
+async fn verify_head_consistent(
+    cfg: &LitConfig, url: &str, public_key: &str, head: &ReleaseLogHead,
+) -> Result<()> {
+    let head_path = cfg.release_log_head_path();
+    let mut last_seen = LAST_SEEN_HEAD.write().await;
+    let last = match last_seen.as_ref() {
+        Some(last) => last.clone(),
+        None => {
+            // Any signed tree would do as the first head seen, so start from the trusted one.
+            let trusted = cfg.release_log_trusted_head()?;
+            match load_head(head_path.as_path(), public_key) {
+                Some(stored) if stored.tree_size >= trusted.tree_size => {
+                    verify_heads_consistent(url, &trusted, &stored).await?;
+                    stored
+                }
+                _ => trusted,
+            }
+        }
+    };
+
+    verify_heads_consistent(url, &last, head).await?;
+
+    if head.tree_size > last.tree_size {
+        save_head(head_path.as_path(), head)?;
+        let _ = last_seen.insert(head.clone());
+    } else {
+        let _ = last_seen.insert(last);
+    }
+
+    Ok(())
+}
+
+async fn verify_heads_consistent(url: &str, a: &ReleaseLogHead, b: &ReleaseLogHead) -> Result<()> {
+    let (old, new) = if a.tree_size <= b.tree_size { (a, b) } else { (b, a) };
+
+    if old.tree_size == new.tree_size {
+        if !old.same_tree(new) {
+            return Err(validation_err_code(
+                format!(
+                    "release log root differs for tree size: {} ({} vs {})",
+                    old.tree_size, old.root_hash, new.root_hash
+                ),
+                EC::AttestationReleaseLogVerifyFailed,
+                None,
+            )
+            .add_detail("Release log history has been rewritten"));
+        }
+
+        return Ok(());
+    }
+
+    let proof: ReleaseConsistencyProof = get_proof(format!(
+        "{}/release/log/consistency/{}/{}",
+        url.trim_end_matches('/'),
+        old.tree_size,
+        new.tree_size
+    ))
+    .await?;
+
+    // The proof must be between the heads we hold (not whatever the log returned).
+    if !proof.old.same_tree(old) || !proof.new.same_tree(new) {
+        return Err(validation_err_code(
+            format!(
+                "release log consistency proof heads do not match (tree size: {} -> {})",
+                old.tree_size, new.tree_size
+            ),
+            EC::AttestationReleaseLogVerifyFailed,
+            None,
+        )
+        .add_detail("Release log history has been rewritten"));
+    }
+
+    proof.verify()
+}
+
+// A head that can't be read (or isn't signed by the prov) is dropped, the next one seen
+// replaces it.
+fn load_head(path: &Path, public_key: &str) -> Option<ReleaseLogHead> {
+    let data = fs::read(path).ok()?;
+    match serde_json::from_slice::<ReleaseLogHead>(&data[..]) {
+        Ok(head) if head.verify_signature(public_key).is_ok() => Some(head),
+        _ => {
+            warn!("ignoring invalid release log head: {:?}", path);
+            None
+        }
+    }
+}
+
+fn save_head(path: &Path, head: &ReleaseLogHead) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| io_err(e, Some(format!("failed to make dir: {parent:?}"))))?;
+    }
+
+    let data = serde_json::to_vec(head).map_err(|e| conversion_err(e, None))?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, data)
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .map_err(|e| io_err(e, Some(format!("failed to write: {path:?}"))))
+}
+
+async fn get_proof<T>(url: String) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let client = reqwest::Client::builder()
+        .timeout(RELEASE_LOG_TIMEOUT)
+        .build()
+        .map_err(|e| http_client_err(e, Some("failed to build release log client".into())))?;
+
+    let resp = client
+        .get(url.as_str())
+        .send()
+        .await
+        .map_err(|e| http_client_err(e, Some(format!("failed to request: {url}"))))?
+        .error_for_status()
+        .map_err(|e| http_client_err(e, Some(format!("failed to request: {url}"))))?;
+
+    let resp: ProofResponse<T> = resp
+        .json()
+        .await
+        .map_err(|e| http_client_err(e, Some(format!("failed to parse response from: {url}"))))?;
+
+    Ok(resp.proof)
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use libsecp256k1::{sign, verify, Message, PublicKey, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use lit_core::utils::binary::{bytes_to_hex, hex_to_bytes};
+
+use crate::error::{conversion_err, validation_err_code, Result, EC};
+use crate::transparency::merkle::{leaf_hash, verify_consistency, verify_inclusion, Hash};
+
+/// A release manifest recorded in the transparency log.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseLogEntry {
+    pub release_id: String,
+    pub manifest_cid: String,
+    /// The manifest hash (hex of the sha512).
+    pub manifest_hash: Option<String>,
+    /// Measurement (hex) to measurement profile (i.e. 'EPYC-v4:2').
+    pub measurements: BTreeMap<String, String>,
+    pub unix_time: u64,
+}
+
+impl ReleaseLogEntry {
+    pub fn leaf_hash(&self) -> Result<Hash> {
+        let data = serde_json::to_vec(self).map_err(|e| conversion_err(e, None))?;
+
+        Ok(leaf_hash(&data))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseLogHead {
+    pub tree_size: u64,
+    /// The root hash (hex).
+    pub root_hash: String,
+    /// The signature (hex) of the release log key over the tree size and root hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl ReleaseLogHead {
+    pub fn new(tree_size: u64, root_hash: &Hash) -> Self {
+        Self { tree_size, root_hash: bytes_to_hex(root_hash), signature: None }
+    }
+
+    pub fn root_hash_bytes(&self) -> Result<Hash> {
+        hash_from_hex(&self.root_hash)
+    }
+
+    /// Whether both heads are of the same tree (ignoring the signatures).
+    pub fn same_tree(&self, other: &ReleaseLogHead) -> bool {
+        self.tree_size == other.tree_size && self.root_hash.eq(&other.root_hash)
+    }
+
+    pub fn sign(&mut self, private_key: &str) -> Result<()> {
+        let private_key = SecretKey::parse_slice(&hex_to_bytes(private_key)?[..])
+            .map_err(|e| conversion_err(e, None))?;
+
+        let (sig, _) = sign(&self.message()?, &private_key);
+        self.signature = Some(bytes_to_hex(sig.serialize()));
+
+        Ok(())
+    }
+
+    pub fn verify_signature(&self, public_key: &str) -> Result<()> {
+        let public_key = PublicKey::parse_slice(&hex_to_bytes(public_key)?[..], None)
+            .map_err(|e| conversion_err(e, None))?;
+        let sig = self
+            .signature
+            .as_ref()
+            .map(hex_to_bytes)
+            .transpose()?
+            .map(|sig| Signature::parse_standard_slice(&sig[..]))
+            .transpose()
+            .map_err(|e| conversion_err(e, None))?;
+
+        match sig {
+            Some(sig) if verify(&self.message()?, &sig, &public_key) => Ok(()),
+            _ => Err(validation_err_code(
+                format!("release log head signature is invalid (tree size: {})", self.tree_size),
+                EC::AttestationReleaseLogVerifyFailed,
+                None,
+            )
+            .add_detail("Release log head is not signed by the release log key")),
+        }
+    }
+
+    fn message(&self) -> Result<Message> {
+        let mut hasher = Sha256::new();
+        hasher.update("release-log-head");
+        hasher.update(self.tree_size.to_be_bytes());
+        hasher.update(self.root_hash_bytes()?);
+
+        Message::parse_slice(hasher.finalize().as_slice()).map_err(|e| conversion_err(e, None))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseInclusionProof {
+    pub entry: ReleaseLogEntry,
+    pub leaf_index: u64,
+    pub head: ReleaseLogHead,
+    /// The audit path (hex).
+    pub audit_path: Vec<String>,
+}
+
+impl ReleaseInclusionProof {
+    pub fn verify(&self) -> Result<()> {
+        let leaf = self.entry.leaf_hash()?;
+        let root = self.head.root_hash_bytes()?;
+        let path = hashes_from_hex(&self.audit_path)?;
+
+        if !verify_inclusion(&leaf, self.leaf_index, self.head.tree_size, &path, &root) {
+            return Err(validation_err_code(
+                format!(
+                    "release log inclusion proof is invalid (release id: {}, leaf index: {}, tree size: {})",
+                    self.entry.release_id, self.leaf_index, self.head.tree_size
+                ),
+                EC::AttestationReleaseLogVerifyFailed,
+                None,
+            )
+            .add_detail("Release is not included in the release log"));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseConsistencyProof {
+    pub old: ReleaseLogHead,
+    pub new: ReleaseLogHead,
+    /// The consistency proof (hex).
+    pub proof: Vec<String>,
+}
+
+impl ReleaseConsistencyProof {
+    pub fn verify(&self) -> Result<()> {
+        let old_root = self.old.root_hash_bytes()?;
+        let new_root = self.new.root_hash_bytes()?;
+        let proof = hashes_from_hex(&self.proof)?;
+
+        if !verify_consistency(self.old.tree_size, self.new.tree_size, &old_root, &new_root, &proof)
+        {
+            return Err(validation_err_code(
+                format!(
+                    "release log consistency proof is invalid (tree size: {} -> {})",
+                    self.old.tree_size, self.new.tree_size
+                ),
+                EC::AttestationReleaseLogVerifyFailed,
+                None,
+            )
+            .add_detail("Release log history has been rewritten"));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn hashes_to_hex(hashes: &[Hash]) -> Vec<String> {
+    hashes.iter().map(bytes_to_hex).collect()
+}
+
+fn hashes_from_hex(hashes: &[String]) -> Result<Vec<Hash>> {
+    hashes.iter().map(hash_from_hex).collect()
+}
+
+fn hash_from_hex(hash: &String) -> Result<Hash> {
+    hex_to_bytes(hash)?
+        .try_into()
+        .map_err(|_| conversion_err(format!("invalid release log hash: {hash}"), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use libsecp256k1::{PublicKey, SecretKey};
+    use lit_core::utils::binary::bytes_to_hex;
+
+    use crate::transparency::merkle::{consistency_path, inclusion_path, root_hash};
+    use crate::transparency::types::{
+        hashes_to_hex, ReleaseConsistencyProof, ReleaseInclusionProof, ReleaseLogEntry,
+        ReleaseLogHead,
+    };
+
+    fn entry(i: usize) -> ReleaseLogEntry {
+        ReleaseLogEntry {
+            release_id: format!("release-{i}"),
+            manifest_cid: format!("QmCid{i}"),
+            manifest_hash: None,
+            measurements: BTreeMap::from([(format!("{i:02x}"), "EPYC-v4:2".to_string())]),
+            unix_time: i as u64,
+        }
+    }
+
+    #[test]
+    fn release_proofs_test() {
+        let entries: Vec<ReleaseLogEntry> = (0..5).map(entry).collect();
+        let leaves: Vec<_> = entries.iter().map(|e| e.leaf_hash().unwrap()).collect();
+        let head = ReleaseLogHead::new(5, &root_hash(&leaves));
+
+        let mut proof = ReleaseInclusionProof {
+            entry: entries[3].clone(),
+            leaf_index: 3,
+            head: head.clone(),
+            audit_path: hashes_to_hex(&inclusion_path(&leaves, 3)),
+        };
+        proof.verify().expect("expected inclusion proof to verify");
+
+        // Tampered entry.
+        proof.entry.manifest_cid = "QmOther".into();
+        assert!(proof.verify().is_err());
+
+        let consistency = ReleaseConsistencyProof {
+            old: ReleaseLogHead::new(2, &root_hash(&leaves[..2])),
+            new: head,
+            proof: hashes_to_hex(&consistency_path(&leaves, 2)),
+        };
+        consistency.verify().expect("expected consistency proof to verify");
+
+        // Serialised proofs round trip.
+        let json = serde_json::to_string(&consistency).unwrap();
+        assert_eq!(serde_json::from_str::<ReleaseConsistencyProof>(&json).unwrap(), consistency);
+    }
+
+    #[test]
+    fn release_head_signature_test() {
+        let private_key = SecretKey::parse(&[7u8; 32]).unwrap();
+        let public_key = bytes_to_hex(PublicKey::from_secret_key(&private_key).serialize());
+        let other_key = bytes_to_hex(
+            PublicKey::from_secret_key(&SecretKey::parse(&[8u8; 32]).unwrap()).serialize(),
+        );
+
+        let leaves: Vec<_> = (0..3).map(|i| entry(i).leaf_hash().unwrap()).collect();
+        let mut head = ReleaseLogHead::new(3, &root_hash(&leaves));
+        assert!(head.verify_signature(&public_key).is_err());
+
+        head.sign(&bytes_to_hex(private_key.serialize())).unwrap();
+        head.verify_signature(&public_key).expect("expected head signature to verify");
+        assert!(head.verify_signature(&other_key).is_err());
+
+        // Signed heads are of the same tree as unsigned ones.
+        let unsigned = ReleaseLogHead::new(3, &root_hash(&leaves));
+        assert!(head.same_tree(&unsigned));
+
+        // The signature covers the tree size.
+        let mut tampered = head.clone();
+        tampered.tree_size = 2;
+        assert!(tampered.verify_signature(&public_key).is_err());
+    }
+}
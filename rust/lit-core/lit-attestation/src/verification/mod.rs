@@ -14,10 +14,12 @@ use lit_core::utils::binary::bytes_to_hex;
 pub use policy::*;
 
 use crate::attestation::{AdminSignedType, Attestation, AttestationType, DATA_KEY_RELEASE_ID};
+use crate::config::LitAttestationConfig;
 use crate::error::{
     attestation_err_code, conversion_err, conversion_err_code, unexpected_err_code,
     validation_err_code, Result, EC,
 };
+use crate::transparency::verify_release_logged;
 use crate::utils::sev_snp::FamilyIdBuilder;
 use crate::verification::blockchain::{
     get_release, get_release_register_contract, get_staking_balances_contract,
@@ -294,6 +296,11 @@ pub(crate) async fn verify_release(
         .add_detail(format!("Release Id ({release_id}) is not active")));
     }
 
+    // Verify release is in the release log
+    if cfg.release_log_require_inclusion() {
+        verify_release_logged(cfg, release_id.as_str(), &release.cid[..]).await?;
+    }
+
     // Verify environment matches
     let release_env = LitEnv::try_from(release.env).map_err(|e| {
         conversion_err_code(
@@ -5,7 +5,7 @@ use proc_macro_error::{abort, abort_call_site};
 use quote::{quote, ToTokens};
 use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
-use syn::{self, parenthesized, Attribute, Data, DeriveInput, Expr, Ident, LitStr, Token};
+use syn::{self, parenthesized, Attribute, Data, DeriveInput, Expr, Fields, Ident, LitStr, Token};
 
 pub(crate) fn derive_error_code(input: &DeriveInput) -> TokenStream {
     let name = input.ident.clone();
@@ -16,13 +16,24 @@ pub(crate) fn derive_error_code(input: &DeriveInput) -> TokenStream {
             let mut code_variants: Vec<TokenStream2> = Vec::new();
             let mut kind_variants: Vec<TokenStream2> = Vec::new();
             let mut http_code_variants: Vec<TokenStream2> = Vec::new();
+            let mut retryable_variants: Vec<TokenStream2> = Vec::new();
+            let mut catalog_entries: Vec<TokenStream2> = Vec::new();
 
             for variant in e.variants.iter() {
                 let variant_name = variant.ident.clone();
                 let variant_name_str = variant_name.to_string();
 
+                if !matches!(variant.fields, Fields::Unit) {
+                    abort!(variant, "`#[derive(ErrorCode)]` only supports unit variants");
+                }
+
                 let mut added_kind = false;
                 let mut added_http_code = false;
+                let mut added_retryable = false;
+
+                catalog_entries.push(quote! {
+                    ::lit_core::error::CodeCatalogEntry::from_code(&#name::#variant_name),
+                });
 
                 code_variants.push(quote! {
                     #name::#variant_name => Cow::from(#variant_name_str),
@@ -44,6 +55,12 @@ pub(crate) fn derive_error_code(input: &DeriveInput) -> TokenStream {
                                     });
                                     added_http_code = true;
                                 }
+                                MagicAttrName::Retryable => {
+                                    retryable_variants.push(quote! {
+                                        #name::#variant_name => Some(#val),
+                                    });
+                                    added_retryable = true;
+                                }
                             }
                         }
                     }
@@ -59,6 +76,11 @@ pub(crate) fn derive_error_code(input: &DeriveInput) -> TokenStream {
                         #name::#variant_name => None,
                     });
                 }
+                if !added_retryable {
+                    retryable_variants.push(quote! {
+                        #name::#variant_name => None,
+                    });
+                }
             }
 
             let modified = quote! {
@@ -80,6 +102,24 @@ pub(crate) fn derive_error_code(input: &DeriveInput) -> TokenStream {
                             #(#http_code_variants)*
                         }
                     }
+
+                    fn retryable(&self) -> bool {
+                        let retryable: Option<bool> = match self {
+                            #(#retryable_variants)*
+                        };
+
+                        retryable.unwrap_or_else(|| {
+                            self.kind().map(|k| k.is_retryable()).unwrap_or(false)
+                        })
+                    }
+                }
+
+                impl #impl_generics ::lit_core::error::CodeCatalog for #name #ty_generics #where_clause {
+                    fn catalog() -> Vec<::lit_core::error::CodeCatalogEntry> {
+                        vec![
+                            #(#catalog_entries)*
+                        ]
+                    }
                 }
             };
 
@@ -128,6 +168,7 @@ impl Parse for CodeAttr {
         let magic = match name_str.as_str() {
             "kind" => Some(MagicAttrName::Kind),
             "http_status" => Some(MagicAttrName::HttpStatus),
+            "retryable" => Some(MagicAttrName::Retryable),
             _ => None,
         };
 
@@ -171,6 +212,7 @@ impl Parse for CodeAttr {
 pub enum MagicAttrName {
     Kind,
     HttpStatus,
+    Retryable,
 }
 
 #[derive(Clone)]
@@ -480,6 +480,11 @@ impl LitConfig {
         matches!(self.litos_guest(), Ok(true))
     }
 
+    /// The path of a local config file (i.e. the admin override), as read by the builder.
+    pub fn local_config_path(&self, config_file: &str) -> PathBuf {
+        PathBuf::from(format!("./{config_file}.{CFG_EXT}"))
+    }
+
     // Local config
     pub fn save_local_config(
         &self, config_file: &str, data: &HashMap<String, String>,
@@ -517,7 +522,7 @@ impl LitConfig {
     pub fn save_toml_as_local_config(
         &self, config_file: &str, toml: &SimpleToml,
     ) -> Result<String> {
-        let config_file = PathBuf::from(format!("./{}.{CFG_EXT}", config_file));
+        let config_file = self.local_config_path(config_file);
         toml.write_file(config_file.as_path())?;
 
         Ok(config_file.display().to_string())
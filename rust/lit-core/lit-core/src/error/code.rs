@@ -5,6 +5,7 @@ use std::fmt::{Debug, Display, Formatter};
 use std::sync::Arc;
 
 use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
 use lit_core_derive::{Description, ErrorCode};
 
@@ -17,6 +18,49 @@ pub trait Code: Display + Debug + Description {
     fn code(&self) -> Cow<str>;
     fn kind(&self) -> Option<Kind>;
     fn http_status(&self) -> Option<u16>;
+
+    /// Whether the client may retry the request (i.e. against another node).
+    fn retryable(&self) -> bool {
+        self.kind().map(|k| k.is_retryable()).unwrap_or(false)
+    }
+}
+
+/// Every code of an error code enum (implemented by `#[derive(ErrorCode)]`).
+pub trait CodeCatalog {
+    fn catalog() -> Vec<CodeCatalogEntry>;
+}
+
+/// A published description of an error code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeCatalogEntry {
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    pub http_status: u16,
+    pub retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl CodeCatalogEntry {
+    pub fn from_code<C>(code: &C) -> Self
+    where
+        C: Code + ?Sized,
+    {
+        Self {
+            code: code.code().to_string(),
+            kind: code.kind(),
+            http_status: code.http_status().unwrap_or(500),
+            retryable: code.retryable(),
+            description: code.description(),
+        }
+    }
+}
+
+/// The catalog of codes defined by this crate.
+pub fn core_error_catalog() -> Vec<CodeCatalogEntry> {
+    EC::catalog()
 }
 
 #[allow(dead_code)]
@@ -36,13 +80,19 @@ pub struct StaticCode {
     kind: Option<Kind>,
     http_status: Option<u16>,
     description: Option<String>,
+    retryable: Option<bool>,
 }
 
 impl StaticCode {
     pub fn new(
         code: String, kind: Option<Kind>, http_status: Option<u16>, description: Option<String>,
     ) -> Self {
-        Self { code, kind, http_status, description }
+        Self { code, kind, http_status, description, retryable: None }
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = Some(retryable);
+        self
     }
 }
 
@@ -78,4 +128,28 @@ impl Code for StaticCode {
     fn http_status(&self) -> Option<u16> {
         self.http_status
     }
+
+    fn retryable(&self) -> bool {
+        self.retryable.unwrap_or_else(|| self.kind().map(|k| k.is_retryable()).unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{core_error_catalog, Kind};
+
+    #[test]
+    fn catalog_test() {
+        let catalog = core_error_catalog();
+
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog[0].code, "CoreFatal");
+        assert_eq!(catalog[0].kind, Some(Kind::Unexpected));
+        assert_eq!(catalog[0].http_status, 500);
+        assert!(!catalog[0].retryable);
+        assert_eq!(
+            catalog[0].description.as_deref(),
+            Some("A fatal error occured in the lit core system")
+        );
+    }
 }
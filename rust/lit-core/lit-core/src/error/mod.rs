@@ -422,6 +422,15 @@ pub enum Kind {
     SevSnp,
 }
 
+impl Kind {
+    /// Whether an error of this kind is likely transient (i.e. the request may succeed if
+    /// retried, possibly against another node). Codes may override this via
+    /// `#[code(retryable = ...)]`.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Kind::Timeout | Kind::Connect | Kind::Lock | Kind::IPFS | Kind::HttpClient)
+    }
+}
+
 // constructors
 
 #[macro_export]
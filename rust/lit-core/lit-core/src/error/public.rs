@@ -6,9 +6,22 @@ use std::sync::Arc;
 use crate::error::{serializer_err, ArcCode, Error, Kind, Result, StaticCode, PKG_NAME};
 use crate::types::Description;
 
+/// Prefix of the RFC 7807 `type` URI for errors with a code (i.e. `urn:lit:error:NodeBadInput`).
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:lit:error:";
+/// The RFC 7807 `type` for errors without a code.
+pub const PROBLEM_TYPE_BLANK: &str = "about:blank";
+/// Content type for `PublicError` responses.
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// The public (client facing) form of an error, serialized as an RFC 7807 problem document
+/// (with the code, correlation id and retryability as extension members).
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PublicError {
+    #[serde(rename = "type", default = "default_problem_type")]
+    problem_type: String,
+    #[serde(default)]
+    title: String,
     error_kind: Kind,
     #[serde(skip_serializing_if = "Option::is_none")]
     error_code: Option<String>,
@@ -19,6 +32,12 @@ pub struct PublicError {
     correlation_id: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     details: Vec<String>,
+    #[serde(default)]
+    retryable: bool,
+}
+
+fn default_problem_type() -> String {
+    PROBLEM_TYPE_BLANK.into()
 }
 
 impl PublicError {
@@ -60,17 +79,32 @@ impl PublicError {
     pub fn message(&self) -> Option<&String> {
         self.message.as_ref()
     }
+
+    pub fn error_code(&self) -> Option<&String> {
+        self.error_code.as_ref()
+    }
+
+    pub fn correlation_id(&self) -> Option<&String> {
+        self.correlation_id.as_ref()
+    }
+
+    pub fn retryable(&self) -> bool {
+        self.retryable
+    }
 }
 
 impl Default for PublicError {
     fn default() -> Self {
         Self {
+            problem_type: default_problem_type(),
+            title: format!("{:?}", Kind::Unexpected),
             error_kind: Kind::Unexpected,
             error_code: None,
             status: 500,
             message: Some("An unexpected internal server error occured.".into()),
             correlation_id: None,
             details: Vec::new(),
+            retryable: false,
         }
     }
 }
@@ -108,10 +142,12 @@ impl From<Error> for PublicError {
         let mut code: Option<String> = None;
         let mut status: Option<u16> = None;
         let mut message: Option<String> = None;
+        let mut retryable = real.kind().is_retryable();
         if let Some(c) = real.code() {
             code = Some(c.code().to_string());
             status = c.http_status();
             message = c.description();
+            retryable = c.retryable();
         }
 
         // Defaults
@@ -133,13 +169,21 @@ impl From<Error> for PublicError {
             }
         });
 
+        let (problem_type, title) = match code.as_ref() {
+            Some(code) => (format!("{PROBLEM_TYPE_PREFIX}{code}"), code.clone()),
+            None => (default_problem_type(), format!("{:?}", real.kind())),
+        };
+
         PublicError {
+            problem_type,
+            title,
             error_kind: real.kind(),
             error_code: code,
             status: status.unwrap(),
             message,
             correlation_id: None,
             details,
+            retryable,
         }
     }
 }
@@ -148,12 +192,15 @@ impl From<PublicError> for Error {
     fn from(val: PublicError) -> Error {
         let mut code: Option<ArcCode> = None;
         if let Some(error_code) = val.error_code.as_ref() {
-            code = Some(Arc::new(StaticCode::new(
-                error_code.clone(),
-                Some(val.error_kind.clone()),
-                Some(val.status),
-                val.message.clone(),
-            )))
+            code = Some(Arc::new(
+                StaticCode::new(
+                    error_code.clone(),
+                    Some(val.error_kind.clone()),
+                    Some(val.status),
+                    val.message.clone(),
+                )
+                .with_retryable(val.retryable),
+            ))
         }
 
         Error::new(
@@ -183,6 +230,9 @@ mod tests {
         assert_eq!(public.message, Some("A fatal error occured in the lit core system".into()));
         assert_eq!(public.correlation_id, None);
         assert_eq!(public.details, Vec::<String>::new());
+        assert_eq!(public.problem_type, "urn:lit:error:CoreFatal");
+        assert_eq!(public.title, "CoreFatal");
+        assert!(!public.retryable);
 
         let public = public.with_correlation_id("1234").add_detail("Something");
 
@@ -200,7 +250,22 @@ mod tests {
 
         let json = public.to_json().unwrap().to_string();
 
-        assert_eq!(json, "{\"details\":[\"Some juicy details\",\"Some more\"],\"errorCode\":\"CoreFatal\",\"errorKind\":\"SevSnp\",\"message\":\"A fatal error occured in the lit core system\",\"status\":500}");
+        assert_eq!(json, "{\"details\":[\"Some juicy details\",\"Some more\"],\"errorCode\":\"CoreFatal\",\"errorKind\":\"SevSnp\",\"message\":\"A fatal error occured in the lit core system\",\"retryable\":false,\"status\":500,\"title\":\"CoreFatal\",\"type\":\"urn:lit:error:CoreFatal\"}");
+    }
+
+    #[test]
+    fn public_retryable_test() {
+        let public: PublicError = timeout_err("timed out", None).into();
+
+        assert!(public.retryable);
+        assert_eq!(public.problem_type, PROBLEM_TYPE_BLANK);
+        assert_eq!(public.title, "Timeout");
+
+        // The code takes precedence over the kind.
+        let public: PublicError = timeout_err_code("timed out", EC::CoreFatal, None).into();
+
+        assert!(!public.retryable);
+        assert_eq!(public.title, "CoreFatal");
     }
 
     #[test]
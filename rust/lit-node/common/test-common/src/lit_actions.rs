@@ -314,6 +314,7 @@ pub async fn execute_lit_action_session_sigs(
             js_params: Some(js_params.clone().unwrap_or_default()),
             auth_methods: auth_methods.clone(),
             epoch,
+            typescript: None,
         };
         let json_body = serde_json::to_string(&execute_request).unwrap();
         json_body_vec.push(json_body);
@@ -350,6 +351,7 @@ pub async fn execute_lit_action(
         js_params,
         auth_methods,
         epoch,
+        typescript: None,
     };
 
     let json_body = serde_json::to_string(&execute_request).unwrap();
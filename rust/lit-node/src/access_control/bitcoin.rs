@@ -0,0 +1,595 @@
+//! Bitcoin access control conditions.
+//!
+//! Balances and UTXOs are queried from an Esplora compatible REST API, configured as the `bitcoin`
+//! (or `bitcoinTestnet`) chain in the RPC resolver. Inscriptions, Runes and BRC-20 balances are
+//! queried from a Hiro compatible ordinals indexer, configured as `bitcoinOrdinals` (or
+//! `bitcoinTestnetOrdinals`).
+use super::substitute_special_params;
+use crate::auth::auth_material::JsonAuthSig;
+use crate::auth::validators::bitcoin::{BitcoinAddress, BitcoinNetwork};
+use crate::error::{serializer_err_code, unexpected_err_code, validation_err_code, Result, EC};
+use crate::models::BitcoinCondition;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::warn;
+use url::Url;
+
+const ORDINALS_RPC_SUFFIX: &str = "Ordinals";
+/// Runes and BRC-20 amounts are compared as fixed point numbers with this many decimals.
+const AMOUNT_DECIMALS: usize = 18;
+
+/// Base URLs of the services a condition is checked against.
+pub(crate) struct BitcoinEndpoints {
+    pub network: BitcoinNetwork,
+    pub esplora: String,
+    pub ordinals: Option<String>,
+}
+
+impl BitcoinEndpoints {
+    fn resolve(chain: &str) -> Result<Self> {
+        let network = BitcoinNetwork::from_chain(chain).ok_or_else(|| {
+            validation_err_code(
+                format!("invalid chain for bitcoin: {}", chain),
+                EC::NodeBlockchainChainUnknown,
+                None,
+            )
+        })?;
+
+        Ok(Self {
+            network,
+            esplora: super::rpc_url(chain)?,
+            ordinals: super::rpc_url(format!("{}{}", chain, ORDINALS_RPC_SUFFIX)).ok(),
+        })
+    }
+
+    fn ordinals(&self) -> Result<&String> {
+        self.ordinals.as_ref().ok_or_else(|| {
+            validation_err_code(
+                "no ordinals indexer configured for this chain",
+                EC::NodeBlockchainChainUnknown,
+                None,
+            )
+        })
+    }
+}
+
+/// The value a condition resolves to, before the return value test is applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BitcoinValue {
+    /// An amount in the smallest unit (sats), or scaled to `AMOUNT_DECIMALS` for tokens.
+    Amount(u128),
+    Bool(bool),
+    Address(String),
+}
+
+#[derive(Deserialize)]
+struct EsploraAddress {
+    chain_stats: EsploraAddressStats,
+}
+
+#[derive(Deserialize)]
+struct EsploraAddressStats {
+    funded_txo_sum: u64,
+    spent_txo_sum: u64,
+}
+
+#[derive(Deserialize)]
+struct EsploraUtxo {
+    txid: String,
+    vout: u32,
+    status: EsploraTxStatus,
+}
+
+#[derive(Deserialize)]
+struct EsploraTxStatus {
+    confirmed: bool,
+}
+
+#[derive(Deserialize)]
+struct OrdinalsInscription {
+    address: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Brc20Balances {
+    results: Vec<Brc20Balance>,
+}
+
+#[derive(Deserialize)]
+struct Brc20Balance {
+    ticker: String,
+    overall_balance: String,
+}
+
+#[derive(Deserialize)]
+struct RuneHolder {
+    balance: String,
+}
+
+pub async fn check_condition(
+    condition: &BitcoinCondition,
+    auth_sig: &JsonAuthSig,
+    bls_root_pubkey: &String,
+    current_action_ipfs_id: Option<&String>,
+) -> Result<bool> {
+    let endpoints = BitcoinEndpoints::resolve(&condition.chain)?;
+
+    let mut parameters = Vec::with_capacity(condition.parameters.len());
+    for param in &condition.parameters {
+        parameters.push(
+            substitute_special_params(param, auth_sig, bls_root_pubkey, current_action_ipfs_id)
+                .await?,
+        );
+    }
+    let expected = substitute_special_params(
+        &condition.return_value_test.value,
+        auth_sig,
+        bls_root_pubkey,
+        current_action_ipfs_id,
+    )
+    .await?;
+
+    let value = query_value(&condition.method, &parameters, &endpoints).await?;
+    check_return_value(condition, &value, &expected)
+}
+
+async fn query_value(
+    method: &str,
+    parameters: &[String],
+    endpoints: &BitcoinEndpoints,
+) -> Result<BitcoinValue> {
+    match (method, parameters) {
+        ("balance", [address]) => {
+            check_address(address, endpoints.network)?;
+            let stats: EsploraAddress =
+                get_json(endpoint_url(&endpoints.esplora, &["address", address])?).await?;
+            let balance = stats
+                .chain_stats
+                .funded_txo_sum
+                .saturating_sub(stats.chain_stats.spent_txo_sum);
+            Ok(BitcoinValue::Amount(balance as u128))
+        }
+        ("utxo", [address, outpoint]) => {
+            check_address(address, endpoints.network)?;
+            let (txid, vout) = outpoint
+                .split_once(':')
+                .and_then(|(txid, vout)| Some((txid, vout.parse::<u32>().ok()?)))
+                .ok_or_else(|| invalid_condition("outpoint must be formatted as <txid>:<vout>"))?;
+            let utxos: Vec<EsploraUtxo> = get_json(endpoint_url(
+                &endpoints.esplora,
+                &["address", address, "utxo"],
+            )?)
+            .await?;
+            Ok(BitcoinValue::Bool(utxos.iter().any(|u| {
+                u.status.confirmed && u.vout == vout && u.txid.eq_ignore_ascii_case(txid)
+            })))
+        }
+        ("inscription", [inscription_id]) => {
+            let inscription: OrdinalsInscription = get_json(endpoint_url(
+                endpoints.ordinals()?,
+                &["ordinals", "v1", "inscriptions", inscription_id],
+            )?)
+            .await?;
+            Ok(BitcoinValue::Address(
+                inscription.address.unwrap_or_default(),
+            ))
+        }
+        ("runes", [address, rune]) => {
+            check_address(address, endpoints.network)?;
+            let url = endpoint_url(
+                endpoints.ordinals()?,
+                &["runes", "v1", "etchings", rune, "holders", address],
+            )?;
+            // The indexer returns 404 for addresses that don't hold the rune.
+            let balance = match get_optional_json::<RuneHolder>(url).await? {
+                Some(holder) => parse_amount(&holder.balance)?,
+                None => 0,
+            };
+            Ok(BitcoinValue::Amount(balance))
+        }
+        ("brc20", [address, ticker]) => {
+            check_address(address, endpoints.network)?;
+            let mut url = endpoint_url(
+                endpoints.ordinals()?,
+                &["ordinals", "v1", "brc-20", "balances", address],
+            )?;
+            url.query_pairs_mut().append_pair("ticker", ticker);
+            let balances: Brc20Balances = get_json(url).await?;
+            let balance = match balances
+                .results
+                .iter()
+                .find(|b| b.ticker.eq_ignore_ascii_case(ticker))
+            {
+                Some(b) => parse_amount(&b.overall_balance)?,
+                None => 0,
+            };
+            Ok(BitcoinValue::Amount(balance))
+        }
+        _ => Err(invalid_condition(format!(
+            "unsupported bitcoin condition method '{}' with {} parameters",
+            method,
+            parameters.len()
+        ))),
+    }
+}
+
+fn check_return_value(
+    condition: &BitcoinCondition,
+    value: &BitcoinValue,
+    expected: &str,
+) -> Result<bool> {
+    let comparator = condition.return_value_test.comparator.as_str();
+    trace!("Testing: Is {:?} {:?} {:?}", value, comparator, expected);
+
+    let ordering = match value {
+        BitcoinValue::Amount(amount) => {
+            // Sats are integers, tokens are scaled to a fixed number of decimals.
+            let expected = match condition.method.as_str() {
+                "balance" => expected
+                    .parse::<u128>()
+                    .map_err(|e| validation_err_code(e, EC::NodeInvalidACCReturnValueTest, None))?,
+                _ => parse_amount(expected)?,
+            };
+            amount.cmp(&expected)
+        }
+        BitcoinValue::Bool(b) => {
+            let expected = expected
+                .parse::<bool>()
+                .map_err(|e| validation_err_code(e, EC::NodeInvalidACCReturnValueTest, None))?;
+            b.cmp(&expected)
+        }
+        BitcoinValue::Address(address) => address.as_str().cmp(expected),
+    };
+
+    match comparator {
+        ">" => Ok(ordering.is_gt()),
+        "<" => Ok(ordering.is_lt()),
+        ">=" => Ok(ordering.is_ge()),
+        "<=" => Ok(ordering.is_le()),
+        "=" => Ok(ordering.is_eq()),
+        "!=" => Ok(ordering.is_ne()),
+        _ => {
+            warn!("Error - unsupported return value test comparator");
+            Ok(false)
+        }
+    }
+}
+
+/// Parse a decimal amount into a fixed point integer with `AMOUNT_DECIMALS` decimals (extra
+/// decimals are truncated).
+fn parse_amount(amount: &str) -> Result<u128> {
+    let invalid = || {
+        validation_err_code(
+            format!("invalid amount: {}", amount),
+            EC::NodeInvalidACCReturnValueTest,
+            None,
+        )
+    };
+
+    let (int, frac) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if (int.is_empty() && frac.is_empty())
+        || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let frac: String = frac
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(AMOUNT_DECIMALS)
+        .collect();
+    let int = if int.is_empty() {
+        0
+    } else {
+        int.parse::<u128>().map_err(|_| invalid())?
+    };
+
+    int.checked_mul(10u128.pow(AMOUNT_DECIMALS as u32))
+        .and_then(|v| v.checked_add(frac.parse::<u128>().ok()?))
+        .ok_or_else(invalid)
+}
+
+fn check_address(address: &str, network: BitcoinNetwork) -> Result<()> {
+    let (address_network, _) = BitcoinAddress::parse(address)
+        .map_err(|e| invalid_condition(format!("invalid bitcoin address '{}': {}", address, e)))?;
+    if address_network != network {
+        return Err(invalid_condition(format!(
+            "bitcoin address '{}' is not on {}",
+            address,
+            network.chain()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Append `segments` to the path of `base`, escaping them (they come from the condition).
+fn endpoint_url(base: &str, segments: &[&str]) -> Result<Url> {
+    if segments
+        .iter()
+        .any(|s| s.is_empty() || *s == "." || *s == "..")
+    {
+        return Err(invalid_condition(
+            "empty or relative bitcoin condition parameter",
+        ));
+    }
+
+    let mut url = Url::parse(base).map_err(|e| {
+        unexpected_err_code(
+            e,
+            EC::NodeRpcError,
+            Some("Invalid bitcoin indexer url".into()),
+        )
+    })?;
+    url.path_segments_mut()
+        .map_err(|_| {
+            unexpected_err_code(
+                format!("bitcoin indexer url can't have a path: {}", base),
+                EC::NodeRpcError,
+                None,
+            )
+        })?
+        .pop_if_empty()
+        .extend(segments);
+
+    Ok(url)
+}
+
+fn invalid_condition<M: Into<String>>(msg: M) -> crate::error::Error {
+    validation_err_code(msg.into(), EC::NodeBitcoinInvalidCondition, None)
+}
+
+async fn get_json<T: DeserializeOwned>(url: Url) -> Result<T> {
+    let not_found = url.to_string();
+    get_optional_json(url).await?.ok_or_else(|| {
+        unexpected_err_code(
+            format!("not found: {}", not_found),
+            EC::NodeRpcError,
+            Some("Error making bitcoin indexer call".into()),
+        )
+    })
+}
+
+async fn get_optional_json<T: DeserializeOwned>(url: Url) -> Result<Option<T>> {
+    debug!("hitting bitcoin url: {}", url);
+    let resp = reqwest::get(url).await.map_err(|e| {
+        unexpected_err_code(
+            e,
+            EC::NodeRpcError,
+            Some("Error making bitcoin indexer call".into()),
+        )
+    })?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let resp = resp.error_for_status().map_err(|e| {
+        unexpected_err_code(
+            e,
+            EC::NodeRpcError,
+            Some("Error making bitcoin indexer call".into()),
+        )
+    })?;
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| unexpected_err_code(e, EC::NodeRpcError, None))?;
+    trace!("bitcoin response body: {}", body);
+
+    serde_json::from_str(&body)
+        .map(Some)
+        .map_err(|e| serializer_err_code(e, EC::NodeBitcoinJSONError, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_return_value, endpoint_url, parse_amount, query_value, BitcoinEndpoints, BitcoinValue,
+    };
+    use crate::auth::validators::bitcoin::BitcoinNetwork;
+    use crate::models::{BitcoinCondition, JsonReturnValueTestV2};
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const TXID: &str = "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b";
+
+    async fn mock_indexer() -> (MockServer, BitcoinEndpoints) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/address/{}", ADDRESS)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "address": ADDRESS,
+                "chain_stats": { "funded_txo_sum": 150000, "spent_txo_sum": 50000, "tx_count": 3 },
+                "mempool_stats": { "funded_txo_sum": 7, "spent_txo_sum": 0, "tx_count": 1 },
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/address/{}/utxo", ADDRESS)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "txid": TXID, "vout": 1, "value": 100000, "status": { "confirmed": true } },
+                { "txid": TXID, "vout": 2, "value": 7, "status": { "confirmed": false } },
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ordinals/v1/inscriptions/abci0"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "id": "abci0", "address": ADDRESS })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/ordinals/v1/brc-20/balances/{}", ADDRESS)))
+            .and(query_param("ticker", "ordi"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{ "ticker": "ordi", "overall_balance": "12.5" }],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/runes/v1/etchings/DOG/holders/{}", ADDRESS)))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "address": ADDRESS, "balance": "42" })),
+            )
+            .mount(&server)
+            .await;
+
+        let endpoints = BitcoinEndpoints {
+            network: BitcoinNetwork::Mainnet,
+            esplora: server.uri(),
+            ordinals: Some(server.uri()),
+        };
+        (server, endpoints)
+    }
+
+    fn params(p: &[&str]) -> Vec<String> {
+        p.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn condition(method: &str, comparator: &str, value: &str) -> BitcoinCondition {
+        BitcoinCondition {
+            chain: "bitcoin".to_string(),
+            method: method.to_string(),
+            parameters: vec![],
+            return_value_test: JsonReturnValueTestV2 {
+                key: "".to_string(),
+                comparator: comparator.to_string(),
+                value: value.to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn query_value_test() {
+        let (_server, endpoints) = mock_indexer().await;
+
+        assert_eq!(
+            query_value("balance", &params(&[ADDRESS]), &endpoints)
+                .await
+                .unwrap(),
+            BitcoinValue::Amount(100000)
+        );
+        assert_eq!(
+            query_value(
+                "utxo",
+                &params(&[ADDRESS, &format!("{}:1", TXID)]),
+                &endpoints
+            )
+            .await
+            .unwrap(),
+            BitcoinValue::Bool(true)
+        );
+        // unconfirmed
+        assert_eq!(
+            query_value(
+                "utxo",
+                &params(&[ADDRESS, &format!("{}:2", TXID)]),
+                &endpoints
+            )
+            .await
+            .unwrap(),
+            BitcoinValue::Bool(false)
+        );
+        assert_eq!(
+            query_value("inscription", &params(&["abci0"]), &endpoints)
+                .await
+                .unwrap(),
+            BitcoinValue::Address(ADDRESS.to_string())
+        );
+        assert_eq!(
+            query_value("brc20", &params(&[ADDRESS, "ordi"]), &endpoints)
+                .await
+                .unwrap(),
+            BitcoinValue::Amount(parse_amount("12.5").unwrap())
+        );
+        assert_eq!(
+            query_value("runes", &params(&[ADDRESS, "DOG"]), &endpoints)
+                .await
+                .unwrap(),
+            BitcoinValue::Amount(parse_amount("42").unwrap())
+        );
+        // not a holder
+        assert_eq!(
+            query_value("runes", &params(&[ADDRESS, "CAT"]), &endpoints)
+                .await
+                .unwrap(),
+            BitcoinValue::Amount(0)
+        );
+
+        assert!(
+            query_value("balance", &params(&["not-an-address"]), &endpoints)
+                .await
+                .is_err()
+        );
+        assert!(query_value("utxo", &params(&[ADDRESS, TXID]), &endpoints)
+            .await
+            .is_err());
+        assert!(query_value("unknown", &params(&[ADDRESS]), &endpoints)
+            .await
+            .is_err());
+
+        // mainnet address against a testnet condition
+        let testnet = BitcoinEndpoints {
+            network: BitcoinNetwork::Testnet,
+            esplora: endpoints.esplora.clone(),
+            ordinals: None,
+        };
+        assert!(query_value("balance", &params(&[ADDRESS]), &testnet)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn endpoint_url_test() {
+        assert_eq!(
+            endpoint_url("http://indexer/api/", &["inscriptions", "a/b?c#d"])
+                .unwrap()
+                .as_str(),
+            "http://indexer/api/inscriptions/a%2Fb%3Fc%23d"
+        );
+        assert_eq!(
+            endpoint_url("http://indexer", &["runes", "DOG CAT"])
+                .unwrap()
+                .as_str(),
+            "http://indexer/runes/DOG%20CAT"
+        );
+        assert!(endpoint_url("http://indexer", &["inscriptions", ".."]).is_err());
+        assert!(endpoint_url("http://indexer", &["inscriptions", ""]).is_err());
+    }
+
+    #[test]
+    fn check_return_value_test() {
+        let balance = BitcoinValue::Amount(100000);
+        assert!(check_return_value(&condition("balance", ">=", ""), &balance, "100000").unwrap());
+        assert!(!check_return_value(&condition("balance", ">", ""), &balance, "100000").unwrap());
+
+        let tokens = BitcoinValue::Amount(parse_amount("12.5").unwrap());
+        assert!(check_return_value(&condition("brc20", ">", ""), &tokens, "12.49").unwrap());
+        assert!(check_return_value(&condition("brc20", "<", ""), &tokens, "13").unwrap());
+
+        let owned = BitcoinValue::Bool(true);
+        assert!(check_return_value(&condition("utxo", "=", ""), &owned, "true").unwrap());
+        assert!(check_return_value(&condition("utxo", "=", ""), &owned, "yes").is_err());
+
+        let owner = BitcoinValue::Address(ADDRESS.to_string());
+        assert!(check_return_value(&condition("inscription", "=", ""), &owner, ADDRESS).unwrap());
+        assert!(
+            !check_return_value(&condition("inscription", "=", ""), &owner, "bc1other").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_amount_test() {
+        assert_eq!(parse_amount("1").unwrap(), 10u128.pow(18));
+        assert_eq!(parse_amount("0.5").unwrap(), 5 * 10u128.pow(17));
+        assert_eq!(parse_amount(".5").unwrap(), 5 * 10u128.pow(17));
+        assert!(parse_amount("").is_err());
+        assert!(parse_amount("-1").is_err());
+        assert!(parse_amount("1e5").is_err());
+    }
+}
@@ -706,7 +706,8 @@ mod tests {
             )),
             kyve: None,
             cheqd: None,
-            juno: None
+            juno: None,
+            bitcoin: None,
         }
     }
 
@@ -28,6 +28,7 @@ use crate::{
     utils::web::EndpointVersion,
 };
 
+pub mod bitcoin;
 pub mod cosmos;
 pub mod evm_contract;
 pub mod sol_rpc;
@@ -1,4 +1,4 @@
-use super::{cosmos, evm_contract, sol_rpc, validate_boolean_expression};
+use super::{bitcoin, cosmos, evm_contract, sol_rpc, validate_boolean_expression};
 use crate::auth::auth_material::{AuthSigItem, MultipleAuthSigs};
 use crate::auth::resources::LitResourceAbility;
 use crate::error::{validation_err, validation_err_code, Result, EC};
@@ -239,6 +239,22 @@ async fn check_condition(
             )
             .await?
         }
+        UnifiedAccessControlCondition::BitcoinCondition(bitcoin_condition) => {
+            auth_sig = auth_sigs.bitcoin.clone().ok_or_else(|| {
+                validation_err(
+                    "BTC auth sig is missing while checking a BitcoinCondition",
+                    None,
+                )
+            })?;
+
+            bitcoin::check_condition(
+                bitcoin_condition,
+                &auth_sig,
+                bls_root_pubkey,
+                current_action_ipfs_id,
+            )
+            .await?
+        }
     };
 
     Ok(UnifiedConditionCheckResult {
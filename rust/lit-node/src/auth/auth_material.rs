@@ -25,12 +25,14 @@ use super::{
         extract_requested_resources_from_session_sig, extract_wallet_sig,
         validate_and_extract_wallet_sig, validate_session_sig,
     },
-    validators::wallet_sig::validate_wallet_sig_by_chain,
+    validators::{bitcoin::BitcoinAddress, wallet_sig::validate_wallet_sig_by_chain},
 };
 
 pub const AUTH_SIG_DERIVED_VIA_SESSION_SIG: &str = "litSessionSignViaNacl";
 pub const AUTH_SIG_DERIVED_VIA_BLS_NETWORK_SIG: &str = "lit.bls";
 pub const AUTH_SIG_DERIVED_VIA_CONTRACT_SIG: &str = "EIP1271";
+/// Bitcoin wallet signatures (BIP-322, or the legacy `signmessage` format).
+pub const AUTH_SIG_DERIVED_VIA_BIP322: &str = "bip322";
 pub const AUTH_SIG_SESSION_SIG_ALGO: &str = "ed25519";
 pub const AUTH_SIG_BLS_NETWORK_SIG_ALGO: &str = "LIT_BLS";
 
@@ -390,7 +392,7 @@ impl JsonAuthSig {
                 ));
             }
             AuthMaterialType::ContractSig => {
-                validate_eip1271_signature(self, chain).await?;
+                validate_eip1271_signature(self, chain, cfg).await?;
                 self.clone()
             }
             AuthMaterialType::SessionSig => {
@@ -430,6 +432,11 @@ impl JsonAuthSig {
                 Chain::from_str(c).map_err(|e| unexpected_err("Unable to parse chain", None))?,
             );
             Ok(new_auth_sig)
+        } else if new_auth_sig.derived_via == AUTH_SIG_DERIVED_VIA_BIP322 {
+            // Bitcoin wallets may not send a chain, the address determines the network.
+            let (network, _) = BitcoinAddress::parse(&new_auth_sig.address)?;
+            new_auth_sig.chain = Some(network.chain());
+            Ok(new_auth_sig)
         } else {
             Ok(new_auth_sig)
         }
@@ -455,6 +462,7 @@ pub struct MultipleAuthSigs {
     pub kyve: Option<JsonAuthSig>,
     pub cheqd: Option<JsonAuthSig>,
     pub juno: Option<JsonAuthSig>,
+    pub bitcoin: Option<JsonAuthSig>,
 }
 
 impl MultipleAuthSigs {
@@ -472,6 +480,7 @@ impl MultipleAuthSigs {
             && self.kyve.is_none()
             && self.cheqd.is_none()
             && self.juno.is_none()
+            && self.bitcoin.is_none()
         {
             return Err(validation_err_code(
                 "No auth sig detected",
@@ -565,6 +574,20 @@ impl MultipleAuthSigs {
             );
         }
 
+        if let Some(auth_sig) = &self.bitcoin {
+            self.bitcoin = Some(
+                auth_sig
+                    .validate_and_get_wallet_sig(
+                        requested_lit_resource_ability,
+                        &Some(bitcoin_chain(auth_sig)?),
+                        cfg,
+                        bls_root_pubkey,
+                        endpoint_version,
+                    )
+                    .await?,
+            );
+        }
+
         Ok(())
     }
 
@@ -655,6 +678,19 @@ impl MultipleAuthSigs {
             wallet_sig = Some(auth_sig.clone());
         }
 
+        if let Some(auth_sig) = &self.bitcoin {
+            auth_sig
+                .validate_and_get_wallet_sig(
+                    requested_lit_resource_ability,
+                    &Some(bitcoin_chain(auth_sig)?),
+                    cfg,
+                    bls_root_pubkey,
+                    endpoint_version,
+                )
+                .await?;
+            wallet_sig = Some(auth_sig.clone());
+        }
+
         wallet_sig.expect_or_err_code(EC::NodeInvalidMultipleAuthSigs, "No auth sig detected")
     }
 
@@ -684,6 +720,10 @@ impl MultipleAuthSigs {
             resources = auth_sig.resources()?;
         }
 
+        if let Some(auth_sig) = &self.bitcoin {
+            resources = auth_sig.resources()?;
+        }
+
         Ok(resources)
     }
 
@@ -710,6 +750,9 @@ impl MultipleAuthSigs {
                 "juno" => {
                     mult_auth_sigs.juno = Some(auth_sig.to_owned());
                 }
+                "bitcoin" | "bitcoinTestnet" => {
+                    mult_auth_sigs.bitcoin = Some(auth_sig.to_owned());
+                }
                 _ => {
                     mult_auth_sigs.ethereum = Some(auth_sig.to_owned());
                 }
@@ -720,6 +763,12 @@ impl MultipleAuthSigs {
     }
 }
 
+/// The bitcoin chain (mainnet or testnet) is determined by the auth sig address.
+fn bitcoin_chain(auth_sig: &JsonAuthSig) -> Result<String> {
+    let (network, _) = BitcoinAddress::parse(&auth_sig.address)?;
+    Ok(network.chain().to_string())
+}
+
 // Custom deserialization logic for JsonAuthSig
 
 #[derive(Deserialize)]
@@ -1009,6 +1058,7 @@ mod multiple_auth_sigs_tests {
                     kyve: None,
                     cheqd: None,
                     juno: None,
+                    bitcoin: None,
                 },
                 expected_resource_ids: vec![
                     "524a697a410a417fb95a9f52d57cba5fa7c87b3acd3b408cf14560fa52691251".into(),
@@ -1053,6 +1103,7 @@ mod multiple_auth_sigs_tests {
                     kyve: None,
                     cheqd: None,
                     juno: None,
+                    bitcoin: None,
                 },
                 expected_resource_ids: vec!["123".into()],
                 expected_resource_prefixes: vec![LitResourcePrefix::PKP],
@@ -1095,6 +1146,7 @@ mod multiple_auth_sigs_tests {
                     kyve: None,
                     cheqd: None,
                     juno: None,
+                    bitcoin: None,
                 },
                 expected_resource_ids: vec!["123".into()],
                 expected_resource_prefixes: vec![LitResourcePrefix::PKP],
@@ -1137,6 +1189,7 @@ mod multiple_auth_sigs_tests {
                     )),
                     cheqd: None,
                     juno: None,
+                    bitcoin: None,
                 },
                 expected_resource_ids: vec!["123".into()],
                 expected_resource_prefixes: vec![LitResourcePrefix::PKP],
@@ -1179,6 +1232,7 @@ mod multiple_auth_sigs_tests {
                         None,
                     )),
                     juno: None,
+                    bitcoin: None,
                 },
                 expected_resource_ids: vec!["123".into()],
                 expected_resource_prefixes: vec![LitResourcePrefix::PKP],
@@ -1221,6 +1275,7 @@ mod multiple_auth_sigs_tests {
                         AuthMaterialType::SessionSig,
                         None,
                     )),
+                    bitcoin: None,
                 },
                 expected_resource_ids: vec!["123".into()],
                 expected_resource_prefixes: vec![LitResourcePrefix::PKP],
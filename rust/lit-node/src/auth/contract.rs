@@ -1,9 +1,13 @@
+use crate::config::LitNodeConfig;
 use crate::error::{blockchain_err_code, validation_err_code, Result, EC};
 use encoding::hex_to_bytes;
 use ethers::middleware::SignerMiddleware;
+use ethers::providers::Middleware;
 use ethers::signers::LocalWallet;
+use ethers::types::Address;
 use ethers::{contract::abigen, types::Bytes};
 
+use lit_core::config::LitConfig;
 use lit_core::error::Unexpected;
 use lit_core::utils::binary::bytes_to_hex;
 use rand_core::OsRng;
@@ -15,10 +19,102 @@ use lit_blockchain::resolver::rpc::{RpcHealthcheckPoller, ENDPOINT_MANAGER};
 
 const VALID_SIGNATURE_WORD: &str = "1626ba7e";
 
+/// Suffix of a signature wrapped per EIP-6492, i.e. one made by a smart wallet that has not
+/// been deployed yet.
+pub const EIP6492_MAGIC_SUFFIX: [u8; 32] = [
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+];
+
+abigen!(
+    UniversalSigValidator,
+    r#"[
+            function isValidSig(address _signer, bytes32 _hash, bytes calldata _signature) external returns (bool)
+        ]"#,
+);
+
+/// Whether the signature is wrapped per EIP-6492.
+pub fn is_eip6492_signature(sig: &[u8]) -> bool {
+    sig.len() > EIP6492_MAGIC_SUFFIX.len() && sig.ends_with(&EIP6492_MAGIC_SUFFIX)
+}
+
+/// Validate an EIP-6492 wrapped signature of `hash` by the (possibly undeployed) smart wallet
+/// `signer`.
+///
+/// The wrapped signature carries the wallet's factory and deploy calldata, so it can't be checked
+/// with `isValidSignature` directly. Instead we `eth_call` the universal validator, which
+/// simulates the deployment and then performs the EIP-1271 check. The validator is set with
+/// `node.eip6492_validator_address` and these signatures are rejected while it is unset.
+pub async fn validate_eip6492_signature(
+    signer: Address,
+    hash: [u8; 32],
+    sig: &[u8],
+    chain: &Option<String>,
+    validator_address: Option<Address>,
+) -> Result<()> {
+    let validator_address = validator_address.expect_or_err_code(
+        EC::NodeEIP6492ValidatorNotConfigured,
+        "No EIP-6492 validator configured for counterfactual smart wallet signatures (node.eip6492_validator_address)",
+    )?;
+    let c = chain
+        .clone()
+        .expect_or_err_code(EC::NodeBlockchainChainUnknown, "Empty chain value")?;
+
+    let wallet = LocalWallet::new(&mut OsRng);
+    let provider = ENDPOINT_MANAGER.get_provider(&c)?;
+    let client = SignerMiddleware::new(provider.clone(), wallet.clone());
+
+    check_eip6492_signature(Arc::new(client), validator_address, signer, hash, sig, &c).await
+}
+
+/// Ask the universal validator at `validator_address` whether `sig` is `signer`'s signature of
+/// `hash`.
+async fn check_eip6492_signature<M: Middleware + 'static>(
+    client: Arc<M>,
+    validator_address: Address,
+    signer: Address,
+    hash: [u8; 32],
+    sig: &[u8],
+    c: &str,
+) -> Result<()> {
+    let contract = UniversalSigValidator::new(validator_address, client);
+
+    let is_valid = contract
+        .is_valid_sig(signer, hash, sig.to_vec().into())
+        .call()
+        .await
+        .map_err(|e| {
+            blockchain_err_code(
+                e,
+                EC::NodeBlockchainError,
+                Some(format!(
+                    "Call to EIP-6492 validator {:?} isValidSig function failed on chain {}",
+                    validator_address, c
+                )),
+            )
+        })?;
+    if !is_valid {
+        return Err(validation_err_code(
+            "EIP6492 Authsig failed",
+            EC::NodeContractAuthsigUnauthorized,
+            Some(format!(
+                "Authsig failed for counterfactual wallet {:?} via validator {:?} on chain {}",
+                signer, validator_address, c
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
 /// Validate a signature that is meant to be validated by a smart contract per EIP-1271.
+///
+/// Signatures wrapped per EIP-6492 (the contract is not deployed yet) are validated through
+/// the configured universal validator instead.
 pub async fn validate_eip1271_signature(
     auth_sig: &JsonAuthSig,
     chain: &Option<String>,
+    cfg: &LitConfig,
 ) -> Result<()> {
     let presented_address = ethers::types::Address::from_str(&auth_sig.address)
         .map_err(|e| validation_err_code(e, EC::NodeAuthSigAddressConversionError, None))?;
@@ -31,6 +127,27 @@ pub async fn validate_eip1271_signature(
         .clone()
         .expect_or_err_code(EC::NodeBlockchainChainUnknown, "Empty chain value")?;
 
+    let signed_message_vec = hex_to_bytes(&auth_sig.signed_message)?;
+    let signed_message_bytes: [u8; 32] = signed_message_vec.try_into().map_err(|e| {
+        validation_err_code(
+            "Invalid length for EIP1271 signed message - must be 32 bytes",
+            EC::NodeSerializationError,
+            None,
+        )
+    })?;
+    let sig = hex_to_bytes(&auth_sig.sig)?;
+
+    if is_eip6492_signature(&sig) {
+        return validate_eip6492_signature(
+            presented_address,
+            signed_message_bytes,
+            &sig,
+            chain,
+            cfg.eip6492_validator_address().ok(),
+        )
+        .await;
+    }
+
     abigen!(
         EIP1271,
         r#"[
@@ -42,15 +159,6 @@ pub async fn validate_eip1271_signature(
     let client = SignerMiddleware::new(provider.clone(), wallet.clone());
     let contract = EIP1271::new(presented_address, Arc::new(client.clone()));
 
-    let signed_message_vec = hex_to_bytes(&auth_sig.signed_message)?;
-    let signed_message_bytes: [u8; 32] = signed_message_vec.try_into().map_err(|e| {
-        validation_err_code(
-            "Invalid length for EIP1271 signed message - must be 32 bytes",
-            EC::NodeSerializationError,
-            None,
-        )
-    })?;
-    let sig = hex_to_bytes(&auth_sig.sig)?;
     // debug!(
     //     "signed_message_bytes {} with length {:?}",
     //     bytes_to_hex(&signed_message_bytes),
@@ -85,3 +193,101 @@ pub async fn validate_eip1271_signature(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ethers::abi::{self, Token};
+    use ethers::providers::Provider;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::{Address, Bytes};
+    use rand_core::OsRng;
+
+    use super::{
+        check_eip6492_signature, is_eip6492_signature, validate_eip6492_signature,
+        UniversalSigValidator, EIP6492_MAGIC_SUFFIX,
+    };
+    use crate::error::EC;
+
+    /// A signature by the owner of a smart wallet that has not been deployed yet, wrapped with
+    /// the factory call that would deploy it.
+    fn counterfactual_signature(owner: &LocalWallet, hash: [u8; 32]) -> Vec<u8> {
+        let inner = owner.sign_hash(hash.into()).unwrap().to_vec();
+        let deploy_calldata = [
+            &[0x5f, 0xbf, 0xb9, 0xcf][..],
+            &[0u8; 12],
+            owner.address().as_bytes(),
+        ]
+        .concat();
+        let mut sig = abi::encode(&[
+            Token::Address(Address::repeat_byte(0xfa)),
+            Token::Bytes(deploy_calldata),
+            Token::Bytes(inner),
+        ]);
+        sig.extend_from_slice(&EIP6492_MAGIC_SUFFIX);
+        sig
+    }
+
+    #[tokio::test]
+    async fn counterfactual_wallet_signature_test() {
+        let owner = LocalWallet::new(&mut OsRng);
+        let smart_wallet = Address::repeat_byte(0x42);
+        let validator = Address::repeat_byte(0x64);
+        let message: siwe::Message = format!(
+            "localhost wants you to sign in with your Ethereum account:\n{}\n\nThis is a test.\n\nURI: lit:session:abc\nVersion: 1\nChain ID: 1\nNonce: 0123456789abcdef\nIssued At: 2023-01-01T00:00:00Z",
+            ethers::utils::to_checksum(&smart_wallet, None)
+        )
+        .parse()
+        .unwrap();
+        let hash = message.eip191_hash().unwrap();
+        let sig = counterfactual_signature(&owner, hash);
+        assert!(is_eip6492_signature(&sig));
+
+        // Without a configured validator the signature is rejected before touching the chain.
+        let err =
+            validate_eip6492_signature(smart_wallet, hash, &sig, &Some("ethereum".into()), None)
+                .await
+                .unwrap_err();
+        assert!(err.is_code(EC::NodeEIP6492ValidatorNotConfigured, false));
+
+        // The wrapped signature is handed to the validator as is.
+        let (provider, mock) = Provider::mocked();
+        let client = Arc::new(provider);
+        let expected = UniversalSigValidator::new(validator, client.clone())
+            .is_valid_sig(smart_wallet, hash, sig.clone().into())
+            .tx;
+        mock.push(Bytes::from(abi::encode(&[Token::Bool(true)])))
+            .unwrap();
+        check_eip6492_signature(
+            client.clone(),
+            validator,
+            smart_wallet,
+            hash,
+            &sig,
+            "ethereum",
+        )
+        .await
+        .unwrap();
+        mock.assert_request("eth_call", (expected, "latest"))
+            .unwrap();
+
+        mock.push(Bytes::from(abi::encode(&[Token::Bool(false)])))
+            .unwrap();
+        let err = check_eip6492_signature(client, validator, smart_wallet, hash, &sig, "ethereum")
+            .await
+            .unwrap_err();
+        assert!(err.is_code(EC::NodeContractAuthsigUnauthorized, false));
+    }
+
+    #[test]
+    fn is_eip6492_signature_test() {
+        let mut wrapped = vec![0xab; 96];
+        wrapped.extend_from_slice(&EIP6492_MAGIC_SUFFIX);
+
+        assert!(is_eip6492_signature(&wrapped));
+        assert!(!is_eip6492_signature(&wrapped[..wrapped.len() - 1]));
+        assert!(!is_eip6492_signature(&EIP6492_MAGIC_SUFFIX));
+        assert!(!is_eip6492_signature(&[0xab; 65]));
+    }
+}
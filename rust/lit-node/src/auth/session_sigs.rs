@@ -175,6 +175,8 @@ pub(crate) async fn validate_session_sig(
         &session_key_signed_message.capabilities,
         requested_lit_resource_ability,
         bls_root_pubkey,
+        chain,
+        cfg,
     )
     .await
     .map_err(|e| {
@@ -320,6 +322,8 @@ async fn validate_capabilities_array<'a>(
     capabilities: &'a Vec<JsonAuthSig>,
     requested_lit_resource_ability: &LitResourceAbility,
     bls_root_pubkey: &String,
+    chain: &Option<String>,
+    cfg: &LitConfig,
 ) -> Result<&'a JsonAuthSig> {
     if capabilities.is_empty() {
         return Err(validation_err_code(
@@ -330,8 +334,10 @@ async fn validate_capabilities_array<'a>(
         .add_source_to_details());
     }
 
-    // Init validator
-    let auth_sig_validator: Arc<dyn SessionSigAuthSigValidator> = Arc::new(SiweValidator::new());
+    // Init validator, smart wallets that are not deployed yet sign with EIP-6492.
+    let auth_sig_validator: Arc<dyn SessionSigAuthSigValidator> = Arc::new(
+        SiweValidator::with_eip6492(chain.clone(), cfg.eip6492_validator_address().ok()),
+    );
 
     // Validate each capability
     for inner_auth_sig in capabilities {
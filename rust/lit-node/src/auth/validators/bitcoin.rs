@@ -0,0 +1,822 @@
+//! Bitcoin wallet signatures.
+//!
+//! Supports BIP-322 "simple" and "full" signatures for P2WPKH and P2TR addresses, and the legacy
+//! `signmessage` format (BIP-137) for P2PKH and P2WPKH addresses. The signed message must be a
+//! SIWE style "Sign in with Bitcoin" message (see `BitcoinSignInMessage`).
+use crate::auth::auth_material::JsonAuthSig;
+use crate::constants::{Chain, CHAIN_BITCOIN, CHAIN_BITCOIN_TESTNET};
+use crate::error::{conversion_err_code, parser_err_code, validation_err_code, Result, EC};
+use bech32::{FromBase32, Variant};
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use ripemd::{Digest, Ripemd160};
+use sha2::Sha256;
+use std::str::FromStr;
+
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+const TAP_SIGHASH_TAG: &[u8] = b"TapSighash";
+const SIGNED_MESSAGE_PREFIX: &[u8] = b"\x18Bitcoin Signed Message:\n";
+
+const SIGN_IN_HEADER_SUFFIX: &str = " wants you to sign in with your Bitcoin account:";
+
+const SIGHASH_DEFAULT: u8 = 0x00;
+const SIGHASH_ALL: u8 = 0x01;
+const OP_RETURN: u8 = 0x6a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitcoinNetwork {
+    Mainnet,
+    Testnet,
+}
+
+impl BitcoinNetwork {
+    pub fn from_chain(chain: &str) -> Option<Self> {
+        match chain {
+            CHAIN_BITCOIN => Some(BitcoinNetwork::Mainnet),
+            CHAIN_BITCOIN_TESTNET => Some(BitcoinNetwork::Testnet),
+            _ => None,
+        }
+    }
+
+    pub fn chain(&self) -> Chain {
+        match self {
+            BitcoinNetwork::Mainnet => Chain::Bitcoin,
+            BitcoinNetwork::Testnet => Chain::BitcoinTestnet,
+        }
+    }
+
+    fn bech32_hrp(&self) -> &'static str {
+        match self {
+            BitcoinNetwork::Mainnet => "bc",
+            BitcoinNetwork::Testnet => "tb",
+        }
+    }
+
+    fn p2pkh_version(&self) -> u8 {
+        match self {
+            BitcoinNetwork::Mainnet => 0x00,
+            BitcoinNetwork::Testnet => 0x6f,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitcoinAddress {
+    P2pkh([u8; 20]),
+    P2wpkh([u8; 20]),
+    P2tr([u8; 32]),
+}
+
+impl BitcoinAddress {
+    /// Parse a P2PKH (base58), P2WPKH (bech32) or P2TR (bech32m) address.
+    pub fn parse(address: &str) -> Result<(BitcoinNetwork, Self)> {
+        match bech32::decode(address) {
+            Ok((hrp, data, variant)) => Self::parse_segwit(&hrp, &data, variant),
+            Err(_) => Self::parse_base58(address),
+        }
+    }
+
+    fn parse_segwit(
+        hrp: &str,
+        data: &[bech32::u5],
+        variant: Variant,
+    ) -> Result<(BitcoinNetwork, Self)> {
+        let network = [BitcoinNetwork::Mainnet, BitcoinNetwork::Testnet]
+            .into_iter()
+            .find(|n| n.bech32_hrp() == hrp)
+            .ok_or_else(|| unsupported_address(format!("unknown address prefix '{}'", hrp)))?;
+        let (version, program) = data
+            .split_first()
+            .ok_or_else(|| unsupported_address("empty witness program"))?;
+        let program = Vec::<u8>::from_base32(program)
+            .map_err(|e| conversion_err_code(e, EC::NodeAuthSigAddressConversionError, None))?;
+
+        let address = match (version.to_u8(), variant, program.len()) {
+            (0, Variant::Bech32, 20) => BitcoinAddress::P2wpkh(to_array(&program)?),
+            (1, Variant::Bech32m, 32) => BitcoinAddress::P2tr(to_array(&program)?),
+            (version, _, len) => {
+                return Err(unsupported_address(format!(
+                    "unsupported witness program (version {}, {} bytes)",
+                    version, len
+                )))
+            }
+        };
+
+        Ok((network, address))
+    }
+
+    fn parse_base58(address: &str) -> Result<(BitcoinNetwork, Self)> {
+        let data = bs58::decode(address)
+            .into_vec()
+            .map_err(|e| conversion_err_code(e, EC::NodeAuthSigAddressConversionError, None))?;
+        if data.len() != 25 || sha256d(&data[..21])[..4] != data[21..] {
+            return Err(unsupported_address("invalid base58check address"));
+        }
+
+        let network = [BitcoinNetwork::Mainnet, BitcoinNetwork::Testnet]
+            .into_iter()
+            .find(|n| n.p2pkh_version() == data[0])
+            .ok_or_else(|| unsupported_address("only P2PKH base58 addresses are supported"))?;
+
+        Ok((network, BitcoinAddress::P2pkh(to_array(&data[1..21])?)))
+    }
+
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        match self {
+            // OP_DUP OP_HASH160 <20> OP_EQUALVERIFY OP_CHECKSIG
+            BitcoinAddress::P2pkh(hash) => [&[0x76, 0xa9, 0x14][..], hash, &[0x88, 0xac]].concat(),
+            // OP_0 <20>
+            BitcoinAddress::P2wpkh(hash) => [&[0x00, 0x14][..], hash].concat(),
+            // OP_1 <32>
+            BitcoinAddress::P2tr(key) => [&[0x51, 0x20][..], key].concat(),
+        }
+    }
+}
+
+/// A SIWE (EIP-4361) style message signed by a Bitcoin account:
+///
+/// ```text
+/// example.com wants you to sign in with your Bitcoin account:
+/// bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l
+///
+/// An optional statement.
+///
+/// URI: https://example.com/login
+/// Version: 1
+/// Chain ID: 1
+/// Nonce: 32891756
+/// Issued At: 2024-01-01T00:00:00Z
+/// Expiration Time: 2024-01-02T00:00:00Z
+/// ```
+///
+/// Only the fields the node checks are kept; the others are accepted as is.
+#[derive(Debug, Clone)]
+pub struct BitcoinSignInMessage {
+    pub domain: String,
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub issued_at: siwe::TimeStamp,
+    pub expiration_time: Option<siwe::TimeStamp>,
+    pub not_before: Option<siwe::TimeStamp>,
+}
+
+impl BitcoinSignInMessage {
+    pub fn parse(message: &str) -> Result<Self> {
+        let mut lines = message.lines();
+
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(SIGN_IN_HEADER_SUFFIX))
+            .ok_or_else(|| sign_in_err("missing the sign in header"))?;
+        if !is_valid_domain(domain) {
+            return Err(sign_in_err(format!("invalid domain '{}'", domain)));
+        }
+        let address = lines
+            .next()
+            .filter(|address| !address.is_empty())
+            .ok_or_else(|| sign_in_err("missing the address"))?;
+        if lines.next() != Some("") {
+            return Err(sign_in_err("expected an empty line after the address"));
+        }
+
+        let mut lines = lines.peekable();
+        let mut statement = None;
+        if let Some(line) = lines.next_if(|line| !line.starts_with("URI: ")) {
+            statement = Some(line.to_string());
+            if lines.next() != Some("") {
+                return Err(sign_in_err("expected an empty line after the statement"));
+            }
+        }
+
+        let mut uri = None;
+        let mut issued_at = None;
+        let mut expiration_time = None;
+        let mut not_before = None;
+        for line in lines {
+            let (field, value) = line.split_once(": ").unwrap_or((line, ""));
+            let slot = match field {
+                "URI" => &mut uri,
+                "Issued At" => &mut issued_at,
+                "Expiration Time" => &mut expiration_time,
+                "Not Before" => &mut not_before,
+                _ => continue,
+            };
+            if slot.replace(value).is_some() {
+                return Err(sign_in_err(format!("duplicate field '{}'", field)));
+            }
+        }
+
+        let uri = uri.ok_or_else(|| sign_in_err("missing the URI"))?;
+        url::Url::parse(uri).map_err(|e| {
+            parser_err_code(e, EC::NodeSIWEMessageError, Some("Invalid URI".into()))
+        })?;
+
+        Ok(Self {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            statement,
+            uri: uri.to_string(),
+            issued_at: parse_timestamp(issued_at.ok_or_else(|| sign_in_err("missing Issued At"))?)?,
+            expiration_time: expiration_time.map(parse_timestamp).transpose()?,
+            not_before: not_before.map(parse_timestamp).transpose()?,
+        })
+    }
+}
+
+/// A domain is an RFC 3986 authority (i.e. `example.com` or `localhost:7470`).
+fn is_valid_domain(domain: &str) -> bool {
+    if domain.is_empty() || domain.contains(char::is_whitespace) {
+        return false;
+    }
+    match url::Url::parse(&format!("https://{}", domain)) {
+        Ok(url) => {
+            url.host_str().is_some()
+                && url.username().is_empty()
+                && url.path() == "/"
+                && url.query().is_none()
+                && url.fragment().is_none()
+        }
+        Err(_) => false,
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<siwe::TimeStamp> {
+    siwe::TimeStamp::from_str(value).map_err(|e| {
+        parser_err_code(
+            e,
+            EC::NodeSIWEMessageError,
+            Some(format!("Invalid timestamp '{}'", value)),
+        )
+    })
+}
+
+fn sign_in_err<M: Into<String>>(msg: M) -> crate::error::Error {
+    parser_err_code(
+        format!("Invalid Bitcoin sign in message: {}", msg.into()),
+        EC::NodeSIWEMessageError,
+        None,
+    )
+}
+
+/// Validate a Bitcoin wallet signature of `signed_message`, optionally checking the address
+/// belongs to the network of `chain`.
+pub fn validate_bitcoin_auth_sig(auth_sig: &JsonAuthSig, chain: Option<&str>) -> Result<bool> {
+    let (network, address) = BitcoinAddress::parse(&auth_sig.address)?;
+    if let Some(chain) = chain {
+        if BitcoinNetwork::from_chain(chain) != Some(network) {
+            return Err(validation_err_code(
+                format!("{} is not a {} address", auth_sig.address, chain),
+                EC::NodeInvalidAuthSig,
+                None,
+            ));
+        }
+    }
+
+    let sig = data_encoding::BASE64
+        .decode(auth_sig.sig.as_bytes())
+        .map_err(|e| parser_err_code(e, EC::NodeAuthSigSignatureConversionError, None))?;
+    let message = auth_sig.signed_message.as_bytes();
+
+    if is_legacy_signature(&sig) {
+        verify_legacy(&address, message, &sig)
+    } else {
+        verify_bip322(&address, message, &sig)
+    }
+}
+
+/// A legacy signature is 65 bytes, the first being the BIP-137 header.
+fn is_legacy_signature(sig: &[u8]) -> bool {
+    sig.len() == 65 && (27..=42).contains(&sig[0])
+}
+
+fn verify_legacy(address: &BitcoinAddress, message: &[u8], sig: &[u8]) -> Result<bool> {
+    // 27-30: uncompressed P2PKH, 31-34: compressed P2PKH, 35-38: P2SH-P2WPKH, 39-42: P2WPKH
+    let header = sig[0];
+    let compressed = header >= 31;
+    let recovery_id = k256::ecdsa::RecoveryId::from_byte((header - 27) & 3)
+        .ok_or_else(|| signature_err("invalid recovery id"))?;
+    let signature = k256::ecdsa::Signature::from_slice(&sig[1..])
+        .map_err(|e| validation_err_code(e, EC::NodeAuthSigSignatureConversionError, None))?;
+
+    let hash = signmessage_hash(message);
+    let key = match k256::ecdsa::VerifyingKey::recover_from_prehash(&hash, &signature, recovery_id)
+    {
+        Ok(key) => key,
+        Err(e) => {
+            debug!("Unable to recover bitcoin signmessage key: {:?}", e);
+            return Ok(false);
+        }
+    };
+    let pubkey_hash = hash160(key.to_encoded_point(compressed).as_bytes());
+
+    Ok(match address {
+        BitcoinAddress::P2pkh(hash) => (27..=34).contains(&header) && *hash == pubkey_hash,
+        BitcoinAddress::P2wpkh(hash) => compressed && *hash == pubkey_hash,
+        BitcoinAddress::P2tr(_) => false,
+    })
+}
+
+fn verify_bip322(address: &BitcoinAddress, message: &[u8], sig: &[u8]) -> Result<bool> {
+    let script_pubkey = address.script_pubkey();
+    let to_spend_txid = to_spend_txid(&tagged_hash(BIP322_TAG, message), &script_pubkey);
+
+    // "simple" signatures are just the witness stack, "full" ones the whole to_sign transaction.
+    let to_sign = match parse_witness(sig) {
+        Some(witness) => ToSign::simple(to_spend_txid, witness),
+        None => ToSign::parse(sig)?,
+    };
+    if to_sign.prevout != (to_spend_txid, 0) {
+        return Err(signature_err(
+            "to_sign does not spend the to_spend transaction",
+        ));
+    }
+
+    match address {
+        BitcoinAddress::P2wpkh(hash) => verify_p2wpkh(&to_sign, hash),
+        BitcoinAddress::P2tr(key) => verify_p2tr(&to_sign, key, &script_pubkey),
+        // Per BIP-322, P2PKH addresses sign with the legacy format.
+        BitcoinAddress::P2pkh(_) => Ok(false),
+    }
+}
+
+fn verify_p2wpkh(to_sign: &ToSign, hash: &[u8; 20]) -> Result<bool> {
+    let [sig, pubkey] = to_sign.witness.as_slice() else {
+        return Err(signature_err("P2WPKH witness must be <signature> <pubkey>"));
+    };
+    let Some((&sighash_type, der)) = sig.split_last() else {
+        return Err(signature_err("empty signature"));
+    };
+    if sighash_type != SIGHASH_ALL {
+        return Err(signature_err("only SIGHASH_ALL is supported"));
+    }
+    if hash160(pubkey) != *hash {
+        return Ok(false);
+    }
+
+    let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(pubkey)
+        .map_err(|e| conversion_err_code(e, EC::NodeAuthSigAddressConversionError, None))?;
+    let signature = k256::ecdsa::Signature::from_der(der)
+        .map_err(|e| validation_err_code(e, EC::NodeAuthSigSignatureConversionError, None))?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    // BIP-143 sighash, spending a 0 value output.
+    let script_code = [&[0x19, 0x76, 0xa9, 0x14][..], hash, &[0x88, 0xac]].concat();
+    let mut preimage = Vec::new();
+    preimage.extend_from_slice(&to_sign.version.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(&to_sign.outpoint()));
+    preimage.extend_from_slice(&sha256d(&to_sign.sequence.to_le_bytes()));
+    preimage.extend_from_slice(&to_sign.outpoint());
+    preimage.extend_from_slice(&script_code);
+    preimage.extend_from_slice(&0u64.to_le_bytes());
+    preimage.extend_from_slice(&to_sign.sequence.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(&to_sign.outputs()));
+    preimage.extend_from_slice(&to_sign.lock_time.to_le_bytes());
+    preimage.extend_from_slice(&(sighash_type as u32).to_le_bytes());
+
+    Ok(key.verify_prehash(&sha256d(&preimage), &signature).is_ok())
+}
+
+fn verify_p2tr(to_sign: &ToSign, key: &[u8; 32], script_pubkey: &[u8]) -> Result<bool> {
+    // Key path spends only, without an annex.
+    let [sig] = to_sign.witness.as_slice() else {
+        return Err(signature_err(
+            "P2TR witness must be a single key path signature",
+        ));
+    };
+    let sighash_type = match sig.len() {
+        64 => SIGHASH_DEFAULT,
+        65 if sig[64] == SIGHASH_ALL => SIGHASH_ALL,
+        _ => {
+            return Err(signature_err(
+                "only SIGHASH_DEFAULT and SIGHASH_ALL are supported",
+            ))
+        }
+    };
+
+    let key = k256::schnorr::VerifyingKey::from_bytes(key)
+        .map_err(|e| conversion_err_code(e, EC::NodeAuthSigAddressConversionError, None))?;
+    let signature = k256::schnorr::Signature::try_from(&sig[..64])
+        .map_err(|e| validation_err_code(e, EC::NodeAuthSigSignatureConversionError, None))?;
+
+    // BIP-341 sighash (epoch 0), spending a 0 value output.
+    let mut message = vec![0x00, sighash_type];
+    message.extend_from_slice(&to_sign.version.to_le_bytes());
+    message.extend_from_slice(&to_sign.lock_time.to_le_bytes());
+    message.extend_from_slice(&sha256(&to_sign.outpoint()));
+    message.extend_from_slice(&sha256(&0u64.to_le_bytes()));
+    message.extend_from_slice(&sha256(
+        &[&varint(script_pubkey.len()), script_pubkey].concat(),
+    ));
+    message.extend_from_slice(&sha256(&to_sign.sequence.to_le_bytes()));
+    message.extend_from_slice(&sha256(&to_sign.outputs()));
+    message.push(0x00); // spend type: key path, no annex
+    message.extend_from_slice(&0u32.to_le_bytes()); // input index
+
+    Ok(key
+        .verify_prehash(&tagged_hash(TAP_SIGHASH_TAG, &message), &signature)
+        .is_ok())
+}
+
+/// The BIP-322 `to_sign` transaction (its single input and output).
+struct ToSign {
+    version: u32,
+    lock_time: u32,
+    prevout: ([u8; 32], u32),
+    sequence: u32,
+    witness: Vec<Vec<u8>>,
+    output_script: Vec<u8>,
+}
+
+impl ToSign {
+    fn simple(to_spend_txid: [u8; 32], witness: Vec<Vec<u8>>) -> Self {
+        Self {
+            version: 0,
+            lock_time: 0,
+            prevout: (to_spend_txid, 0),
+            sequence: 0,
+            witness,
+            output_script: vec![OP_RETURN],
+        }
+    }
+
+    /// Parse a "full" signature. Proof of funds (additional inputs) is not supported.
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(data);
+        let version = r.u32()?;
+        if r.take(2)? != [0x00, 0x01] {
+            return Err(signature_err("to_sign must be a segwit transaction"));
+        }
+        if r.varint()? != 1 {
+            return Err(signature_err("to_sign must have a single input"));
+        }
+        let prevout = (to_array(r.take(32)?)?, r.u32()?);
+        let script_sig_len = r.varint()?;
+        if script_sig_len != 0 {
+            return Err(signature_err("to_sign must have an empty scriptSig"));
+        }
+        let sequence = r.u32()?;
+        if r.varint()? != 1 || r.u64()? != 0 {
+            return Err(signature_err("to_sign must have a single 0 value output"));
+        }
+        let output_script_len = r.varint()?;
+        let output_script = r.take(output_script_len)?.to_vec();
+        if output_script != [OP_RETURN] {
+            return Err(signature_err("to_sign output must be OP_RETURN"));
+        }
+        let witness = r.witness()?;
+        let lock_time = r.u32()?;
+        if !r.is_empty() {
+            return Err(signature_err("trailing data after to_sign"));
+        }
+
+        Ok(Self {
+            version,
+            lock_time,
+            prevout,
+            sequence,
+            witness,
+            output_script,
+        })
+    }
+
+    fn outpoint(&self) -> Vec<u8> {
+        [&self.prevout.0[..], &self.prevout.1.to_le_bytes()].concat()
+    }
+
+    fn outputs(&self) -> Vec<u8> {
+        serialize_output(0, &self.output_script)
+    }
+}
+
+/// The txid of the BIP-322 `to_spend` transaction (internal byte order).
+fn to_spend_txid(message_hash: &[u8; 32], script_pubkey: &[u8]) -> [u8; 32] {
+    let script_sig = [&[0x00, 0x20][..], message_hash].concat();
+
+    let mut tx = Vec::new();
+    tx.extend_from_slice(&0u32.to_le_bytes()); // version
+    tx.push(1);
+    tx.extend_from_slice(&[0u8; 32]);
+    tx.extend_from_slice(&0xffffffffu32.to_le_bytes());
+    tx.extend_from_slice(&varint(script_sig.len()));
+    tx.extend_from_slice(&script_sig);
+    tx.extend_from_slice(&0u32.to_le_bytes()); // sequence
+    tx.push(1);
+    tx.extend_from_slice(&serialize_output(0, script_pubkey));
+    tx.extend_from_slice(&0u32.to_le_bytes()); // lock time
+
+    sha256d(&tx)
+}
+
+/// Parse a consensus encoded witness stack, `None` unless it spans all of `data`.
+fn parse_witness(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut r = Reader::new(data);
+    let witness = r.witness().ok()?;
+    r.is_empty().then_some(witness)
+}
+
+pub(crate) fn signmessage_hash(message: &[u8]) -> [u8; 32] {
+    sha256d(&[SIGNED_MESSAGE_PREFIX, &varint(message.len()), message].concat())
+}
+
+fn serialize_output(value: u64, script: &[u8]) -> Vec<u8> {
+    [&value.to_le_bytes()[..], &varint(script.len()), script].concat()
+}
+
+fn varint(n: usize) -> Vec<u8> {
+    match n {
+        0..=0xfc => vec![n as u8],
+        0xfd..=0xffff => [&[0xfd][..], &(n as u16).to_le_bytes()].concat(),
+        _ => [&[0xfe][..], &(n as u32).to_le_bytes()].concat(),
+    }
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+fn tagged_hash(tag: &[u8], data: &[u8]) -> [u8; 32] {
+    let tag = sha256(tag);
+    sha256(&[&tag[..], &tag, data].concat())
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(sha256(data)).into()
+}
+
+fn to_array<const N: usize>(data: &[u8]) -> Result<[u8; N]> {
+    data.try_into()
+        .map_err(|e| conversion_err_code(e, EC::NodeAuthSigAddressConversionError, None))
+}
+
+fn unsupported_address<M: Into<String>>(msg: M) -> crate::error::Error {
+    validation_err_code(msg.into(), EC::NodeAuthSigAddressConversionError, None)
+}
+
+fn signature_err(msg: &str) -> crate::error::Error {
+    validation_err_code(msg, EC::NodeAuthSigSignatureConversionError, None)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.data.len() < n {
+            return Err(signature_err("unexpected end of data"));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(to_array(self.take(4)?)?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(to_array(self.take(8)?)?))
+    }
+
+    fn varint(&mut self) -> Result<usize> {
+        Ok(match self.take(1)?[0] {
+            0xfd => u16::from_le_bytes(to_array(self.take(2)?)?) as usize,
+            0xfe => u32::from_le_bytes(to_array(self.take(4)?)?) as usize,
+            0xff => return Err(signature_err("varint too large")),
+            n => n as usize,
+        })
+    }
+
+    fn witness(&mut self) -> Result<Vec<Vec<u8>>> {
+        let count = self.varint()?;
+        let mut items = Vec::new();
+        for _ in 0..count {
+            let len = self.varint()?;
+            items.push(self.take(len)?.to_vec());
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash160, sha256d, signmessage_hash};
+    use super::{
+        tagged_hash, to_spend_txid, validate_bitcoin_auth_sig, BitcoinAddress, BitcoinNetwork,
+        BitcoinSignInMessage, BIP322_TAG,
+    };
+    use crate::auth::auth_material::JsonAuthSig;
+    use crate::auth::validators::wallet_sig::validate_wallet_sig_by_chain;
+    use crate::tests::common::get_test_config;
+    use crate::utils::encoding;
+    use chrono::{Duration, Utc};
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+    use lit_core::config::LitConfig;
+
+    // Test vectors from BIP-322.
+    const P2WPKH_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR_ADDRESS: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+    const P2PKH_ADDRESS: &str = "14vV3aCHBeStb5bkenkNHbe2YAFinYdXgc";
+
+    fn auth_sig(address: &str, message: &str, sig: &str) -> JsonAuthSig {
+        JsonAuthSig::new(
+            sig.to_string(),
+            "bip322".to_string(),
+            message.to_string(),
+            address.to_string(),
+            None,
+        )
+    }
+
+    #[test]
+    fn parse_address_test() {
+        assert!(matches!(
+            BitcoinAddress::parse(P2WPKH_ADDRESS).unwrap(),
+            (BitcoinNetwork::Mainnet, BitcoinAddress::P2wpkh(_))
+        ));
+        assert!(matches!(
+            BitcoinAddress::parse(P2TR_ADDRESS).unwrap(),
+            (BitcoinNetwork::Mainnet, BitcoinAddress::P2tr(_))
+        ));
+        assert!(matches!(
+            BitcoinAddress::parse(P2PKH_ADDRESS).unwrap(),
+            (BitcoinNetwork::Mainnet, BitcoinAddress::P2pkh(_))
+        ));
+        // P2SH
+        assert!(BitcoinAddress::parse("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").is_err());
+    }
+
+    #[test]
+    fn to_spend_test() {
+        let (_, address) = BitcoinAddress::parse(P2WPKH_ADDRESS).unwrap();
+        let message_hash = tagged_hash(BIP322_TAG, b"Hello World");
+        assert_eq!(
+            encoding::bytes_to_hex(message_hash),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let mut txid = to_spend_txid(&message_hash, &address.script_pubkey());
+        txid.reverse();
+        assert_eq!(
+            encoding::bytes_to_hex(txid),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+    }
+
+    #[test]
+    fn bip322_simple_test() {
+        let p2wpkh_sig = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        assert!(validate_bitcoin_auth_sig(
+            &auth_sig(P2WPKH_ADDRESS, "Hello World", p2wpkh_sig),
+            None
+        )
+        .unwrap());
+        assert!(
+            !validate_bitcoin_auth_sig(&auth_sig(P2WPKH_ADDRESS, "Hello", p2wpkh_sig), None)
+                .unwrap()
+        );
+
+        let p2tr_sig =
+            "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+        assert!(validate_bitcoin_auth_sig(
+            &auth_sig(P2TR_ADDRESS, "Hello World", p2tr_sig),
+            Some("bitcoin")
+        )
+        .unwrap());
+        assert!(validate_bitcoin_auth_sig(
+            &auth_sig(P2TR_ADDRESS, "Hello World", p2tr_sig),
+            Some("bitcoinTestnet")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn legacy_signmessage_test() {
+        let sig = "H8afIFiPjPQL6T5sRhUK4XI8+w/A0EyndMzw+fNIr4H7OlTBL/8pQCNOj0gizKaCjK8uRrqikbnAV4u/+Qs0gcA=";
+        assert!(
+            validate_bitcoin_auth_sig(&auth_sig(P2PKH_ADDRESS, "Hello World", sig), None).unwrap()
+        );
+        assert!(!validate_bitcoin_auth_sig(&auth_sig(P2PKH_ADDRESS, "Hello", sig), None).unwrap());
+
+        let segwit_sig = "J8afIFiPjPQL6T5sRhUK4XI8+w/A0EyndMzw+fNIr4H7OlTBL/8pQCNOj0gizKaCjK8uRrqikbnAV4u/+Qs0gcA=";
+        assert!(validate_bitcoin_auth_sig(
+            &auth_sig(P2WPKH_ADDRESS, "Hello World", segwit_sig),
+            None
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn parse_sign_in_message_test() {
+        let message = format!(
+            "localhost:7470 wants you to sign in with your Bitcoin account:\n{}\n\nSign in to Lit.\n\nURI: https://localhost/login\nVersion: 1\nChain ID: 1\nNonce: 32891756\nIssued At: 2024-01-01T00:00:00Z\nExpiration Time: 2024-01-02T00:00:00Z",
+            P2WPKH_ADDRESS
+        );
+        let parsed = BitcoinSignInMessage::parse(&message).unwrap();
+        assert_eq!(parsed.domain, "localhost:7470");
+        assert_eq!(parsed.address, P2WPKH_ADDRESS);
+        assert_eq!(parsed.statement.as_deref(), Some("Sign in to Lit."));
+        assert_eq!(parsed.uri, "https://localhost/login");
+        assert_eq!(
+            parsed.expiration_time.unwrap().to_string(),
+            "2024-01-02T00:00:00Z"
+        );
+
+        // The statement is optional.
+        let message = format!(
+            "example.com wants you to sign in with your Bitcoin account:\n{}\n\nURI: https://example.com\nIssued At: 2024-01-01T00:00:00Z",
+            P2WPKH_ADDRESS
+        );
+        let parsed = BitcoinSignInMessage::parse(&message).unwrap();
+        assert_eq!(parsed.statement, None);
+        assert!(parsed.expiration_time.is_none());
+
+        assert!(BitcoinSignInMessage::parse("Hello World").is_err());
+        assert!(BitcoinSignInMessage::parse(
+            &message.replace("example.com wants", "example.com/path wants")
+        )
+        .is_err());
+        assert!(BitcoinSignInMessage::parse(
+            &message.replace("Issued At: 2024-01-01T00:00:00Z", "")
+        )
+        .is_err());
+        assert!(
+            BitcoinSignInMessage::parse(&format!("{}\nURI: https://other.com", message)).is_err()
+        );
+    }
+
+    /// Sign `message` with the legacy format, returning the P2PKH address and signature.
+    fn sign_legacy(key: &k256::ecdsa::SigningKey, message: &str) -> (String, String) {
+        let pubkey = key.verifying_key().to_encoded_point(true);
+        let mut address = [&[0x00][..], &hash160(pubkey.as_bytes())].concat();
+        address.extend_from_slice(&sha256d(&address)[..4]);
+
+        let (sig, recovery_id) = key
+            .sign_prehash_recoverable(&signmessage_hash(message.as_bytes()))
+            .unwrap();
+        let sig = [&[31 + recovery_id.to_byte()][..], &sig.to_bytes()].concat();
+
+        (
+            bs58::encode(address).into_string(),
+            data_encoding::BASE64.encode(&sig),
+        )
+    }
+
+    fn sign_in_message(address: &str, uri: &str, expiration: chrono::DateTime<Utc>) -> String {
+        format!(
+            "localhost:7470 wants you to sign in with your Bitcoin account:\n{}\n\nURI: {}\nVersion: 1\nChain ID: 1\nNonce: 32891756\nIssued At: {}\nExpiration Time: {}",
+            address,
+            uri,
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.fZ"),
+            expiration.format("%Y-%m-%dT%H:%M:%S%.fZ")
+        )
+    }
+
+    async fn validate_signed(
+        cfg: &LitConfig,
+        key: &k256::ecdsa::SigningKey,
+        message: String,
+    ) -> crate::error::Result<()> {
+        let (address, sig) = sign_legacy(key, &message);
+        let auth_sig = auth_sig(&address, &message, &sig);
+        validate_wallet_sig_by_chain(&auth_sig, &Some("bitcoin".to_string()), cfg).await
+    }
+
+    #[tokio::test]
+    async fn validate_sign_in_message_test() {
+        let cfg = get_test_config();
+        let key = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+        let (address, _) = sign_legacy(&key, "");
+        let uri = "https://localhost/login";
+
+        let tomorrow = Utc::now() + Duration::days(1);
+        let message = sign_in_message(&address, uri, tomorrow);
+        assert!(validate_signed(&cfg, &key, message).await.is_ok());
+
+        // Signed, but not a sign in message.
+        let message = "Hello World".to_string();
+        assert!(validate_signed(&cfg, &key, message).await.is_err());
+        // Expired.
+        let message = sign_in_message(&address, uri, Utc::now() - Duration::days(1));
+        assert!(validate_signed(&cfg, &key, message).await.is_err());
+        // For another address.
+        let message = sign_in_message(P2PKH_ADDRESS, uri, tomorrow);
+        assert!(validate_signed(&cfg, &key, message).await.is_err());
+        // A session signature.
+        let message = sign_in_message(&address, "lit:session:abcd", tomorrow);
+        assert!(validate_signed(&cfg, &key, message).await.is_err());
+    }
+}
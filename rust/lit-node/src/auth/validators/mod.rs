@@ -1,4 +1,5 @@
 pub mod auth_sig;
+pub mod bitcoin;
 pub mod cosmos;
 pub mod siwe;
 pub mod solana;
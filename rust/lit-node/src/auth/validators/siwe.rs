@@ -9,6 +9,7 @@ use crate::{
         auth_material::{siwe_hash_to_bls_session_hash, AuthMaterialType, JsonAuthSig},
         capabilities::recap::extract_and_verify_all_capabilities,
         capabilities::session_capability_object::SessionCapabilityObject,
+        contract::{is_eip6492_signature, validate_eip6492_signature},
     },
     error::{parser_err_code, validation_err_code, Result, EC},
     utils::encoding,
@@ -16,11 +17,26 @@ use crate::{
 
 use super::auth_sig::{CapabilityAuthSigValidator, SessionSigAuthSigValidator};
 
-pub(crate) struct SiweValidator;
+pub(crate) struct SiweValidator {
+    /// The chain and universal validator used to check EIP-6492 wrapped signatures.
+    eip6492_chain: Option<String>,
+    eip6492_validator: Option<Address>,
+}
 
 impl SiweValidator {
     pub fn new() -> SiweValidator {
-        SiweValidator {}
+        SiweValidator {
+            eip6492_chain: None,
+            eip6492_validator: None,
+        }
+    }
+
+    /// A validator that also accepts SIWE messages signed by counterfactual smart wallets.
+    pub fn with_eip6492(chain: Option<String>, validator: Option<Address>) -> SiweValidator {
+        SiweValidator {
+            eip6492_chain: chain,
+            eip6492_validator: validator,
+        }
     }
 }
 
@@ -35,6 +51,35 @@ impl SiweValidator {
             .add_msg_to_details()
         })
     }
+
+    /// Verify a SIWE message signed with an EIP-6492 wrapped signature.
+    async fn verify_eip6492_siwe(&self, siwe_message: &Message, sig: &[u8]) -> Result<()> {
+        if !siwe_message.valid_now() {
+            return Err(validation_err_code(
+                "The SIWE message is not valid now.  It has expired or is not yet valid",
+                EC::NodeSIWEMessageError,
+                None,
+            ));
+        }
+
+        let siwe_hash = siwe_message.eip191_hash().map_err(|e| {
+            parser_err_code(
+                e,
+                EC::NodeSIWEMessageError,
+                Some("Error hashing SIWE message".into()),
+            )
+            .add_msg_to_details()
+        })?;
+
+        validate_eip6492_signature(
+            Address::from_slice(&siwe_message.address),
+            siwe_hash.into(),
+            sig,
+            &self.eip6492_chain,
+            self.eip6492_validator,
+        )
+        .await
+    }
 }
 
 impl CapabilityAuthSigValidator for SiweValidator {
@@ -49,12 +94,14 @@ impl CapabilityAuthSigValidator for SiweValidator {
         let sig_as_array = sig_as_array.as_slice();
 
         // Verify SIWE message.
-        let now = OffsetDateTime::now_utc();
-        if let Err(e) = siwe_message
+        if is_eip6492_signature(sig_as_array) {
+            self.verify_eip6492_siwe(&siwe_message, sig_as_array)
+                .await?;
+        } else if let Err(e) = siwe_message
             .verify(
                 sig_as_array,
                 &VerificationOpts {
-                    timestamp: Some(now),
+                    timestamp: Some(OffsetDateTime::now_utc()),
                     ..Default::default()
                 },
             )
@@ -187,12 +234,14 @@ impl SessionSigAuthSigValidator for SiweValidator {
         let sig_as_array = sig_as_array.as_slice();
 
         // Verify SIWE message.
-        let now = OffsetDateTime::now_utc();
-        if let Err(e) = siwe_message
+        if is_eip6492_signature(sig_as_array) {
+            self.verify_eip6492_siwe(&siwe_message, sig_as_array)
+                .await?;
+        } else if let Err(e) = siwe_message
             .verify(
                 sig_as_array,
                 &VerificationOpts {
-                    timestamp: Some(now),
+                    timestamp: Some(OffsetDateTime::now_utc()),
                     ..Default::default()
                 },
             )
@@ -1,15 +1,17 @@
+use crate::auth::contract::{is_eip6492_signature, validate_eip6492_signature};
+use crate::auth::validators::bitcoin::{validate_bitcoin_auth_sig, BitcoinSignInMessage};
 use crate::auth::validators::cosmos::validate_cosmos_auth_sig;
 use crate::auth::validators::solana::validate_solana_auth_sig;
 use crate::error::{
     conversion_err, parser_err, parser_err_code, unexpected_err, validation_err,
     validation_err_code, Result, EC,
 };
-use crate::utils::siwe::validate_siwe;
+use crate::utils::siwe::{validate_siwe, validate_siwe_timestamps};
 use std::str::FromStr;
 
 use std::convert::TryInto;
 
-use crate::auth::auth_material::JsonAuthSig;
+use crate::auth::auth_material::{JsonAuthSig, AUTH_SIG_DERIVED_VIA_BIP322};
 use crate::config::LitNodeConfig as _;
 use crate::utils::encoding;
 use ethers::{types::Signature, utils::keccak256};
@@ -27,7 +29,6 @@ pub(crate) async fn validate_wallet_sig_by_chain(
     chain: &Option<String>,
     cfg: &LitConfig,
 ) -> Result<()> {
-    let enable_siwe_validation = matches!(cfg.enable_siwe_validation(), Ok(true));
     match chain {
         Some(chain) => match chain.as_str() {
             "solana" | "solanaDevnet" | "solanaTestnet" => {
@@ -54,10 +55,136 @@ pub(crate) async fn validate_wallet_sig_by_chain(
                 }
                 Ok(())
             }
-            _ => validate_wallet_sig(auth_sig, enable_siwe_validation).await,
+            "bitcoin" | "bitcoinTestnet" => {
+                debug!("Checking bitcoin signature");
+                validate_bitcoin_wallet_sig(auth_sig, Some(chain), cfg)
+            }
+            _ => validate_evm_wallet_sig(auth_sig, &Some(chain.clone()), cfg).await,
         },
-        None => validate_wallet_sig(auth_sig, enable_siwe_validation).await,
+        None if auth_sig.derived_via == AUTH_SIG_DERIVED_VIA_BIP322 => {
+            debug!("Checking bitcoin signature");
+            validate_bitcoin_wallet_sig(auth_sig, None, cfg)
+        }
+        None => validate_evm_wallet_sig(auth_sig, &None, cfg).await,
+    }
+}
+
+/// Validate a Bitcoin wallet signature (BIP-322 or legacy `signmessage`) of a "Sign in with
+/// Bitcoin" message, with the same URI and time checks as SIWE messages.
+fn validate_bitcoin_wallet_sig(
+    auth_sig: &JsonAuthSig,
+    chain: Option<&str>,
+    cfg: &LitConfig,
+) -> Result<()> {
+    if !validate_bitcoin_auth_sig(auth_sig, chain)? {
+        return Err(validation_err_code(
+            "Signature is not valid",
+            EC::NodeInvalidAuthSig,
+            None,
+        ));
+    }
+
+    let message = BitcoinSignInMessage::parse(&auth_sig.signed_message)?;
+    if message.address != auth_sig.address {
+        return Err(validation_err_code(
+            "The address in the sign in message does not match the address in the auth sig",
+            EC::NodeInvalidAuthSig,
+            None,
+        ));
+    }
+    validate_auth_uri(&message.uri)?;
+
+    if matches!(cfg.enable_siwe_validation(), Ok(true)) {
+        validate_siwe_timestamps(&message.issued_at, message.expiration_time.as_ref())
+            .map_err(|e| validation_err(e, Some("SIWE validation failed".into())))?;
+    }
+
+    Ok(())
+}
+
+/// Validate EVM-compatible wallet signature, including EIP-6492 wrapped signatures made by
+/// smart wallets that have not been deployed yet.
+async fn validate_evm_wallet_sig(
+    auth_sig: &JsonAuthSig,
+    chain: &Option<String>,
+    cfg: &LitConfig,
+) -> Result<()> {
+    let enable_siwe_validation = matches!(cfg.enable_siwe_validation(), Ok(true));
+    match encoding::hex_to_bytes(&auth_sig.sig) {
+        Ok(sig) if is_eip6492_signature(&sig) => {
+            debug!("Checking EIP-6492 signature");
+            validate_eip6492_wallet_sig(auth_sig, &sig, chain, cfg, enable_siwe_validation).await
+        }
+        _ => validate_wallet_sig(auth_sig, enable_siwe_validation).await,
+    }
+}
+
+/// Validate a SIWE message signed by a counterfactual smart wallet.
+async fn validate_eip6492_wallet_sig(
+    auth_sig: &JsonAuthSig,
+    sig: &[u8],
+    chain: &Option<String>,
+    cfg: &LitConfig,
+    enable_siwe_validation: bool,
+) -> Result<()> {
+    let presented_address = ethers::types::Address::from_str(&auth_sig.address)
+        .map_err(|e| validation_err_code(e, EC::NodeAuthSigAddressConversionError, None))?;
+
+    let message: Message = auth_sig
+        .signed_message
+        .parse()
+        .map_err(|e| parser_err(e, Some("Parse error on SIWE".into())).add_msg_to_details())?;
+    validate_auth_siwe_message(&message, enable_siwe_validation)?;
+
+    if ethers::types::Address::from_slice(&message.address) != presented_address {
+        return Err(validation_err_code(
+            "The address in the SIWE message does not match the address in the auth sig",
+            EC::NodeInvalidAuthSig,
+            None,
+        ));
+    }
+
+    let hash = message
+        .eip191_hash()
+        .map_err(|e| parser_err(e, Some("Error hashing SIWE message".into())))?;
+
+    validate_eip6492_signature(
+        presented_address,
+        hash.into(),
+        sig,
+        chain,
+        cfg.eip6492_validator_address().ok(),
+    )
+    .await
+}
+
+/// Checks of the SIWE message shared by all top level auth sigs.
+fn validate_auth_siwe_message(message: &Message, enable_siwe_validation: bool) -> Result<()> {
+    validate_auth_uri(message.uri.as_str())?;
+
+    // validate time-related parameters
+    if enable_siwe_validation {
+        validate_siwe(message)
+            .map_err(|e| validation_err(e, Some("SIWE validation failed".into())))?;
     }
+
+    Ok(())
+}
+
+/// Do not let delegation or session signatures be used as a top level auth sig.
+fn validate_auth_uri(uri: &str) -> Result<()> {
+    if INVALID_SIWE_URIS_FOR_AUTH
+        .iter()
+        .any(|invalid| uri.starts_with(invalid))
+    {
+        return Err(validation_err_code(
+            format!("Invalid URI for top level auth sig: {}", uri),
+            EC::NodeInvalidAuthSig,
+            None,
+        ));
+    }
+
+    Ok(())
 }
 
 /// Validate EVM-compatible wallet signature.
@@ -82,27 +209,7 @@ async fn validate_wallet_sig(auth_sig: &JsonAuthSig, enable_siwe_validation: boo
         .signed_message
         .parse()
         .map_err(|e| parser_err(e, Some("Parse error on SIWE".into())).add_msg_to_details())?;
-
-    // do not let delegation or session signatures be used as an auth sig here
-    if INVALID_SIWE_URIS_FOR_AUTH
-        .iter()
-        .any(|uri| message.uri.to_string().starts_with(uri))
-    {
-        return Err(validation_err_code(
-            format!(
-                "Invalid URI for top level auth sig: {}",
-                message.uri.as_str()
-            ),
-            EC::NodeInvalidAuthSig,
-            None,
-        ));
-    }
-
-    // validate time-related parameters
-    if enable_siwe_validation {
-        validate_siwe(&message)
-            .map_err(|e| validation_err(e, Some("SIWE validation failed".into())))?;
-    }
+    validate_auth_siwe_message(&message, enable_siwe_validation)?;
 
     let sig_as_array = encoding::hex_to_bytes(&auth_sig.sig)
         .map_err(|e| parser_err_code(e, EC::NodeSIWESigConversionError, None))?
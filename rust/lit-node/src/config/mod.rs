@@ -28,6 +28,7 @@ use crate::{
 };
 
 pub mod chain;
+pub mod schema;
 
 pub const CFG_SECTION_KEY: &str = "node";
 
@@ -65,12 +66,45 @@ pub static CFG_KEY_RESTORE_LOG_INTERVAL_MS: &str = "restore_log_interval";
 pub static CFG_KEY_ACTIONS_SOCKET: &str = "actions_socket";
 pub static CFG_KEY_ACTIONS_SANDBOX: &str = "enable_actions_sandbox";
 pub static CFG_KEY_HEALTH_POLL_INTERVAL_MS: &str = "health_poll_interval";
+pub static CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS: &str = "config_rollback_window";
+pub static CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS: &str = "config_health_check_interval";
+pub static CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS: &str = "config_health_failure_window";
+pub static CFG_KEY_ADMIN_ADDRESSES: &str = "admin_addresses";
+pub static CFG_KEY_ADMIN_QUORUM_THRESHOLD: &str = "admin_quorum_threshold";
+pub static CFG_KEY_ADMIN_PROPOSAL_TTL_MS: &str = "admin_proposal_ttl";
+pub static CFG_KEY_ENABLE_USAGE_RECORDS: &str = "enable_usage_records";
+pub static CFG_KEY_USAGE_RETENTION_DAYS: &str = "usage_retention_days";
+pub static CFG_KEY_EIP6492_VALIDATOR_ADDRESS: &str = "eip6492_validator_address";
+pub static CFG_KEY_ACTIONS_MODULE_ALLOWLIST: &str = "actions_module_allowlist";
+pub static CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES: &str = "actions_storage_quota_bytes";
+pub static CFG_KEY_ACTIONS_STORAGE_PATH: &str = "actions_storage_path";
+pub static CFG_KEY_ACTIONS_EGRESS_ALLOW_HOSTS: &str = "actions_egress_allow_hosts";
+pub static CFG_KEY_ACTIONS_EGRESS_DENY_HOSTS: &str = "actions_egress_deny_hosts";
+pub static CFG_KEY_ACTIONS_EGRESS_ALLOW_CIDRS: &str = "actions_egress_allow_cidrs";
+pub static CFG_KEY_ACTIONS_EGRESS_DENY_CIDRS: &str = "actions_egress_deny_cidrs";
+pub static CFG_KEY_ACTIONS_EGRESS_BLOCK_PRIVATE: &str = "actions_egress_block_private";
+pub static CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES: &str = "actions_fetch_max_response_bytes";
+pub static CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS: &str = "actions_fetch_timeout";
+pub static CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES: &str = "actions_fetch_max_total_bytes";
+pub static CFG_KEY_ENABLE_BEACON: &str = "enable_beacon";
+pub static CFG_KEY_CONFIG_HISTORY_PATH: &str = "config_history_path";
 
 // Defaults
 pub static CFG_KEY_CHAIN_POLLING_INTERVAL_MS_DEFAULT: i64 = 30000;
 pub static CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT: i64 = 30000;
 pub static CFG_KEY_RESTORE_LOG_INTERVAL_MS_DEFAULT: i64 = 1000 * 60 * 10;
 pub static CFG_KEY_ACTIONS_SOCKET_DEFAULT: &str = "/tmp/lit_actions.sock";
+pub static CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS_DEFAULT: i64 = 1000 * 60 * 2;
+pub static CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS_DEFAULT: i64 = 1000 * 10;
+pub static CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS_DEFAULT: i64 = 1000 * 60;
+pub static CFG_KEY_ADMIN_PROPOSAL_TTL_MS_DEFAULT: i64 = 1000 * 60 * 60;
+pub static CFG_KEY_USAGE_RETENTION_DAYS_DEFAULT: i64 = 90;
+pub static CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES_DEFAULT: i64 = 64 * 1024;
+pub static CFG_KEY_ACTIONS_STORAGE_PATH_DEFAULT: &str = "./action_storage.db";
+pub static CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES_DEFAULT: i64 = 10 * 1024 * 1024;
+pub static CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS_DEFAULT: i64 = 15000;
+pub static CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES_DEFAULT: i64 = 50 * 1024 * 1024;
+pub static CFG_KEY_CONFIG_HISTORY_PATH_DEFAULT: &str = "./config_history";
 
 static REQUIRED_CFG_KEYS: [&str; 9] = [
     CFG_KEY_STAKER_ADDRESS,
@@ -84,7 +118,7 @@ static REQUIRED_CFG_KEYS: [&str; 9] = [
     CFG_KEY_DOMAIN,
 ];
 
-static USER_EDITABLE_KEYS: [&str; 23] = [
+static USER_EDITABLE_KEYS: [&str; 40] = [
     CFG_KEY_RPC_URL,
     CFG_KEY_ADMIN_ADDRESS,
     CFG_KEY_STAKER_ADDRESS,
@@ -108,6 +142,24 @@ static USER_EDITABLE_KEYS: [&str; 23] = [
     CFG_KEY_ENABLE_SIWE_VALIDATION,
     CFG_KEY_ACTIONS_SANDBOX,
     CFG_KEY_HEALTH_POLL_INTERVAL_MS,
+    CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS,
+    CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS,
+    CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS,
+    CFG_KEY_ADMIN_ADDRESSES,
+    CFG_KEY_ADMIN_QUORUM_THRESHOLD,
+    CFG_KEY_ADMIN_PROPOSAL_TTL_MS,
+    CFG_KEY_EIP6492_VALIDATOR_ADDRESS,
+    CFG_KEY_ACTIONS_MODULE_ALLOWLIST,
+    CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES,
+    CFG_KEY_ACTIONS_EGRESS_ALLOW_HOSTS,
+    CFG_KEY_ACTIONS_EGRESS_DENY_HOSTS,
+    CFG_KEY_ACTIONS_EGRESS_ALLOW_CIDRS,
+    CFG_KEY_ACTIONS_EGRESS_DENY_CIDRS,
+    CFG_KEY_ACTIONS_EGRESS_BLOCK_PRIVATE,
+    CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES,
+    CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS,
+    CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES,
+    CFG_KEY_ENABLE_BEACON,
 ];
 
 static USER_EDITABLE_KEYS_IN_SECTIONS: [&str; 3] = [
@@ -134,6 +186,9 @@ pub trait LitNodeConfig {
     fn coms_keys_sender_privkey(&self) -> Result<String>;
     fn coms_keys_receiver_privkey(&self) -> Result<String>;
     fn admin_address(&self) -> Result<H160>;
+    fn admin_signers(&self) -> Result<Vec<H160>>;
+    fn admin_quorum_threshold(&self) -> Result<usize>;
+    fn admin_proposal_ttl_ms(&self) -> Result<u64>;
     fn key_path(&self, staker_address: &str) -> PathBuf;
     fn typed_key_path(&self, keytype: &str, staker_address: &str) -> PathBuf;
     fn webauthn_allowed_origins(&self) -> Result<Vec<Url>>;
@@ -142,6 +197,21 @@ pub trait LitNodeConfig {
     fn http_client_timeout(&self) -> Result<u64>;
     fn http_client_patience(&self) -> Result<u64>;
     fn actions_socket(&self) -> Result<std::path::PathBuf>;
+    fn actions_module_allowlist(&self) -> Result<Vec<String>>;
+    fn actions_storage_quota_bytes(&self) -> Result<i64>;
+    fn actions_storage_path(&self) -> Result<std::path::PathBuf>;
+    fn config_history_path(&self) -> Result<std::path::PathBuf>;
+    fn actions_egress_allow_hosts(&self) -> Result<Vec<String>>;
+    fn actions_egress_deny_hosts(&self) -> Result<Vec<String>>;
+    fn actions_egress_allow_cidrs(&self) -> Result<Vec<String>>;
+    fn actions_egress_deny_cidrs(&self) -> Result<Vec<String>>;
+    fn actions_egress_block_private(&self) -> Result<bool>;
+    fn actions_fetch_max_response_bytes(&self) -> Result<i64>;
+    fn actions_fetch_timeout_ms(&self) -> Result<i64>;
+    fn actions_fetch_max_total_bytes(&self) -> Result<i64>;
+
+    // randomness beacon
+    fn enable_beacon(&self) -> Result<bool>;
 
     // Feature flag bool accessors
     fn enable_proxied_http_client(&self) -> Result<bool>;
@@ -155,6 +225,7 @@ pub trait LitNodeConfig {
     fn enable_http_header_descriptors(&self) -> Result<bool>;
     fn enable_siwe_validation(&self) -> Result<bool>;
     fn enable_actions_sandbox(&self) -> Result<bool>;
+    fn enable_usage_records(&self) -> Result<bool>;
 
     // communications parameters for ECDSA rounds
     fn ecdsa_round_timeout(&self) -> Result<i64>;
@@ -171,6 +242,17 @@ pub trait LitNodeConfig {
 
     // endpoint polling and healthcheck
     fn rpc_health_poll_interval(&self) -> Result<i64>;
+
+    // dynamic reconfiguration
+    fn config_rollback_window_ms(&self) -> Result<i64>;
+    fn config_health_check_interval_ms(&self) -> Result<i64>;
+    fn config_health_failure_window_ms(&self) -> Result<i64>;
+
+    // usage records
+    fn usage_retention_days(&self) -> Result<i64>;
+
+    // smart wallet signatures
+    fn eip6492_validator_address(&self) -> Result<H160>;
 }
 
 impl LitNodeConfig for LitConfig {
@@ -218,7 +300,55 @@ impl LitNodeConfig for LitConfig {
             .set_section_default(CFG_KEY_ACTIONS_SOCKET, CFG_KEY_ACTIONS_SOCKET_DEFAULT)
             .set_section_default(CFG_KEY_ACTIONS_SANDBOX, "true")
             .set_section_default(CFG_KEY_HEALTH_POLL_INTERVAL_MS, "60000")
-            .set_section_default(CFG_KEY_ENABLE_RATE_LIMITING_ALLOCATION, "false");
+            .set_section_default(CFG_KEY_ENABLE_RATE_LIMITING_ALLOCATION, "false")
+            .set_section_default(
+                CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS,
+                CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS,
+                CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS,
+                CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS_DEFAULT.to_string(),
+            )
+            .set_section_default(CFG_KEY_ADMIN_QUORUM_THRESHOLD, "1")
+            .set_section_default(CFG_KEY_ENABLE_USAGE_RECORDS, "true")
+            .set_section_default(
+                CFG_KEY_USAGE_RETENTION_DAYS,
+                CFG_KEY_USAGE_RETENTION_DAYS_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_ADMIN_PROPOSAL_TTL_MS,
+                CFG_KEY_ADMIN_PROPOSAL_TTL_MS_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES,
+                CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_ACTIONS_STORAGE_PATH,
+                CFG_KEY_ACTIONS_STORAGE_PATH_DEFAULT,
+            )
+            .set_section_default(
+                CFG_KEY_CONFIG_HISTORY_PATH,
+                CFG_KEY_CONFIG_HISTORY_PATH_DEFAULT,
+            )
+            .set_section_default(CFG_KEY_ACTIONS_EGRESS_BLOCK_PRIVATE, "true")
+            .set_section_default(
+                CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES,
+                CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS,
+                CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS_DEFAULT.to_string(),
+            )
+            .set_section_default(
+                CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES,
+                CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES_DEFAULT.to_string(),
+            )
+            .set_section_default(CFG_KEY_ENABLE_BEACON, "true");
 
         // Apply others
         builder = <LitConfig as LitBlockchainConfig>::apply_defaults(builder)?;
@@ -356,6 +486,51 @@ impl LitNodeConfig for LitConfig {
             })
     }
 
+    /// The admin signer set: `admin_address` plus any (comma separated) `admin_addresses`.
+    fn admin_signers(&self) -> Result<Vec<H160>> {
+        let mut signers = vec![self.admin_address()?];
+
+        if let Ok(addresses) = self.get_section_string(CFG_KEY_ADMIN_ADDRESSES) {
+            for address in addresses
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+            {
+                let address = address.parse::<H160>().map_err(|e| {
+                    parser_err(
+                        e,
+                        Some(format!(
+                            "Could not convert admin address '{}' to H160",
+                            address
+                        )),
+                    )
+                })?;
+                if !signers.contains(&address) {
+                    signers.push(address);
+                }
+            }
+        }
+
+        Ok(signers)
+    }
+
+    /// Number of distinct admin signatures required for sensitive operations.
+    fn admin_quorum_threshold(&self) -> Result<usize> {
+        let threshold = self.get_section_int(CFG_KEY_ADMIN_QUORUM_THRESHOLD)?;
+        if threshold < 1 {
+            return Err(validation_err(
+                format!("{} must be at least 1", CFG_KEY_ADMIN_QUORUM_THRESHOLD),
+                None,
+            ));
+        }
+
+        Ok(threshold as usize)
+    }
+
+    fn admin_proposal_ttl_ms(&self) -> Result<u64> {
+        Ok(self.get_section_int(CFG_KEY_ADMIN_PROPOSAL_TTL_MS)?.max(0) as u64)
+    }
+
     fn key_path(&self, staker_address: &str) -> PathBuf {
         key_path(staker_address)
     }
@@ -448,6 +623,10 @@ impl LitNodeConfig for LitConfig {
         self.get_section_bool(CFG_KEY_ENABLE_SIWE_VALIDATION)
     }
 
+    fn enable_usage_records(&self) -> Result<bool> {
+        self.get_section_bool(CFG_KEY_ENABLE_USAGE_RECORDS)
+    }
+
     fn enable_actions_sandbox(&self) -> Result<bool> {
         self.get_section_bool(CFG_KEY_ACTIONS_SANDBOX)
     }
@@ -493,9 +672,119 @@ impl LitNodeConfig for LitConfig {
             .map(Into::into)
     }
 
+    /// IPFS CIDs that Lit Actions may import as ES modules (comma separated). Empty allows any CID.
+    fn actions_module_allowlist(&self) -> Result<Vec<String>> {
+        Ok(section_list(self, CFG_KEY_ACTIONS_MODULE_ALLOWLIST))
+    }
+
+    /// Storage available to each PKP and action pair via `Lit.Actions.storage`, for every
+    /// request per kilosecond of the rate limit NFT paying for the execution (at least once).
+    fn actions_storage_quota_bytes(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES)
+    }
+
+    fn actions_storage_path(&self) -> Result<std::path::PathBuf> {
+        self.get_section_string(CFG_KEY_ACTIONS_STORAGE_PATH)
+            .map(std::path::PathBuf::from)
+    }
+
+    /// Where versions of the admin override (and the audit log of changes) are kept.
+    fn config_history_path(&self) -> Result<std::path::PathBuf> {
+        self.get_section_string(CFG_KEY_CONFIG_HISTORY_PATH)
+            .map(std::path::PathBuf::from)
+    }
+
+    /// Hosts that Lit Actions may fetch from (comma separated, `*.` matches subdomains).
+    /// Empty allows any host.
+    fn actions_egress_allow_hosts(&self) -> Result<Vec<String>> {
+        Ok(section_list(self, CFG_KEY_ACTIONS_EGRESS_ALLOW_HOSTS))
+    }
+
+    /// Hosts that Lit Actions may never fetch from (comma separated).
+    fn actions_egress_deny_hosts(&self) -> Result<Vec<String>> {
+        Ok(section_list(self, CFG_KEY_ACTIONS_EGRESS_DENY_HOSTS))
+    }
+
+    /// Address ranges that Lit Actions may always fetch from, even if private (comma separated).
+    fn actions_egress_allow_cidrs(&self) -> Result<Vec<String>> {
+        Ok(section_list(self, CFG_KEY_ACTIONS_EGRESS_ALLOW_CIDRS))
+    }
+
+    /// Address ranges that Lit Actions may never fetch from (comma separated).
+    fn actions_egress_deny_cidrs(&self) -> Result<Vec<String>> {
+        Ok(section_list(self, CFG_KEY_ACTIONS_EGRESS_DENY_CIDRS))
+    }
+
+    /// Whether Lit Actions are blocked from fetching loopback, private and link-local addresses.
+    fn actions_egress_block_private(&self) -> Result<bool> {
+        self.get_section_bool(CFG_KEY_ACTIONS_EGRESS_BLOCK_PRIVATE)
+    }
+
+    fn actions_fetch_max_response_bytes(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES)
+    }
+
+    fn actions_fetch_timeout_ms(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS)
+    }
+
+    /// Total size of all responses fetched by a single Lit Action execution.
+    fn actions_fetch_max_total_bytes(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES)
+    }
+
+    /// Whether this node takes part in the randomness beacon.  The round schedule is fixed
+    /// (see `tss::blsful::beacon`), as time locks depend on it.
+    fn enable_beacon(&self) -> Result<bool> {
+        self.get_section_bool(CFG_KEY_ENABLE_BEACON)
+    }
+
     fn rpc_health_poll_interval(&self) -> Result<i64> {
         self.get_section_int(CFG_KEY_HEALTH_POLL_INTERVAL_MS)
     }
+
+    fn config_rollback_window_ms(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS)
+    }
+
+    fn config_health_check_interval_ms(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS)
+    }
+
+    fn config_health_failure_window_ms(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS)
+    }
+
+    fn usage_retention_days(&self) -> Result<i64> {
+        self.get_section_int(CFG_KEY_USAGE_RETENTION_DAYS)
+    }
+
+    /// The ERC-6492 universal signature validator used for counterfactual (undeployed)
+    /// smart wallets. It must be deployed at this address on every chain it is used with. There
+    /// is no default, EIP-6492 signatures are rejected until it is set.
+    fn eip6492_validator_address(&self) -> Result<H160> {
+        self.get_section_string(CFG_KEY_EIP6492_VALIDATOR_ADDRESS)?
+            .parse::<H160>()
+            .map_err(|e| {
+                parser_err(
+                    e,
+                    Some("Could not convert eip6492_validator_address to H160".to_string()),
+                )
+            })
+    }
+}
+
+// A comma separated list, empty if the key isn't set
+fn section_list(cfg: &LitConfig, key: &str) -> Vec<String> {
+    cfg.get_section_string(key)
+        .map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 pub(crate) fn key_path(staker_address: &str) -> PathBuf {
@@ -507,6 +796,10 @@ pub(crate) fn key_path(staker_address: &str) -> PathBuf {
     PathBuf::from(&path_root)
 }
 
+pub(crate) fn usage_path() -> PathBuf {
+    PathBuf::from("./usage")
+}
+
 pub(crate) fn backup_key_path(staker_address: &str) -> PathBuf {
     let mut path = key_path(staker_address);
     path.push("backup");
@@ -524,6 +817,13 @@ pub(crate) fn beaver_triple_path(staker_address: &str) -> PathBuf {
     path
 }
 
+pub(crate) fn dkg_transcript_path(key_type: &str, staker_address: &str) -> PathBuf {
+    let mut path = key_path(staker_address);
+    path.push("transcripts");
+    path.push(key_type);
+    path
+}
+
 pub(crate) fn typed_key_path(key_type: &str, staker_address: &str) -> PathBuf {
     let mut path = key_path(staker_address);
     path.push(key_type);
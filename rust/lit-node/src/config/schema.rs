@@ -0,0 +1,235 @@
+use ethers::types::H160;
+use lit_blockchain::config::{
+    CFG_KEY_BLOCKCHAIN_CHAIN_ID, CFG_KEY_BLOCKCHAIN_CHAIN_NAME,
+    CFG_KEY_BLOCKCHAIN_WALLET_DEFAULT_PRIVATE_KEY,
+};
+use url::Url;
+
+use crate::config::{
+    CFG_KEY_ACTIONS_EGRESS_ALLOW_CIDRS, CFG_KEY_ACTIONS_EGRESS_ALLOW_HOSTS,
+    CFG_KEY_ACTIONS_EGRESS_BLOCK_PRIVATE, CFG_KEY_ACTIONS_EGRESS_DENY_CIDRS,
+    CFG_KEY_ACTIONS_EGRESS_DENY_HOSTS, CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES,
+    CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES, CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS,
+    CFG_KEY_ACTIONS_MODULE_ALLOWLIST, CFG_KEY_ACTIONS_SANDBOX, CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES,
+    CFG_KEY_ADMIN_ADDRESS, CFG_KEY_ADMIN_ADDRESSES, CFG_KEY_ADMIN_PROPOSAL_TTL_MS,
+    CFG_KEY_ADMIN_QUORUM_THRESHOLD, CFG_KEY_BLS_KEY_BLINDER, CFG_KEY_CHAIN_POLLING_INTERVAL_MS,
+    CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS, CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS,
+    CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS,
+    CFG_KEY_ECDSA_BATCH_SEND_INTERVAL, CFG_KEY_ECDSA_KEY_BLINDER, CFG_KEY_ECDSA_ROOT_PUBKEY_COUNT,
+    CFG_KEY_ECDSA_ROUND_TIMEOUT, CFG_KEY_EIP6492_VALIDATOR_ADDRESS,
+    CFG_KEY_ENABLE_ACTIONS_ALLOWLIST, CFG_KEY_ENABLE_BEACON, CFG_KEY_ENABLE_ECDSA_BATCH_SENDING,
+    CFG_KEY_ENABLE_ECDSA_DKG, CFG_KEY_ENABLE_ECDSA_DKG_BATCH_SENDING,
+    CFG_KEY_ENABLE_EPOCH_TRANSITIONS, CFG_KEY_ENABLE_PROXIED_HTTP_CLIENT,
+    CFG_KEY_ENABLE_RATE_LIMITING, CFG_KEY_ENABLE_RATE_LIMITING_ALLOCATION,
+    CFG_KEY_ENABLE_SIWE_VALIDATION, CFG_KEY_ENTER_RESTORE_STATE, CFG_KEY_HEALTH_POLL_INTERVAL_MS,
+    CFG_KEY_MESSAGE_QUEUE_PROCESS_LENGTH, CFG_KEY_RPC_URL, CFG_KEY_STAKER_ADDRESS,
+    CFG_KEY_WEBAUTHN_ALLOWED_ORIGINS, CFG_SECTION_KEY,
+};
+use crate::error::{validation_err, Result};
+use crate::functions::egress::Cidr;
+
+/// The type of a user editable config value (all values are stored as strings).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigValueType {
+    Bool,
+    UInt,
+    String,
+    Url,
+    UrlList,
+    Address,
+    AddressList,
+    CidrList,
+    Hex,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigKeySchema {
+    pub typ: ConfigValueType,
+    /// Sensitive values are redacted in the audit log.
+    pub sensitive: bool,
+}
+
+impl ConfigKeySchema {
+    const fn new(typ: ConfigValueType) -> Self {
+        Self {
+            typ,
+            sensitive: false,
+        }
+    }
+
+    const fn sensitive(typ: ConfigValueType) -> Self {
+        Self {
+            typ,
+            sensitive: true,
+        }
+    }
+}
+
+/// The schema of a user editable key (i.e. `node.enable_rate_limiting`).
+pub fn config_key_schema(full_key: &str) -> Option<ConfigKeySchema> {
+    if full_key == CFG_KEY_BLOCKCHAIN_WALLET_DEFAULT_PRIVATE_KEY {
+        return Some(ConfigKeySchema::sensitive(ConfigValueType::Hex));
+    } else if full_key == CFG_KEY_BLOCKCHAIN_CHAIN_ID {
+        return Some(ConfigKeySchema::new(ConfigValueType::UInt));
+    } else if full_key == CFG_KEY_BLOCKCHAIN_CHAIN_NAME {
+        return Some(ConfigKeySchema::new(ConfigValueType::String));
+    }
+
+    let key = full_key.strip_prefix(&format!("{}.", CFG_SECTION_KEY))?;
+    let schema = match key {
+        k if k == CFG_KEY_RPC_URL => ConfigKeySchema::new(ConfigValueType::Url),
+        k if k == CFG_KEY_ADMIN_ADDRESS
+            || k == CFG_KEY_STAKER_ADDRESS
+            || k == CFG_KEY_EIP6492_VALIDATOR_ADDRESS =>
+        {
+            ConfigKeySchema::new(ConfigValueType::Address)
+        }
+        k if k == CFG_KEY_ENABLE_PROXIED_HTTP_CLIENT
+            || k == CFG_KEY_ENABLE_RATE_LIMITING
+            || k == CFG_KEY_ENABLE_RATE_LIMITING_ALLOCATION
+            || k == CFG_KEY_ENABLE_ACTIONS_ALLOWLIST
+            || k == CFG_KEY_ENABLE_EPOCH_TRANSITIONS
+            || k == CFG_KEY_ENABLE_ECDSA_DKG
+            || k == CFG_KEY_ENABLE_ECDSA_BATCH_SENDING
+            || k == CFG_KEY_ENABLE_ECDSA_DKG_BATCH_SENDING
+            || k == CFG_KEY_ENTER_RESTORE_STATE
+            || k == CFG_KEY_ENABLE_SIWE_VALIDATION
+            || k == CFG_KEY_ACTIONS_SANDBOX
+            || k == CFG_KEY_ACTIONS_EGRESS_BLOCK_PRIVATE
+            || k == CFG_KEY_ENABLE_BEACON =>
+        {
+            ConfigKeySchema::new(ConfigValueType::Bool)
+        }
+        k if k == CFG_KEY_MESSAGE_QUEUE_PROCESS_LENGTH
+            || k == CFG_KEY_ECDSA_ROUND_TIMEOUT
+            || k == CFG_KEY_ECDSA_BATCH_SEND_INTERVAL
+            || k == CFG_KEY_CHAIN_POLLING_INTERVAL_MS
+            || k == CFG_KEY_ECDSA_ROOT_PUBKEY_COUNT
+            || k == CFG_KEY_HEALTH_POLL_INTERVAL_MS
+            || k == CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS
+            || k == CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS
+            || k == CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS
+            || k == CFG_KEY_ADMIN_QUORUM_THRESHOLD
+            || k == CFG_KEY_ADMIN_PROPOSAL_TTL_MS
+            || k == CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES
+            || k == CFG_KEY_ACTIONS_FETCH_MAX_RESPONSE_BYTES
+            || k == CFG_KEY_ACTIONS_FETCH_TIMEOUT_MS
+            || k == CFG_KEY_ACTIONS_FETCH_MAX_TOTAL_BYTES =>
+        {
+            ConfigKeySchema::new(ConfigValueType::UInt)
+        }
+        k if k == CFG_KEY_ADMIN_ADDRESSES => ConfigKeySchema::new(ConfigValueType::AddressList),
+        k if k == CFG_KEY_WEBAUTHN_ALLOWED_ORIGINS => {
+            ConfigKeySchema::new(ConfigValueType::UrlList)
+        }
+        k if k == CFG_KEY_ACTIONS_MODULE_ALLOWLIST
+            || k == CFG_KEY_ACTIONS_EGRESS_ALLOW_HOSTS
+            || k == CFG_KEY_ACTIONS_EGRESS_DENY_HOSTS =>
+        {
+            ConfigKeySchema::new(ConfigValueType::String)
+        }
+        k if k == CFG_KEY_ACTIONS_EGRESS_ALLOW_CIDRS || k == CFG_KEY_ACTIONS_EGRESS_DENY_CIDRS => {
+            ConfigKeySchema::new(ConfigValueType::CidrList)
+        }
+        k if k == CFG_KEY_BLS_KEY_BLINDER || k == CFG_KEY_ECDSA_KEY_BLINDER => {
+            ConfigKeySchema::sensitive(ConfigValueType::Hex)
+        }
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
+pub fn is_sensitive_config_key(full_key: &str) -> bool {
+    config_key_schema(full_key)
+        .map(|s| s.sensitive)
+        .unwrap_or(false)
+}
+
+/// Validate a value against the schema for the key.
+pub fn validate_config_value(full_key: &str, value: &str) -> Result<()> {
+    let schema = config_key_schema(full_key).ok_or_else(|| {
+        validation_err(
+            format!("user editing of config key '{}' not allowed", full_key),
+            None,
+        )
+    })?;
+
+    let valid = match schema.typ {
+        ConfigValueType::Bool => matches!(value, "true" | "false"),
+        ConfigValueType::UInt => value.parse::<u64>().is_ok(),
+        ConfigValueType::String => !value.is_empty(),
+        ConfigValueType::Url => Url::parse(value).is_ok(),
+        ConfigValueType::UrlList => value.split(',').all(|s| Url::parse(s).is_ok()),
+        ConfigValueType::Address => value.parse::<H160>().is_ok(),
+        ConfigValueType::AddressList => value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .all(|s| s.parse::<H160>().is_ok()),
+        ConfigValueType::CidrList => value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .all(|s| s.parse::<Cidr>().is_ok()),
+        ConfigValueType::Hex => {
+            let hex = value.strip_prefix("0x").unwrap_or(value);
+            !hex.is_empty() && hex::decode(hex).is_ok()
+        }
+    };
+
+    if !valid {
+        return Err(validation_err(
+            format!(
+                "invalid value for config key '{}' (expected {:?})",
+                full_key, schema.typ
+            ),
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_sensitive_config_key, validate_config_value};
+
+    #[test]
+    fn validate_config_value_test() {
+        assert!(validate_config_value("node.enable_rate_limiting", "true").is_ok());
+        assert!(validate_config_value("node.enable_rate_limiting", "yes").is_err());
+        assert!(validate_config_value("node.ecdsa_round_timeout", "30000").is_ok());
+        assert!(validate_config_value("node.ecdsa_round_timeout", "-1").is_err());
+        assert!(validate_config_value(
+            "node.admin_address",
+            "0x50e2dac5e78B5905CB09495547452cEE64426db2"
+        )
+        .is_ok());
+        assert!(validate_config_value("node.admin_address", "0x1234").is_err());
+        assert!(validate_config_value(
+            "node.admin_addresses",
+            "0x50e2dac5e78B5905CB09495547452cEE64426db2, 0x1234"
+        )
+        .is_err());
+        assert!(
+            validate_config_value("node.webauthn_allowed_origins", "http://*/,https://*/").is_ok()
+        );
+        assert!(
+            validate_config_value("node.actions_egress_allow_cidrs", "10.1.0.0/16, fd00::/8")
+                .is_ok()
+        );
+        assert!(validate_config_value("node.actions_egress_deny_cidrs", "10.0.0.0/33").is_err());
+        assert!(validate_config_value("node.port", "8080").is_err());
+        assert!(validate_config_value("blockchain.chain_id", "175177").is_ok());
+    }
+
+    #[test]
+    fn is_sensitive_config_key_test() {
+        assert!(is_sensitive_config_key("node.bls_key_blinder"));
+        assert!(is_sensitive_config_key(
+            "blockchain.wallet.default.private_key"
+        ));
+        assert!(!is_sensitive_config_key("node.rpc_url"));
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lit_core::config::LitConfig;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::trace;
+
+use crate::auth::auth_material::JsonAuthSig;
+use crate::config::schema::is_sensitive_config_key;
+use crate::config::LitNodeConfig;
+use crate::endpoints::admin::config_patch::ConfigDiffEntry;
+use crate::error::{io_err, serializer_err, unexpected_err, Result};
+
+const AUDIT_LOG_FN: &str = "audit.log";
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigChangeReason {
+    /// The admin override as it was before the first recorded change.
+    Baseline,
+    /// A whole-config replacement via `/web/admin/set`.
+    Set,
+    /// A per-key patch via `/web/admin/config/patch`.
+    Patch,
+    /// An admin requested rollback to a previous version.
+    Rollback,
+    /// Health checks failed after a change and the previous version was restored.
+    AutoRollback,
+}
+
+/// A version of the admin override config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHistoryEntry {
+    pub version: u64,
+    pub applied_at: u64,
+    pub reason: ConfigChangeReason,
+    pub previous_version: Option<u64>,
+    pub admin_address: Option<String>,
+    pub config: BTreeMap<String, String>,
+}
+
+impl ConfigHistoryEntry {
+    /// A copy that is safe to return to clients / write to logs.
+    pub fn redacted(&self) -> Self {
+        let mut entry = self.clone();
+        for (key, value) in entry.config.iter_mut() {
+            if is_sensitive_config_key(key) {
+                *value = REDACTED.to_string();
+            }
+        }
+        entry
+    }
+}
+
+/// An append-only record of every applied change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigAuditEntry {
+    pub version: u64,
+    pub timestamp: u64,
+    pub reason: ConfigChangeReason,
+    pub diff: Vec<ConfigDiffEntry>,
+    pub admin_address: Option<String>,
+    pub admin_signature: Option<String>,
+    pub admin_signed_message: Option<String>,
+    pub note: Option<String>,
+}
+
+/// Versioned history of the admin override config, persisted on disk.
+///
+/// Each version is written to `v{version}.json`, and every change is appended to `audit.log`
+/// (one JSON object per line).
+#[derive(Debug, Clone)]
+pub struct ConfigHistory {
+    path: PathBuf,
+}
+
+impl ConfigHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The history kept at the configured `node.config_history_path`.
+    pub fn from_config(cfg: &LitConfig) -> Result<Self> {
+        Ok(Self::new(cfg.config_history_path()?))
+    }
+
+    pub async fn list(&self) -> Result<Vec<ConfigHistoryEntry>> {
+        let mut entries = Vec::new();
+        let mut dir = match fs::read_dir(&self.path).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(io_err(e, None)),
+        };
+
+        while let Some(item) = dir.next_entry().await.map_err(|e| io_err(e, None))? {
+            let file_name = item.file_name().to_string_lossy().to_string();
+            if file_name.starts_with('v') && file_name.ends_with(".json") {
+                let data = fs::read(item.path()).await.map_err(|e| io_err(e, None))?;
+                let entry: ConfigHistoryEntry = serde_json::from_slice(&data).map_err(|e| {
+                    serializer_err(e, Some(format!("failed to parse {}", file_name)))
+                })?;
+                entries.push(entry);
+            }
+        }
+
+        entries.sort_by_key(|e| e.version);
+        Ok(entries)
+    }
+
+    pub async fn latest(&self) -> Result<Option<ConfigHistoryEntry>> {
+        Ok(self.list().await?.pop())
+    }
+
+    pub async fn get(&self, version: u64) -> Result<Option<ConfigHistoryEntry>> {
+        let path = self.version_path(version);
+        match fs::read(&path).await {
+            Ok(data) => Ok(Some(
+                serde_json::from_slice(&data).map_err(|e| serializer_err(e, None))?,
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_err(e, None)),
+        }
+    }
+
+    /// Record the baseline (pre-change) config if nothing has been recorded yet.
+    pub async fn ensure_baseline(&self, config: &BTreeMap<String, String>) -> Result<()> {
+        if self.latest().await?.is_none() {
+            self.record(
+                ConfigChangeReason::Baseline,
+                config.clone(),
+                None,
+                vec![],
+                None,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Store a new version and append the change to the audit log.
+    pub async fn record(
+        &self,
+        reason: ConfigChangeReason,
+        config: BTreeMap<String, String>,
+        auth_sig: Option<&JsonAuthSig>,
+        diff: Vec<ConfigDiffEntry>,
+        note: Option<String>,
+    ) -> Result<ConfigHistoryEntry> {
+        fs::create_dir_all(&self.path)
+            .await
+            .map_err(|e| io_err(e, None))?;
+
+        let previous_version = self.latest().await?.map(|e| e.version);
+        let version = previous_version.map(|v| v + 1).unwrap_or(0);
+        let now = unix_now()?;
+
+        let entry = ConfigHistoryEntry {
+            version,
+            applied_at: now,
+            reason,
+            previous_version,
+            admin_address: auth_sig.map(|a| a.address.clone()),
+            config,
+        };
+        let data = serde_json::to_vec_pretty(&entry).map_err(|e| serializer_err(e, None))?;
+        fs::write(self.version_path(version), data)
+            .await
+            .map_err(|e| io_err(e, None))?;
+
+        self.append_audit(&ConfigAuditEntry {
+            version,
+            timestamp: now,
+            reason,
+            diff: diff.into_iter().map(|d| d.redacted()).collect(),
+            admin_address: auth_sig.map(|a| a.address.clone()),
+            admin_signature: auth_sig.map(|a| a.sig.clone()),
+            admin_signed_message: auth_sig.map(|a| a.signed_message.clone()),
+            note,
+        })
+        .await?;
+
+        trace!("Recorded config version {} ({:?})", version, reason);
+
+        Ok(entry)
+    }
+
+    pub async fn audit_log(&self) -> Result<Vec<ConfigAuditEntry>> {
+        let data = match fs::read_to_string(self.path.join(AUDIT_LOG_FN)).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(io_err(e, None)),
+        };
+
+        data.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).map_err(|e| serializer_err(e, None)))
+            .collect()
+    }
+
+    async fn append_audit(&self, entry: &ConfigAuditEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry).map_err(|e| serializer_err(e, None))?;
+        line.push('\n');
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.join(AUDIT_LOG_FN))
+            .await
+            .map_err(|e| io_err(e, Some("failed to open config audit log".into())))?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| io_err(e, Some("failed to write config audit log".into())))?;
+        file.flush().await.map_err(|e| io_err(e, None))
+    }
+
+    fn version_path(&self, version: u64) -> PathBuf {
+        self.path.join(format!("v{:08}.json", version))
+    }
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| unexpected_err(e, None))?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{ConfigChangeReason, ConfigHistory};
+    use crate::endpoints::admin::config_patch::ConfigDiffEntry;
+
+    #[tokio::test]
+    async fn record_and_list_test() {
+        let file = temp_file::empty();
+        let dir = file.path().with_extension("d");
+        let history = ConfigHistory::new(dir.clone());
+
+        let mut config = BTreeMap::new();
+        config.insert("node.enable_rate_limiting".to_string(), "true".to_string());
+        history.ensure_baseline(&config).await.unwrap();
+        history.ensure_baseline(&config).await.unwrap();
+
+        config.insert("node.bls_key_blinder".to_string(), "abcd".to_string());
+        let diff = vec![ConfigDiffEntry {
+            key: "node.bls_key_blinder".to_string(),
+            old: None,
+            new: Some("abcd".to_string()),
+        }];
+        let entry = history
+            .record(ConfigChangeReason::Patch, config, None, diff, None)
+            .await
+            .unwrap();
+
+        assert_eq!(entry.version, 1);
+        assert_eq!(entry.previous_version, Some(0));
+        assert_eq!(history.list().await.unwrap().len(), 2);
+        assert_eq!(
+            history.get(1).await.unwrap().unwrap().config["node.bls_key_blinder"],
+            "abcd"
+        );
+        assert_eq!(
+            entry.redacted().config["node.bls_key_blinder"],
+            "<redacted>"
+        );
+
+        let audit = history.audit_log().await.unwrap();
+        assert_eq!(audit.len(), 2);
+        assert_eq!(audit[1].reason, ConfigChangeReason::Patch);
+        assert_eq!(audit[1].diff[0].new.as_deref(), Some("<redacted>"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,549 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use lit_core::config::{LitConfig, ReloadableLitConfig, CFG_ADMIN_OVERRIDE_NAME};
+use lit_core::utils::toml::SimpleToml;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::auth::auth_material::JsonAuthSig;
+use crate::config::schema::{is_sensitive_config_key, validate_config_value};
+use crate::config::{
+    LitNodeConfig, CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS_DEFAULT,
+    CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS_DEFAULT, CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS_DEFAULT,
+};
+use crate::endpoints::admin::config_history::{
+    ConfigChangeReason, ConfigHistory, ConfigHistoryEntry,
+};
+use crate::error::{validation_err, Result};
+use crate::peers::PeerState;
+
+/// Consecutive failed health checks required before an automatic rollback (which must also
+/// span the configured health failure window).
+const HEALTH_CHECK_MAX_FAILURES: u32 = 3;
+
+lazy_static! {
+    /// Serializes all writes to the admin override (and its history).
+    static ref CONFIG_WRITE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum ConfigPatchOp {
+    /// Set `key` (i.e. `node.enable_rate_limiting`) to `value`.
+    Set { key: String, value: String },
+    /// Remove `key` from the admin override (reverting to the default).
+    Remove { key: String },
+}
+
+impl ConfigPatchOp {
+    pub fn key(&self) -> &str {
+        match self {
+            ConfigPatchOp::Set { key, .. } => key,
+            ConfigPatchOp::Remove { key } => key,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiffEntry {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl ConfigDiffEntry {
+    pub fn redacted(mut self) -> Self {
+        if is_sensitive_config_key(&self.key) {
+            self.old = self.old.map(|_| "<redacted>".to_string());
+            self.new = self.new.map(|_| "<redacted>".to_string());
+        }
+        self
+    }
+}
+
+/// The outcome of applying (or dry-running) a change.
+#[derive(Debug, Clone)]
+pub struct ConfigChangeResult {
+    pub diff: Vec<ConfigDiffEntry>,
+    pub entry: Option<ConfigHistoryEntry>,
+}
+
+/// Load the current admin override as a flat map of `section.key` to value.
+pub fn load_admin_override(cfg: &LitConfig) -> Result<BTreeMap<String, String>> {
+    let path = admin_override_path(cfg);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let toml = SimpleToml::try_from(path.as_path())?;
+    let mut map = BTreeMap::new();
+    for (section, values) in toml.data().iter() {
+        for (key, value) in values.iter() {
+            map.insert(format!("{}.{}", section, key), value.clone());
+        }
+    }
+
+    Ok(map)
+}
+
+/// Validate every key and value of a whole config (i.e. one being restored) against the schema.
+pub fn validate_config(cfg: &LitConfig, config: &BTreeMap<String, String>) -> Result<()> {
+    cfg.verify_user_editable(&config.clone().into_iter().collect())?;
+    for (key, value) in config.iter() {
+        validate_config_value(key, value)?;
+    }
+
+    Ok(())
+}
+
+/// Validate every key and value of the patch ops against the schema.
+pub fn validate_patch_ops(cfg: &LitConfig, ops: &[ConfigPatchOp]) -> Result<()> {
+    if ops.is_empty() {
+        return Err(validation_err("no config patch ops supplied", None));
+    }
+
+    for op in ops {
+        cfg.verify_user_editable(&HashMap::from([(op.key().to_string(), String::new())]))?;
+
+        if let ConfigPatchOp::Set { key, value } = op {
+            validate_config_value(key, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply patch ops to `current`, validating every key and value against the schema.
+pub fn apply_patch_ops(
+    cfg: &LitConfig,
+    current: &BTreeMap<String, String>,
+    ops: &[ConfigPatchOp],
+) -> Result<BTreeMap<String, String>> {
+    validate_patch_ops(cfg, ops)?;
+
+    let mut next = current.clone();
+    for op in ops {
+        match op {
+            ConfigPatchOp::Set { key, value } => {
+                next.insert(key.clone(), value.clone());
+            }
+            ConfigPatchOp::Remove { key } => {
+                next.remove(key);
+            }
+        }
+    }
+
+    Ok(next)
+}
+
+pub fn diff_configs(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<ConfigDiffEntry> {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let (o, n) = (old.get(key), new.get(key));
+            if o == n {
+                return None;
+            }
+
+            Some(ConfigDiffEntry {
+                key: key.clone(),
+                old: o.cloned(),
+                new: n.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Write `new_config` as the admin override, reload and record the change.
+///
+/// Unless this is itself a rollback, a health watcher is started which restores the
+/// previous version if the node loses RPC or peer connectivity within the rollback window,
+/// and doesn't recover within the health failure window.
+pub async fn apply_config_change(
+    reloadable_cfg: &ReloadableLitConfig,
+    peer_state: Arc<PeerState>,
+    new_config: BTreeMap<String, String>,
+    reason: ConfigChangeReason,
+    auth_sig: Option<&JsonAuthSig>,
+    dry_run: bool,
+) -> Result<ConfigChangeResult> {
+    let _guard = CONFIG_WRITE_LOCK.lock().await;
+
+    let current = load_admin_override(&reloadable_cfg.load_full())?;
+    apply_locked(
+        reloadable_cfg,
+        peer_state,
+        current,
+        new_config,
+        reason,
+        auth_sig,
+        dry_run,
+    )
+    .await
+}
+
+/// Apply patch ops to the current admin override, like `apply_config_change`.
+///
+/// The override is read, patched and written back under the config write lock, so that
+/// concurrent patches can't overwrite each other.
+pub async fn apply_config_patch(
+    reloadable_cfg: &ReloadableLitConfig,
+    peer_state: Arc<PeerState>,
+    ops: &[ConfigPatchOp],
+    auth_sig: Option<&JsonAuthSig>,
+    dry_run: bool,
+) -> Result<ConfigChangeResult> {
+    let _guard = CONFIG_WRITE_LOCK.lock().await;
+
+    let cfg = reloadable_cfg.load_full();
+    let current = load_admin_override(&cfg)?;
+    let new_config = apply_patch_ops(&cfg, &current, ops)?;
+    apply_locked(
+        reloadable_cfg,
+        peer_state,
+        current,
+        new_config,
+        ConfigChangeReason::Patch,
+        auth_sig,
+        dry_run,
+    )
+    .await
+}
+
+// Must be called with the config write lock held.
+async fn apply_locked(
+    reloadable_cfg: &ReloadableLitConfig,
+    peer_state: Arc<PeerState>,
+    current: BTreeMap<String, String>,
+    new_config: BTreeMap<String, String>,
+    reason: ConfigChangeReason,
+    auth_sig: Option<&JsonAuthSig>,
+    dry_run: bool,
+) -> Result<ConfigChangeResult> {
+    let diff = diff_configs(&current, &new_config);
+    if dry_run {
+        return Ok(ConfigChangeResult { diff, entry: None });
+    }
+
+    ConfigHistory::from_config(&reloadable_cfg.load_full())?
+        .ensure_baseline(&current)
+        .await?;
+
+    // Capture health before the change, so a node that was already unhealthy isn't rolled back.
+    let baseline_health = check_health(&peer_state).await;
+
+    let entry = write_config(reloadable_cfg, new_config, reason, auth_sig, diff.clone()).await?;
+
+    if reason != ConfigChangeReason::Rollback {
+        if let Some(previous_version) = entry.previous_version {
+            spawn_health_watcher(
+                reloadable_cfg.clone(),
+                peer_state,
+                entry.version,
+                previous_version,
+                baseline_health,
+            );
+        }
+    }
+
+    Ok(ConfigChangeResult {
+        diff,
+        entry: Some(entry),
+    })
+}
+
+async fn write_config(
+    reloadable_cfg: &ReloadableLitConfig,
+    new_config: BTreeMap<String, String>,
+    reason: ConfigChangeReason,
+    auth_sig: Option<&JsonAuthSig>,
+    diff: Vec<ConfigDiffEntry>,
+) -> Result<ConfigHistoryEntry> {
+    let data: HashMap<String, String> = new_config.clone().into_iter().collect();
+    reloadable_cfg
+        .load_full()
+        .save_local_config(CFG_ADMIN_OVERRIDE_NAME, &data)?;
+    reloadable_cfg.reload()?;
+
+    ConfigHistory::from_config(&reloadable_cfg.load_full())?
+        .record(reason, new_config, auth_sig, diff, None)
+        .await
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HealthStatus {
+    rpc_ok: bool,
+    connected_peers: usize,
+}
+
+async fn check_health(peer_state: &PeerState) -> HealthStatus {
+    let rpc_ok = match peer_state.get_block_number().await {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Config health check: RPC unhealthy: {:?}", e);
+            false
+        }
+    };
+    let connected_peers = peer_state
+        .connected_nodes()
+        .map(|nodes| nodes.len())
+        .unwrap_or(0);
+
+    HealthStatus {
+        rpc_ok,
+        connected_peers,
+    }
+}
+
+fn is_healthy(baseline: &HealthStatus, current: &HealthStatus) -> bool {
+    (current.rpc_ok || !baseline.rpc_ok) && current.connected_peers >= baseline.connected_peers
+}
+
+/// Tracks consecutive failed health checks. A rollback is only due once the node has failed
+/// `HEALTH_CHECK_MAX_FAILURES` checks in a row spanning at least `window`, so that a short
+/// dip (i.e. a peer restarting) doesn't roll back a good change.
+#[derive(Debug)]
+struct HealthFailures {
+    window: Duration,
+    count: u32,
+    since: Option<Instant>,
+}
+
+impl HealthFailures {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            count: 0,
+            since: None,
+        }
+    }
+
+    fn is_failing(&self) -> bool {
+        self.since.is_some()
+    }
+
+    /// Record the outcome of a health check, returning whether a rollback is due.
+    fn record(&mut self, healthy: bool, now: Instant) -> bool {
+        if healthy {
+            self.count = 0;
+            self.since = None;
+            return false;
+        }
+
+        self.count += 1;
+        let since = *self.since.get_or_insert(now);
+        self.count >= HEALTH_CHECK_MAX_FAILURES && now.duration_since(since) >= self.window
+    }
+}
+
+fn spawn_health_watcher(
+    reloadable_cfg: ReloadableLitConfig,
+    peer_state: Arc<PeerState>,
+    version: u64,
+    previous_version: u64,
+    baseline: HealthStatus,
+) {
+    tokio::spawn(async move {
+        let cfg = reloadable_cfg.load_full();
+        let window = Duration::from_millis(
+            cfg.config_rollback_window_ms()
+                .unwrap_or(CFG_KEY_CONFIG_ROLLBACK_WINDOW_MS_DEFAULT)
+                .max(0) as u64,
+        );
+        let interval = Duration::from_millis(
+            cfg.config_health_check_interval_ms()
+                .unwrap_or(CFG_KEY_CONFIG_HEALTH_CHECK_INTERVAL_MS_DEFAULT)
+                .max(100) as u64,
+        );
+        let failure_window = Duration::from_millis(
+            cfg.config_health_failure_window_ms()
+                .unwrap_or(CFG_KEY_CONFIG_HEALTH_FAILURE_WINDOW_MS_DEFAULT)
+                .max(0) as u64,
+        );
+
+        let mut elapsed = Duration::ZERO;
+        let mut failures = HealthFailures::new(failure_window);
+        // A failure which started within the rollback window is followed until it either
+        // recovers or lasts long enough to roll back.
+        while elapsed < window || failures.is_failing() {
+            tokio::time::sleep(interval).await;
+            elapsed += interval;
+
+            // A newer change supersedes this watcher.
+            let history = match ConfigHistory::from_config(&reloadable_cfg.load_full()) {
+                Ok(history) => history,
+                Err(e) => {
+                    error!("Failed to load config history: {:?}", e);
+                    return;
+                }
+            };
+            match history.latest().await {
+                Ok(Some(latest)) if latest.version == version => {}
+                _ => return,
+            }
+
+            let current = check_health(&peer_state).await;
+            let healthy = is_healthy(&baseline, &current);
+            let rollback = failures.record(healthy, Instant::now());
+            if healthy {
+                continue;
+            }
+
+            warn!(
+                "Config version {} failed health check ({} in a row): {:?} (baseline: {:?})",
+                version, failures.count, current, baseline
+            );
+            if rollback {
+                if let Err(e) = auto_rollback(&reloadable_cfg, previous_version).await {
+                    error!(
+                        "Failed to roll back config to version {}: {:?}",
+                        previous_version, e
+                    );
+                }
+                return;
+            }
+        }
+
+        info!("Config version {} passed health checks", version);
+    });
+}
+
+async fn auto_rollback(reloadable_cfg: &ReloadableLitConfig, previous_version: u64) -> Result<()> {
+    let _guard = CONFIG_WRITE_LOCK.lock().await;
+
+    let cfg = reloadable_cfg.load_full();
+    let previous = ConfigHistory::from_config(&cfg)?
+        .get(previous_version)
+        .await?
+        .ok_or_else(|| {
+            validation_err(
+                format!("config version {} not found", previous_version),
+                None,
+            )
+        })?;
+
+    // The schema may have changed since the version was recorded.
+    validate_config(&cfg, &previous.config)?;
+
+    warn!("Rolling back config to version {}", previous_version);
+    let diff = diff_configs(&load_admin_override(&cfg)?, &previous.config);
+    write_config(
+        reloadable_cfg,
+        previous.config,
+        ConfigChangeReason::AutoRollback,
+        None,
+        diff,
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn admin_override_path(cfg: &LitConfig) -> PathBuf {
+    cfg.local_config_path(CFG_ADMIN_OVERRIDE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{apply_patch_ops, diff_configs, validate_config, ConfigPatchOp, HealthFailures};
+    use crate::tests::common::get_test_config;
+
+    #[test]
+    fn apply_patch_ops_test() {
+        let cfg = get_test_config();
+        let mut current = BTreeMap::new();
+        current.insert("node.enable_rate_limiting".to_string(), "true".to_string());
+        current.insert("node.ecdsa_round_timeout".to_string(), "20000".to_string());
+
+        let next = apply_patch_ops(
+            &cfg,
+            &current,
+            &[
+                ConfigPatchOp::Set {
+                    key: "node.enable_rate_limiting".to_string(),
+                    value: "false".to_string(),
+                },
+                ConfigPatchOp::Remove {
+                    key: "node.ecdsa_round_timeout".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+
+        let diff = diff_configs(&current, &next);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].key, "node.ecdsa_round_timeout");
+        assert_eq!(diff[0].new, None);
+        assert_eq!(diff[1].new.as_deref(), Some("false"));
+
+        // Invalid values and keys are rejected.
+        assert!(apply_patch_ops(
+            &cfg,
+            &current,
+            &[ConfigPatchOp::Set {
+                key: "node.enable_rate_limiting".to_string(),
+                value: "maybe".to_string(),
+            }],
+        )
+        .is_err());
+        assert!(apply_patch_ops(
+            &cfg,
+            &current,
+            &[ConfigPatchOp::Set {
+                key: "node.port".to_string(),
+                value: "1234".to_string(),
+            }],
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn validate_config_test() {
+        let cfg = get_test_config();
+        let mut config = BTreeMap::new();
+        config.insert("node.enable_rate_limiting".to_string(), "true".to_string());
+        assert!(validate_config(&cfg, &config).is_ok());
+
+        config.insert("node.ecdsa_round_timeout".to_string(), "soon".to_string());
+        assert!(validate_config(&cfg, &config).is_err());
+
+        config.remove("node.ecdsa_round_timeout");
+        config.insert("node.port".to_string(), "1234".to_string());
+        assert!(validate_config(&cfg, &config).is_err());
+    }
+
+    #[test]
+    fn health_failures_test() {
+        let start = Instant::now();
+        let mut failures = HealthFailures::new(Duration::from_secs(60));
+
+        // A short dip doesn't roll back, however many checks fail.
+        for i in 0..5 {
+            assert!(!failures.record(false, start + Duration::from_secs(i)));
+        }
+        assert!(!failures.record(true, start + Duration::from_secs(10)));
+        assert!(!failures.is_failing());
+
+        // A sustained failure does.
+        assert!(!failures.record(false, start + Duration::from_secs(20)));
+        assert!(!failures.record(false, start + Duration::from_secs(50)));
+        assert!(failures.is_failing());
+        assert!(failures.record(false, start + Duration::from_secs(80)));
+    }
+}
@@ -1,3 +1,11 @@
+use crate::endpoints::admin::config_history::{ConfigChangeReason, ConfigHistory};
+use crate::endpoints::admin::config_patch::{
+    apply_config_change, apply_config_patch, validate_config, validate_patch_ops,
+};
+use crate::endpoints::admin::quorum::{
+    auth_sig_address, authorize_admin_operation, check_admin_signer_auth_sig,
+    check_proposal_auth_sig, AdminOperation, AdminQuorum,
+};
 #[cfg(feature = "testing")]
 use crate::endpoints::admin::utils::get_test_recovery_party;
 use crate::endpoints::admin::utils::{
@@ -5,6 +13,9 @@ use crate::endpoints::admin::utils::{
 };
 use crate::error::{parser_err, validation_err, validation_err_code, EC};
 use crate::models;
+use crate::peers::PeerState;
+use crate::rate_limiting::models::RateLimitDB;
+use crate::rate_limiting::usage::UsageExportFormat;
 #[cfg(not(feature = "testing"))]
 use crate::tss::common::backup::get_recovery_party;
 use crate::tss::common::restore::{
@@ -17,7 +28,7 @@ use lit_api_core::error::ApiError;
 use lit_api_core::http::rocket::helper::stream::ChildStream;
 use lit_blockchain::resolver::rpc::config::{RpcConfig, RPC_CONFIG_PROTECTED_CHAINS};
 use lit_blockchain::resolver::rpc::{RpcResolver, RPC_RESOLVER};
-use lit_core::config::{ReloadableLitConfig, CFG_ADMIN_OVERRIDE_NAME};
+use lit_core::config::ReloadableLitConfig;
 use rocket::data::ByteUnit;
 use rocket::http::{ContentType, Status};
 use rocket::response::status;
@@ -34,6 +45,8 @@ use super::guards::AdminAuthSig;
 pub async fn admin_set(
     remote_addr: SocketAddr,
     reloadable_cfg: &State<ReloadableLitConfig>,
+    peer_state: &State<Arc<PeerState>>,
+    quorum: &State<Arc<AdminQuorum>>,
     request: Json<models::JsonAdminSetRequest>,
 ) -> status::Custom<Value> {
     let cfg = reloadable_cfg.load_full();
@@ -55,12 +68,33 @@ pub async fn admin_set(
         .handle();
     }
 
-    // write the config to the config file
-    if let Err(e) = cfg.save_local_config(CFG_ADMIN_OVERRIDE_NAME, &request.new_config) {
+    let operation = AdminOperation::ConfigSet {
+        new_config: request.new_config.clone().into_iter().collect(),
+        rpc_config: request.rpc_config.clone(),
+    };
+    if let Err(e) = authorize_admin_operation(
+        &cfg,
+        quorum,
+        &request.auth_sig,
+        request.proposal_id.as_deref(),
+        &operation,
+    )
+    .await
+    {
         return e.handle();
     }
 
-    if let Err(e) = reloadable_cfg.reload() {
+    // write the config to the config file (recording it in the history)
+    if let Err(e) = apply_config_change(
+        reloadable_cfg,
+        peer_state.inner().clone(),
+        request.new_config.clone().into_iter().collect(),
+        ConfigChangeReason::Set,
+        Some(&request.auth_sig),
+        false,
+    )
+    .await
+    {
         return e.handle();
     }
 
@@ -125,14 +159,363 @@ pub async fn admin_set(
     );
 }
 
+#[instrument(name = "POST /web/admin/config/patch", skip_all, ret)]
+pub async fn admin_config_patch(
+    reloadable_cfg: &State<ReloadableLitConfig>,
+    peer_state: &State<Arc<PeerState>>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminConfigPatchRequest>,
+) -> status::Custom<Value> {
+    let cfg = reloadable_cfg.load_full();
+
+    if let Err(e) = check_admin_auth_sig(&cfg, &request.auth_sig) {
+        return e.handle();
+    }
+
+    if let Err(e) = validate_patch_ops(&cfg, &request.ops) {
+        return e.add_msg_to_details().handle();
+    }
+
+    if !request.dry_run {
+        let operation = AdminOperation::ConfigPatch {
+            ops: request.ops.clone(),
+        };
+        if let Err(e) = authorize_admin_operation(
+            &cfg,
+            quorum,
+            &request.auth_sig,
+            request.proposal_id.as_deref(),
+            &operation,
+        )
+        .await
+        {
+            return e.handle();
+        }
+    }
+
+    let res = match apply_config_patch(
+        reloadable_cfg,
+        peer_state.inner().clone(),
+        &request.ops,
+        Some(&request.auth_sig),
+        request.dry_run,
+    )
+    .await
+    {
+        Ok(res) => res,
+        Err(e) => return e.handle(),
+    };
+
+    let diff: Vec<_> = res.diff.into_iter().map(|d| d.redacted()).collect();
+
+    return status::Custom(
+        Status::Ok,
+        json!({
+            "success": "true",
+            "dryRun": request.dry_run,
+            "version": res.entry.map(|e| e.version),
+            "diff": diff,
+        }),
+    );
+}
+
+#[instrument(name = "POST /web/admin/config/history", skip_all, ret)]
+pub async fn admin_config_history(
+    cfg: &State<ReloadableLitConfig>,
+    auth: Json<models::AdminAuth>,
+) -> status::Custom<Value> {
+    let cfg = cfg.load_full();
+
+    if let Err(e) = check_admin_auth_sig(&cfg, &auth.auth_sig) {
+        return e.handle();
+    }
+
+    let history = match ConfigHistory::from_config(&cfg) {
+        Ok(history) => history,
+        Err(e) => return e.handle(),
+    };
+    let versions = match history.list().await {
+        Ok(versions) => versions,
+        Err(e) => return e.handle(),
+    };
+    let audit = match history.audit_log().await {
+        Ok(audit) => audit,
+        Err(e) => return e.handle(),
+    };
+    let versions: Vec<_> = versions.iter().map(|v| v.redacted()).collect();
+
+    return status::Custom(
+        Status::Ok,
+        json!({
+            "success": "true",
+            "versions": versions,
+            "audit": audit,
+        }),
+    );
+}
+
+#[instrument(name = "POST /web/admin/config/rollback", skip_all, ret)]
+pub async fn admin_config_rollback(
+    reloadable_cfg: &State<ReloadableLitConfig>,
+    peer_state: &State<Arc<PeerState>>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminConfigRollbackRequest>,
+) -> status::Custom<Value> {
+    let cfg = reloadable_cfg.load_full();
+
+    if let Err(e) = check_admin_auth_sig(&cfg, &request.auth_sig) {
+        return e.handle();
+    }
+
+    let history = match ConfigHistory::from_config(&cfg) {
+        Ok(history) => history,
+        Err(e) => return e.handle(),
+    };
+    let target = match history.get(request.version).await {
+        Ok(Some(target)) => target,
+        Ok(None) => {
+            return validation_err(
+                format!("config version {} not found", request.version),
+                None,
+            )
+            .add_msg_to_details()
+            .handle();
+        }
+        Err(e) => return e.handle(),
+    };
+
+    // The schema may have changed since the version was recorded.
+    if let Err(e) = validate_config(&cfg, &target.config) {
+        return e.add_msg_to_details().handle();
+    }
+
+    if !request.dry_run {
+        let operation = AdminOperation::ConfigRollback {
+            version: request.version,
+        };
+        if let Err(e) = authorize_admin_operation(
+            &cfg,
+            quorum,
+            &request.auth_sig,
+            request.proposal_id.as_deref(),
+            &operation,
+        )
+        .await
+        {
+            return e.handle();
+        }
+    }
+
+    let res = match apply_config_change(
+        reloadable_cfg,
+        peer_state.inner().clone(),
+        target.config,
+        ConfigChangeReason::Rollback,
+        Some(&request.auth_sig),
+        request.dry_run,
+    )
+    .await
+    {
+        Ok(res) => res,
+        Err(e) => return e.handle(),
+    };
+
+    let diff: Vec<_> = res.diff.into_iter().map(|d| d.redacted()).collect();
+
+    return status::Custom(
+        Status::Ok,
+        json!({
+            "success": "true",
+            "dryRun": request.dry_run,
+            "version": res.entry.map(|e| e.version),
+            "diff": diff,
+        }),
+    );
+}
+
+#[instrument(name = "POST /web/admin/proposal/create", skip_all, ret)]
+pub async fn admin_proposal_create(
+    cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminProposalCreateRequest>,
+) -> status::Custom<Value> {
+    let cfg = cfg.load_full();
+
+    if let Err(e) = check_admin_signer_auth_sig(&cfg, &request.auth_sig) {
+        return e.handle();
+    }
+
+    let proposer = match auth_sig_address(&request.auth_sig) {
+        Ok(proposer) => proposer,
+        Err(e) => return e.handle(),
+    };
+    let ttl_ms = match cfg.admin_proposal_ttl_ms() {
+        Ok(ttl_ms) => ttl_ms,
+        Err(e) => return e.handle(),
+    };
+    let threshold = match cfg.admin_quorum_threshold() {
+        Ok(threshold) => threshold,
+        Err(e) => return e.handle(),
+    };
+
+    let proposal = match quorum
+        .propose(proposer, request.operation.clone(), ttl_ms)
+        .await
+    {
+        Ok(proposal) => proposal,
+        Err(e) => return e.handle(),
+    };
+
+    return status::Custom(
+        Status::Ok,
+        json!({
+            "success": "true",
+            "proposal": proposal,
+            "resource": proposal.resource(),
+            "threshold": threshold,
+        }),
+    );
+}
+
+#[instrument(name = "POST /web/admin/proposal/approve", skip_all, ret)]
+pub async fn admin_proposal_approve(
+    cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminProposalApproveRequest>,
+) -> status::Custom<Value> {
+    let cfg = cfg.load_full();
+
+    let proposal = match quorum.get(&request.proposal_id).await {
+        Some(proposal) => proposal,
+        None => {
+            return validation_err_code(
+                format!("admin proposal {} not found", request.proposal_id),
+                EC::NodeAdminUnauthorized,
+                None,
+            )
+            .handle();
+        }
+    };
+
+    // The approval must be signed for this specific proposal.
+    let signer = match check_proposal_auth_sig(&cfg, &request.auth_sig, &proposal) {
+        Ok(signer) => signer,
+        Err(e) => return e.handle(),
+    };
+
+    let proposal = match quorum.approve(&request.proposal_id, signer).await {
+        Ok(proposal) => proposal,
+        Err(e) => return e.handle(),
+    };
+    let threshold = match cfg.admin_quorum_threshold() {
+        Ok(threshold) => threshold,
+        Err(e) => return e.handle(),
+    };
+
+    return status::Custom(
+        Status::Ok,
+        json!({
+            "success": "true",
+            "proposal": proposal,
+            "threshold": threshold,
+        }),
+    );
+}
+
+#[instrument(name = "POST /web/admin/proposal/list", skip_all, ret)]
+pub async fn admin_proposal_list(
+    cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
+    auth: Json<models::AdminAuth>,
+) -> status::Custom<Value> {
+    let cfg = cfg.load_full();
+
+    if let Err(e) = check_admin_signer_auth_sig(&cfg, &auth.auth_sig) {
+        return e.handle();
+    }
+
+    let proposals = match quorum.pending().await {
+        Ok(proposals) => proposals,
+        Err(e) => return e.handle(),
+    };
+
+    return status::Custom(
+        Status::Ok,
+        json!({
+            "success": "true",
+            "proposals": proposals,
+        }),
+    );
+}
+
+/// The largest window that can be exported in one request.
+const USAGE_EXPORT_MAX_WINDOW_SECS: u64 = 31 * 24 * 60 * 60;
+
+#[instrument(name = "POST /web/admin/usage/export", skip_all)]
+pub async fn admin_usage_export(
+    cfg: &State<ReloadableLitConfig>,
+    rate_limit_db: &State<Arc<RateLimitDB>>,
+    request: Json<models::JsonAdminUsageExportRequest>,
+) -> Result<(ContentType, String), status::Custom<Value>> {
+    let cfg = cfg.load_full();
+
+    if let Err(e) = check_admin_auth_sig(&cfg, &request.auth_sig) {
+        return Err(e.handle());
+    }
+
+    if request.to.saturating_sub(request.from) > USAGE_EXPORT_MAX_WINDOW_SECS {
+        return Err(validation_err(
+            format!(
+                "usage export window may not exceed {} seconds",
+                USAGE_EXPORT_MAX_WINDOW_SECS
+            ),
+            None,
+        )
+        .handle());
+    }
+
+    let records = match rate_limit_db
+        .usage
+        .query(
+            request.from.saturating_mul(1000),
+            request.to.saturating_mul(1000),
+        )
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => return Err(e.handle()),
+    };
+
+    let body = match request.format.render(&records) {
+        Ok(body) => body,
+        Err(e) => return Err(e.handle()),
+    };
+    let content_type = match request.format {
+        UsageExportFormat::Csv => ContentType::CSV,
+        UsageExportFormat::Ndjson => ContentType::new("application", "x-ndjson"),
+    };
+
+    Ok((content_type, body))
+}
+
 #[instrument(name = "POST /web/admin/get", skip_all, ret)]
 pub async fn admin_get(
     cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
     auth: Json<models::AdminAuth>,
 ) -> status::Custom<Value> {
     let cfg = cfg.load_full();
 
-    if let Err(e) = check_admin_auth_sig(&cfg, &auth.auth_sig) {
+    // The exported config includes the key blinders and the wallet private key.
+    if let Err(e) = authorize_admin_operation(
+        &cfg,
+        quorum,
+        &auth.auth_sig,
+        auth.proposal_id.as_deref(),
+        &AdminOperation::GetConfig,
+    )
+    .await
+    {
         return e.handle();
     }
 
@@ -170,11 +553,20 @@ pub async fn admin_get(
 pub async fn admin_get_blinders(
     cfg: &State<ReloadableLitConfig>,
     restore_state: &State<Arc<RwLock<RestoreState>>>,
+    quorum: &State<Arc<AdminQuorum>>,
     auth: Json<models::AdminAuth>,
 ) -> status::Custom<Value> {
     let cfg = cfg.load_full();
 
-    if let Err(e) = check_admin_auth_sig(&cfg, &auth.auth_sig) {
+    if let Err(e) = authorize_admin_operation(
+        &cfg,
+        quorum,
+        &auth.auth_sig,
+        auth.proposal_id.as_deref(),
+        &AdminOperation::GetBlinders,
+    )
+    .await
+    {
         return e.handle();
     }
 
@@ -194,12 +586,24 @@ pub async fn admin_get_blinders(
 pub async fn admin_get_key_backup(
     cfg: &State<ReloadableLitConfig>,
     restore_state: &State<Arc<RwLock<RestoreState>>>,
+    quorum: &State<Arc<AdminQuorum>>,
     auth: models::AdminAuth,
     node_set_hash: Option<String>,
 ) -> Result<ChildStream, status::Custom<Value>> {
     let cfg = cfg.load_full();
 
-    if let Err(e) = check_admin_auth_sig(&cfg, &auth.auth_sig) {
+    let operation = AdminOperation::GetKeyBackup {
+        node_set_hash: node_set_hash.clone(),
+    };
+    if let Err(e) = authorize_admin_operation(
+        &cfg,
+        quorum,
+        &auth.auth_sig,
+        auth.proposal_id.as_deref(),
+        &operation,
+    )
+    .await
+    {
         return Err(e.handle());
     }
     trace!("Auth sig check passed");
@@ -1,3 +1,6 @@
+pub mod config_history;
+pub mod config_patch;
 pub mod endpoints;
 pub mod guards;
+pub mod quorum;
 pub mod utils;
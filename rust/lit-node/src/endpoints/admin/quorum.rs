@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::types::H160;
+use lit_blockchain::resolver::rpc::config::RpcConfig;
+use lit_core::config::LitConfig;
+use serde::{Deserialize, Serialize};
+use siwe::Message;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::auth::auth_material::JsonAuthSig;
+use crate::config::LitNodeConfig;
+use crate::endpoints::admin::config_patch::ConfigPatchOp;
+use crate::endpoints::admin::utils::check_admin_auth_sig;
+use crate::endpoints::auth_sig::{check_auth_sig, LITNODE_ADMIN_RES};
+use crate::error::{parser_err_code, unexpected_err, validation_err_code, Result, EC};
+
+/// SIWE resource an admin must include (alongside `litNodeAdmin://*`) to approve a proposal.
+pub const LITNODE_ADMIN_PROPOSAL_RES_PREFIX: &str = "litNodeAdminProposal://";
+
+/// A sensitive admin operation which requires a quorum of admin signers when
+/// `admin_quorum_threshold` is greater than one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AdminOperation {
+    #[serde(rename_all = "camelCase")]
+    GetKeyBackup {
+        node_set_hash: Option<String>,
+    },
+    GetBlinders,
+    /// The user editable config, which includes the key blinders and the wallet private key.
+    GetConfig,
+    #[serde(rename_all = "camelCase")]
+    ConfigSet {
+        new_config: BTreeMap<String, String>,
+        rpc_config: RpcConfig,
+    },
+    ConfigPatch {
+        ops: Vec<ConfigPatchOp>,
+    },
+    ConfigRollback {
+        version: u64,
+    },
+}
+
+impl AdminOperation {
+    /// Operations match if their JSON representations are equal (so key order is irrelevant).
+    fn matches(&self, other: &AdminOperation) -> bool {
+        match (serde_json::to_value(self), serde_json::to_value(other)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminProposal {
+    pub id: String,
+    pub operation: AdminOperation,
+    pub proposer: H160,
+    pub approvals: BTreeSet<H160>,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub executed: bool,
+}
+
+impl AdminProposal {
+    /// The SIWE resource binding an approval to this proposal.
+    pub fn resource(&self) -> String {
+        format!("{}{}", LITNODE_ADMIN_PROPOSAL_RES_PREFIX, self.id)
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Approvals from addresses that are (still) part of the signer set.
+    fn valid_approvals(&self, signers: &[H160]) -> usize {
+        self.approvals
+            .iter()
+            .filter(|a| signers.contains(a))
+            .count()
+    }
+}
+
+/// Pending M-of-N admin proposals.
+///
+/// Sensitive operations are proposed by one admin, co-signed by distinct admins from the
+/// signer set (see `LitNodeConfig::admin_signers`) within the proposal TTL, and may then be
+/// executed exactly once.
+///
+/// Proposals are only held in memory: restarting the node cancels every pending proposal, and
+/// it has to be proposed and approved again.
+#[derive(Debug, Default)]
+pub struct AdminQuorum {
+    proposals: RwLock<HashMap<String, AdminProposal>>,
+}
+
+impl AdminQuorum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a proposal. The (already verified) proposer counts as the first approval.
+    pub async fn propose(
+        &self,
+        proposer: H160,
+        operation: AdminOperation,
+        ttl_ms: u64,
+    ) -> Result<AdminProposal> {
+        let now = unix_now()?;
+        let proposal = AdminProposal {
+            id: hex::encode(rand::random::<[u8; 16]>()),
+            operation,
+            proposer,
+            approvals: BTreeSet::from([proposer]),
+            created_at: now,
+            expires_at: now + ttl_ms / 1000,
+            executed: false,
+        };
+
+        let mut proposals = self.proposals.write().await;
+        proposals.retain(|_, p| !p.is_expired(now) && !p.executed);
+        proposals.insert(proposal.id.clone(), proposal.clone());
+
+        info!(
+            "Admin proposal {} created by {:?}: {:?}",
+            proposal.id, proposer, proposal.operation
+        );
+
+        Ok(proposal)
+    }
+
+    /// Record an approval from an (already verified) admin signer.
+    pub async fn approve(&self, id: &str, signer: H160) -> Result<AdminProposal> {
+        let now = unix_now()?;
+        let mut proposals = self.proposals.write().await;
+        let proposal = proposals.get_mut(id).ok_or_else(|| unknown_proposal(id))?;
+
+        if proposal.is_expired(now) || proposal.executed {
+            return Err(validation_err_code(
+                format!("admin proposal {} has expired or was already executed", id),
+                EC::NodeAdminUnauthorized,
+                None,
+            ));
+        }
+
+        if !proposal.approvals.insert(signer) {
+            return Err(validation_err_code(
+                format!("admin proposal {} was already approved by {:?}", id, signer),
+                EC::NodeAdminUnauthorized,
+                None,
+            ));
+        }
+
+        info!("Admin proposal {} approved by {:?}", id, signer);
+
+        Ok(proposal.clone())
+    }
+
+    /// Mark the proposal executed if it has reached quorum and matches `operation`.
+    pub async fn consume(
+        &self,
+        id: &str,
+        operation: &AdminOperation,
+        signers: &[H160],
+        threshold: usize,
+    ) -> Result<()> {
+        let now = unix_now()?;
+        let mut proposals = self.proposals.write().await;
+        let proposal = proposals.get_mut(id).ok_or_else(|| unknown_proposal(id))?;
+
+        if proposal.is_expired(now) || proposal.executed {
+            return Err(validation_err_code(
+                format!("admin proposal {} has expired or was already executed", id),
+                EC::NodeAdminUnauthorized,
+                None,
+            ));
+        }
+
+        if !proposal.operation.matches(operation) {
+            return Err(validation_err_code(
+                format!(
+                    "admin proposal {} does not match the requested operation",
+                    id
+                ),
+                EC::NodeAdminUnauthorized,
+                None,
+            ));
+        }
+
+        let approvals = proposal.valid_approvals(signers);
+        if approvals < threshold {
+            return Err(validation_err_code(
+                format!(
+                    "admin proposal {} has {} of {} required approvals",
+                    id, approvals, threshold
+                ),
+                EC::NodeAdminUnauthorized,
+                None,
+            ));
+        }
+
+        proposal.executed = true;
+        info!("Admin proposal {} executed", id);
+
+        Ok(())
+    }
+
+    pub async fn get(&self, id: &str) -> Option<AdminProposal> {
+        self.proposals.read().await.get(id).cloned()
+    }
+
+    pub async fn pending(&self) -> Result<Vec<AdminProposal>> {
+        let now = unix_now()?;
+        Ok(self
+            .proposals
+            .read()
+            .await
+            .values()
+            .filter(|p| !p.is_expired(now) && !p.executed)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Authorize a sensitive admin operation.
+///
+/// The caller must always present a valid auth sig from `admin_address`; the other admin
+/// signers can only propose and approve. When the quorum threshold is
+/// greater than one the request must also reference a proposal for the same operation which
+/// has been approved by enough distinct signers.
+pub(crate) async fn authorize_admin_operation(
+    cfg: &LitConfig,
+    quorum: &AdminQuorum,
+    auth_sig: &JsonAuthSig,
+    proposal_id: Option<&str>,
+    operation: &AdminOperation,
+) -> Result<()> {
+    check_admin_auth_sig(cfg, auth_sig)?;
+
+    let threshold = cfg.admin_quorum_threshold()?;
+    if threshold <= 1 {
+        return Ok(());
+    }
+
+    let signers = cfg.admin_signers()?;
+    if threshold > signers.len() {
+        return Err(validation_err_code(
+            format!(
+                "admin quorum threshold ({}) exceeds the number of admin signers ({})",
+                threshold,
+                signers.len()
+            ),
+            EC::NodeAdminUnauthorized,
+            None,
+        ));
+    }
+
+    let proposal_id = proposal_id.ok_or_else(|| {
+        validation_err_code(
+            "this operation requires an approved admin proposal (proposalId)",
+            EC::NodeAdminUnauthorized,
+            None,
+        )
+    })?;
+
+    quorum
+        .consume(proposal_id, operation, &signers, threshold)
+        .await
+}
+
+/// Verify that `auth_sig` is a valid admin auth sig from any of the admin signers.
+///
+/// Only the proposal endpoints accept the additional `admin_addresses`: they may propose, list
+/// and approve, but every other admin request still requires `admin_address` itself.
+pub(crate) fn check_admin_signer_auth_sig(cfg: &LitConfig, auth_sig: &JsonAuthSig) -> Result<()> {
+    let signers = cfg.admin_signers()?;
+    check_auth_sig(cfg, auth_sig, LITNODE_ADMIN_RES, &signers)
+}
+
+/// Verify that `auth_sig` is a valid admin signer auth sig which includes the proposal resource.
+pub(crate) fn check_proposal_auth_sig(
+    cfg: &LitConfig,
+    auth_sig: &JsonAuthSig,
+    proposal: &AdminProposal,
+) -> Result<H160> {
+    check_admin_signer_auth_sig(cfg, auth_sig)?;
+
+    let message: Message = auth_sig.signed_message.parse().map_err(|e| {
+        parser_err_code(
+            e,
+            EC::NodeAdminUnauthorized,
+            Some("Parse error on SIWE".into()),
+        )
+    })?;
+    let resource = proposal.resource();
+    if !message.resources.iter().any(|r| r.as_str() == resource) {
+        return Err(validation_err_code(
+            format!("Required resource of {} not found", resource),
+            EC::NodeAdminUnauthorized,
+            None,
+        ));
+    }
+
+    auth_sig_address(auth_sig)
+}
+
+pub(crate) fn auth_sig_address(auth_sig: &JsonAuthSig) -> Result<H160> {
+    H160::from_str(&auth_sig.address).map_err(|e| {
+        parser_err_code(
+            e,
+            EC::NodeAdminUnauthorized,
+            Some("Failed to parse auth sig address".into()),
+        )
+    })
+}
+
+fn unknown_proposal(id: &str) -> crate::error::Error {
+    validation_err_code(
+        format!("admin proposal {} not found", id),
+        EC::NodeAdminUnauthorized,
+        None,
+    )
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| unexpected_err(e, None))?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H160;
+
+    use super::{AdminOperation, AdminQuorum};
+
+    #[tokio::test]
+    async fn quorum_test() {
+        let signers = vec![
+            H160::from_low_u64_be(1),
+            H160::from_low_u64_be(2),
+            H160::from_low_u64_be(3),
+        ];
+        let quorum = AdminQuorum::new();
+        let op = AdminOperation::ConfigRollback { version: 3 };
+
+        let proposal = quorum
+            .propose(signers[0], op.clone(), 60_000)
+            .await
+            .unwrap();
+
+        // Not enough approvals yet, and the proposer can't approve twice.
+        assert!(quorum
+            .consume(&proposal.id, &op, &signers, 2)
+            .await
+            .is_err());
+        assert!(quorum.approve(&proposal.id, signers[0]).await.is_err());
+
+        quorum.approve(&proposal.id, signers[1]).await.unwrap();
+
+        // The operation must match the proposal.
+        assert!(quorum
+            .consume(
+                &proposal.id,
+                &AdminOperation::ConfigRollback { version: 4 },
+                &signers,
+                2
+            )
+            .await
+            .is_err());
+
+        // Approvals from removed signers don't count.
+        assert!(quorum
+            .consume(&proposal.id, &op, &signers[..1], 2)
+            .await
+            .is_err());
+
+        quorum
+            .consume(&proposal.id, &op, &signers, 2)
+            .await
+            .unwrap();
+
+        // Proposals are single use.
+        assert!(quorum
+            .consume(&proposal.id, &op, &signers, 2)
+            .await
+            .is_err());
+        assert!(quorum.pending().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_proposal_test() {
+        let signer = H160::from_low_u64_be(1);
+        let quorum = AdminQuorum::new();
+
+        let proposal = quorum
+            .propose(signer, AdminOperation::GetBlinders, 0)
+            .await
+            .unwrap();
+
+        assert!(quorum
+            .approve(&proposal.id, H160::from_low_u64_be(2))
+            .await
+            .is_err());
+        assert!(quorum
+            .consume(&proposal.id, &AdminOperation::GetBlinders, &[signer], 1)
+            .await
+            .is_err());
+    }
+}
@@ -2,12 +2,13 @@
 use crate::auth::auth_material::AuthSigItem;
 use crate::auth::resources::PKPNFTResource;
 use crate::constants::CHAIN_ETHEREUM;
-use crate::error::unexpected_err;
+use crate::error::{parser_err_code, unexpected_err, validation_err_code, EC};
 use crate::models;
 use crate::models::auth::SessionKeySignedMessage;
 use crate::pkp::auth::AuthMethodScope;
 use crate::pkp::utils::{claim_key, sign_ecdsa};
 use crate::rate_limiting::models::UserContext;
+use crate::rate_limiting::usage::UsageRecorder;
 use crate::rate_limiting::{check_rate_limit, models::RateLimitDB};
 use crate::tss::common::tss_state::TssState;
 use crate::utils::web::get_auth_context;
@@ -134,22 +135,25 @@ pub(crate) async fn pkp_sign(
                 None => "Rate limit exceeded.  Try again later.".to_string(),
             };
             warn!("{}", msg);
-            return status::Custom(
+            return validation_err_code(msg.clone(), EC::NodeRateLimitExceeded, None).handle_with_legacy_code(
                 Status::BadRequest,
-                json!({"message": msg, "errorCode": "rate_limit_exceeded"}),
+                "rate_limit_exceeded",
+                &msg,
             );
         }
 
+        let mut usage = UsageRecorder::new(&cfg, rate_limit_db.usage.clone(), "/web/pkp/sign", tracing.clone().correlation_id().to_string(), request_start);
+        usage.rate_limit(user_address, &rate_limit_check_return);
+
         // check for single or multiple auth sigs and do the session key
         // capability check.  set the wallet that provided the capabilities as the
         // main auth sig wallet.
         let auth_sig = match &json_pkp_signing_request.auth_sig {
             AuthSigItem::Single(single_auth_sig) => single_auth_sig.clone(),
             AuthSigItem::Multiple(_) => {
-                return status::Custom(
-                    Status::BadRequest,
-                    json!({"message": "Multiple auth sigs not supported by Lit Actions", "errorCode": "unsupported_auth_sig"}),
-                );
+                let msg = "Multiple auth sigs not supported by Lit Actions";
+                return validation_err_code(msg, EC::NodeAuthSigNotSupported, None)
+                    .handle_with_legacy_code(Status::BadRequest, "unsupported_auth_sig", msg);
             }
         };
 
@@ -186,10 +190,10 @@ pub(crate) async fn pkp_sign(
                     Ok(session_key_signed_message) => session_key_signed_message,
                     Err(e) => {
                         error!("Error parsing session sig in pkp_sign");
-                        return status::Custom(
-                            Status::BadRequest,
-                            json!({"message": "Either you've have passed an AuthSig or the sessionSig is incorrectly formatted", "errorCode": "unsupported_auth_sig"}),
-                        );
+                        let msg = "Either you've have passed an AuthSig or the sessionSig is incorrectly formatted";
+                        return parser_err_code(e, EC::NodeAuthSigSignedMessageConversionError, Some(msg.into()))
+                            .add_msg_to_details()
+                            .handle_with_legacy_code(Status::BadRequest, "unsupported_auth_sig", msg);
                     }
                 };
 
@@ -265,6 +269,9 @@ pub(crate) async fn pkp_sign(
             }
         };
 
+        usage.signatures(1);
+        usage.finish().await;
+
         timing.insert("total".to_string(), request_start.elapsed());
 
         debug!("POST /web/pkp/sign timing: {:?}", timing);
@@ -1,4 +1,6 @@
+use crate::error::{unexpected_err_code, Error, EC};
 use crate::tasks::realtime_metrics::MetricsMessage;
+use lit_api_core::error::ApiError;
 use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::{serde_json::json, Value};
@@ -13,28 +15,32 @@ pub async fn metrics(
 ) -> status::Custom<Value> {
     let (tx, rx) = flume::bounded(1);
     let inquiry = metrics_tx.send(MetricsMessage::Poll(tx));
-    if inquiry.is_err() {
-        return status::Custom(
-            Status::InternalServerError,
-            json!({
-                "success": "false",
-                "error": "Failed to send inquiry to metrics task.",
-            }),
+    if let Err(e) = inquiry {
+        let msg = "Failed to send inquiry to metrics task.";
+        return metrics_error(
+            unexpected_err_code(e.to_string(), EC::NodeSystemFault, Some(msg.into())),
+            msg,
         );
     }
 
     let result = match rx.recv_async().await {
         Ok(result) => result,
-        Err(_) => {
-            return status::Custom(
-                Status::InternalServerError,
-                json!({
-                    "success": "false",
-                    "error": "rx.recv_async().await is_err()",
-                }),
+        Err(e) => {
+            let msg = "Failed to receive metrics from metrics task.";
+            return metrics_error(
+                unexpected_err_code(e, EC::NodeSystemFault, Some(msg.into())),
+                msg,
             );
         }
     };
 
     status::Custom(Status::Ok, json!(result))
 }
+
+// Keeps the fields of the response that predates the error catalog.
+fn metrics_error(err: Error, msg: &str) -> status::Custom<Value> {
+    let mut res = err.handle();
+    res.1["success"] = "false".into();
+    res.1["error"] = msg.into();
+    res
+}
@@ -1,18 +1,22 @@
 use crate::endpoints::admin::guards::AdminAuthSig;
+use crate::endpoints::admin::quorum::AdminQuorum;
 use crate::endpoints::{admin, pkp, recovery, web_client};
 use crate::models;
+use crate::peers::PeerState;
 use crate::rate_limiting::models::RateLimitDB;
 use crate::siwe_db::rpc::EthBlockhashCache;
 use crate::tss::common::restore::RestoreState;
 use crate::tss::common::tss_state::TssState;
 use crate::tss::dkg::curves::common::CurveType;
 use crate::utils::rocket::guards::{ClientContext, RequestHeaders};
+use crate::utils::rocket::sealed_response::HandshakeKeys;
 use crate::utils::web::ConcurrencyGuard;
 use crate::utils::web::EndpointVersion;
 use lit_api_core::context::{SdkVersion, Tracing, TracingRequired};
 use lit_api_core::http::rocket::helper::stream::ChildStream;
 use lit_core::config::ReloadableLitConfig;
 use moka::future::Cache;
+use rocket::http::ContentType;
 use rocket::response::status;
 use rocket::serde::json::{Json, Value};
 use rocket::{Data, Route, State};
@@ -29,11 +33,24 @@ pub(crate) fn routes() -> Vec<Route> {
         admin_get_key_backup,
         admin_set_key_backup,
         admin_get_blinders,
+        admin_config_patch,
+        admin_config_history,
+        admin_config_rollback,
+        admin_proposal_create,
+        admin_proposal_approve,
+        admin_proposal_list,
+        admin_usage_export,
         recovery_set_dec_share,
         recovery_get_dec_key_share,
         recovery_delete_dec_key_share,
         handshake,
+        error_catalog,
+        beacon_latest,
+        beacon_round,
+        dkg_transcripts,
+        dkg_transcript,
         encryption_sign,
+        timelock_sign,
         signing_access_control_condition,
         sign_session_key,
         pkp_sign,
@@ -47,18 +64,92 @@ pub(crate) fn routes() -> Vec<Route> {
 pub async fn admin_set(
     remote_addr: SocketAddr,
     reloadable_cfg: &State<ReloadableLitConfig>,
+    peer_state: &State<Arc<PeerState>>,
+    quorum: &State<Arc<AdminQuorum>>,
     request: Json<models::JsonAdminSetRequest>,
 ) -> status::Custom<Value> {
-    admin::endpoints::admin_set(remote_addr, reloadable_cfg, request).await
+    admin::endpoints::admin_set(remote_addr, reloadable_cfg, peer_state, quorum, request).await
+}
+
+#[post("/web/admin/config/patch", format = "json", data = "<request>")]
+#[instrument(name = "POST /web/admin/config/patch", skip_all, ret)]
+pub async fn admin_config_patch(
+    reloadable_cfg: &State<ReloadableLitConfig>,
+    peer_state: &State<Arc<PeerState>>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminConfigPatchRequest>,
+) -> status::Custom<Value> {
+    admin::endpoints::admin_config_patch(reloadable_cfg, peer_state, quorum, request).await
+}
+
+#[post("/web/admin/config/history", format = "json", data = "<auth>")]
+#[instrument(name = "POST /web/admin/config/history", skip_all, ret)]
+pub async fn admin_config_history(
+    cfg: &State<ReloadableLitConfig>,
+    auth: Json<models::AdminAuth>,
+) -> status::Custom<Value> {
+    admin::endpoints::admin_config_history(cfg, auth).await
+}
+
+#[post("/web/admin/config/rollback", format = "json", data = "<request>")]
+#[instrument(name = "POST /web/admin/config/rollback", skip_all, ret)]
+pub async fn admin_config_rollback(
+    reloadable_cfg: &State<ReloadableLitConfig>,
+    peer_state: &State<Arc<PeerState>>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminConfigRollbackRequest>,
+) -> status::Custom<Value> {
+    admin::endpoints::admin_config_rollback(reloadable_cfg, peer_state, quorum, request).await
+}
+
+#[post("/web/admin/proposal/create", format = "json", data = "<request>")]
+#[instrument(name = "POST /web/admin/proposal/create", skip_all, ret)]
+pub async fn admin_proposal_create(
+    cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminProposalCreateRequest>,
+) -> status::Custom<Value> {
+    admin::endpoints::admin_proposal_create(cfg, quorum, request).await
+}
+
+#[post("/web/admin/proposal/approve", format = "json", data = "<request>")]
+#[instrument(name = "POST /web/admin/proposal/approve", skip_all, ret)]
+pub async fn admin_proposal_approve(
+    cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
+    request: Json<models::JsonAdminProposalApproveRequest>,
+) -> status::Custom<Value> {
+    admin::endpoints::admin_proposal_approve(cfg, quorum, request).await
+}
+
+#[post("/web/admin/proposal/list", format = "json", data = "<auth>")]
+#[instrument(name = "POST /web/admin/proposal/list", skip_all, ret)]
+pub async fn admin_proposal_list(
+    cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
+    auth: Json<models::AdminAuth>,
+) -> status::Custom<Value> {
+    admin::endpoints::admin_proposal_list(cfg, quorum, auth).await
+}
+
+#[post("/web/admin/usage/export", format = "json", data = "<request>")]
+#[instrument(name = "POST /web/admin/usage/export", skip_all)]
+pub async fn admin_usage_export(
+    cfg: &State<ReloadableLitConfig>,
+    rate_limit_db: &State<Arc<RateLimitDB>>,
+    request: Json<models::JsonAdminUsageExportRequest>,
+) -> Result<(ContentType, String), status::Custom<Value>> {
+    admin::endpoints::admin_usage_export(cfg, rate_limit_db, request).await
 }
 
 #[post("/web/admin/get", format = "json", data = "<auth>")]
 #[instrument(name = "POST /web/admin/get", skip_all, ret)]
 pub async fn admin_get(
     cfg: &State<ReloadableLitConfig>,
+    quorum: &State<Arc<AdminQuorum>>,
     auth: Json<models::AdminAuth>,
 ) -> status::Custom<Value> {
-    admin::endpoints::admin_get(cfg, auth).await
+    admin::endpoints::admin_get(cfg, quorum, auth).await
 }
 
 #[get("/web/admin/get_key_backup?<auth>&<node_set_hash>")]
@@ -66,10 +157,11 @@ pub async fn admin_get(
 pub async fn admin_get_key_backup(
     cfg: &State<ReloadableLitConfig>,
     restore_state: &State<Arc<RwLock<RestoreState>>>,
+    quorum: &State<Arc<AdminQuorum>>,
     auth: models::AdminAuth,
     node_set_hash: Option<String>,
 ) -> Result<ChildStream, status::Custom<Value>> {
-    admin::endpoints::admin_get_key_backup(cfg, restore_state, auth, node_set_hash).await
+    admin::endpoints::admin_get_key_backup(cfg, restore_state, quorum, auth, node_set_hash).await
 }
 
 #[post("/web/admin/set_key_backup", format = "binary", data = "<data>")]
@@ -88,9 +180,10 @@ pub async fn admin_set_key_backup(
 pub async fn admin_get_blinders(
     cfg: &State<ReloadableLitConfig>,
     restore_state: &State<Arc<RwLock<RestoreState>>>,
+    quorum: &State<Arc<AdminQuorum>>,
     auth: Json<models::AdminAuth>,
 ) -> status::Custom<Value> {
-    admin::endpoints::admin_get_blinders(cfg, restore_state, auth).await
+    admin::endpoints::admin_get_blinders(cfg, restore_state, quorum, auth).await
 }
 
 #[post("/web/recovery/set_dec_share", format = "json", data = "<request>")]
@@ -127,6 +220,45 @@ pub async fn recovery_delete_dec_key_share(
     recovery::endpoints::recovery_delete_dec_key_share(guard, restore_state, cfg, request).await
 }
 
+#[get("/web/errors")]
+#[instrument(name = "GET /web/errors", skip_all)]
+pub async fn error_catalog() -> status::Custom<Value> {
+    web_client::error_catalog().await
+}
+
+#[get("/web/beacon/latest")]
+#[instrument(name = "GET /web/beacon/latest", skip_all)]
+pub async fn beacon_latest() -> status::Custom<Value> {
+    web_client::beacon(None).await
+}
+
+#[get("/web/beacon/<round>")]
+#[instrument(name = "GET /web/beacon", skip_all)]
+pub async fn beacon_round(round: u64) -> status::Custom<Value> {
+    web_client::beacon(Some(round)).await
+}
+
+#[get("/web/dkg/transcripts/<curve_type>/<pubkey>")]
+#[instrument(name = "GET /web/dkg/transcripts", skip_all)]
+pub async fn dkg_transcripts(
+    session: &State<Arc<TssState>>,
+    curve_type: &str,
+    pubkey: &str,
+) -> status::Custom<Value> {
+    web_client::dkg_transcripts(session, curve_type, pubkey, None).await
+}
+
+#[get("/web/dkg/transcripts/<curve_type>/<pubkey>/<epoch>")]
+#[instrument(name = "GET /web/dkg/transcript", skip_all)]
+pub async fn dkg_transcript(
+    session: &State<Arc<TssState>>,
+    curve_type: &str,
+    pubkey: &str,
+    epoch: u64,
+) -> status::Custom<Value> {
+    web_client::dkg_transcripts(session, curve_type, pubkey, Some(epoch)).await
+}
+
 /*
 curl --header "Content-Type: application/json" \
   --request POST \
@@ -145,6 +277,7 @@ pub async fn handshake(
     version: SdkVersion,
     cfg: &State<ReloadableLitConfig>,
     eth_blockhash_cache: &State<Arc<EthBlockhashCache>>,
+    handshake_keys: &State<Arc<HandshakeKeys>>,
 ) -> status::Custom<Value> {
     web_client::handshake(
         guard,
@@ -155,10 +288,27 @@ pub async fn handshake(
         version,
         cfg,
         eth_blockhash_cache,
+        handshake_keys,
     )
     .await
 }
 
+#[post(
+    "/web/encryption/timelock/sign",
+    format = "json",
+    data = "<timelock_sign_request>"
+)]
+#[instrument(name = "POST /web/encryption/timelock/sign", skip_all, ret)]
+pub(crate) async fn timelock_sign(
+    guard: ConcurrencyGuard<'_>,
+    session: &State<Arc<TssState>>,
+    remote_addr: SocketAddr,
+    timelock_sign_request: Json<models::TimeLockSignRequest>,
+    tracing: Tracing,
+) -> status::Custom<Value> {
+    web_client::timelock_sign(session, remote_addr, timelock_sign_request, tracing).await
+}
+
 #[allow(clippy::too_many_arguments)]
 #[post(
     "/web/encryption/sign",
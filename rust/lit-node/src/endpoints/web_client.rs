@@ -1,5 +1,5 @@
 #[allow(unused_imports)]
-use ethers::types::Bytes;
+use ethers::types::{Address, Bytes};
 use ipfs_hasher::IpfsHasher;
 use lit_api_core::context::Tracer;
 use lit_api_core::context::{with_context, SdkVersion, Tracing, TracingRequired};
@@ -25,14 +25,14 @@ use url::Url;
 use crate::auth::auth_material::{siwe_hash_to_bls_session_hash, AuthSigItem};
 use crate::auth::lit_resource::LitResource;
 use crate::auth::resources::{AccessControlConditionResource, LitResourceAbility};
-use crate::config::LitNodeConfig;
+use crate::config::{LitNodeConfig, CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES_DEFAULT};
 use crate::constants::CHAIN_ETHEREUM;
 use crate::error::{
     connect_err_code, conversion_err, memory_limit_err_code, timeout_err_code, unexpected_err,
     unexpected_err_code, validation_err_code, EC,
 };
 use crate::error::{parser_err, parser_err_code};
-use crate::functions::action_client;
+use crate::functions::{action_client, storage};
 use crate::models::auth::SessionKeySignedMessage;
 use crate::models::auth::{LIT_RESOURCE_KEY_RAC, LIT_RESOURCE_PREFIX_RAC};
 use crate::models::{
@@ -46,13 +46,20 @@ use crate::pkp::auth::{
     serialize_auth_context_for_checking_against_contract_data, AuthMethodScope,
 };
 use crate::rate_limiting::models::UserContext;
+use crate::rate_limiting::usage::UsageRecorder;
 use crate::rate_limiting::{check_rate_limit, models::RateLimitDB};
 use crate::siwe_db::utils::make_timestamp_siwe_compatible;
+use crate::tss::blsful::beacon;
+use crate::tss::blsful::timelock::{self, TimeLock};
 use crate::tss::common::curve_type::CurveType;
+use crate::tss::common::storage::{read_transcript_from_disk, transcript_epochs_on_disk};
 use crate::tss::common::tss_state::TssState;
+use crate::tss::common::verification_key::verification_key_shares;
+use crate::tss::dkg::transcript::DkgTranscript;
 use crate::utils::attestation::create_attestation;
 use crate::utils::encoding;
 use crate::utils::rocket::guards::{ClientContext, RequestHeaders};
+use crate::utils::rocket::sealed_response::HandshakeKeys;
 use crate::utils::web::get_auth_context_from_session_sigs;
 use crate::utils::web::EndpointVersion;
 use crate::utils::web::{
@@ -196,12 +203,16 @@ pub(crate) async fn signing_access_control_condition(
                 None => "Rate limit exceeded.  Try again later.".to_string(),
             };
             warn!("{}", msg);
-            return status::Custom(
+            return validation_err_code(msg.clone(), EC::NodeRateLimitExceeded, None).handle_with_legacy_code(
                 Status::PaymentRequired,
-                json!({"message": msg, "errorCode": "rate_limit_exceeded"}),
+                "rate_limit_exceeded",
+                &msg,
             );
         }
 
+        let mut usage = UsageRecorder::new(&cfg, rate_limit_db.usage.clone(), "/web/signing/access_control_condition", tracing.clone().correlation_id().to_string(), request_start);
+        usage.rate_limit(user_address, &rate_limit_check_return);
+
         let before = std::time::Instant::now();
         // Check whether user satisfies access control conditions
         let check_result = check_multiple_access_control_conditions(
@@ -317,6 +328,8 @@ pub(crate) async fn signing_access_control_condition(
             }
         };
         timing.insert("sign JWT".to_string(), before.elapsed());
+        usage.signatures(1);
+        usage.finish().await;
 
         timing.insert("total".to_string(), request_start.elapsed());
 
@@ -458,12 +471,16 @@ pub(crate) async fn encryption_sign(
                 None => "Rate limit exceeded.  Try again later.".to_string(),
             };
             warn!("{}", msg);
-            return status::Custom(
+            return validation_err_code(msg.clone(), EC::NodeRateLimitExceeded, None).handle_with_legacy_code(
                 Status::PaymentRequired,
-                json!({"message": msg, "errorCode": "rate_limit_exceeded"}),
+                "rate_limit_exceeded",
+                &msg,
             );
         }
 
+        let mut usage = UsageRecorder::new(&cfg, rate_limit_db.usage.clone(), "/web/encryption/sign", tracing.clone().correlation_id().to_string(), request_start);
+        usage.rate_limit(user_address, &rate_limit_check_return);
+
         let before = std::time::Instant::now();
         // Check whether user satisfies access control conditions
         let check_result = check_multiple_access_control_conditions(
@@ -521,6 +538,8 @@ pub(crate) async fn encryption_sign(
             }
         };
         timing.insert("sign identity parameter".to_string(), before.elapsed());
+        usage.signatures(1);
+        usage.finish().await;
         timing.insert("total".to_string(), request_start.elapsed());
 
         debug!("POST /web/encryption/sign timing: {:?}", timing);
@@ -536,6 +555,140 @@ pub(crate) async fn encryption_sign(
     }).await
 }
 
+/// Release the signature share of a time lock once its time has passed.  There
+/// is no auth sig or access control check, see `tss::blsful::timelock`, so
+/// requests are rate limited by client address instead.
+#[instrument(name = "POST /web/encryption/timelock/sign", skip_all, ret)]
+pub(crate) async fn timelock_sign(
+    session: &Arc<TssState>,
+    remote_addr: SocketAddr,
+    timelock_sign_request: Json<models::TimeLockSignRequest>,
+    tracing: Tracing,
+) -> status::Custom<Value> {
+    with_context(tracing, async move {
+        debug!("timelock_sign, request: {:?}", timelock_sign_request);
+
+        if let Err(e) = timelock::check_sign_rate_limit(remote_addr.ip()).await {
+            return e.handle();
+        }
+
+        let time_lock = match TimeLock::from_request(
+            timelock_sign_request.round,
+            timelock_sign_request.timestamp,
+        ) {
+            Ok(time_lock) => time_lock,
+            Err(e) => {
+                return e.handle();
+            }
+        };
+        if let Err(e) = time_lock.check_released() {
+            return e.handle();
+        }
+
+        let cipher_state = match session.get_cipher_state(CurveType::BLS) {
+            Ok(cipher_state) => cipher_state,
+            Err(e) => {
+                return e.handle();
+            }
+        };
+        let epoch = match timelock_sign_request.epoch {
+            0 => None,
+            _ => Some(timelock_sign_request.epoch),
+        };
+        let (signature_share, share_index) =
+            match cipher_state.sign(time_lock.identity(), epoch).await {
+                Ok(signature_share) => signature_share,
+                Err(e) => {
+                    return e.handle();
+                }
+            };
+
+        status::Custom(
+            Status::Ok,
+            json!(models::EncryptionSignResponse {
+                result: "success".to_string(),
+                signature_share,
+                share_index,
+            }),
+        )
+    })
+    .await
+}
+
+/// The published catalog of error codes (see `error::ErrorCatalog`).
+pub async fn error_catalog() -> status::Custom<Value> {
+    status::Custom(Status::Ok, json!(error::error_catalog()))
+}
+
+/// A round of the randomness beacon (see `tss::blsful::beacon`), or the latest one.
+pub async fn beacon(round: Option<u64>) -> status::Custom<Value> {
+    match beacon::cached_round(round) {
+        Ok(beacon_round) => status::Custom(Status::Ok, json!(beacon_round)),
+        Err(e) => e.handle(),
+    }
+}
+
+#[instrument(name = "GET /web/dkg/transcripts", skip_all)]
+pub async fn dkg_transcripts(
+    session: &State<Arc<TssState>>,
+    curve_type: &str,
+    pubkey: &str,
+    epoch: Option<u64>,
+) -> status::Custom<Value> {
+    let curve_type = match CurveType::from_str(curve_type) {
+        Ok(curve_type) => curve_type,
+        Err(e) => {
+            return validation_err_code(e, EC::NodeInvalidCurveType, None).handle();
+        }
+    };
+    let pubkey = pubkey.trim_start_matches("0x");
+    // The key names the files read, so only hex is accepted
+    if pubkey.is_empty() || !pubkey.chars().all(|c| c.is_ascii_hexdigit()) {
+        return validation_err_code(
+            format!("Invalid public key: {}", pubkey),
+            EC::NodeBadInput,
+            None,
+        )
+        .handle();
+    }
+    let staker_address = &session.peer_state.hex_staker_address();
+
+    let epochs = match epoch {
+        Some(epoch) => vec![epoch],
+        None => match transcript_epochs_on_disk(pubkey, curve_type, staker_address).await {
+            Ok(epochs) => epochs,
+            Err(e) => return e.handle(),
+        },
+    };
+
+    let mut transcripts = Vec::with_capacity(epochs.len());
+    for epoch in epochs {
+        match read_transcript_from_disk::<DkgTranscript>(pubkey, epoch, curve_type, staker_address)
+            .await
+        {
+            Ok(transcript) => transcripts.push(transcript),
+            Err(e) => {
+                return validation_err_code(
+                    e,
+                    EC::NodeDKGTranscriptNotFound,
+                    Some(format!("No DKG transcript for {} epoch {}", pubkey, epoch)),
+                )
+                .handle();
+            }
+        }
+    }
+    if transcripts.is_empty() {
+        return validation_err_code(
+            format!("No DKG transcripts for {}", pubkey),
+            EC::NodeDKGTranscriptNotFound,
+            None,
+        )
+        .handle();
+    }
+
+    status::Custom(Status::Ok, json!(transcripts))
+}
+
 /*
 curl --header "Content-Type: application/json" \
   --request POST \
@@ -554,12 +707,17 @@ pub async fn handshake(
     version: SdkVersion,
     cfg: &State<ReloadableLitConfig>,
     eth_blockhash_cache: &State<Arc<EthBlockhashCache>>,
+    handshake_keys: &State<Arc<HandshakeKeys>>,
 ) -> status::Custom<Value> {
     let request_start = std::time::Instant::now();
     let mut timing: BTreeMap<String, Duration> = BTreeMap::new();
 
     let cfg = cfg.load_full();
 
+    let sealing_key_id = handshake_keys
+        .register(&json_handshake_request.client_public_key)
+        .await;
+
     let before = std::time::Instant::now();
     let ecdsa_root_keys = match session.get_signing_state(CurveType::K256) {
         Ok(signing_state) => signing_state.root_keys().await,
@@ -580,6 +738,11 @@ pub async fn handshake(
     };
     timing.insert("get bls root keys".to_string(), before.elapsed());
 
+    let before = std::time::Instant::now();
+    let mut key_shares = verification_key_shares(session, CurveType::BLS, &bls_root_keys).await;
+    key_shares.extend(verification_key_shares(session, CurveType::K256, &ecdsa_root_keys).await);
+    timing.insert("get verification key shares".to_string(), before.elapsed());
+
     // FIXME: remove this "unwrap_or" once we've shipped sending the challenge in the SDK
     let challenge = json_handshake_request
         .challenge
@@ -636,6 +799,8 @@ pub async fn handshake(
                 hd_root_pubkeys: ecdsa_root_keys,
                 attestation,
                 latest_blockhash,
+                verification_key_shares: key_shares,
+                sealing_key_id,
             }),
         );
     }
@@ -651,6 +816,8 @@ pub async fn handshake(
             hd_root_pubkeys: ecdsa_root_keys,
             attestation,
             latest_blockhash,
+            verification_key_shares: key_shares,
+            sealing_key_id,
         }),
     )
 }
@@ -766,12 +933,16 @@ pub(crate) async fn execute_function(
                 None => "Rate limit exceeded.  Try again later.".to_string(),
             };
             warn!("{}", msg);
-            return status::Custom(
+            return validation_err_code(msg.clone(), EC::NodeRateLimitExceeded, None).handle_with_legacy_code(
                 Status::PaymentRequired,
-                json!({"message": msg, "errorCode": "rate_limit_exceeded"}),
+                "rate_limit_exceeded",
+                &msg,
             );
         }
 
+        let mut usage = UsageRecorder::new(&cfg, rate_limit_db.usage.clone(), "/web/execute", tracing.clone().correlation_id().to_string(), request_start);
+        usage.rate_limit(user_address, &rate_limit_check_return);
+
         let before = std::time::Instant::now();
         // determine if the user passed code or an ipfs hash
         let derived_ipfs_id;
@@ -816,6 +987,7 @@ pub(crate) async fn execute_function(
         timing.insert("derived IPFS CID".to_string(), before.elapsed());
 
         debug!("derived_ipfs_id: {}", derived_ipfs_id);
+        usage.action_ipfs_id(Some(derived_ipfs_id.clone()));
 
         let before = std::time::Instant::now();
         // check if the IPFS id is in the allowlist
@@ -843,10 +1015,9 @@ pub(crate) async fn execute_function(
         let auth_sig = match &json_execution_request.auth_sig {
             AuthSigItem::Single(single_auth_sig) => single_auth_sig,
             AuthSigItem::Multiple(_) => {
-                return status::Custom(
-                    Status::BadRequest,
-                    json!({"message": "Multiple auth sigs not supported by Lit Actions", "errorCode": "unsupported_auth_sig"}),
-                );
+                let msg = "Multiple auth sigs not supported by Lit Actions";
+                return validation_err_code(msg, EC::NodeAuthSigNotSupported, None)
+                    .handle_with_legacy_code(Status::BadRequest, "unsupported_auth_sig", msg);
             }
         };
 
@@ -883,10 +1054,10 @@ pub(crate) async fn execute_function(
                     Ok(session_key_signed_message) => session_key_signed_message,
                     Err(e) => {
                         error!("Error parsing session sig in execute_js");
-                        return status::Custom(
-                            Status::BadRequest,
-                            json!({"message": "Either you've have passed an AuthSig or the sessionSig is incorrectly formatted", "errorCode": "unsupported_auth_sig"}),
-                        );
+                        let msg = "Either you've have passed an AuthSig or the sessionSig is incorrectly formatted";
+                        return parser_err_code(e, EC::NodeAuthSigSignedMessageConversionError, Some(msg.into()))
+                            .add_msg_to_details()
+                            .handle_with_legacy_code(Status::BadRequest, "unsupported_auth_sig", msg);
                     }
                 };
 
@@ -931,6 +1102,11 @@ pub(crate) async fn execute_function(
         timing.insert("auth context".to_string(), before.elapsed());
         trace!("Got auth context");
 
+        let storage_quota_bytes = storage::quota_bytes(
+            cfg.actions_storage_quota_bytes().unwrap_or(CFG_KEY_ACTIONS_STORAGE_QUOTA_BYTES_DEFAULT) as u64,
+            rate_limit_check_return.charged_nft.as_ref(),
+        );
+
         let deno_execution_env = models::DenoExecutionEnv {
             tss_state: Some(tss_state.as_ref().clone()),
             auth_context,
@@ -963,6 +1139,7 @@ pub(crate) async fn execute_function(
             .http_headers(http_headers)
             .epoch(epoch)
             .endpoint_version(endpoint_version)
+            .storage_quota_bytes(storage_quota_bytes)
             .build()
             .map_err(|e| unexpected_err_code(e, EC::NodeJsExecutionError, Some("Error building action client".into())))
         {
@@ -972,7 +1149,8 @@ pub(crate) async fn execute_function(
         let execution_result = client.execute_js(action_client::ExecutionOptions {
             code: code_to_run,
             globals: json_execution_request.js_params.clone(),
-            action_ipfs_id: Some(derived_ipfs_id),
+            action_ipfs_id: Some(derived_ipfs_id.clone()),
+            typescript: json_execution_request.typescript,
         }).await;
         timing.insert("js execution".to_string(), before.elapsed());
 
@@ -993,10 +1171,18 @@ pub(crate) async fn execute_function(
                     },
                     _ => {}
                 }
+                if let Some(violation) = client.egress_violation().filter(|v| err.to_string().contains(&v.message)) {
+                    return validation_err_code(violation.clone(), EC::NodeActionEgressPolicyViolation, serde_json::to_string(violation).ok())
+                        .add_source_to_details()
+                        .handle_with_logs(logs);
+                }
                 if let Some(source_err) = err.source() {
-                    return status::Custom(
-                        Status::BadRequest, json!({"success": false, "error": source_err.to_string(), "logs": logs}),
-                    );
+                    let mut res = validation_err_code(source_err.to_string(), EC::NodeLitActionError, None)
+                        .handle_with_logs(logs);
+                    // Clients still read these from the response that predates the error catalog
+                    res.1["success"] = false.into();
+                    res.1["error"] = source_err.to_string().into();
+                    return res;
                 }
                 return unexpected_err_code(err, EC::NodeJsExecutionError, Some("Error executing JS".into())).handle_with_logs(logs);
             }
@@ -1004,6 +1190,10 @@ pub(crate) async fn execute_function(
 
         trace!("js execution task completed");
 
+        usage.signatures(execution_state.signed_data.len() as u32);
+        usage.bytes_decrypted(execution_state.decrypted_bytes);
+        usage.finish().await;
+
         timing.insert("total".to_string(), request_start.elapsed());
         debug!("POST /web/execute timing: {:?}", timing);
 
@@ -1039,6 +1229,7 @@ pub(crate) async fn sign_session_key(
 
     with_context(tracing.clone(), async move {
         let cfg = cfg.load_full();
+        let mut usage = UsageRecorder::new(&cfg, rate_limit_db.usage.clone(), "/web/sign_session_key", tracing.clone().correlation_id().to_string(), request_start);
 
         debug!(
             "sign_session_key, request: {}",
@@ -1233,6 +1424,7 @@ pub(crate) async fn sign_session_key(
                     .handle();
             }
         };
+        usage.user_address(Address::from(pkp_eth_address));
 
         let before = std::time::Instant::now();
         // Validate the SIWE message.
@@ -1358,6 +1550,7 @@ pub(crate) async fn sign_session_key(
                     code: code_to_run,
                     globals: json_sign_session_key_request.js_params.clone(),
                     action_ipfs_id: derived_ipfs_id.clone(),
+                    ..Default::default()
                 }).await;
                 let execution_state = match execution_result {
                     Ok(state) => state,
@@ -1379,10 +1572,26 @@ pub(crate) async fn sign_session_key(
                             _ => {}
                         }
 
+                        if let Some(violation) = client
+                            .egress_violation()
+                            .filter(|v| err.to_string().contains(&v.message))
+                        {
+                            return validation_err_code(
+                                violation.clone(),
+                                EC::NodeActionEgressPolicyViolation,
+                                serde_json::to_string(violation).ok(),
+                            )
+                            .add_source_to_details()
+                            .handle_with_logs(logs);
+                        }
+
                         if let Some(source_err) = err.source() {
-                            return status::Custom(
-                                Status::BadRequest, json!({"success": false, "error": source_err.to_string(), "logs": logs}),
-                            );
+                            let mut res = validation_err_code(source_err.to_string(), EC::NodeLitActionError, None)
+                                .handle_with_logs(logs);
+                            // Clients still read these from the response that predates the error catalog
+                            res.1["success"] = false.into();
+                            res.1["error"] = source_err.to_string().into();
+                            return res;
                         }
 
                         return unexpected_err_code(
@@ -1464,6 +1673,8 @@ pub(crate) async fn sign_session_key(
 
         let hex_pubkey = encoding::bytes_to_hex(&pkp_public_key);
 
+        usage.action_ipfs_id(derived_ipfs_id.clone());
+
         let siwe_to_sign;
         let to_sign;
         let before = std::time::Instant::now();
@@ -1546,6 +1757,9 @@ pub(crate) async fn sign_session_key(
 
                 debug!("POST /web/sign_session_key timing: {:?}", timing);
 
+                usage.signatures(1);
+                usage.finish().await;
+
                 status::Custom(
                     Status::Ok,
                     json!(JsonSignSessionKeyResponse {
@@ -1752,6 +1966,9 @@ pub(crate) async fn sign_session_key(
                 timing.insert("total".to_string(), request_start.elapsed());
                 debug!("POST /web/sign_session_key timing: {:?}", timing);
 
+                usage.signatures(1);
+                usage.finish().await;
+
                 // return the signature share
                 status::Custom(
                     Status::Ok,
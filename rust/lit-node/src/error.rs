@@ -1,11 +1,48 @@
 use derive_more::Display;
+use lazy_static::lazy_static;
+use lit_api_core::error::api_error_catalog;
 use lit_core::error::*;
 pub use lit_core::error::{Error, Result, Unexpected};
 use lit_core::generate_pkg_constructors;
 use lit_core_derive::{Description, ErrorCode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::fmt::Debug;
 pub const PKG_NAME: &str = "lit_node";
 
+/// Version of the published error catalog. Bump when a code is removed or its meaning,
+/// status or retryability changes (adding codes only changes the hash).
+pub const ERROR_CATALOG_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref ERROR_CATALOG: ErrorCatalog = {
+        let mut errors = EC::catalog();
+        errors.extend(api_error_catalog());
+
+        let hash = Sha256::digest(serde_json::to_vec(&errors).unwrap_or_default());
+
+        ErrorCatalog {
+            version: ERROR_CATALOG_VERSION,
+            hash: hex::encode(hash),
+            errors,
+        }
+    };
+}
+
+/// Every error code a node may return (see `GET /web/errors`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCatalog {
+    pub version: u32,
+    /// Hash of `errors`, so clients can cheaply detect catalog changes.
+    pub hash: String,
+    pub errors: Vec<CodeCatalogEntry>,
+}
+
+pub fn error_catalog() -> &'static ErrorCatalog {
+    &ERROR_CATALOG
+}
+
 // constructors
 
 #[derive(Debug, Display, Description, ErrorCode)]
@@ -15,7 +52,7 @@ pub(crate) enum EC {
     #[code(kind = Unexpected, http_status = 500)]
     NodeSystemFault,
     /// Lit nodes failed to check the condition possibly due to RPC servers being down or because the condition is making an incorrect smart contract call that reverts
-    #[code(kind = Validation, http_status = 502)]
+    #[code(kind = Validation, http_status = 502, retryable = true)]
     NodeAccessControlConditionsCheckFailed,
     /// The access control condition check returned that you are not permitted to access this content.  Are you sure you meet the conditions?  Check the auth_sig and the other conditions
     #[code(kind = Validation, http_status = 401)]
@@ -93,7 +130,8 @@ pub(crate) enum EC {
     #[code(kind = Validation, http_status = 400)]
     NodeAuthSigSignatureConversionError,
 
-    #[code(kind = Validation, http_status = 500)]
+    /// Key generation has not completed on this node
+    #[code(kind = Validation, http_status = 500, retryable = true)]
     NodeNoKeyGenError,
 
     /// auth_sig address couldn't be converted to ed25519_dalek PublicKey
@@ -112,7 +150,7 @@ pub(crate) enum EC {
     #[code(kind = Validation, http_status = 400)]
     NodeInvalidBooleanConditionType,
     /// Lit node client isn't connected to the Lit network
-    #[code(kind = Config, http_status = 400)]
+    #[code(kind = Config, http_status = 400, retryable = true)]
     NodeLitNodeClientNotReady,
     /// Must pass either access_control_conditions or evm_contract_conditions or sol_rpc_conditions
     #[code(kind = Validation, http_status = 400)]
@@ -124,7 +162,7 @@ pub(crate) enum EC {
     #[code(kind = Validation, http_status = 404)]
     NodeResourceIdNotFound,
     /// Lit nodes failed to complete the RPC call possibly due to RPC servers being down or because the RPC call is making an incorrect smart contract call that reverts
-    #[code(kind = Validation, http_status = 502)]
+    #[code(kind = Validation, http_status = 502, retryable = true)]
     NodeRpcError,
     /// Error updating the condition possibly because the condition is permanent or the user isn't the creator of the condition
     #[code(kind = Unexpected, http_status = 403)]
@@ -262,16 +300,16 @@ pub(crate) enum EC {
     #[code(kind = Validation, http_status = 502)]
     NodeInvalidBlockhash,
     /// The node has no Eeid
-    #[code(kind = Unexpected, http_status = 502)]
+    #[code(kind = Unexpected, http_status = 502, retryable = true)]
     NodeBlsNoEeidError,
     /// Eeid and Dkg round message has mismatched epoch
-    #[code(kind = Unexpected, http_status = 502)]
+    #[code(kind = Unexpected, http_status = 502, retryable = true)]
     NodeBlsWrongEpochError,
     /// Timeout waiting for DKG messages from other nodes
-    #[code(kind = Unexpected, http_status = 500)]
+    #[code(kind = Unexpected, http_status = 500, retryable = true)]
     NodeDkgRoundTimeoutError,
     /// Participant not Initialized yet
-    #[code(kind = Unexpected, http_status = 502)]
+    #[code(kind = Unexpected, http_status = 502, retryable = true)]
     NodeBlsParticipantUninitialized,
     /// Invalid smart contract function parameters
     #[code(kind = Parser, http_status = 400)]
@@ -286,7 +324,7 @@ pub(crate) enum EC {
     #[code(kind = Blockchain, http_status = 422)]
     NodeBadInput,
     /// The peer cannot be found.
-    #[code(kind = Unexpected, http_status = 500)]
+    #[code(kind = Unexpected, http_status = 500, retryable = true)]
     NodePeerNotFound,
     /// The node could not perform an encryption / decryption operation.
     #[code(kind = Unexpected, http_status = 500)]
@@ -300,61 +338,120 @@ pub(crate) enum EC {
     /// Could not find PKP token ID.
     #[code(kind = Validation, http_status = 400)]
     NodePKPTokenIdNotFound,
-    // Invalid challenge is used for WebAuthn.
+    /// Invalid challenge is used for WebAuthn.
     #[code(kind = Validation, http_status = 400)]
     NodeInvalidWebAuthnChallenge,
 
-    // Requested a key type that is not supported by the node.
+    /// Requested a key type that is not supported by the node.
     #[code(kind = Validation, http_status = 401)]
     NodePKPKeyTypeRequestNotSupported,
-    // Failed to perform the conversion from Lit Config to Contract Resolver
+    /// Failed to perform the conversion from Lit Config to Contract Resolver
     #[code(kind = Unexpected, http_status = 500)]
     NodeContractResolverConversionFailed,
-    // Failed to complete DKG round
-    #[code(kind = Unexpected, http_status = 500)]
+    /// Failed to complete DKG round
+    #[code(kind = Unexpected, http_status = 500, retryable = true)]
     NodeDkgRoundFailed,
-    // Failed to complete DKG round
+    /// Failed to complete DKG round
     #[code(kind = Unexpected, http_status = 500)]
     NodeDKGInvalidValue,
-    // Lit Actions feature flag is not enabled
+    /// Lit Actions feature flag is not enabled
     #[code(kind = Unexpected, http_status = 400)]
     NodeLitActionsNotEnabled,
-    // Lit Actions returned false for sessionSig signing authentication
+    /// Lit Actions returned false for sessionSig signing authentication
     #[code(kind = Validation, http_status = 401)]
     NodeLitActionsSessionSigAuthenticationFailed,
-    // The PKP permissions check failed
+    /// The PKP permissions check failed
     #[code(kind = Validation, http_status = 401)]
     NodePKPNotAuthorized,
-    // The PKP isn't the signer for the SIWE message
+    /// The PKP isn't the signer for the SIWE message
     #[code(kind = Validation, http_status = 401)]
     NodeInvalidPKPAddress,
-    // The user sent an unsupported curve type when requesting to sign something
+    /// The user sent an unsupported curve type when requesting to sign something
     #[code(kind = Validation, http_status = 400)]
     NodeInvalidCurveType,
-    // The user sent an auth_sig for signing a session_key
+    /// The user sent an auth_sig for signing a session_key
     #[code(kind = Validation, http_status = 400)]
     NodeInvalidAuthSigForSessionKey,
-    // The user sent a auth_methods for endpoint
+    /// The user sent a auth_methods for endpoint
     #[code(kind = Validation, http_status = 400)]
     NodeCannotProvideAuthMethodForEndpoint,
-    // The network root BLS key was not found
-    #[code(kind = Unexpected, http_status = 500)]
+    /// The network root BLS key was not found
+    #[code(kind = Unexpected, http_status = 500, retryable = true)]
     NodeBLSRootKeyNotFound,
-    // Concurrency limit reached
-    #[code(kind = Unexpected, http_status = 429)]
+    /// Concurrency limit reached
+    #[code(kind = Unexpected, http_status = 429, retryable = true)]
     NodeConcurrencyOverload,
-    // Invalid Signature Requested
+    /// Invalid Signature Requested
     #[code(kind = Validation, http_status = 401)]
     NodeSignatureNotSupported,
-    // Invalid Signature Requested
+    /// Invalid Signature Requested
     #[code(kind = Validation, http_status = 400)]
     NodeCannotProvideAuthSigForEndpoint,
-    // Can't define AuthContext resources in user capability
+    /// Can't define AuthContext resources in user capability
     #[code(kind = Validation, http_status = 401)]
     NodeInvalidAuthContextResource,
-    // Siwe message doesn't contain expiration time
+    /// Siwe message doesn't contain expiration time
     #[code(kind = Validation, http_status = 401)]
     NodeUndefinedSiweExpiration,
+    /// Rate limit exceeded. Purchase or use a capacity credit (rate limit NFT), or try again later
+    #[code(kind = Validation, http_status = 402)]
+    NodeRateLimitExceeded,
+    /// The Lit Action threw an error
+    #[code(kind = Validation, http_status = 400)]
+    NodeLitActionError,
+    /// An EIP-6492 (counterfactual smart wallet) signature was presented but no universal validator is configured
+    #[code(kind = Config, http_status = 501)]
+    NodeEIP6492ValidatorNotConfigured,
+    /// Bitcoin indexer response not in JSON format
+    #[code(kind = Serializer, http_status = 502)]
+    NodeBitcoinJSONError,
+    /// Bitcoin invalid condition
+    #[code(kind = Validation, http_status = 400)]
+    NodeBitcoinInvalidCondition,
+    /// A fetch by the Lit Action was blocked or aborted by the node's egress policy
+    #[code(kind = Validation, http_status = 403)]
+    NodeActionEgressPolicyViolation,
+    /// The requested randomness beacon round hasn't been produced, e.g. because it is in the future
+    #[code(kind = Validation, http_status = 404)]
+    NodeBeaconRoundUnavailable,
+    /// Time-locked data was requested before its time or beacon round
+    #[code(kind = Validation, http_status = 403)]
+    NodeTimeLockNotReleased,
+    /// No DKG transcript is stored for the key and epoch
+    #[code(kind = Validation, http_status = 404)]
+    NodeDKGTranscriptNotFound,
+    /// A DKG transcript doesn't check out against its commitments
+    #[code(kind = Validation, http_status = 422)]
+    NodeDKGTranscriptInvalid,
 }
 
 generate_pkg_constructors!(PKG_NAME, pub(crate), EC);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{error_catalog, EC};
+    use lit_core::error::Code;
+
+    #[test]
+    fn error_catalog_test() {
+        let catalog = error_catalog();
+
+        let codes: HashSet<&str> = catalog.errors.iter().map(|e| e.code.as_str()).collect();
+        assert_eq!(codes.len(), catalog.errors.len());
+        assert!(codes.contains("NodeBadInput"));
+        assert!(codes.contains("CoreApiRouteNotFound"));
+
+        let overload = catalog
+            .errors
+            .iter()
+            .find(|e| e.code == "NodeConcurrencyOverload")
+            .unwrap();
+        assert!(overload.retryable);
+        assert_eq!(overload.http_status, 429);
+
+        assert!(!EC::NodeInvalidAuthSig.retryable());
+        assert!(EC::NodeJsConnectionError.retryable());
+    }
+}
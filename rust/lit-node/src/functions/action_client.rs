@@ -4,7 +4,7 @@
 //! which are shared with lit_actions, enabling a secure execution environment.
 
 use std::borrow::BorrowMut;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error as _;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -21,10 +21,12 @@ use lit_actions_grpc::tonic::{
     metadata::MetadataMap, transport::Error as TransportError, Code, Extensions, Request, Status,
 };
 use lit_actions_grpc::{proto::*, unix};
+use lit_blockchain::config::LitBlockchainConfig as _;
 use lit_blockchain::resolver::rpc::{RpcHealthcheckPoller, ENDPOINT_MANAGER};
 use lit_core::config::LitConfig;
 use lit_core::error::Unexpected;
 use lit_core::utils::binary::bytes_to_hex;
+use lit_core::utils::ipfs::verify_ipfs_cid;
 use moka::future::Cache;
 use tracing::{debug, instrument};
 
@@ -34,12 +36,15 @@ use crate::auth::lit_resource::LitResource;
 use crate::auth::resources::AccessControlConditionResource;
 use crate::config::LitNodeConfig as _;
 use crate::error::{connect_err, conversion_err, memory_limit_err, timeout_err, unexpected_err};
+use crate::functions::egress::{EgressPolicy, EgressViolation, EgressViolationKind};
+use crate::functions::storage::{self, ActionStorage, StorageKeys, StorageRecord};
 use crate::models::{self, RequestConditions, UnifiedConditionCheckResult};
 use crate::p2p_comms::CommsManager;
 use crate::peers::peer_state::models::SimplePeerExt;
 use crate::pkp;
 use crate::tasks::beaver_manager::listener::leader_addr;
 use crate::tasks::beaver_manager::models::generate_hash;
+use crate::tss::blsful::beacon;
 use crate::tss::dkg::curves::common::CurveType;
 use crate::utils::encoding::{self, BeBytes, BeHex, CompressedPointHex, UncompressedPointHex};
 use crate::utils::web::{get_bls_root_pubkey, hash_access_control_conditions, EndpointVersion};
@@ -56,6 +61,7 @@ const DEFAULT_MAX_SIGN_COUNT: u32 = 10; // 10 signature requests per action exec
 const DEFAULT_MAX_BROADCAST_AND_COLLECT_COUNT: u32 = 30;
 const DEFAULT_MAX_CALL_DEPTH: u32 = 5;
 const DEFAULT_CLIENT_TIMEOUT_MS_BUFFER: u64 = 5000;
+const DEFAULT_STORAGE_QUOTA_BYTES: u64 = 64 * 1024; // 64KB
 
 #[derive(Debug, Default, Builder)]
 pub struct Client {
@@ -94,6 +100,14 @@ pub struct Client {
     max_broadcast_and_collect_count: u32,
     #[builder(default = "DEFAULT_MAX_CALL_DEPTH")]
     max_call_depth: u32,
+    #[builder(default = "DEFAULT_STORAGE_QUOTA_BYTES")]
+    storage_quota_bytes: u64,
+    #[builder(default, setter(into, strip_option))]
+    egress_policy: Option<EgressPolicy>,
+
+    // Storage (derived on first use)
+    #[builder(setter(skip))]
+    storage_keys: Option<StorageKeys>,
 
     // State
     #[builder(setter(skip))]
@@ -111,6 +125,18 @@ pub struct ExecutionState {
     pub claim_data: HashMap<String, models::JsonPKPClaimKeyResponse>,
     pub contract_call_count: u32,
     pub broadcast_and_collect_count: u32,
+    pub decrypted_bytes: u64,
+    pub send_transaction_count: u32,
+    pub storage_op_count: u32,
+    pub storage_namespaces: HashSet<String>,
+    pub fetched_bytes: u64,
+    pub egress_violation: Option<EgressViolation>,
+}
+
+struct CombinedEcdsaSignature {
+    r: k256::AffinePoint,
+    s: k256::Scalar,
+    recid: u8,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -118,6 +144,8 @@ pub struct ExecutionOptions {
     pub code: Arc<String>,
     pub globals: Option<serde_json::Value>,
     pub action_ipfs_id: Option<String>,
+    /// Whether the code is TypeScript; detected by lit_actions if unset.
+    pub typescript: Option<bool>,
 }
 
 impl From<&str> for ExecutionOptions {
@@ -166,6 +194,23 @@ impl Client {
         &self.state.logs
     }
 
+    /// The last fetch blocked or aborted by the egress policy, if any.
+    pub fn egress_violation(&self) -> Option<&EgressViolation> {
+        self.state.egress_violation.as_ref()
+    }
+
+    fn egress_policy(&self) -> Result<EgressPolicy> {
+        match &self.egress_policy {
+            Some(policy) => Ok(policy.clone()),
+            None => EgressPolicy::from_config(self.lit_config()),
+        }
+    }
+
+    fn record_egress_violation(&mut self, violation: EgressViolation) -> anyhow::Error {
+        self.state.egress_violation = Some(violation.clone());
+        violation.into()
+    }
+
     fn tss_state_and_txn_prefix(
         &self,
     ) -> Result<(crate::tss::common::tss_state::TssState, String)> {
@@ -210,23 +255,29 @@ impl Client {
     ) -> Result<ExecutionState, crate::error::Error> {
         self.reset_state();
         let opts = opts.into();
-        Box::pin(self.execute_js_inner(opts.code, opts.globals, opts.action_ipfs_id, 0))
-            .await
-            .map_err(|e| {
-                if let Some(status) = e.downcast_ref::<Status>() {
-                    match status.code() {
-                        Code::DeadlineExceeded => timeout_err(status.message(), None),
-                        Code::ResourceExhausted => memory_limit_err(status.message(), None),
-                        _ => unexpected_err(e, None),
-                    }
-                } else if let Some(te) = e.downcast_ref::<TransportError>() {
-                    connect_err(te.source().unwrap_or(te).to_string(), None)
-                } else if let Some(se) = e.downcast_ref::<flume::SendError<ExecuteJsRequest>>() {
-                    connect_err(se.source().unwrap_or(se).to_string(), None)
-                } else {
-                    unexpected_err(e, None)
+        Box::pin(self.execute_js_inner(
+            opts.code,
+            opts.globals,
+            opts.action_ipfs_id,
+            opts.typescript,
+            0,
+        ))
+        .await
+        .map_err(|e| {
+            if let Some(status) = e.downcast_ref::<Status>() {
+                match status.code() {
+                    Code::DeadlineExceeded => timeout_err(status.message(), None),
+                    Code::ResourceExhausted => memory_limit_err(status.message(), None),
+                    _ => unexpected_err(e, None),
                 }
-            })
+            } else if let Some(te) = e.downcast_ref::<TransportError>() {
+                connect_err(te.source().unwrap_or(te).to_string(), None)
+            } else if let Some(se) = e.downcast_ref::<flume::SendError<ExecuteJsRequest>>() {
+                connect_err(se.source().unwrap_or(se).to_string(), None)
+            } else {
+                unexpected_err(e, None)
+            }
+        })
     }
 
     #[instrument(skip(self), err)]
@@ -235,6 +286,7 @@ impl Client {
         code: Arc<String>,
         globals: Option<serde_json::Value>,
         action_ipfs_id: Option<String>,
+        typescript: Option<bool>,
         call_depth: u32,
     ) -> Result<ExecutionState> {
         if code.len() > DEFAULT_MAX_CODE_LENGTH {
@@ -308,6 +360,7 @@ impl Client {
                     http_headers: self.http_headers.clone(),
                     timeout: Some(self.timeout_ms),
                     memory_limit: Some(self.memory_limit_mb),
+                    typescript,
                 }
                 .into(),
             )
@@ -383,18 +436,49 @@ impl Client {
                 self.state.logs.push_str(&message);
                 PrintResponse {}.into()
             }
-            UnionResponse::IncrementFetchCount(IncrementFetchCountRequest {}) => {
+            UnionResponse::IncrementFetchCount(IncrementFetchCountRequest { url }) => {
                 self.state.fetch_count += 1;
                 if self.state.fetch_count > self.max_fetch_count {
                     bail!("You may not send more than {} HTTP requests per session and you have attempted to exceed that limit.",
                         self.max_fetch_count
                     );
                 }
+                let policy = self.egress_policy()?;
+                let addrs = match policy.check(&url).await {
+                    Ok(addrs) => addrs,
+                    Err(violation) => return Err(self.record_egress_violation(violation)),
+                };
                 IncrementFetchCountResponse {
                     fetch_count: self.state.fetch_count,
+                    addrs: addrs.iter().map(ToString::to_string).collect(),
+                    max_response_bytes: policy.max_response_bytes,
+                    timeout_ms: policy.timeout_ms,
+                    remaining_bytes: policy
+                        .max_total_bytes
+                        .saturating_sub(self.state.fetched_bytes),
                 }
                 .into()
             }
+            UnionResponse::RecordFetch(RecordFetchRequest {
+                url,
+                bytes,
+                violation,
+            }) => {
+                self.state.fetched_bytes += bytes;
+                let policy = self.egress_policy()?;
+                let kind = match violation.as_str() {
+                    "" if self.state.fetched_bytes > policy.max_total_bytes => {
+                        Some(EgressViolationKind::TotalBytesExceeded)
+                    }
+                    "" => None,
+                    kind => Some(kind.parse()?),
+                };
+                if let Some(kind) = kind {
+                    let violation = policy.response_violation(kind, &url);
+                    return Err(self.record_egress_violation(violation));
+                }
+                RecordFetchResponse {}.into()
+            }
             UnionResponse::PkpPermissionsGetPermitted(PkpPermissionsGetPermittedRequest {
                 method,
                 token_id,
@@ -504,6 +588,7 @@ impl Client {
                 ciphertext,
             }) => {
                 let plaintext = super::aes::aes_decrypt(symmetric_key, ciphertext).await?;
+                self.state.decrypted_bytes += plaintext.len() as u64;
                 AesDecryptResponse { plaintext }.into()
             }
             UnionResponse::GetLatestNonce(GetLatestNonceRequest { address, chain }) => {
@@ -650,8 +735,14 @@ impl Client {
                     .transpose()?;
 
                 // NB: Using execute_js_inner instead of execute_js to avoid resetting state
-                let res = Box::pin(self.execute_js_inner(code, globals, Some(ipfs_id), call_depth))
-                    .await?;
+                let res = Box::pin(self.execute_js_inner(
+                    code,
+                    globals,
+                    Some(ipfs_id),
+                    None, // detected from the code
+                    call_depth,
+                ))
+                .await?;
 
                 CallChildResponse {
                     response: res.response,
@@ -775,6 +866,7 @@ impl Client {
                         bail!("Failed to convert decrypted bytes to string.")
                     }
                 };
+                self.state.decrypted_bytes += result_as_bytes.len() as u64;
 
                 DecryptAndCombineResponse { result }.into()
             }
@@ -897,6 +989,7 @@ impl Client {
                                 bail!("Failed to convert decrypted bytes to string.")
                             }
                         };
+                        self.state.decrypted_bytes += result_as_bytes.len() as u64;
 
                         result
                     }
@@ -909,63 +1002,10 @@ impl Client {
                 public_key,
                 sig_name,
             }) => {
-                self.increment_broad_and_collect_counter()?;
-                let (tss_state, txn_prefix) = self.tss_state_and_txn_prefix()?;
-                let txn_prefix = format!("{}_combine_{}", txn_prefix, sig_name);
-
-                let result = self
-                    .sign_ecdsa_helper(
-                        to_sign.clone(),
-                        public_key.clone(),
-                        sig_name.clone(),
-                        &[1], // AuthMethodScope::SignAnything
-                        self.epoch,
-                        action_ipfs_id,
-                    )
+                let sig = self
+                    .sign_and_combine_ecdsa_helper(to_sign, public_key, sig_name, action_ipfs_id)
                     .await?;
 
-                // remove the signed data from the state if we find it.  May want to check scaling of borrow_mut after POC.
-                let signed_data = self
-                    .state
-                    .signed_data
-                    .borrow_mut()
-                    .remove(&sig_name)
-                    .expect_or_err("No signed data found")?;
-
-                use crate::models::SignedData;
-                let cm = CommsManager::new(&tss_state, 0, &txn_prefix, "0").await?;
-                let mut shares = cm
-                    .broadcast_and_collect::<SignedData, SignedData>(signed_data.clone())
-                    .await?;
-
-                let msg_hash = k256::Scalar::from_be_bytes(&to_sign).unwrap_or_default();
-                let public_key = k256::AffinePoint::from_uncompressed_hex(&public_key)
-                    .ok_or(anyhow::Error::msg("Failed to parse public key"))?;
-
-                shares.push((signed_data.share_index as u16, signed_data.clone()));
-
-                // remove shares where the big_r is blank
-                shares.retain(|(_, share)| !share.signature_share.is_empty());
-
-                let first_share = shares.first().expect_or_err("No shares found")?.1.clone();
-
-                let presignature_big_r: k256::AffinePoint =
-                    serde_json::from_str(&first_share.big_r).unwrap_or(k256::AffinePoint::IDENTITY);
-
-                let shares: Vec<k256::Scalar> = shares
-                    .iter()
-                    .map(|(_, share)| {
-                        serde_json::from_str::<k256::Scalar>(&share.signature_share)
-                            .unwrap_or_default()
-                    })
-                    .collect();
-
-                let sig = lit_ecdsa_wasm_combine::combiners::k256_cait_sith::do_combine_signature(
-                    public_key,
-                    presignature_big_r,
-                    msg_hash,
-                    shares,
-                );
                 // done inline, as we may remove this code.
                 #[derive(serde::Serialize, serde::Deserialize)]
                 struct Rsv {
@@ -983,6 +1023,21 @@ impl Client {
 
                 SignAndCombineEcdsaResponse { result }.into()
             }
+            UnionResponse::SendTransaction(SendTransactionRequest {
+                chain,
+                public_key,
+                tx,
+            }) => {
+                let tx: ethers::types::Eip1559TransactionRequest = serde_json::from_slice(&tx)
+                    .map_err(|e| conversion_err(e, Some("Invalid transaction".into())))?;
+                let tx_hash = self
+                    .send_transaction_helper(chain, public_key, tx, action_ipfs_id)
+                    .await?;
+                SendTransactionResponse {
+                    tx_hash: format!("{tx_hash:#x}"),
+                }
+                .into()
+            }
             UnionResponse::GetRpcUrl(GetRpcUrlRequest { chain }) => {
                 let result = match rpc_url(chain) {
                     Ok(url) => url,
@@ -1087,7 +1142,74 @@ impl Client {
                 }
                 .into()
             }
+            UnionResponse::LoadModule(LoadModuleRequest { cid }) => {
+                let allowlist = self.lit_config().actions_module_allowlist()?;
+                if !allowlist.is_empty() && !allowlist.contains(&cid) {
+                    bail!("The module ipfs://{} is not in this node's allowlist of importable modules.", cid);
+                }
+
+                let ipfs_cache = self.ipfs_cache()?;
+                let code =
+                    crate::utils::web::get_ipfs_file(&cid, self.lit_config(), ipfs_cache.clone())
+                        .await?;
+                // The gateway is untrusted, make sure it returned the module we asked for
+                if let Err(e) = verify_ipfs_cid(&cid, code.as_bytes()) {
+                    ipfs_cache.invalidate(&cid).await;
+                    return Err(e.into());
+                }
+
+                LoadModuleResponse {
+                    code: code.to_string(),
+                }
+                .into()
+            }
+            UnionResponse::LoadWasm(LoadWasmRequest { cid }) => {
+                // Verifies the CID of the fetched bytes
+                let code = crate::utils::web::get_ipfs_bytes(&cid, self.lit_config()).await?;
+                LoadWasmResponse { code }.into()
+            }
+
+            UnionResponse::Storage(StorageRequest {
+                method,
+                public_key,
+                key,
+                value,
+                expected_version,
+            }) => {
+                let result = self
+                    .storage_helper(
+                        method,
+                        public_key,
+                        key,
+                        value,
+                        expected_version,
+                        action_ipfs_id,
+                    )
+                    .await?;
+                StorageResponse {
+                    result: serde_json::to_vec(&result)?,
+                }
+                .into()
+            }
+            UnionResponse::GetRandomness(GetRandomnessRequest { round }) => {
+                self.increment_broad_and_collect_counter()?;
+                let (tss_state, txn_prefix) = self.tss_state_and_txn_prefix()?;
+                let txn_prefix = format!(
+                    "{}_beacon_{}",
+                    txn_prefix, self.state.broadcast_and_collect_count
+                );
+                if !self.lit_config().enable_beacon()? {
+                    bail!("The randomness beacon is disabled");
+                }
 
+                let beacon_round = beacon::produce_round(&tss_state, &txn_prefix, round).await?;
+                GetRandomnessResponse {
+                    round: beacon_round.round,
+                    signature: beacon_round.signature,
+                    randomness: beacon_round.randomness,
+                }
+                .into()
+            }
             UnionResponse::Result(_) => unreachable!(), // handled in main loop
         })
     }
@@ -1224,6 +1346,408 @@ impl Client {
         Ok("success".to_string())
     }
 
+    /// Sign with the PKP on every node and combine the signature shares of all nodes.
+    async fn sign_and_combine_ecdsa_helper(
+        &mut self,
+        to_sign: Vec<u8>,
+        public_key: String,
+        sig_name: String,
+        action_ipfs_id: Option<String>,
+    ) -> Result<CombinedEcdsaSignature> {
+        self.increment_broad_and_collect_counter()?;
+        let (tss_state, txn_prefix) = self.tss_state_and_txn_prefix()?;
+        let txn_prefix = format!("{}_combine_{}", txn_prefix, sig_name);
+
+        self.sign_ecdsa_helper(
+            to_sign.clone(),
+            public_key.clone(),
+            sig_name.clone(),
+            &[1], // AuthMethodScope::SignAnything
+            self.epoch,
+            action_ipfs_id,
+        )
+        .await?;
+
+        // remove the signed data from the state if we find it.  May want to check scaling of borrow_mut after POC.
+        let signed_data = self
+            .state
+            .signed_data
+            .borrow_mut()
+            .remove(&sig_name)
+            .expect_or_err("No signed data found")?;
+
+        use crate::models::SignedData;
+        let cm = CommsManager::new(&tss_state, 0, &txn_prefix, "0").await?;
+        let mut shares = cm
+            .broadcast_and_collect::<SignedData, SignedData>(signed_data.clone())
+            .await?;
+
+        let msg_hash = k256::Scalar::from_be_bytes(&to_sign).unwrap_or_default();
+        let public_key = k256::AffinePoint::from_uncompressed_hex(&public_key)
+            .ok_or(anyhow::Error::msg("Failed to parse public key"))?;
+
+        shares.push((signed_data.share_index as u16, signed_data.clone()));
+
+        // remove shares where the big_r is blank
+        shares.retain(|(_, share)| !share.signature_share.is_empty());
+
+        let first_share = shares.first().expect_or_err("No shares found")?.1.clone();
+
+        let presignature_big_r: k256::AffinePoint =
+            serde_json::from_str(&first_share.big_r).unwrap_or(k256::AffinePoint::IDENTITY);
+
+        let shares: Vec<k256::Scalar> = shares
+            .iter()
+            .map(|(_, share)| {
+                serde_json::from_str::<k256::Scalar>(&share.signature_share).unwrap_or_default()
+            })
+            .collect();
+
+        let sig = lit_ecdsa_wasm_combine::combiners::k256_cait_sith::do_combine_signature(
+            public_key,
+            presignature_big_r,
+            msg_hash,
+            shares,
+        );
+
+        Ok(CombinedEcdsaSignature {
+            r: sig.r,
+            s: sig.s,
+            recid: sig.recid,
+        })
+    }
+
+    /// Fill in, sign and broadcast a transaction from a PKP. The leader fills in
+    /// the transaction and broadcasts it once signed; every node checks what the
+    /// leader filled in against its own view of the chain before signing it.
+    /// Nonce collisions and underpriced replacements are retried with a fresh
+    /// nonce or higher fees, and a reserved nonce is given back on failure.
+    async fn send_transaction_helper(
+        &mut self,
+        chain: String,
+        public_key: String,
+        tx: ethers::types::Eip1559TransactionRequest,
+        action_ipfs_id: Option<String>,
+    ) -> Result<ethers::types::H256> {
+        use super::transaction::{self, SendError, SendOutcome};
+        use ethers::prelude::*;
+        use ethers::types::transaction::eip2718::TypedTransaction;
+
+        let public_key = public_key.replace("0x", "");
+        let from = transaction::address_from_public_key(&encoding::hex_to_bytes(&public_key)?)?;
+        let provider: Provider<Http> = ENDPOINT_MANAGER.get_provider(chain.as_str())?;
+
+        let (tss_state, txn_prefix) = self.tss_state_and_txn_prefix()?;
+        let (leader_addr, is_leader) = self
+            .leader_helper(generate_hash(txn_prefix.clone()))
+            .await?;
+        let mut peers = tss_state.peer_state.peers().await?;
+        peers.set_all_protocol_indices(0);
+        let leader = vec![peers
+            .peer_at_address(&leader_addr)
+            .expect_or_err("Leader address not in peer list")?
+            .clone()];
+
+        self.state.send_transaction_count += 1;
+        let send_id = self.state.send_transaction_count;
+        let explicit_nonce = tx.nonce.is_some();
+        let requested = tx.clone();
+        let mut tx = tx;
+        let mut reservation = transaction::NonceReservation::new(&chain, from);
+
+        for attempt in 0..transaction::MAX_SEND_ATTEMPTS {
+            let name = format!("send_tx_{send_id}_{attempt}");
+
+            // Everyone signs the transaction as filled in by the leader
+            self.increment_broad_and_collect_counter()?;
+            let cm = CommsManager::new(&tss_state, 0, &format!("{txn_prefix}_{name}"), "0").await?;
+            if is_leader {
+                let reserves_nonce = tx.nonce.is_none();
+                tx = transaction::fill_transaction(&provider, &chain, from, tx).await?;
+                if reserves_nonce {
+                    reservation.hold(tx.nonce.expect_or_err("Transaction without nonce")?);
+                }
+                cm.broadcast::<String>(serde_json::to_string(&tx)?).await?;
+            } else {
+                let values = cm.collect_from::<String>(&leader).await?;
+                let filled = values.first().expect_or_err("No transaction from leader")?;
+                tx = serde_json::from_str(&filled.1)?;
+            }
+            tx.nonce.expect_or_err("Transaction without nonce")?;
+            let bounds = transaction::transaction_bounds(&provider, from).await?;
+            transaction::check_filled_transaction(&requested, &tx, from, &bounds)?;
+            let chain_id = bounds.chain_id;
+
+            let typed_tx = TypedTransaction::Eip1559(tx.clone());
+            let sig = self
+                .sign_and_combine_ecdsa_helper(
+                    typed_tx.sighash().as_bytes().to_vec(),
+                    public_key.clone(),
+                    name.clone(),
+                    action_ipfs_id.clone(),
+                )
+                .await?;
+            let sig = transaction::eth_signature(&sig.r, &sig.s, sig.recid, chain_id);
+            let signed_tx = typed_tx.rlp_signed(&sig);
+            let tx_hash = H256::from(keccak256(&signed_tx));
+
+            self.increment_broad_and_collect_counter()?;
+            let cm =
+                CommsManager::new(&tss_state, 0, &format!("{txn_prefix}_{name}_sent"), "0").await?;
+            let outcome = if is_leader {
+                let outcome = match provider.send_raw_transaction(signed_tx).await {
+                    Ok(_) => SendOutcome::Sent(tx_hash),
+                    Err(e) => {
+                        let message = e.to_string();
+                        match transaction::classify_send_error(&message) {
+                            SendError::AlreadyKnown => SendOutcome::Sent(tx_hash),
+                            SendError::NonceTooLow if !explicit_nonce => {
+                                transaction::reset_nonce(&chain, from);
+                                tx.nonce = None;
+                                SendOutcome::Retry(message)
+                            }
+                            SendError::Underpriced if explicit_nonce => {
+                                // Replacing a pending transaction of the user
+                                transaction::bump_fees(&mut tx);
+                                SendOutcome::Retry(message)
+                            }
+                            SendError::Underpriced => {
+                                // Collided with a pending transaction sent elsewhere
+                                tx.nonce = None;
+                                SendOutcome::Retry(message)
+                            }
+                            // The reserved nonce is given back when returning
+                            _ => SendOutcome::Failed(message),
+                        }
+                    }
+                };
+                // Sent, or the nonce turned out to be used
+                if !matches!(outcome, SendOutcome::Failed(_)) {
+                    reservation.keep();
+                }
+                cm.broadcast::<String>(serde_json::to_string(&outcome)?)
+                    .await?;
+                outcome
+            } else {
+                let values = cm.collect_from::<String>(&leader).await?;
+                let outcome = values.first().expect_or_err("No outcome from leader")?;
+                serde_json::from_str(&outcome.1)?
+            };
+
+            match outcome {
+                SendOutcome::Sent(tx_hash) => {
+                    debug!("Sent transaction {tx_hash:#x} on {chain}");
+                    return Ok(tx_hash);
+                }
+                SendOutcome::Retry(message) => {
+                    debug!("Retrying transaction on {chain}: {message}");
+                }
+                SendOutcome::Failed(message) => {
+                    bail!("Failed to send transaction: {message}");
+                }
+            }
+        }
+
+        bail!(
+            "Failed to send transaction after {} attempts",
+            transaction::MAX_SEND_ATTEMPTS
+        )
+    }
+
+    /// `Lit.Actions.storage`. Every node applies the operation to its own replica,
+    /// after reconciling the replicas of the key with the other nodes.
+    async fn storage_helper(
+        &mut self,
+        method: String,
+        public_key: String,
+        key: String,
+        value: Option<String>,
+        expected_version: Option<u64>,
+        action_ipfs_id: Option<String>,
+    ) -> Result<serde_json::Value> {
+        let action_ipfs_id =
+            action_ipfs_id.expect_or_err("Storage is only available to actions run by IPFS id")?;
+        let public_key = encoding::hex_to_bytes(public_key.replace("0x", ""))?;
+        let namespace = storage::namespace(&public_key, &action_ipfs_id);
+
+        if !self.state.storage_namespaces.contains(&namespace) {
+            let token_id = format!("0x{}", encoding::bytes_to_hex(keccak256(&public_key)));
+            let is_permitted = pkp::utils::pkp_permissions_is_permitted(
+                token_id,
+                self.lit_config(),
+                "isPermittedAction".to_string(),
+                vec![serde_json::Value::String(action_ipfs_id.clone())],
+            )
+            .await?;
+            if !is_permitted {
+                bail!("Action {action_ipfs_id} is not permitted to use the storage of this PKP");
+            }
+            self.state.storage_namespaces.insert(namespace.clone());
+        }
+
+        let storage = ActionStorage::open(&self.lit_config().actions_storage_path()?)?;
+        let mut keys = match self.storage_keys.take() {
+            Some(keys) => keys,
+            None => StorageKeys::try_new(self.lit_config())?,
+        };
+        let result = self
+            .storage_op(
+                &storage,
+                &mut keys,
+                method,
+                namespace,
+                key,
+                value,
+                expected_version,
+            )
+            .await;
+        self.storage_keys = Some(keys);
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn storage_op(
+        &mut self,
+        storage: &ActionStorage,
+        keys: &mut StorageKeys,
+        method: String,
+        namespace: String,
+        key: String,
+        value: Option<String>,
+        expected_version: Option<u64>,
+    ) -> Result<serde_json::Value> {
+        use serde_json::json;
+
+        if method == "list" {
+            // `key` is the prefix here; keys held by a threshold of nodes are listed
+            let local = storage.list(&namespace, &key)?;
+            let replicas = self.storage_collect(local.clone()).await?;
+            let threshold = self.storage_threshold().await?;
+            let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+            for k in replicas.iter().flatten() {
+                *counts.entry(k).or_default() += 1;
+            }
+            let keys: Vec<&String> = counts
+                .into_iter()
+                .filter(|(_, count)| *count >= threshold)
+                .map(|(k, _)| k)
+                .collect();
+            return Ok(json!(keys));
+        }
+
+        storage::validate_key(&key)?;
+        let current = self
+            .storage_resolve(storage, keys, &namespace, &key)
+            .await?;
+        let current_version = current.as_ref().map_or(0, |r| r.version);
+        let exists = current.as_ref().is_some_and(|r| r.value.is_some());
+
+        match method.as_str() {
+            "get" => {
+                return Ok(match current {
+                    Some(StorageRecord {
+                        version,
+                        value: Some(value),
+                    }) => json!({ "value": value, "version": version }),
+                    _ => serde_json::Value::Null,
+                })
+            }
+            "put" | "delete" => {}
+            _ => bail!("Unknown storage method: {method}"),
+        }
+
+        // Compare-and-swap: 0 expects the key to not exist
+        if let Some(expected_version) = expected_version {
+            let actual = if exists { current_version } else { 0 };
+            if expected_version != actual {
+                bail!(
+                    "Storage version mismatch for {key}: expected {expected_version}, found {actual}"
+                );
+            }
+        }
+        if method == "delete" && !exists {
+            return Ok(json!(false));
+        }
+
+        if let Some(value) = &value {
+            let usage =
+                storage.usage_after_write(&namespace, &key, current.as_ref(), Some(value))?;
+            if usage > self.storage_quota_bytes {
+                bail!(
+                    "Storage quota of {} bytes exceeded for this PKP and action",
+                    self.storage_quota_bytes
+                );
+            }
+        }
+
+        let record = StorageRecord {
+            version: current_version + 1,
+            value: if method == "put" { value } else { None },
+        };
+        storage.set(keys, &namespace, &key, &record).await?;
+
+        // Concurrent writes of the same version converge on a single winner on
+        // every node; make sure it's ours.
+        let stored = self
+            .storage_resolve(storage, keys, &namespace, &key)
+            .await?;
+        if stored.as_ref() != Some(&record) {
+            bail!("Storage conflict for {key}: it was modified concurrently");
+        }
+
+        Ok(match method.as_str() {
+            "put" => json!(record.version),
+            _ => json!(true),
+        })
+    }
+
+    /// Reconcile the replicas of a key, repairing the local one if it's stale.
+    async fn storage_resolve(
+        &mut self,
+        storage: &ActionStorage,
+        keys: &mut StorageKeys,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<StorageRecord>> {
+        let local = storage.get(keys, namespace, key).await?;
+        let replicas = self.storage_collect(local.clone()).await?;
+        let threshold = self.storage_threshold().await?;
+        let resolved = storage::resolve(&replicas, threshold)?;
+
+        if let Some(record) = &resolved {
+            if local.as_ref() != Some(record) {
+                storage.set(keys, namespace, key, record).await?;
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Exchange a value with all other nodes, returning everyone's (including ours).
+    async fn storage_collect<T>(&mut self, local: T) -> Result<Vec<T>>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        self.increment_broad_and_collect_counter()?;
+        self.state.storage_op_count += 1;
+        let (tss_state, txn_prefix) = self.tss_state_and_txn_prefix()?;
+        let txn_prefix = format!("{}_storage_{}", txn_prefix, self.state.storage_op_count);
+
+        let cm = CommsManager::new(&tss_state, 0, &txn_prefix, "0").await?;
+        let mut values: Vec<T> = cm
+            .broadcast_and_collect::<&T, T>(&local)
+            .await?
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect();
+        values.push(local);
+        Ok(values)
+    }
+
+    async fn storage_threshold(&self) -> Result<usize> {
+        let (tss_state, _) = self.tss_state_and_txn_prefix()?;
+        Ok(tss_state.peer_state.peers().await?.threshold_for_set() as usize)
+    }
+
     async fn check_access_control_conditions_helper(
         &self,
         conditions: &Vec<models::UnifiedAccessControlConditionItem>,
@@ -0,0 +1,506 @@
+//! The egress policy for `fetch` in Lit Actions: which hosts and addresses may be
+//! reached, and how much data may be downloaded within an execution.
+//!
+//! Addresses are checked after resolving the host name on the node, and the
+//! checked addresses are handed to the actions server, which connects to them
+//! without resolving the host again (so DNS rebinding can't bypass the check).
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use lit_core::config::LitConfig;
+use serde::Serialize;
+use url::{Host, Url};
+
+use crate::config::LitNodeConfig as _;
+
+pub(crate) const DEFAULT_MAX_RESPONSE_BYTES: u64 = 10 * 1024 * 1024; // 10MB
+pub(crate) const DEFAULT_FETCH_TIMEOUT_MS: u64 = 15000; // 15s
+pub(crate) const DEFAULT_MAX_TOTAL_BYTES: u64 = 50 * 1024 * 1024; // 50MB
+
+lazy_static! {
+    /// Loopback, private, link-local, multicast, reserved and other non-routable
+    /// ranges. IPv4-mapped IPv6 addresses are checked against the IPv4 ranges.
+    static ref PRIVATE_RANGES: Vec<Cidr> = [
+        "0.0.0.0/8",
+        "10.0.0.0/8",
+        "100.64.0.0/10",
+        "127.0.0.0/8",
+        "169.254.0.0/16",
+        "172.16.0.0/12",
+        "192.0.0.0/24",
+        "192.168.0.0/16",
+        "198.18.0.0/15",
+        "224.0.0.0/4",
+        "240.0.0.0/4", // includes 255.255.255.255
+        "::/96",       // includes :: and ::1, and the deprecated IPv4-compatible addresses
+        "64:ff9b::/96",
+        "64:ff9b:1::/48",
+        "100::/64",
+        "2001::/23",
+        "2002::/16",
+        "fc00::/7",
+        "fe80::/10",
+        "fec0::/10",
+        "ff00::/8",
+    ]
+    .iter()
+    .map(|cidr| cidr.parse().expect("valid CIDR"))
+    .collect();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EgressViolationKind {
+    /// The URL scheme can't be fetched (only http, https, data and blob are allowed).
+    SchemeDenied,
+    /// The host is denied or not on the allow list.
+    HostDenied,
+    /// The host resolves to a denied or private address.
+    AddressDenied,
+    /// The response body is larger than the per-response limit.
+    ResponseTooLarge,
+    /// All responses of the execution together are larger than the total limit.
+    TotalBytesExceeded,
+    /// The request took longer than the per-request timeout.
+    Timeout,
+}
+
+impl FromStr for EgressViolationKind {
+    type Err = anyhow::Error;
+
+    /// Parse a violation detected by the actions server while reading a response.
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "response_too_large" => Self::ResponseTooLarge,
+            "total_bytes_exceeded" => Self::TotalBytesExceeded,
+            "timeout" => Self::Timeout,
+            _ => bail!("Unknown fetch violation: {s}"),
+        })
+    }
+}
+
+/// A fetch blocked or aborted by the egress policy.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EgressViolation {
+    pub kind: EgressViolationKind,
+    pub url: String,
+    pub message: String,
+}
+
+impl EgressViolation {
+    fn new(kind: EgressViolationKind, url: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            url: url.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for EgressViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Egress policy violation: {}", self.message)
+    }
+}
+
+impl std::error::Error for EgressViolation {}
+
+/// An IPv4 or IPv6 network, e.g. `10.0.0.0/8`. A bare address is a single host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| anyhow!("Invalid CIDR address: {s}"))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| anyhow!("Invalid CIDR prefix length: {s}"))?,
+            None => max_len,
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Which hosts and addresses Lit Actions may fetch from, and how much.
+///
+/// When `allow_hosts` is not empty, only those hosts can be reached; `deny_hosts`
+/// are never reachable. Host patterns are either exact or `*.example.com`, which
+/// matches any subdomain. Addresses in `allow_cidrs` are always reachable, while
+/// those in `deny_cidrs` (and private ones, if `block_private` is set) are not.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgressPolicy {
+    pub allow_hosts: Vec<String>,
+    pub deny_hosts: Vec<String>,
+    pub allow_cidrs: Vec<Cidr>,
+    pub deny_cidrs: Vec<Cidr>,
+    pub block_private: bool,
+    pub max_response_bytes: u64,
+    pub timeout_ms: u64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for EgressPolicy {
+    fn default() -> Self {
+        Self {
+            allow_hosts: Vec::new(),
+            deny_hosts: Vec::new(),
+            allow_cidrs: Vec::new(),
+            deny_cidrs: Vec::new(),
+            block_private: true,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            timeout_ms: DEFAULT_FETCH_TIMEOUT_MS,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+        }
+    }
+}
+
+impl EgressPolicy {
+    pub fn from_config(cfg: &LitConfig) -> Result<Self> {
+        let parse_cidrs = |cidrs: Vec<String>| {
+            cidrs
+                .iter()
+                .map(|cidr| cidr.parse())
+                .collect::<Result<Vec<Cidr>>>()
+        };
+        let default = Self::default();
+
+        Ok(Self {
+            allow_hosts: cfg.actions_egress_allow_hosts()?,
+            deny_hosts: cfg.actions_egress_deny_hosts()?,
+            allow_cidrs: parse_cidrs(cfg.actions_egress_allow_cidrs()?)?,
+            deny_cidrs: parse_cidrs(cfg.actions_egress_deny_cidrs()?)?,
+            block_private: cfg
+                .actions_egress_block_private()
+                .unwrap_or(default.block_private),
+            max_response_bytes: cfg
+                .actions_fetch_max_response_bytes()
+                .map(|v| v as u64)
+                .unwrap_or(default.max_response_bytes),
+            timeout_ms: cfg
+                .actions_fetch_timeout_ms()
+                .map(|v| v as u64)
+                .unwrap_or(default.timeout_ms),
+            max_total_bytes: cfg
+                .actions_fetch_max_total_bytes()
+                .map(|v| v as u64)
+                .unwrap_or(default.max_total_bytes),
+        })
+    }
+
+    /// The violation for a response that was aborted while being read.
+    pub fn response_violation(&self, kind: EgressViolationKind, url: &str) -> EgressViolation {
+        let message = match kind {
+            EgressViolationKind::ResponseTooLarge => format!(
+                "The response from {url} is larger than {} bytes",
+                self.max_response_bytes
+            ),
+            EgressViolationKind::TotalBytesExceeded => format!(
+                "You may not fetch more than {} bytes per session and you have attempted to exceed that limit.",
+                self.max_total_bytes
+            ),
+            EgressViolationKind::Timeout => format!(
+                "The request to {url} took longer than {}ms",
+                self.timeout_ms
+            ),
+            _ => format!("Fetching {url} is not allowed"),
+        };
+        EgressViolation::new(kind, url, message)
+    }
+
+    /// Check whether the URL may be fetched, resolving its host if necessary.
+    ///
+    /// Returns the checked addresses of the host, which are the only ones the
+    /// request may connect to (none for URLs that never leave the actions server).
+    pub async fn check(&self, url: &str) -> std::result::Result<Vec<IpAddr>, EgressViolation> {
+        let violation = |kind, message: String| EgressViolation::new(kind, url, message);
+
+        let parsed = Url::parse(url).map_err(|e| {
+            violation(
+                EgressViolationKind::SchemeDenied,
+                format!("Invalid URL {url}: {e}"),
+            )
+        })?;
+        match parsed.scheme() {
+            "http" | "https" => {}
+            // These never leave the actions server
+            "data" | "blob" => return Ok(Vec::new()),
+            scheme => {
+                return Err(violation(
+                    EgressViolationKind::SchemeDenied,
+                    format!("Fetching {scheme}: URLs is not allowed"),
+                ))
+            }
+        }
+
+        let host = parsed.host_str().unwrap_or_default();
+        self.check_host(host)
+            .map_err(|message| violation(EgressViolationKind::HostDenied, message))?;
+
+        let addrs = match parsed.host() {
+            Some(Host::Domain(domain)) => {
+                let port = parsed.port_or_known_default().unwrap_or(443);
+                // Fail closed, the request can't be allowed without checking an address
+                let addrs: Vec<IpAddr> = tokio::net::lookup_host((domain, port))
+                    .await
+                    .map_err(|e| {
+                        violation(
+                            EgressViolationKind::HostDenied,
+                            format!("Host {domain} could not be resolved: {e}"),
+                        )
+                    })?
+                    .map(|addr| addr.ip())
+                    .collect();
+                if addrs.is_empty() {
+                    return Err(violation(
+                        EgressViolationKind::HostDenied,
+                        format!("Host {domain} could not be resolved"),
+                    ));
+                }
+                addrs
+            }
+            Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+            Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+            None => {
+                return Err(violation(
+                    EgressViolationKind::HostDenied,
+                    format!("URL {url} has no host"),
+                ))
+            }
+        };
+
+        for addr in addrs.iter() {
+            self.check_addr(*addr)
+                .map_err(|message| violation(EgressViolationKind::AddressDenied, message))?;
+        }
+
+        Ok(addrs)
+    }
+
+    fn check_host(&self, host: &str) -> std::result::Result<(), String> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        if self.deny_hosts.iter().any(|p| host_matches(p, &host)) {
+            return Err(format!("Host {host} is denied"));
+        }
+        if !self.allow_hosts.is_empty() && !self.allow_hosts.iter().any(|p| host_matches(p, &host))
+        {
+            return Err(format!("Host {host} is not on the allow list"));
+        }
+        Ok(())
+    }
+
+    fn check_addr(&self, addr: IpAddr) -> std::result::Result<(), String> {
+        let addr = canonical(addr);
+        if self.allow_cidrs.iter().any(|cidr| cidr.contains(addr)) {
+            return Ok(());
+        }
+        if self.deny_cidrs.iter().any(|cidr| cidr.contains(addr)) {
+            return Err(format!("Address {addr} is denied"));
+        }
+        if self.block_private && PRIVATE_RANGES.iter().any(|cidr| cidr.contains(addr)) {
+            return Err(format!("Address {addr} is private"));
+        }
+        Ok(())
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches('.').to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .strip_suffix(suffix)
+            .map_or(false, |rest| rest.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+// Check IPv4-mapped IPv6 addresses (::ffff:10.0.0.1) against IPv4 ranges
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        ip => ip,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> EgressPolicy {
+        EgressPolicy::default()
+    }
+
+    #[test]
+    fn parses_cidrs() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("11.0.0.1".parse().unwrap()));
+        assert!(cidr.contains("::ffff:10.0.0.1".parse().unwrap()));
+
+        let host: Cidr = "2001:db8::1".parse().unwrap();
+        assert_eq!(host.to_string(), "2001:db8::1/128");
+        assert!(host.contains("2001:db8::1".parse().unwrap()));
+        assert!(!host.contains("2001:db8::2".parse().unwrap()));
+
+        let any: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains("8.8.8.8".parse().unwrap()));
+
+        for invalid in ["10.0.0.0/33", "fe80::/129", "example.com", "10.0.0.0/"] {
+            assert!(invalid.parse::<Cidr>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn matches_hosts() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("Example.COM.", "example.com"));
+        assert!(!host_matches("example.com", "api.example.com"));
+        assert!(host_matches("*.example.com", "api.example.com"));
+        assert!(!host_matches("*.example.com", "example.com"));
+        assert!(!host_matches("*.example.com", "badexample.com"));
+    }
+
+    #[tokio::test]
+    async fn blocks_private_addresses_by_default() {
+        for url in [
+            "http://127.0.0.1:8080/",
+            "http://10.0.0.1/",
+            "http://192.168.1.1/",
+            "http://172.16.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://192.0.0.170/",
+            "http://198.18.0.1/",
+            "http://224.0.0.1/",
+            "http://239.255.255.250/",
+            "http://240.0.0.1/",
+            "http://255.255.255.255/",
+            "http://[::]/",
+            "http://[::127.0.0.1]/",
+            "http://[::ffff:198.18.0.1]/",
+            "http://[::ffff:224.0.0.1]/",
+            "http://[64:ff9b::a00:1]/",
+            "http://[2002:a00:1::]/",
+            "http://[fec0::1]/",
+            "http://[ff02::1]/",
+        ] {
+            let err = policy().check(url).await.unwrap_err();
+            assert_eq!(err.kind, EgressViolationKind::AddressDenied, "{url}");
+        }
+
+        assert_eq!(
+            policy().check("https://1.1.1.1/").await.unwrap(),
+            vec!["1.1.1.1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(
+            policy()
+                .check("https://[2606:4700:4700::1111]/")
+                .await
+                .unwrap(),
+            vec!["2606:4700:4700::1111".parse::<IpAddr>().unwrap()]
+        );
+        assert!(policy().check("data:text/plain,hi").await.is_ok());
+        assert_eq!(
+            policy().check("file:///etc/passwd").await.unwrap_err().kind,
+            EgressViolationKind::SchemeDenied
+        );
+    }
+
+    #[tokio::test]
+    async fn denies_unresolvable_hosts() {
+        // .invalid never resolves (RFC 2606)
+        assert_eq!(
+            policy()
+                .check("https://lit-egress-test.invalid/")
+                .await
+                .unwrap_err()
+                .kind,
+            EgressViolationKind::HostDenied
+        );
+    }
+
+    #[tokio::test]
+    async fn applies_operator_lists() {
+        let policy = EgressPolicy {
+            allow_hosts: vec!["*.example.com".into(), "10.1.2.3".into()],
+            deny_hosts: vec!["bad.example.com".into()],
+            allow_cidrs: vec!["10.1.0.0/16".parse().unwrap()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy
+                .check("https://bad.example.com/")
+                .await
+                .unwrap_err()
+                .kind,
+            EgressViolationKind::HostDenied
+        );
+        assert_eq!(
+            policy.check("https://example.org/").await.unwrap_err().kind,
+            EgressViolationKind::HostDenied
+        );
+        assert!(policy.check("http://10.1.2.3/").await.is_ok());
+        assert_eq!(
+            policy.check("http://1.1.1.1/").await.unwrap_err().kind,
+            EgressViolationKind::HostDenied
+        );
+
+        let policy = EgressPolicy {
+            deny_cidrs: vec!["1.1.1.0/24".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.check("http://1.1.1.1/").await.unwrap_err().kind,
+            EgressViolationKind::AddressDenied
+        );
+    }
+}
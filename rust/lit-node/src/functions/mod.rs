@@ -1,5 +1,8 @@
 pub mod action_client;
 mod aes;
+pub(crate) mod egress;
+pub(crate) mod storage;
+mod transaction;
 
 #[cfg(test)]
 mod tests;
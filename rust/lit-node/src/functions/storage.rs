@@ -0,0 +1,621 @@
+//! Key-value storage for Lit Actions (`Lit.Actions.storage`).
+//!
+//! Values are namespaced by PKP and action IPFS id, and every node keeps its own
+//! replica, encrypted under a key derived through the node's KDF (with its own
+//! context) and the namespace. Each write bumps the version of the key (deletes
+//! leave a tombstone, compacted after `TOMBSTONE_TTL`), which is what
+//! compare-and-swap operates on. Replicas are reconciled through the p2p layer
+//! on every operation, see [`resolve`].
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context as _, Result};
+use lit_attestation::kdf::Kdf;
+use lit_core::config::LitConfig;
+use rusqlite::{params, Connection, OptionalExtension as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::rate_limiting::models::RateLimitNft;
+
+const KDF_CONTEXT: &str = "lit-action-storage";
+const KEY_DERIVATION_DOMAIN: &[u8] = b"lit-action-storage-v1";
+const MAX_KEY_LENGTH: usize = 256;
+/// How long deleted keys are remembered, replicas offline for longer may bring
+/// a deleted value back.
+const TOMBSTONE_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A replica of a key: the plaintext value and its version. `value` is `None`
+/// for deleted keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) struct StorageRecord {
+    pub version: u64,
+    pub value: Option<String>,
+}
+
+impl StorageRecord {
+    fn digest(&self) -> Vec<u8> {
+        Sha256::digest(self.value.as_deref().unwrap_or_default()).to_vec()
+    }
+
+    fn size(key: &str, value: Option<&str>) -> u64 {
+        value.map_or(0, |v| (key.len() + v.len()) as u64)
+    }
+}
+
+/// The namespace of a PKP and action pair.
+pub(crate) fn namespace(public_key: &[u8], action_ipfs_id: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(public_key);
+    hasher.update(b":");
+    hasher.update(action_ipfs_id.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The storage quota of a namespace, scaled by the rate limit NFT paying for the execution.
+pub(crate) fn quota_bytes(base_quota_bytes: u64, nft: Option<&RateLimitNft>) -> u64 {
+    let multiplier = nft
+        .map(|nft| nft.requests_per_kilosecond.min(u64::MAX.into()).as_u64())
+        .unwrap_or(1)
+        .max(1);
+    base_quota_bytes.saturating_mul(multiplier)
+}
+
+pub(crate) fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        bail!("Storage keys must be between 1 and {MAX_KEY_LENGTH} bytes long");
+    }
+    Ok(())
+}
+
+/// Pick the record agreed on by at least `threshold` replicas (a missing key
+/// counts as the oldest version), preferring the highest version.
+pub(crate) fn resolve(
+    replicas: &[Option<StorageRecord>],
+    threshold: usize,
+) -> Result<Option<StorageRecord>> {
+    let mut counts: HashMap<&Option<StorageRecord>, usize> = HashMap::new();
+    for replica in replicas {
+        *counts.entry(replica).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= threshold)
+        .map(|(record, _)| record)
+        // Ties are settled like concurrent writes, see `ActionStorage::set`
+        .max_by_key(|record| record.as_ref().map(|r| (r.version, r.digest())))
+        .cloned()
+        .with_context(|| {
+            format!(
+                "Storage replicas disagree: no value is held by {threshold} of {} nodes",
+                replicas.len()
+            )
+        })
+}
+
+/// The root keys replicas are encrypted under, derived through the node's KDF
+/// (see `lit_attestation::kdf`), one per KDF version.
+#[derive(Debug)]
+pub(crate) struct StorageKeys {
+    kdf: Kdf,
+    keys: HashMap<u32, [u8; 32]>,
+}
+
+impl StorageKeys {
+    pub fn new(kdf: Kdf) -> Self {
+        Self {
+            kdf,
+            keys: HashMap::new(),
+        }
+    }
+
+    pub fn try_new(cfg: &LitConfig) -> Result<Self> {
+        Ok(Self::new(Kdf::try_new_for_cfg(cfg, None)?))
+    }
+
+    /// The KDF version new values are encrypted with.
+    pub fn version(&self) -> u32 {
+        self.kdf.version()
+    }
+
+    async fn key(&mut self, version: u32, namespace: &str) -> Result<[u8; 32]> {
+        let root = match self.keys.get(&version) {
+            Some(root) => *root,
+            None => {
+                let root = self.kdf.derive_version(KDF_CONTEXT, version).await?;
+                self.keys.insert(version, root);
+                root
+            }
+        };
+        Ok(derive_key(&root, namespace))
+    }
+}
+
+lazy_static! {
+    /// Open databases by path, so that every execution shares a single handle.
+    static ref DATABASES: Mutex<HashMap<PathBuf, Arc<Database>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug)]
+struct Database {
+    conn: Mutex<Connection>,
+    last_compaction: Mutex<Instant>,
+}
+
+/// The per-node store of encrypted replicas.
+#[derive(Debug, Clone)]
+pub(crate) struct ActionStorage {
+    db: Arc<Database>,
+}
+
+impl ActionStorage {
+    /// Open the store at `path`, or share the handle if it's already open.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut databases = DATABASES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(db) = databases.get(path) {
+            return Ok(Self { db: db.clone() });
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open action storage at {path:?}"))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS
+                action_storage(
+                    namespace TEXT NOT NULL,
+                    key TEXT NOT NULL,
+                    version INTEGER NOT NULL,
+                    digest BLOB NOT NULL,
+                    size INTEGER NOT NULL,
+                    value BLOB,
+                    key_version INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    PRIMARY KEY (namespace, key)
+                )",
+            [],
+        )?;
+        let storage = Self {
+            db: Arc::new(Database {
+                conn: Mutex::new(conn),
+                last_compaction: Mutex::new(Instant::now()),
+            }),
+        };
+        storage.compact(TOMBSTONE_TTL)?;
+
+        databases.insert(path.to_path_buf(), storage.db.clone());
+        Ok(storage)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.db.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub async fn get(
+        &self,
+        keys: &mut StorageKeys,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<StorageRecord>> {
+        let row: Option<(u64, Option<Vec<u8>>, u32)> = self
+            .conn()
+            .query_row(
+                "SELECT version, value, key_version FROM action_storage
+                    WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let Some((version, sealed, key_version)) = row else {
+            return Ok(None);
+        };
+        let value = match sealed {
+            Some(sealed) => {
+                let plaintext = open(&keys.key(key_version, namespace).await?, &sealed)?;
+                Some(String::from_utf8(plaintext)?)
+            }
+            None => None,
+        };
+        Ok(Some(StorageRecord { version, value }))
+    }
+
+    /// Store a record, unless the replica already holds a newer one. Concurrent
+    /// writes of the same version are settled by the larger digest, so that
+    /// every replica converges on the same value.
+    pub async fn set(
+        &self,
+        keys: &mut StorageKeys,
+        namespace: &str,
+        key: &str,
+        record: &StorageRecord,
+    ) -> Result<()> {
+        let key_version = keys.version();
+        let sealed = match record.value.as_ref() {
+            Some(v) => Some(seal(&keys.key(key_version, namespace).await?, v.as_bytes())),
+            None => None,
+        };
+        self.conn().execute(
+            "INSERT INTO action_storage(
+                    namespace, key, version, digest, size, value, key_version, updated_at
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                ON CONFLICT (namespace, key) DO UPDATE SET
+                    version = excluded.version,
+                    digest = excluded.digest,
+                    size = excluded.size,
+                    value = excluded.value,
+                    key_version = excluded.key_version,
+                    updated_at = excluded.updated_at
+                WHERE excluded.version > action_storage.version
+                    OR (excluded.version = action_storage.version
+                        AND excluded.digest > action_storage.digest)",
+            params![
+                namespace,
+                key,
+                record.version,
+                record.digest(),
+                StorageRecord::size(key, record.value.as_deref()),
+                sealed,
+                key_version,
+                unix_time(),
+            ],
+        )?;
+
+        if record.value.is_none() {
+            self.compact_if_due()?;
+        }
+        Ok(())
+    }
+
+    /// Keys (not deleted) starting with `prefix`, in order.
+    pub fn list(&self, namespace: &str, prefix: &str) -> Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT key FROM action_storage
+                WHERE namespace = ?1 AND value IS NOT NULL AND instr(key, ?2) = 1
+                ORDER BY key",
+        )?;
+        let keys = stmt
+            .query_map(params![namespace, prefix], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(keys)
+    }
+
+    /// Bytes used by the keys and values of a namespace.
+    pub fn usage(&self, namespace: &str) -> Result<u64> {
+        let usage: i64 = self.conn().query_row(
+            "SELECT COALESCE(SUM(size), 0) FROM action_storage WHERE namespace = ?1",
+            params![namespace],
+            |row| row.get(0),
+        )?;
+        Ok(usage as u64)
+    }
+
+    /// Bytes the namespace would use after writing `value` to `key`.
+    pub fn usage_after_write(
+        &self,
+        namespace: &str,
+        key: &str,
+        current: Option<&StorageRecord>,
+        value: Option<&str>,
+    ) -> Result<u64> {
+        let current_size = current.map_or(0, |r| StorageRecord::size(key, r.value.as_deref()));
+        Ok((self.usage(namespace)? + StorageRecord::size(key, value)).saturating_sub(current_size))
+    }
+
+    /// Drop the tombstones of keys deleted longer than `ttl` ago.
+    ///
+    /// A tombstone only has to outlive the replicas that may still hold the
+    /// deleted value, after that a missing key resolves the same way.
+    pub fn compact(&self, ttl: Duration) -> Result<usize> {
+        let cutoff = unix_time().saturating_sub(ttl.as_secs());
+        let removed = self.conn().execute(
+            "DELETE FROM action_storage WHERE value IS NULL AND updated_at < ?1",
+            params![cutoff],
+        )?;
+        *self
+            .db
+            .last_compaction
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        if removed > 0 {
+            debug!("Compacted {} action storage tombstones", removed);
+        }
+        Ok(removed)
+    }
+
+    fn compact_if_due(&self) -> Result<()> {
+        let due = self
+            .db
+            .last_compaction
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+            > COMPACTION_INTERVAL;
+        if due {
+            self.compact(TOMBSTONE_TTL)?;
+        }
+        Ok(())
+    }
+}
+
+fn derive_key(root: &[u8; 32], namespace: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(KEY_DERIVATION_DOMAIN);
+    hasher.update(root);
+    hasher.update(namespace.as_bytes());
+    hasher.finalize().into()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// NaCl secretbox (XSalsa20-Poly1305), stored as nonce || tag || ciphertext
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Vec<u8> {
+    use rand::RngCore as _;
+
+    let mut nonce = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut m = vec![0u8; 32];
+    m.extend_from_slice(plaintext);
+    let mut c = vec![0u8; m.len()];
+    // Only fails if the first 32 bytes of `m` aren't zero
+    let _ = sodalite::secretbox(&mut c, &m, &nonce, key);
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&c[16..]);
+    sealed
+}
+
+fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < 24 + 16 {
+        bail!("Stored value is too short");
+    }
+    let nonce: [u8; 24] = sealed[..24].try_into()?;
+
+    let mut c = vec![0u8; 16];
+    c.extend_from_slice(&sealed[24..]);
+    let mut m = vec![0u8; c.len()];
+    if sodalite::secretbox_open(&mut m, &c, &nonce, key).is_err() {
+        bail!("Failed to decrypt stored value");
+    }
+    Ok(m.split_off(32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, U256};
+    use lit_attestation::kdf::local::LocalKdfProvider;
+    use lit_core::config::LitConfigBuilder;
+
+    fn record(version: u64, value: &str) -> Option<StorageRecord> {
+        Some(StorageRecord {
+            version,
+            value: Some(value.to_string()),
+        })
+    }
+
+    fn tombstone(version: u64) -> StorageRecord {
+        StorageRecord {
+            version,
+            value: None,
+        }
+    }
+
+    fn storage_keys(namespace: &str, version: u32) -> StorageKeys {
+        let cfg = LitConfigBuilder::default()
+            .set_override("lit.env", "dev")
+            .build()
+            .unwrap();
+        let kdf = Kdf::try_new_with_provider(
+            &cfg,
+            Arc::new(LocalKdfProvider::legacy()),
+            Some(namespace.into()),
+        )
+        .unwrap()
+        .with_version(version);
+        StorageKeys::new(kdf)
+    }
+
+    #[test]
+    fn resolves_replicas() {
+        // Highest version held by a threshold of nodes
+        assert_eq!(
+            resolve(&[record(2, "b"), record(2, "b"), record(1, "a")], 2).unwrap(),
+            record(2, "b")
+        );
+        assert_eq!(
+            resolve(&[record(3, "c"), record(2, "b"), record(2, "b")], 2).unwrap(),
+            record(2, "b")
+        );
+        // Missing keys count as the oldest version
+        assert_eq!(resolve(&[None, None, record(1, "a")], 2).unwrap(), None);
+        assert_eq!(
+            resolve(&[None, record(1, "a"), record(1, "a")], 2).unwrap(),
+            record(1, "a")
+        );
+        // No agreement
+        assert!(resolve(&[None, record(1, "a"), record(1, "b")], 2).is_err());
+    }
+
+    #[tokio::test]
+    async fn stores_encrypted_records() {
+        let file = temp_file::empty();
+        let storage = ActionStorage::open(file.path()).unwrap();
+        let mut keys = storage_keys("node", 0);
+
+        assert_eq!(storage.get(&mut keys, "ns", "counter").await.unwrap(), None);
+
+        for r in [record(1, "41"), record(2, "42"), record(1, "40")] {
+            storage
+                .set(&mut keys, "ns", "counter", &r.unwrap())
+                .await
+                .unwrap();
+        }
+        // Stale writes are ignored
+        assert_eq!(
+            storage.get(&mut keys, "ns", "counter").await.unwrap(),
+            record(2, "42")
+        );
+
+        // Values are encrypted under the KDF of the node
+        let mut other = storage_keys("other-node", 0);
+        assert!(storage.get(&mut other, "ns", "counter").await.is_err());
+        let raw: Vec<u8> = Connection::open(file.path())
+            .unwrap()
+            .query_row("SELECT value FROM action_storage", [], |row| row.get(0))
+            .unwrap();
+        assert!(!raw.windows(2).any(|w| w == b"42"));
+
+        // Concurrent writes of the same version converge
+        for value in ["a", "b"] {
+            storage
+                .set(&mut keys, "ns", "key", &record(1, value).unwrap())
+                .await
+                .unwrap();
+        }
+        let winner = storage.get(&mut keys, "ns", "key").await.unwrap();
+        storage
+            .set(&mut keys, "ns", "key", &record(1, "a").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(storage.get(&mut keys, "ns", "key").await.unwrap(), winner);
+    }
+
+    #[tokio::test]
+    async fn reads_values_of_older_key_versions() {
+        let file = temp_file::empty();
+        let storage = ActionStorage::open(file.path()).unwrap();
+
+        storage
+            .set(
+                &mut storage_keys("node", 1),
+                "ns",
+                "old",
+                &record(1, "v1").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // After a KDF rotation, old values are still readable and new ones use the new version
+        let mut rotated = storage_keys("node", 2);
+        assert_eq!(
+            storage.get(&mut rotated, "ns", "old").await.unwrap(),
+            record(1, "v1")
+        );
+        storage
+            .set(&mut rotated, "ns", "new", &record(1, "v2").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get(&mut rotated, "ns", "new").await.unwrap(),
+            record(1, "v2")
+        );
+        assert!(storage
+            .get(&mut storage_keys("node", 1), "ns", "new")
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn shares_one_handle_per_path() {
+        let file = temp_file::empty();
+        let a = ActionStorage::open(file.path()).unwrap();
+        let b = ActionStorage::open(file.path()).unwrap();
+        assert!(Arc::ptr_eq(&a.db, &b.db));
+
+        let other = temp_file::empty();
+        let c = ActionStorage::open(other.path()).unwrap();
+        assert!(!Arc::ptr_eq(&a.db, &c.db));
+    }
+
+    #[tokio::test]
+    async fn lists_keys_and_usage() {
+        let file = temp_file::empty();
+        let storage = ActionStorage::open(file.path()).unwrap();
+        let mut keys = storage_keys("node", 0);
+
+        for (key, value) in [("user:1", "a"), ("user:2", "bb"), ("other", "ccc")] {
+            storage
+                .set(&mut keys, "ns", key, &record(1, value).unwrap())
+                .await
+                .unwrap();
+        }
+        storage
+            .set(&mut keys, "ns", "user:1", &tombstone(2))
+            .await
+            .unwrap();
+
+        assert_eq!(storage.list("ns", "user:").unwrap(), vec!["user:2"]);
+        assert_eq!(storage.list("ns", "").unwrap(), vec!["other", "user:2"]);
+        assert!(storage.list("other ns", "").unwrap().is_empty());
+        assert_eq!(storage.usage("ns").unwrap(), 8 + 8);
+        assert_eq!(
+            storage
+                .usage_after_write("ns", "other", record(1, "ccc").as_ref(), Some("c"))
+                .unwrap(),
+            8 + 6
+        );
+    }
+
+    #[tokio::test]
+    async fn compacts_old_tombstones() {
+        let file = temp_file::empty();
+        let storage = ActionStorage::open(file.path()).unwrap();
+        let mut keys = storage_keys("node", 0);
+
+        storage
+            .set(&mut keys, "ns", "kept", &record(1, "a").unwrap())
+            .await
+            .unwrap();
+        storage
+            .set(&mut keys, "ns", "deleted", &tombstone(2))
+            .await
+            .unwrap();
+
+        // Recent tombstones are kept
+        assert_eq!(storage.compact(TOMBSTONE_TTL).unwrap(), 0);
+        assert_eq!(
+            storage.get(&mut keys, "ns", "deleted").await.unwrap(),
+            Some(tombstone(2))
+        );
+
+        storage
+            .conn()
+            .execute(
+                "UPDATE action_storage SET updated_at = updated_at - ?1",
+                params![TOMBSTONE_TTL.as_secs() + 1],
+            )
+            .unwrap();
+        assert_eq!(storage.compact(TOMBSTONE_TTL).unwrap(), 1);
+        assert_eq!(storage.get(&mut keys, "ns", "deleted").await.unwrap(), None);
+        // Values are never compacted
+        assert_eq!(
+            storage.get(&mut keys, "ns", "kept").await.unwrap(),
+            record(1, "a")
+        );
+    }
+
+    #[test]
+    fn scales_quota_with_rate_limit_nft() {
+        let nft = |requests_per_kilosecond: u64| RateLimitNft {
+            id: U256::one(),
+            requests_per_kilosecond: requests_per_kilosecond.into(),
+            expires_at: U256::zero(),
+            owner: Address::zero(),
+        };
+
+        assert_eq!(quota_bytes(1024, None), 1024);
+        assert_eq!(quota_bytes(1024, Some(&nft(0))), 1024);
+        assert_eq!(quota_bytes(1024, Some(&nft(10))), 10 * 1024);
+    }
+}
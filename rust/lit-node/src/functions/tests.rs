@@ -11,6 +11,7 @@ use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use super::action_client::{Client, ClientBuilder, ExecutionOptions, ExecutionState};
+use super::egress::{EgressPolicy, EgressViolationKind};
 use crate::models;
 
 #[ctor::ctor]
@@ -26,6 +27,14 @@ fn server() -> TestServer {
     TestServer::start()
 }
 
+// Mock servers listen on localhost, which is blocked by default
+fn local_egress_policy() -> EgressPolicy {
+    EgressPolicy {
+        allow_cidrs: vec!["127.0.0.0/8".parse().unwrap()],
+        ..Default::default()
+    }
+}
+
 #[rstest]
 #[tokio::test]
 async fn nop(server: TestServer) {
@@ -198,7 +207,11 @@ async fn http_headers(server: TestServer) {
 #[rstest]
 #[tokio::test]
 async fn fetch(server: TestServer) {
-    let mut client = Client::new(server.socket_path());
+    let mut client = ClientBuilder::default()
+        .socket_path(server.socket_path())
+        .egress_policy(local_egress_policy())
+        .build()
+        .unwrap();
 
     let mock_server = MockServer::start().await;
     Mock::given(method("GET"))
@@ -241,6 +254,7 @@ async fn fetch_invocation_limit(server: TestServer) {
     let mut client = ClientBuilder::default()
         .socket_path(server.socket_path())
         .max_fetch_count(1)
+        .egress_policy(local_egress_policy())
         .build()
         .unwrap();
 
@@ -267,6 +281,103 @@ async fn fetch_invocation_limit(server: TestServer) {
     );
 }
 
+#[rstest]
+#[tokio::test]
+async fn fetch_egress_policy(server: TestServer) {
+    let mut client = Client::new(server.socket_path());
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let code = formatdoc! {r#"
+        (async () => {{
+            await fetch("{uri}")
+        }})()
+        "#,
+        uri = &mock_server.uri()
+    };
+
+    let res = client.execute_js(code).await;
+
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .contains("Egress policy violation: Address 127.0.0.1 is private"));
+    let violation = client.egress_violation().unwrap();
+    assert_eq!(violation.kind, EgressViolationKind::AddressDenied);
+    assert_eq!(violation.url, format!("{}/", mock_server.uri()));
+}
+
+#[rstest]
+#[tokio::test]
+async fn fetch_response_limits(server: TestServer) {
+    let mut client = ClientBuilder::default()
+        .socket_path(server.socket_path())
+        .egress_policy(EgressPolicy {
+            max_response_bytes: 1000,
+            max_total_bytes: 2000,
+            ..local_egress_policy()
+        })
+        .build()
+        .unwrap();
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/small"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("a".repeat(800)))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/large"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("a".repeat(2000)))
+        .mount(&mock_server)
+        .await;
+
+    let code = formatdoc! {r#"
+        (async () => {{
+            await fetch("{uri}/large")
+        }})()
+        "#,
+        uri = &mock_server.uri()
+    };
+
+    let res = client.execute_js(code).await;
+
+    assert!(res.unwrap_err().to_string().contains(&format!(
+        "Egress policy violation: The response from {}/large is larger than 1000 bytes",
+        mock_server.uri()
+    )));
+    assert_eq!(
+        client.egress_violation().unwrap().kind,
+        EgressViolationKind::ResponseTooLarge
+    );
+
+    let code = formatdoc! {r#"
+        (async () => {{
+            for (let i = 0; i < 3; i++) {{
+                const text = await fetch("{uri}/small").then(r => r.text())
+                console.log(text.length)
+            }}
+        }})()
+        "#,
+        uri = &mock_server.uri()
+    };
+
+    let res = client.execute_js(code).await;
+
+    assert!(res.unwrap_err().to_string().contains(
+        "You may not fetch more than 2000 bytes per session and you have attempted to exceed that limit."
+    ));
+    assert_eq!(client.logs(), "800\n800\n");
+    assert_eq!(
+        client.egress_violation().unwrap().kind,
+        EgressViolationKind::TotalBytesExceeded
+    );
+}
+
 #[rstest]
 #[tokio::test]
 async fn call_child(server: TestServer) {
@@ -312,6 +423,7 @@ async fn call_child(server: TestServer) {
         .socket_path(server.socket_path())
         .js_env(env.clone())
         .max_fetch_count(3)
+        .egress_policy(local_egress_policy())
         .build()
         .unwrap();
 
@@ -0,0 +1,521 @@
+//! Helpers for `Lit.Actions.sendTransaction`: filling in missing transaction
+//! fields, tracking nonces across concurrent executions and classifying the
+//! errors returned when broadcasting.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use k256::elliptic_curve::point::AffineCoordinates as _;
+use k256::elliptic_curve::scalar::IsHigh as _;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+/// How many times signing and broadcasting is attempted on nonce collisions
+/// and underpriced replacements.
+pub(crate) const MAX_SEND_ATTEMPTS: u32 = 3;
+
+// Gas estimates are raised a bit, as state can change before inclusion
+const GAS_ESTIMATE_BUFFER_PERCENT: u64 = 20;
+// Most clients require replacements to raise both fees by at least 10%
+const FEE_BUMP_PER_MILLE: u64 = 125;
+// How far above its own estimate a node accepts the max fee chosen by the leader
+const MAX_FEE_ESTIMATE_MULTIPLIER: u64 = 3;
+// How far past the pending transaction count a node accepts the nonce chosen by
+// the leader, to leave room for concurrent executions sending from the same PKP
+const MAX_NONCE_AHEAD: u64 = 16;
+
+lazy_static! {
+    /// The next nonce to use per chain and sender, so that concurrent executions
+    /// sending from the same PKP don't pick a nonce that is already in flight.
+    static ref NEXT_NONCES: Mutex<HashMap<(String, Address), U256>> = Mutex::new(HashMap::new());
+}
+
+/// The leader's decision after broadcasting, shared with the other nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum SendOutcome {
+    Sent(H256),
+    Retry(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SendError {
+    /// The same transaction is already in the mempool.
+    AlreadyKnown,
+    /// The nonce has been used by a mined transaction.
+    NonceTooLow,
+    /// A pending transaction with the same nonce pays higher fees.
+    Underpriced,
+    Other,
+}
+
+pub(crate) fn classify_send_error(message: &str) -> SendError {
+    let message = message.to_lowercase();
+    if message.contains("already known") || message.contains("known transaction") {
+        SendError::AlreadyKnown
+    } else if message.contains("nonce too low") {
+        SendError::NonceTooLow
+    } else if message.contains("underpriced") {
+        SendError::Underpriced
+    } else {
+        SendError::Other
+    }
+}
+
+/// What every node checks the transaction filled in by the leader against
+/// before signing it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TransactionBounds {
+    pub chain_id: u64,
+    /// The pending transaction count of the sender
+    pub min_nonce: U256,
+    /// The gas limit of the latest block
+    pub max_gas: U256,
+    /// A multiple of this node's own fee estimate
+    pub max_fee_per_gas: U256,
+}
+
+/// Read the bounds of a transaction from this node's own provider.
+pub(crate) async fn transaction_bounds(
+    provider: &Provider<Http>,
+    from: Address,
+) -> Result<TransactionBounds> {
+    let chain_id = chain_id(provider).await?;
+    let min_nonce = provider
+        .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+        .await?;
+    let max_gas = provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .map(|block| block.gas_limit)
+        .ok_or_else(|| anyhow::anyhow!("No latest block"))?;
+    let (max_fee, _) = provider.estimate_eip1559_fees(None).await?;
+
+    Ok(TransactionBounds {
+        chain_id,
+        min_nonce,
+        max_gas,
+        max_fee_per_gas: max_fee.saturating_mul(MAX_FEE_ESTIMATE_MULTIPLIER.into()),
+    })
+}
+
+/// Check the transaction filled in by the leader: only the fields left out by
+/// the action may be filled in, and only with values within `bounds`. In
+/// particular a nonce picked by the leader must not be used by a pending
+/// transaction, so that the leader can't replace one.
+pub(crate) fn check_filled_transaction(
+    requested: &Eip1559TransactionRequest,
+    filled: &Eip1559TransactionRequest,
+    from: Address,
+    bounds: &TransactionBounds,
+) -> Result<()> {
+    if filled.from != Some(from)
+        || filled.to != requested.to
+        || filled.value != requested.value
+        || filled.data != requested.data
+        || filled.access_list != requested.access_list
+        || requested.gas.is_some_and(|gas| filled.gas != Some(gas))
+        || requested
+            .nonce
+            .is_some_and(|nonce| filled.nonce != Some(nonce))
+    {
+        bail!("The transaction filled in by the leader does not match the requested one");
+    }
+    if filled.chain_id != Some(bounds.chain_id.into()) {
+        bail!(
+            "The transaction filled in by the leader is for chain id {:?}, expected {}",
+            filled.chain_id,
+            bounds.chain_id
+        );
+    }
+
+    if requested.nonce.is_none() {
+        let nonce = filled.nonce.unwrap_or_default();
+        let max_nonce = bounds.min_nonce + MAX_NONCE_AHEAD;
+        if nonce < bounds.min_nonce || nonce > max_nonce {
+            bail!(
+                "The nonce filled in by the leader ({nonce}) is outside of {} to {max_nonce}",
+                bounds.min_nonce
+            );
+        }
+    }
+
+    let gas = filled.gas.unwrap_or_default();
+    if gas.is_zero() || gas > bounds.max_gas {
+        bail!(
+            "The gas limit filled in by the leader ({gas}) is above the block gas limit ({})",
+            bounds.max_gas
+        );
+    }
+
+    let (Some(max_fee), Some(max_priority_fee)) =
+        (filled.max_fee_per_gas, filled.max_priority_fee_per_gas)
+    else {
+        bail!("The transaction filled in by the leader has no fees");
+    };
+    // Fees set by the action may be raised to replace a pending transaction
+    if let Some(requested_fee) = requested.max_priority_fee_per_gas {
+        if !fee_bumps(requested_fee).contains(&max_priority_fee) {
+            bail!(
+                "The priority fee filled in by the leader ({max_priority_fee}) does not match the requested {requested_fee}"
+            );
+        }
+    }
+    let mut fee_ceiling = bounds.max_fee_per_gas;
+    if let Some(requested_fee) = requested.max_fee_per_gas {
+        fee_ceiling = fee_ceiling.max(*fee_bumps(requested_fee).last().unwrap_or(&requested_fee));
+    }
+    if max_priority_fee > max_fee || max_fee > fee_ceiling {
+        bail!(
+            "The fees filled in by the leader ({max_fee}, priority {max_priority_fee}) are above {fee_ceiling}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Gives back a nonce reserved by `fill_transaction` when dropped, unless the
+/// transaction was broadcast (or the nonce turned out to be used), so that a
+/// failed execution doesn't leave a gap.
+pub(crate) struct NonceReservation {
+    chain: String,
+    from: Address,
+    nonce: Option<U256>,
+}
+
+impl NonceReservation {
+    pub fn new(chain: &str, from: Address) -> Self {
+        Self {
+            chain: chain.to_string(),
+            from,
+            nonce: None,
+        }
+    }
+
+    pub fn hold(&mut self, nonce: U256) {
+        self.nonce = Some(nonce);
+    }
+
+    /// The nonce must not be given back anymore.
+    pub fn keep(&mut self) {
+        self.nonce = None;
+    }
+}
+
+impl Drop for NonceReservation {
+    fn drop(&mut self) {
+        if let Some(nonce) = self.nonce.take() {
+            release_nonce(&self.chain, self.from, nonce);
+        }
+    }
+}
+
+/// Returns the Ethereum address of an uncompressed secp256k1 public key.
+pub(crate) fn address_from_public_key(public_key: &[u8]) -> Result<Address> {
+    if public_key.len() != 65 || public_key[0] != 0x04 {
+        bail!("Expected an uncompressed secp256k1 public key");
+    }
+    Ok(Address::from_slice(
+        &ethers::utils::keccak256(&public_key[1..])[12..],
+    ))
+}
+
+/// Fill in the chain id, EIP-1559 fees, gas and nonce of a transaction that
+/// doesn't specify them. The nonce is reserved last, so that a failed estimate
+/// doesn't leave a gap.
+pub(crate) async fn fill_transaction(
+    provider: &Provider<Http>,
+    chain: &str,
+    from: Address,
+    mut tx: Eip1559TransactionRequest,
+) -> Result<Eip1559TransactionRequest> {
+    let chain_id = chain_id(provider).await?;
+    match tx.chain_id {
+        Some(id) if id.as_u64() != chain_id => {
+            bail!("The transaction is for chain id {id}, but {chain} has chain id {chain_id}")
+        }
+        _ => tx.chain_id = Some(chain_id.into()),
+    }
+    tx.from = Some(from);
+
+    if tx.max_fee_per_gas.is_none() || tx.max_priority_fee_per_gas.is_none() {
+        let (max_fee, max_priority_fee) = provider.estimate_eip1559_fees(None).await?;
+        tx.max_fee_per_gas.get_or_insert(max_fee);
+        tx.max_priority_fee_per_gas.get_or_insert(max_priority_fee);
+    }
+
+    if tx.gas.is_none() {
+        let estimate = provider
+            .estimate_gas(&TypedTransaction::Eip1559(tx.clone()), None)
+            .await?;
+        tx.gas = Some(estimate + estimate * GAS_ESTIMATE_BUFFER_PERCENT / 100);
+    }
+
+    if tx.nonce.is_none() {
+        let pending = provider
+            .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+            .await?;
+        tx.nonce = Some(reserve_nonce(chain, from, pending));
+    }
+
+    Ok(tx)
+}
+
+async fn chain_id(provider: &Provider<Http>) -> Result<u64> {
+    let chain_id = provider.get_chainid().await?;
+    u64::try_from(chain_id).map_err(|_| anyhow::anyhow!("Chain id {chain_id} is out of range"))
+}
+
+/// Raise both fees so that the transaction can replace a pending one.
+pub(crate) fn bump_fees(tx: &mut Eip1559TransactionRequest) {
+    for fee in [&mut tx.max_fee_per_gas, &mut tx.max_priority_fee_per_gas]
+        .into_iter()
+        .flatten()
+    {
+        *fee = bump_fee(*fee);
+    }
+}
+
+fn bump_fee(fee: U256) -> U256 {
+    fee + fee * FEE_BUMP_PER_MILLE / 1000 + 1
+}
+
+/// A fee set by the action, followed by the values it is raised to on retries.
+fn fee_bumps(fee: U256) -> Vec<U256> {
+    std::iter::successors(Some(fee), |fee| Some(bump_fee(*fee)))
+        .take(MAX_SEND_ATTEMPTS as usize)
+        .collect()
+}
+
+fn reserve_nonce(chain: &str, from: Address, pending: U256) -> U256 {
+    let mut nonces = NEXT_NONCES.lock().unwrap_or_else(|e| e.into_inner());
+    let next = nonces.entry((chain.to_string(), from)).or_default();
+    let nonce = (*next).max(pending);
+    *next = nonce + 1;
+    nonce
+}
+
+/// Give back a nonce reserved for a transaction that was never broadcast.
+pub(crate) fn release_nonce(chain: &str, from: Address, nonce: U256) {
+    let mut nonces = NEXT_NONCES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(next) = nonces.get_mut(&(chain.to_string(), from)) {
+        if *next == nonce + 1 {
+            *next = nonce;
+        }
+    }
+}
+
+/// Forget the tracked nonce, so that the next one is read from the chain again.
+pub(crate) fn reset_nonce(chain: &str, from: Address) {
+    let mut nonces = NEXT_NONCES.lock().unwrap_or_else(|e| e.into_inner());
+    nonces.remove(&(chain.to_string(), from));
+}
+
+/// Convert a combined ECDSA signature into a (low-s) Ethereum signature.
+pub(crate) fn eth_signature(
+    big_r: &k256::AffinePoint,
+    s: &k256::Scalar,
+    recovery_id: u8,
+    chain_id: u64,
+) -> Signature {
+    let (s, recovery_id) = match bool::from(s.is_high()) {
+        true => (-*s, recovery_id ^ 1),
+        false => (*s, recovery_id),
+    };
+
+    Signature {
+        r: U256::from_big_endian(&big_r.x()),
+        s: U256::from_big_endian(&s.to_bytes()),
+        v: ethers::utils::to_eip155_v(recovery_id, chain_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_send_errors() {
+        for (message, expected) in [
+            ("already known", SendError::AlreadyKnown),
+            ("Known transaction: 0x1234", SendError::AlreadyKnown),
+            (
+                "nonce too low: next nonce 5, tx nonce 4",
+                SendError::NonceTooLow,
+            ),
+            (
+                "replacement transaction underpriced",
+                SendError::Underpriced,
+            ),
+            ("transaction underpriced", SendError::Underpriced),
+            (
+                "insufficient funds for gas * price + value",
+                SendError::Other,
+            ),
+        ] {
+            assert_eq!(classify_send_error(message), expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn reserves_distinct_nonces() {
+        let from = Address::random();
+
+        assert_eq!(reserve_nonce("test", from, 5.into()), 5.into());
+        assert_eq!(reserve_nonce("test", from, 5.into()), 6.into());
+        // The chain is ahead, e.g. transactions were sent elsewhere
+        assert_eq!(reserve_nonce("test", from, 9.into()), 9.into());
+
+        // Only the most recent reservation can be given back
+        release_nonce("test", from, 8.into());
+        assert_eq!(reserve_nonce("test", from, 0.into()), 10.into());
+        release_nonce("test", from, 10.into());
+        assert_eq!(reserve_nonce("test", from, 0.into()), 10.into());
+
+        reset_nonce("test", from);
+        assert_eq!(reserve_nonce("test", from, 3.into()), 3.into());
+    }
+
+    #[test]
+    fn releases_unkept_nonce_reservations() {
+        let from = Address::random();
+
+        let nonce = reserve_nonce("test", from, 5.into());
+        NonceReservation::new("test", from).hold(nonce);
+        assert_eq!(reserve_nonce("test", from, 0.into()), 5.into());
+
+        let mut reservation = NonceReservation::new("test", from);
+        reservation.hold(6.into());
+        reservation.keep();
+        drop(reservation);
+        assert_eq!(reserve_nonce("test", from, 0.into()), 6.into());
+    }
+
+    fn filled(requested: &Eip1559TransactionRequest, from: Address) -> Eip1559TransactionRequest {
+        let mut tx = requested.clone();
+        tx.from = Some(from);
+        tx.chain_id = Some(1.into());
+        tx.nonce.get_or_insert(0.into());
+        tx.gas.get_or_insert(21_000.into());
+        tx.max_fee_per_gas.get_or_insert(100.into());
+        tx.max_priority_fee_per_gas.get_or_insert(10.into());
+        tx
+    }
+
+    #[test]
+    fn checks_filled_transactions() {
+        let from = Address::random();
+        let bounds = TransactionBounds {
+            chain_id: 1,
+            min_nonce: 0.into(),
+            max_gas: 30_000_000.into(),
+            max_fee_per_gas: 300.into(),
+        };
+        let requested = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .value(1);
+
+        let tx = filled(&requested, from);
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_ok());
+
+        let mut tx = filled(&requested, from);
+        tx.value = Some(2.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        let mut tx = filled(&requested, from);
+        tx.chain_id = Some(5.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        let mut tx = filled(&requested, from);
+        tx.gas = Some(30_000_001.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        let mut tx = filled(&requested, from);
+        tx.max_fee_per_gas = Some(301.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        let mut tx = filled(&requested, from);
+        tx.max_priority_fee_per_gas = Some(101.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        // Fees set by the action may be bumped past the estimate
+        let requested = requested
+            .max_fee_per_gas(1000)
+            .max_priority_fee_per_gas(100);
+        let mut tx = filled(&requested, from);
+        bump_fees(&mut tx);
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_ok());
+
+        // A priority fee set by the action can only be bumped
+        let mut tx = filled(&requested, from);
+        tx.max_priority_fee_per_gas = Some(99.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        // A gas limit set by the action can't be changed
+        let requested = requested.gas(50_000);
+        let mut tx = filled(&requested, from);
+        tx.gas = Some(60_000.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+    }
+
+    #[test]
+    fn checks_filled_nonces() {
+        let from = Address::random();
+        let bounds = TransactionBounds {
+            chain_id: 1,
+            min_nonce: 5.into(),
+            max_gas: 30_000_000.into(),
+            max_fee_per_gas: 300.into(),
+        };
+        let requested = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .value(1);
+
+        let mut tx = filled(&requested, from);
+        tx.nonce = Some(5.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_ok());
+
+        // The leader can't pick the nonce of a pending transaction
+        tx.nonce = Some(4.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        tx.nonce = Some((5 + MAX_NONCE_AHEAD + 1).into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+
+        // A nonce set by the action may replace a pending transaction, but can't be changed
+        let requested = requested.nonce(2);
+        let tx = filled(&requested, from);
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_ok());
+
+        let mut tx = filled(&requested, from);
+        tx.nonce = Some(3.into());
+        assert!(check_filled_transaction(&requested, &tx, from, &bounds).is_err());
+    }
+
+    #[test]
+    fn bumps_fees_for_replacement() {
+        let mut tx = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(1000)
+            .max_priority_fee_per_gas(100);
+
+        bump_fees(&mut tx);
+
+        assert_eq!(tx.max_fee_per_gas, Some(1126.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(113.into()));
+    }
+
+    #[test]
+    fn derives_address_from_public_key() {
+        let key = k256::ecdsa::SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = key.verifying_key().to_encoded_point(false);
+
+        assert_eq!(
+            address_from_public_key(public_key.as_bytes()).unwrap(),
+            ethers::utils::secret_key_to_address(&key)
+        );
+        assert!(address_from_public_key(&public_key.as_bytes()[1..]).is_err());
+    }
+}
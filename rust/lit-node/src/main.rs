@@ -6,6 +6,7 @@ extern crate rocket;
 extern crate clap;
 
 use crate::config::load_cfg;
+use crate::endpoints::admin::quorum::AdminQuorum;
 use crate::error::{unexpected_err_code, EC};
 use crate::models::AuthContextCacheExpiry;
 use crate::p2p_comms::web::grpc_transmissions::launch_chatter_server;
@@ -269,6 +270,9 @@ pub fn main() {
         .expect("`enter_restore_state` is set true but the RestoreState constructor failed.");
     let restore_state = Arc::new(RwLock::new(restore_state));
 
+    let admin_quorum = Arc::new(AdminQuorum::new());
+    let handshake_keys = Arc::new(utils::rocket::sealed_response::HandshakeKeys::new());
+
     let fsm_worker_metadata: Arc<dyn FSMWorkerMetadata<LifecycleId = u64>> =
         Arc::new(CounterBasedFSMWorkerMetadata::new());
 
@@ -289,7 +293,6 @@ pub fn main() {
 
     let peer_state_clone = peer_state.clone();
     let comms_cfg = cfg.load_full();
-    let client = http_client.clone();
     let client_rt = tokio::runtime::Builder::new_multi_thread()
         .thread_name("comms-tasks")
         .worker_threads(32 * num_cpus::get_physical())
@@ -302,7 +305,6 @@ pub fn main() {
             comms_cfg.clone(),
             peer_state_clone,
             rx_batch_manager,
-            client,
         ));
     });
 
@@ -381,6 +383,8 @@ pub fn main() {
         let fsm_worker_metadata = fsm_worker_metadata.clone();
         let file_tx_clone = file_tx.clone();
         let ipfs_cache = ipfs_cache.clone();
+        let admin_quorum = admin_quorum.clone();
+        let handshake_keys = handshake_keys.clone();
         Box::pin(async move {
             #[allow(unused_mut)]
             let mut l = Launcher::try_new(cfg.clone(), Some(file_tx_clone))
@@ -400,6 +404,9 @@ pub fn main() {
                         ));
                     })
                 }))
+                .attach(AdHoc::on_response("Sealed Responses", |req, resp| {
+                    Box::pin(utils::rocket::sealed_response::seal_response(req, resp))
+                }))
                 .manage(cfg)
                 .manage(resolver)
                 .manage(peer_state)
@@ -416,7 +423,9 @@ pub fn main() {
                     catchers![bad_input_data_catcher, internal_server_error_catcher],
                 )
                 .manage(tss_state)
-                .manage(restore_state);
+                .manage(restore_state)
+                .manage(admin_quorum)
+                .manage(handshake_keys);
 
             #[cfg(feature = "rtmetrics")]
             {
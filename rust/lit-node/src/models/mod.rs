@@ -23,6 +23,10 @@ use webauthn_rs_core::proto::PublicKeyCredential;
 use xor_name::XorName;
 
 use crate::auth::auth_material::{AuthSigItem, JsonAuthSig};
+use crate::endpoints::admin::config_patch::ConfigPatchOp;
+use crate::endpoints::admin::quorum::AdminOperation;
+use crate::rate_limiting::usage::UsageExportFormat;
+use crate::tss::common::verification_key::VerificationKeyShare;
 use crate::tss::dkg::curves::common::CurveType;
 
 pub mod auth;
@@ -34,6 +38,8 @@ pub mod webauthn_signature_verification_material;
 #[serde(rename_all = "camelCase")]
 pub struct AdminAuth {
     pub auth_sig: JsonAuthSig,
+    #[serde(default)]
+    pub proposal_id: Option<String>,
 }
 
 impl<'r> FromFormField<'r> for AdminAuth {
@@ -57,6 +63,56 @@ pub struct JsonAdminSetRequest {
     pub new_config: HashMap<String, String>,
     pub rpc_config: RpcConfig,
     pub auth_sig: JsonAuthSig,
+    #[serde(default)]
+    pub proposal_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAdminConfigPatchRequest {
+    pub auth_sig: JsonAuthSig,
+    pub ops: Vec<ConfigPatchOp>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub proposal_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAdminConfigRollbackRequest {
+    pub auth_sig: JsonAuthSig,
+    pub version: u64,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub proposal_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAdminProposalCreateRequest {
+    pub auth_sig: JsonAuthSig,
+    pub operation: AdminOperation,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAdminProposalApproveRequest {
+    pub auth_sig: JsonAuthSig,
+    pub proposal_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAdminUsageExportRequest {
+    pub auth_sig: JsonAuthSig,
+    /// Start of the window (unix seconds, inclusive).
+    pub from: u64,
+    /// End of the window (unix seconds, exclusive).
+    pub to: u64,
+    #[serde(default)]
+    pub format: UsageExportFormat,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -224,6 +280,9 @@ pub struct JsonExecutionRequest {
     pub auth_methods: Option<Vec<AuthMethod>>,
     #[serde(default = "default_epoch")]
     pub epoch: u64,
+    /// Whether the code is TypeScript; detected from the code if unset.
+    #[serde(default)]
+    pub typescript: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -310,6 +369,14 @@ pub struct JsonSDKHandshakeResponse {
     pub hd_root_pubkeys: Vec<String>,
     pub attestation: Option<Attestation>,
     pub latest_blockhash: String,
+    /// This node's shares of the BLS and ECDSA root keys.  BLS signature shares
+    /// can be verified with them one by one, ECDSA ones only once combined.
+    #[serde(default)]
+    pub verification_key_shares: Vec<VerificationKeyShare>,
+    /// Send in the `X-Lit-Sealing-Key-Id` header to have response shares
+    /// sealed to the client public key of this handshake.
+    #[serde(default)]
+    pub sealing_key_id: Option<String>,
 }
 
 // #[derive(Debug, Serialize, Deserialize)]
@@ -376,6 +443,17 @@ pub struct EncryptionSignRequest {
     pub epoch: u64,
 }
 
+/// Requests the signature share of a time lock, given either a beacon round or
+/// a Unix time in seconds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeLockSignRequest {
+    pub round: Option<u64>,
+    pub timestamp: Option<u64>,
+    #[serde(default = "default_epoch")]
+    pub epoch: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EncryptionSignResponse {
@@ -440,6 +518,7 @@ pub enum UnifiedAccessControlCondition {
     SolRpcCondition(SolRpcConditionV2Options),
     EVMContractCondition(EVMContractCondition),
     CosmosCondition(CosmosCondition),
+    BitcoinCondition(BitcoinCondition),
 }
 
 pub type CosmosConditionItem = ControlConditionItem<CosmosCondition>;
@@ -454,6 +533,17 @@ pub struct CosmosCondition {
     pub return_value_test: JsonReturnValueTestV2,
 }
 
+pub type BitcoinConditionItem = ControlConditionItem<BitcoinCondition>;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BitcoinCondition {
+    pub chain: String,
+    pub method: String,
+    pub parameters: Vec<String>,
+    pub return_value_test: JsonReturnValueTestV2,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CosmosBlock {
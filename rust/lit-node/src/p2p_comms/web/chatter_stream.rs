@@ -0,0 +1,647 @@
+//! Persistent bidirectional streams for node chatter.
+//!
+//! Each node keeps one `StreamDirect` stream open to every peer it sends to.
+//! Entries are queued per peer and sent in frames of up to `MAX_FRAME_ENTRIES`
+//! entries, with the values compressed when that makes the frame smaller.  The
+//! receiver acknowledges every frame it has read, dropping (and logging) any
+//! entries it can't handle, as those would fail again when resent.  At most
+//! `MAX_UNACKED_FRAMES` frames are in flight.  Once a peer's queue is full,
+//! further entries for it wait for room in the background for up to the round
+//! timeout, so that a slow peer doesn't hold up the others.
+//!
+//! Frames that haven't been acknowledged are resent when the stream is
+//! reconnected after a transport error.  Every entry carries a sequence number within its round, so the
+//! receiver drops entries it has already delivered.  Peers that don't support
+//! streams yet are sent one entry per `SendDirectGrpc` call.
+
+use crate::config::{LitNodeConfig, CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT};
+use crate::error::{unexpected_err, Result};
+use crate::p2p_comms::web::grpc_transmissions::chatter::{
+    chatter_service_client::ChatterServiceClient, ChatterFrame,
+};
+use crate::peers::peer_state::models::SimplePeer;
+use crate::peers::PeerState;
+use crate::tasks::batch_transmissions::{
+    complain_unresponsive, connect_chatter_client, send_direct_grpc,
+};
+use crate::tss::common::models::{
+    NodeTransmissionDetails, NodeTransmissionFrame, SequencedTransmissionEntry,
+};
+use crate::utils;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use lit_core::config::LitConfig;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+use tonic::Code;
+
+/// How many entries can be queued for a peer before further entries have to wait.
+const MAX_QUEUED_ENTRIES: usize = 4096;
+const MAX_UNACKED_FRAMES: usize = 64;
+const MAX_FRAME_ENTRIES: usize = 256;
+const MAX_FRAME_BYTES: usize = 4 * 1024 * 1024;
+const COMPRESSION_THRESHOLD_BYTES: usize = 1024;
+const MAX_DECOMPRESSED_VALUE_BYTES: u64 = 64 * 1024 * 1024;
+/// How often a stream is reconnected without any frame being acknowledged,
+/// before the peer is considered unresponsive and its entries are discarded.
+const MAX_RESUME_ATTEMPTS: u32 = 3;
+const RESUME_DELAY: Duration = Duration::from_secs(1);
+/// Senders forget the sequence numbers of rounds idle for this long.
+const SENDER_SEQUENCE_TTL: Duration = Duration::from_secs(600);
+/// Receivers forget rounds sooner than senders, so that a round whose sequence
+/// numbers restart at zero is never mistaken for a replay.
+const RECEIVER_SEQUENCE_TTL: Duration = Duration::from_secs(300);
+
+/// The sending half of the stream to a peer, owned by the batch worker.
+pub(crate) struct PeerStream {
+    tx: flume::Sender<NodeTransmissionDetails>,
+    last_used: Instant,
+}
+
+impl PeerStream {
+    pub fn spawn(lit_config: Arc<LitConfig>, peer_state: Arc<PeerState>, peer: SimplePeer) -> Self {
+        let (tx, rx) = flume::bounded(MAX_QUEUED_ENTRIES);
+        tokio::spawn(run_peer_stream(lit_config, peer_state, peer, rx));
+        Self {
+            tx,
+            last_used: Instant::now(),
+        }
+    }
+
+    /// Queue an entry for the peer without waiting.  If the queue is full the
+    /// entry waits for room in the background for up to `timeout`, after which
+    /// its round has timed out anyway.  Fails if the stream task has stopped.
+    pub fn send(
+        &mut self,
+        transmission_details: NodeTransmissionDetails,
+        timeout: Duration,
+    ) -> std::result::Result<(), flume::SendError<NodeTransmissionDetails>> {
+        self.last_used = Instant::now();
+        let details = match self.tx.try_send(transmission_details) {
+            Ok(()) => return Ok(()),
+            Err(flume::TrySendError::Disconnected(details)) => {
+                return Err(flume::SendError(details))
+            }
+            Err(flume::TrySendError::Full(details)) => details,
+        };
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let dest_addr = details.dest_peer.socket_address.clone();
+            let round = details.round.clone();
+            match tokio::time::timeout(timeout, tx.send_async(details)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(_)) => error!(
+                    "Chatter stream task to {} has stopped, failed to send entry for round {}",
+                    dest_addr, round
+                ),
+                Err(_) => error!(
+                    "Chatter queue to {} stayed full for {:?}, failed to send entry for round {}",
+                    dest_addr, timeout, round
+                ),
+            }
+        });
+        Ok(())
+    }
+
+    pub fn is_idle(&self, timeout: Duration) -> bool {
+        self.last_used.elapsed() > timeout
+    }
+}
+
+enum StreamEnd {
+    /// The batch worker dropped the stream.
+    Closed,
+    /// The peer doesn't implement `StreamDirect`.
+    Unsupported,
+    Failed(String),
+}
+
+struct SenderState {
+    sender_id: String,
+    stream_id: u64,
+    next_frame_seq: u64,
+    unacked: VecDeque<ChatterFrame>,
+    // The time the oldest unacknowledged frame has been waiting since
+    waiting_since: Instant,
+    acked_since_connect: bool,
+    sequences: HashMap<String, (u64, Instant)>,
+    last_prune: Instant,
+}
+
+impl SenderState {
+    fn new(sender_id: String) -> Self {
+        Self {
+            sender_id,
+            stream_id: rand::random(),
+            next_frame_seq: 0,
+            unacked: VecDeque::new(),
+            waiting_since: Instant::now(),
+            acked_since_connect: false,
+            sequences: HashMap::new(),
+            last_prune: Instant::now(),
+        }
+    }
+
+    fn next_seq(&mut self, scope: &str) -> u64 {
+        let now = Instant::now();
+        if now.duration_since(self.last_prune) > SENDER_SEQUENCE_TTL / 4 {
+            self.sequences
+                .retain(|_, (_, last_used)| now.duration_since(*last_used) < SENDER_SEQUENCE_TTL);
+            self.last_prune = now;
+        }
+
+        let (next, last_used) = self.sequences.entry(scope.to_string()).or_insert((0, now));
+        let seq = *next;
+        *next += 1;
+        *last_used = now;
+        seq
+    }
+
+    /// Batch the given entry with whatever else is queued into an encrypted frame.
+    fn next_frame(
+        &mut self,
+        first: NodeTransmissionDetails,
+        rx: &flume::Receiver<NodeTransmissionDetails>,
+        peer_state: &PeerState,
+        dest_addr: &str,
+    ) -> Result<ChatterFrame> {
+        let mut bytes = first.node_transmission_entry.value.len();
+        let mut entries = vec![first.node_transmission_entry];
+        while entries.len() < MAX_FRAME_ENTRIES && bytes < MAX_FRAME_BYTES {
+            match rx.try_recv() {
+                Ok(details) => {
+                    bytes += details.node_transmission_entry.value.len();
+                    entries.push(details.node_transmission_entry);
+                }
+                Err(_) => break,
+            }
+        }
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| SequencedTransmissionEntry {
+                seq: self.next_seq(&entry.key),
+                entry,
+            })
+            .collect();
+        let encrypted_frame = utils::serde_encrypt::encrypt_and_serialize(
+            peer_state,
+            dest_addr,
+            &pack_frame(entries),
+        )?;
+
+        let frame = ChatterFrame {
+            sender_id: self.sender_id.clone(),
+            stream_id: self.stream_id,
+            frame_seq: self.next_frame_seq,
+            encrypted_frame,
+        };
+        self.next_frame_seq += 1;
+        if self.unacked.is_empty() {
+            self.waiting_since = Instant::now();
+        }
+        self.unacked.push_back(frame.clone());
+        Ok(frame)
+    }
+
+    fn ack(&mut self, frame_seq: u64) {
+        while self
+            .unacked
+            .front()
+            .is_some_and(|frame| frame.frame_seq <= frame_seq)
+        {
+            self.unacked.pop_front();
+        }
+        self.waiting_since = Instant::now();
+        self.acked_since_connect = true;
+    }
+}
+
+async fn run_peer_stream(
+    lit_config: Arc<LitConfig>,
+    peer_state: Arc<PeerState>,
+    peer: SimplePeer,
+    rx: flume::Receiver<NodeTransmissionDetails>,
+) {
+    let timeout = Duration::from_millis(
+        lit_config
+            .ecdsa_round_timeout()
+            .unwrap_or(CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT) as u64,
+    );
+    let mut state = SenderState::new(peer_state.addr.clone());
+    let mut next = None;
+    let mut failed_attempts = 0;
+
+    loop {
+        // Only (re)connect once there is something to send
+        if state.unacked.is_empty() && next.is_none() {
+            match rx.recv_async().await {
+                Ok(details) => next = Some(details),
+                Err(_) => return,
+            }
+        }
+
+        let client = match connect_chatter_client(&lit_config, &peer.socket_address).await {
+            Ok(client) => client,
+            Err(e) => {
+                error!(
+                    "Problem connecting to node #{} ({}): {:?}",
+                    peer.share_index, peer.socket_address, e
+                );
+                complain_unresponsive(&peer_state, &peer).await;
+                discard(&mut state, &mut next, &rx);
+                continue;
+            }
+        };
+
+        let end = stream_to_peer(
+            &mut state,
+            &mut next,
+            client.clone(),
+            &rx,
+            &peer_state,
+            &peer,
+            timeout,
+        )
+        .await;
+        match end {
+            StreamEnd::Closed => return,
+            StreamEnd::Unsupported => {
+                info!(
+                    "Node {} doesn't support chatter streams, sending entries one at a time",
+                    peer.socket_address
+                );
+                send_unary(client, next, rx, peer_state).await;
+                return;
+            }
+            StreamEnd::Failed(reason) => {
+                if state.acked_since_connect {
+                    failed_attempts = 0;
+                }
+                failed_attempts += 1;
+                warn!(
+                    "Chatter stream to {} failed ({} unacknowledged frames, attempt {}): {}",
+                    peer.socket_address,
+                    state.unacked.len(),
+                    failed_attempts,
+                    reason
+                );
+                if failed_attempts >= MAX_RESUME_ATTEMPTS {
+                    complain_unresponsive(&peer_state, &peer).await;
+                    discard(&mut state, &mut next, &rx);
+                    failed_attempts = 0;
+                } else {
+                    tokio::time::sleep(RESUME_DELAY).await;
+                }
+            }
+        }
+    }
+}
+
+async fn stream_to_peer(
+    state: &mut SenderState,
+    next: &mut Option<NodeTransmissionDetails>,
+    mut client: ChatterServiceClient<Channel>,
+    rx: &flume::Receiver<NodeTransmissionDetails>,
+    peer_state: &PeerState,
+    peer: &SimplePeer,
+    timeout: Duration,
+) -> StreamEnd {
+    // The window is never larger than the channel, so these never block
+    let (frame_tx, frame_rx) = flume::bounded(MAX_UNACKED_FRAMES);
+    for frame in state.unacked.iter() {
+        let _ = frame_tx.try_send(frame.clone());
+    }
+    state.waiting_since = Instant::now();
+    state.acked_since_connect = false;
+
+    let response = tokio::time::timeout(timeout, client.stream_direct(frame_rx.into_stream()));
+    let mut acks = match response.await {
+        Ok(Ok(response)) => response.into_inner(),
+        Ok(Err(status)) if status.code() == Code::Unimplemented => return StreamEnd::Unsupported,
+        Ok(Err(status)) => return StreamEnd::Failed(status.to_string()),
+        Err(_) => return StreamEnd::Failed("timed out opening the stream".into()),
+    };
+
+    loop {
+        let ack_deadline = tokio::time::Instant::from_std(state.waiting_since + timeout);
+        tokio::select! {
+            ack = acks.message() => match ack {
+                Ok(Some(ack)) => state.ack(ack.frame_seq),
+                Ok(None) => return StreamEnd::Failed("stream closed by peer".into()),
+                Err(status) => return StreamEnd::Failed(status.to_string()),
+            },
+            _ = tokio::time::sleep_until(ack_deadline), if !state.unacked.is_empty() => {
+                return StreamEnd::Failed("timed out waiting for an acknowledgement".into());
+            }
+            details = next_details(next, rx), if state.unacked.len() < MAX_UNACKED_FRAMES => {
+                let Some(details) = details else {
+                    return StreamEnd::Closed;
+                };
+                let frame = match state.next_frame(details, rx, peer_state, &peer.socket_address) {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        error!("Error creating chatter frame for {}: {:?}", peer.socket_address, e);
+                        continue;
+                    }
+                };
+                if frame_tx.send_async(frame).await.is_err() {
+                    return StreamEnd::Failed("request stream closed".into());
+                }
+            }
+        }
+    }
+}
+
+async fn next_details(
+    next: &mut Option<NodeTransmissionDetails>,
+    rx: &flume::Receiver<NodeTransmissionDetails>,
+) -> Option<NodeTransmissionDetails> {
+    match next.take() {
+        Some(details) => Some(details),
+        None => rx.recv_async().await.ok(),
+    }
+}
+
+fn discard(
+    state: &mut SenderState,
+    next: &mut Option<NodeTransmissionDetails>,
+    rx: &flume::Receiver<NodeTransmissionDetails>,
+) {
+    let discarded = state.unacked.len() + usize::from(next.take().is_some()) + rx.drain().count();
+    state.unacked.clear();
+    if discarded > 0 {
+        warn!("Discarded {} queued frames and entries", discarded);
+    }
+}
+
+async fn send_unary(
+    client: ChatterServiceClient<Channel>,
+    mut next: Option<NodeTransmissionDetails>,
+    rx: flume::Receiver<NodeTransmissionDetails>,
+    peer_state: Arc<PeerState>,
+) {
+    loop {
+        let transmission_details = match next.take() {
+            Some(details) => details,
+            None => match rx.recv_async().await {
+                Ok(details) => details,
+                Err(_) => return,
+            },
+        };
+        let client = client.clone();
+        let peer_state = peer_state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = send_direct_grpc(&transmission_details, &peer_state, client).await {
+                error!(
+                    "Error sending direct grpc transmission to {:?}: {}",
+                    transmission_details.dest_peer.socket_address, e
+                );
+            }
+        });
+    }
+}
+
+/// Compress the entry values, unless that doesn't make them smaller.
+pub(crate) fn pack_frame(entries: Vec<SequencedTransmissionEntry>) -> NodeTransmissionFrame {
+    let size: usize = entries.iter().map(|e| e.entry.value.len()).sum();
+    if size >= COMPRESSION_THRESHOLD_BYTES {
+        let compressed = entries
+            .iter()
+            .map(|e| {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+                encoder.write_all(&e.entry.value)?;
+                let mut compressed = e.clone();
+                compressed.entry.value = encoder.finish()?;
+                Ok(compressed)
+            })
+            .collect::<std::io::Result<Vec<_>>>();
+        match compressed {
+            Ok(compressed)
+                if compressed
+                    .iter()
+                    .map(|e| e.entry.value.len())
+                    .sum::<usize>()
+                    < size =>
+            {
+                return NodeTransmissionFrame {
+                    entries: compressed,
+                    compressed: true,
+                };
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Error compressing chatter frame: {:?}", e),
+        }
+    }
+
+    NodeTransmissionFrame {
+        entries,
+        compressed: false,
+    }
+}
+
+pub(crate) fn unpack_frame(
+    frame: NodeTransmissionFrame,
+) -> Result<Vec<SequencedTransmissionEntry>> {
+    if !frame.compressed {
+        return Ok(frame.entries);
+    }
+
+    frame
+        .entries
+        .into_iter()
+        .map(|mut e| {
+            let mut value = Vec::new();
+            DeflateDecoder::new(e.entry.value.as_slice())
+                .take(MAX_DECOMPRESSED_VALUE_BYTES)
+                .read_to_end(&mut value)
+                .map_err(|err| {
+                    unexpected_err(err, Some("Unable to decompress chatter entry".into()))
+                })?;
+            e.entry.value = value;
+            Ok(e)
+        })
+        .collect()
+}
+
+/// Tracks the next expected sequence number of every round per sender stream,
+/// to drop entries that were resent after a reconnect.
+#[derive(Default)]
+pub(crate) struct SequenceTracker {
+    next: HashMap<(String, u64, String), (u64, Instant)>,
+    last_prune: Option<Instant>,
+}
+
+impl SequenceTracker {
+    /// Returns whether the entry has already been delivered.
+    pub fn is_delivered(&self, sender_id: &str, stream_id: u64, scope: &str, seq: u64) -> bool {
+        self.next
+            .get(&(sender_id.to_string(), stream_id, scope.to_string()))
+            .is_some_and(|(next, _)| seq < *next)
+    }
+
+    /// Returns whether the entry hasn't been delivered before.
+    pub fn accept(&mut self, sender_id: &str, stream_id: u64, scope: &str, seq: u64) -> bool {
+        let now = Instant::now();
+        if self
+            .last_prune
+            .map_or(true, |t| now.duration_since(t) > RECEIVER_SEQUENCE_TTL / 4)
+        {
+            self.next
+                .retain(|_, (_, last_used)| now.duration_since(*last_used) < RECEIVER_SEQUENCE_TTL);
+            self.last_prune = Some(now);
+        }
+
+        match self
+            .next
+            .entry((sender_id.to_string(), stream_id, scope.to_string()))
+        {
+            // Rounds may be joined at any point, e.g. after the state was pruned
+            Entry::Vacant(e) => {
+                e.insert((seq + 1, now));
+                true
+            }
+            Entry::Occupied(mut e) => {
+                let (next, last_used) = e.get_mut();
+                *last_used = now;
+                if seq < *next {
+                    return false;
+                }
+                if seq > *next {
+                    debug!(
+                        "Missing entries {}..{} of {} from {}",
+                        next, seq, scope, sender_id
+                    );
+                }
+                *next = seq + 1;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tss::common::models::NodeTransmissionEntry;
+
+    fn entry(key: &str, seq: u64, value: Vec<u8>) -> SequencedTransmissionEntry {
+        SequencedTransmissionEntry {
+            seq,
+            entry: NodeTransmissionEntry {
+                key: key.to_string(),
+                src_index: 0,
+                dest_index: 1,
+                value,
+                timestamp: 0,
+            },
+        }
+    }
+
+    fn details(round: &str) -> NodeTransmissionDetails {
+        NodeTransmissionDetails {
+            dest_peer: SimplePeer {
+                socket_address: "127.0.0.1:7470".to_string(),
+                share_index: 0,
+                protocol_index: Some(1),
+                staker_address: ethers::types::H160::zero(),
+                key_hash: 0,
+                kicked: false,
+                version: semver::Version::new(0, 0, 0),
+            },
+            round: round.to_string(),
+            node_transmission_entry: entry(round, 0, vec![]).entry,
+        }
+    }
+
+    #[tokio::test]
+    async fn waits_for_room_in_full_queues() {
+        let (tx, rx) = flume::bounded(1);
+        let mut stream = PeerStream {
+            tx,
+            last_used: Instant::now(),
+        };
+
+        assert!(stream.send(details("1"), Duration::from_secs(5)).is_ok());
+        // Queued in the background rather than dropped
+        assert!(stream.send(details("2"), Duration::from_secs(5)).is_ok());
+        assert_eq!(rx.recv_async().await.unwrap().round, "1");
+        assert_eq!(rx.recv_async().await.unwrap().round, "2");
+
+        drop(rx);
+        assert!(stream.send(details("3"), Duration::from_secs(5)).is_err());
+    }
+
+    #[test]
+    fn compresses_large_frames() {
+        let entries = vec![entry("a", 0, vec![7; 4096]), entry("b", 0, vec![1, 2, 3])];
+
+        let frame = pack_frame(entries.clone());
+        assert!(frame.compressed);
+        assert!(frame.entries[0].entry.value.len() < 4096);
+        assert_eq!(unpack_frame(frame).unwrap(), entries);
+
+        let small = vec![entry("a", 0, vec![7; 16])];
+        let frame = pack_frame(small.clone());
+        assert!(!frame.compressed);
+        assert_eq!(unpack_frame(frame).unwrap(), small);
+    }
+
+    #[test]
+    fn skips_compression_without_gain() {
+        let random: Vec<u8> = (0..4096).map(|_| rand::random()).collect();
+        let entries = vec![entry("a", 0, random)];
+
+        let frame = pack_frame(entries.clone());
+        assert!(!frame.compressed);
+        assert_eq!(frame.entries, entries);
+    }
+
+    #[test]
+    fn numbers_entries_per_round() {
+        let mut state = SenderState::new("sender".into());
+
+        assert_eq!(state.next_seq("DKG--0-1-1"), 0);
+        assert_eq!(state.next_seq("DKG--0-1-1"), 1);
+        assert_eq!(state.next_seq("DKG--0-1-2"), 0);
+        assert_eq!(state.next_seq("DKG--0-1-1"), 2);
+    }
+
+    #[test]
+    fn drops_resent_entries() {
+        let mut tracker = SequenceTracker::default();
+
+        assert!(tracker.accept("a", 1, "round", 0));
+        assert!(tracker.accept("a", 1, "round", 1));
+        // Resent after a reconnect
+        assert!(!tracker.accept("a", 1, "round", 0));
+        assert!(!tracker.accept("a", 1, "round", 1));
+        assert!(tracker.accept("a", 1, "round", 2));
+
+        // Other rounds, streams and senders are independent
+        assert!(tracker.accept("a", 1, "other", 0));
+        assert!(tracker.accept("a", 2, "round", 0));
+        assert!(tracker.accept("b", 1, "round", 0));
+
+        // Gaps are accepted, entries before them are not
+        assert!(tracker.accept("a", 1, "round", 5));
+        assert!(!tracker.accept("a", 1, "round", 4));
+    }
+
+    #[test]
+    fn delivered_only_once_accepted() {
+        let mut tracker = SequenceTracker::default();
+
+        assert!(!tracker.is_delivered("a", 1, "round", 0));
+        // e.g. the stream broke before the entry was handled, so the resend is
+        assert!(!tracker.is_delivered("a", 1, "round", 0));
+        assert!(tracker.accept("a", 1, "round", 0));
+        assert!(tracker.is_delivered("a", 1, "round", 0));
+        assert!(!tracker.is_delivered("a", 1, "round", 1));
+        assert!(!tracker.is_delivered("a", 2, "round", 0));
+    }
+}
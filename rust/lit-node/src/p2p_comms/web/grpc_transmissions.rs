@@ -1,21 +1,25 @@
+use crate::p2p_comms::web::chatter_stream::{unpack_frame, SequenceTracker};
 use crate::p2p_comms::web::grpc_transmissions::chatter::{
     chatter_service_server::ChatterService, chatter_service_server::ChatterServiceServer,
-    NodeShareRequestProto, NodeShareResponseProto,
+    ChatterAck, ChatterFrame, NodeShareRequestProto, NodeShareResponseProto,
 };
 use crate::p2p_comms::web::internal::handle_node_share_set;
 use crate::tasks::batch_transmissions::INTERNAL_CHATTER_PORT_OFFSET;
-use crate::tss::common::models::NodeTransmissionEntry;
+use crate::tss::common::models::{NodeTransmissionEntry, NodeTransmissionFrame};
 use crate::tss::common::traits::fsm_worker_metadata::FSMWorkerMetadata;
 use crate::tss::common::tss_state::TssState;
 use crate::utils;
+use futures::Stream;
 use lit_api_core::config::LitApiConfig;
 use lit_core::config::LitConfig;
 use std::fs;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tonic::transport::{Server, ServerTlsConfig};
-use tonic::{self, Code, Status};
+use tonic::{self, Code, Status, Streaming};
 use xor_name::XorName;
 
 #[allow(clippy::unwrap_used)]
@@ -27,10 +31,112 @@ pub mod chatter {
 pub struct ChatterServer {
     pub tss_state: Arc<TssState>,
     pub fsm_worker_metadata: Arc<dyn FSMWorkerMetadata<LifecycleId = u64>>,
+    pub sequences: Arc<Mutex<SequenceTracker>>,
+}
+
+impl ChatterServer {
+    /// Hand the entries of a frame to the round manager.  Frames and entries
+    /// that can't be handled are dropped, as they would fail again if resent.
+    async fn handle_frame(&self, frame: ChatterFrame, remote_addr: SocketAddr) {
+        let peer_state = self.tss_state.peer_state.clone();
+        let tx_round_sender = self.tss_state.tx_round_manager.clone();
+
+        let entries = utils::serde_encrypt::deserialize_and_decrypt::<NodeTransmissionFrame>(
+            peer_state.as_ref(),
+            XorName::from_content(frame.sender_id.as_bytes()),
+            frame.encrypted_frame.as_ref(),
+        )
+        .and_then(unpack_frame);
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!(
+                    "Dropping chatter frame {} from {}: {:?}",
+                    frame.frame_seq, remote_addr, e
+                );
+                return;
+            }
+        };
+        for sequenced in entries {
+            let key = sequenced.entry.key.clone();
+            let delivered = self
+                .sequences
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_delivered(&frame.sender_id, frame.stream_id, &key, sequenced.seq);
+            if delivered {
+                trace!("Dropping resent entry {}", key);
+                continue;
+            }
+            if let Err(e) = handle_node_share_set(
+                &tx_round_sender,
+                &self.fsm_worker_metadata,
+                sequenced.entry,
+                remote_addr,
+            )
+            .await
+            {
+                error!(
+                    "Dropping chatter entry {} from {}: {:?}",
+                    key, remote_addr, e
+                );
+            }
+            self.sequences
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .accept(&frame.sender_id, frame.stream_id, &key, sequenced.seq);
+        }
+    }
 }
 
 #[tonic::async_trait]
 impl ChatterService for ChatterServer {
+    type StreamDirectStream = Pin<Box<dyn Stream<Item = Result<ChatterAck, Status>> + Send>>;
+
+    async fn stream_direct(
+        &self,
+        request: tonic::Request<Streaming<ChatterFrame>>,
+    ) -> tonic::Result<tonic::Response<Self::StreamDirectStream>, tonic::Status> {
+        let remote_addr = match request.remote_addr() {
+            Some(remote_addr) => remote_addr,
+            None => {
+                error!("Could not get remote address of chatter stream");
+                return Err(Status::new(
+                    Code::Internal,
+                    "Could not get remote address".to_string(),
+                ));
+            }
+        };
+        let mut frames = request.into_inner();
+        let (ack_tx, ack_rx) = flume::unbounded();
+        let server = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let frame = match frames.message().await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("Chatter stream from {} ended: {:?}", remote_addr, e);
+                        break;
+                    }
+                };
+                // Only transport errors end the stream, every frame read is acknowledged
+                let frame_seq = frame.frame_seq;
+                server.handle_frame(frame, remote_addr).await;
+                if ack_tx
+                    .send_async(Ok(ChatterAck { frame_seq }))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Ok(tonic::Response::new(Box::pin(ack_rx.into_stream())))
+    }
+
     async fn send_direct_grpc(
         &self,
         request: tonic::Request<NodeShareRequestProto>,
@@ -94,6 +200,7 @@ pub async fn launch_chatter_server(
     let chatter_server = ChatterServer {
         tss_state: tss_state.clone(),
         fsm_worker_metadata: fsm_worker_metadata.clone(),
+        sequences: Arc::new(Mutex::new(SequenceTracker::default())),
     };
     let self_addr = format!(
         "0.0.0.0:{}",
@@ -1,3 +1,4 @@
+pub mod chatter_stream;
 pub mod grpc_transmissions;
 pub mod internal;
 pub mod models;
@@ -29,10 +29,12 @@ use super::models::{PossiblyDelegatedRateLimitNft, RateLimitDB};
 ///
 /// NOTE: This function should be run assuming that there exists at least 1 NFT token in the list
 /// that has not reached its quota. Otherwise, this function will simply be a no-op.
+///
+/// Returns the used requests per second across all tokens, and the token the request was charged to.
 pub(super) async fn allocate_request_to_first_nft_with_quota(
     tokens: &Vec<PossiblyDelegatedRateLimitNft>,
     rate_limit_db: &RateLimitDB,
-) -> f32 {
+) -> (f32, Option<RateLimitNft>) {
     let rate_limit_config = rate_limit_db
         .chain_data_config_manager
         .rate_limit_config
@@ -40,6 +42,7 @@ pub(super) async fn allocate_request_to_first_nft_with_quota(
         .await;
     let window_start = SystemTime::now() - rate_limit_config.default_window_duration_secs;
     let mut used_requests_per_second_from_tokens = 0.0;
+    let mut charged_nft = None;
     for token_with_delegate_info in tokens {
         let token = token_with_delegate_info.nft.clone();
         let nft_usage_map_readable = rate_limit_db.nft_usage_map.read().await;
@@ -85,6 +88,9 @@ pub(super) async fn allocate_request_to_first_nft_with_quota(
                 nft_usage_map_writeable.insert(token.id, new_usage_entries);
             }
         }
+        if charged_nft.is_none() {
+            charged_nft = Some(token.clone());
+        }
         if let Some(signature_hash_uses_key) = &token_with_delegate_info.signature_hash_uses_key {
             let existing_uses = rate_limit_db
                 .delegation_uses_map
@@ -97,7 +103,7 @@ pub(super) async fn allocate_request_to_first_nft_with_quota(
                 .await;
         }
     }
-    used_requests_per_second_from_tokens
+    (used_requests_per_second_from_tokens, charged_nft)
 }
 
 // return the oldest timestamp from the nft usage map
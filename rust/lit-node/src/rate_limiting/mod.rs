@@ -17,6 +17,7 @@ use self::models::{RateLimitCheckReturn, RateLimitDB, UserContext};
 
 mod data;
 pub mod models;
+pub mod usage;
 
 #[allow(clippy::too_many_arguments)]
 #[instrument(skip_all, ret)]
@@ -36,6 +37,7 @@ pub(crate) async fn check_rate_limit(
         return Ok(RateLimitCheckReturn {
             rate_limit_exceeded: false,
             try_again_after: None,
+            charged_nft: None,
         });
     }
 
@@ -60,6 +62,7 @@ pub(crate) async fn check_rate_limit(
             return Ok(RateLimitCheckReturn {
                 rate_limit_exceeded: true,
                 try_again_after: Some(try_again_datetime.to_rfc3339()),
+                charged_nft: None,
             });
         }
     };
@@ -72,6 +75,7 @@ pub(crate) async fn check_rate_limit(
             return Ok(RateLimitCheckReturn {
                 rate_limit_exceeded: true,
                 try_again_after: None,
+                charged_nft: None,
             });
         }
     };
@@ -94,9 +98,11 @@ pub(crate) async fn check_rate_limit(
         if matches!(cfg.enable_rate_limiting_allocation(), Ok(false)) {
             // if there are any authorized NFTs, we're good
             debug!("Rate limiting allocation disabled, just checking if any NFTs have capacity");
+            // Nothing is allocated, so no NFT is charged for the request
             return Ok(RateLimitCheckReturn {
                 rate_limit_exceeded: false,
                 try_again_after: None,
+                charged_nft: None,
             });
         }
         // Free tier has been reached - check against RLI NFT quota.
@@ -112,11 +118,12 @@ pub(crate) async fn check_rate_limit(
 
         // sum up requests used by all RLI NFTs
         // if an NFT has capacity, it will be added to the used requests for that NFT
-        let used_requests_per_second_from_tokens = allocate_request_to_first_nft_with_quota(
-            &all_authorized_rate_limit_nfts,
-            rate_limit_db,
-        )
-        .await;
+        let (used_requests_per_second_from_tokens, charged_nft) =
+            allocate_request_to_first_nft_with_quota(
+                &all_authorized_rate_limit_nfts,
+                rate_limit_db,
+            )
+            .await;
 
         // used_requests_per_second_from_tokens doesn't count the existing request, so add 1 over the whole window
         let used_requests_per_second_from_tokens = used_requests_per_second_from_tokens
@@ -126,6 +133,7 @@ pub(crate) async fn check_rate_limit(
             return Ok(RateLimitCheckReturn {
                 rate_limit_exceeded: false,
                 try_again_after: None,
+                charged_nft,
             });
         }
     }
@@ -150,11 +158,13 @@ pub(crate) async fn check_rate_limit(
         return Ok(RateLimitCheckReturn {
             rate_limit_exceeded: true,
             try_again_after: Some(try_again_datetime.to_rfc3339()),
+            charged_nft: None,
         });
     } else {
         return Ok(RateLimitCheckReturn {
             rate_limit_exceeded: true,
             try_again_after: None,
+            charged_nft: None,
         });
     }
 }
@@ -1,4 +1,5 @@
 use crate::config::chain::ChainDataConfigManager;
+use crate::rate_limiting::usage::UsageStore;
 use ethers::prelude::*;
 use moka::future::Cache;
 use serde::Deserialize;
@@ -31,6 +32,9 @@ pub(crate) struct RateLimitDB {
     pub chain_data_config_manager: Arc<ChainDataConfigManager>,
 
     pub delegation_uses_map: Cache<Vec<u8>, u32>,
+
+    /// Per-request usage records (for analytics / billing export).
+    pub usage: Arc<UsageStore>,
 }
 
 impl RateLimitDB {
@@ -55,6 +59,7 @@ impl RateLimitDB {
                 .time_to_live(Duration::from_secs(30 * 24 * 60 * 60))
                 .max_capacity(1000000)
                 .build(),
+            usage: Arc::new(UsageStore::default()),
         }
     }
 }
@@ -75,6 +80,9 @@ pub struct RateLimitNft {
 pub(crate) struct RateLimitCheckReturn {
     pub rate_limit_exceeded: bool,
     pub try_again_after: Option<String>,
+    /// The NFT the request was charged to, used for usage records. `None` for the
+    /// free tier and when rate limiting allocation is disabled.
+    pub charged_nft: Option<RateLimitNft>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
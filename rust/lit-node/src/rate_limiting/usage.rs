@@ -0,0 +1,470 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{Duration as ChronoDuration, NaiveDate, TimeZone, Utc};
+use ethers::types::Address;
+use lit_core::config::LitConfig;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{trace, warn};
+
+use crate::config::{usage_path, LitNodeConfig, CFG_KEY_USAGE_RETENTION_DAYS_DEFAULT};
+use crate::error::{io_err, serializer_err, validation_err, Result};
+use crate::rate_limiting::models::RateLimitCheckReturn;
+use crate::utils::encoding;
+
+const USAGE_FILE_PREFIX: &str = "usage-";
+const USAGE_FILE_EXT: &str = ".ndjson";
+const USAGE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A single billable request handled by this node, recorded once the request has been admitted
+/// (i.e. passed the rate limit check), whether or not it then succeeds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRecord {
+    /// Unix timestamp (ms) at which the request completed.
+    pub timestamp: u64,
+    pub endpoint: String,
+    pub request_id: String,
+    pub user_address: Option<String>,
+    /// The rate limit NFT the request was allocated to (if any).
+    pub rate_limit_nft_id: Option<String>,
+    /// The owner of the rate limit NFT (i.e. the payer, which may differ from the user when
+    /// capacity was delegated).
+    pub payer: Option<String>,
+    pub action_ipfs_id: Option<String>,
+    pub execution_ms: u64,
+    pub signatures: u32,
+    pub bytes_decrypted: u64,
+    /// Whether the request completed successfully.
+    #[serde(default)]
+    pub success: bool,
+}
+
+impl UsageRecord {
+    const CSV_HEADER: &'static str = "timestamp,endpoint,request_id,user_address,rate_limit_nft_id,payer,action_ipfs_id,execution_ms,signatures,bytes_decrypted,success";
+
+    fn to_csv_row(&self) -> String {
+        let opt = |v: &Option<String>| csv_escape(v.as_deref().unwrap_or(""));
+
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.timestamp,
+            csv_escape(&self.endpoint),
+            csv_escape(&self.request_id),
+            opt(&self.user_address),
+            opt(&self.rate_limit_nft_id),
+            opt(&self.payer),
+            opt(&self.action_ipfs_id),
+            self.execution_ms,
+            self.signatures,
+            self.bytes_decrypted,
+            self.success
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+}
+
+impl UsageExportFormat {
+    pub fn render(&self, records: &[UsageRecord]) -> Result<String> {
+        let mut out = String::new();
+        match self {
+            UsageExportFormat::Ndjson => {
+                for record in records {
+                    out.push_str(
+                        &serde_json::to_string(record).map_err(|e| serializer_err(e, None))?,
+                    );
+                    out.push('\n');
+                }
+            }
+            UsageExportFormat::Csv => {
+                out.push_str(UsageRecord::CSV_HEADER);
+                out.push('\n');
+                for record in records {
+                    out.push_str(&record.to_csv_row());
+                    out.push('\n');
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Collects usage for a request while it is being handled.
+///
+/// Create it where the request is admitted. The record is stored as successful by `finish`; if
+/// the recorder is dropped instead (i.e. the request failed or returned early) it is stored as
+/// failed.
+pub(crate) struct UsageRecorder {
+    record: UsageRecord,
+    store: Arc<UsageStore>,
+    started_at: Instant,
+    enabled: bool,
+    retention_days: i64,
+}
+
+impl UsageRecorder {
+    pub fn new(
+        cfg: &LitConfig,
+        store: Arc<UsageStore>,
+        endpoint: &str,
+        request_id: String,
+        started_at: Instant,
+    ) -> Self {
+        Self {
+            record: UsageRecord {
+                endpoint: endpoint.to_string(),
+                request_id,
+                ..Default::default()
+            },
+            store,
+            started_at,
+            enabled: !matches!(cfg.enable_usage_records(), Ok(false)),
+            retention_days: cfg
+                .usage_retention_days()
+                .unwrap_or(CFG_KEY_USAGE_RETENTION_DAYS_DEFAULT),
+        }
+    }
+
+    pub fn user_address(&mut self, user_address: Address) {
+        self.record.user_address = Some(encoding::bytes_to_hex(user_address));
+    }
+
+    pub fn rate_limit(&mut self, user_address: Option<Address>, res: &RateLimitCheckReturn) {
+        self.record.user_address = user_address.map(encoding::bytes_to_hex);
+        if let Some(nft) = &res.charged_nft {
+            self.record.rate_limit_nft_id = Some(nft.id.to_string());
+            self.record.payer = Some(encoding::bytes_to_hex(nft.owner));
+        }
+    }
+
+    pub fn action_ipfs_id(&mut self, action_ipfs_id: Option<String>) {
+        self.record.action_ipfs_id = action_ipfs_id;
+    }
+
+    pub fn signatures(&mut self, signatures: u32) {
+        self.record.signatures += signatures;
+    }
+
+    pub fn bytes_decrypted(&mut self, bytes: u64) {
+        self.record.bytes_decrypted += bytes;
+    }
+
+    /// Store the record of a successful request. Failures are logged rather than failing the
+    /// request.
+    pub async fn finish(mut self) {
+        if let Some(record) = self.take_record(true) {
+            store_record(&self.store, record, self.retention_days).await;
+        }
+    }
+
+    fn take_record(&mut self, success: bool) -> Option<UsageRecord> {
+        if !std::mem::replace(&mut self.enabled, false) {
+            return None;
+        }
+
+        let mut record = std::mem::take(&mut self.record);
+        record.execution_ms = self.started_at.elapsed().as_millis() as u64;
+        record.timestamp = unix_now_ms();
+        record.success = success;
+        Some(record)
+    }
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        let Some(record) = self.take_record(false) else {
+            return;
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let store = self.store.clone();
+                let retention_days = self.retention_days;
+                handle.spawn(async move {
+                    store_record(&store, record, retention_days).await;
+                });
+            }
+            Err(_) => warn!("Dropped usage record outside of a runtime: {:?}", record),
+        }
+    }
+}
+
+async fn store_record(store: &UsageStore, record: UsageRecord, retention_days: i64) {
+    if let Err(e) = store.append(&record, retention_days).await {
+        warn!("Failed to store usage record: {:?}", e);
+    }
+}
+
+/// Usage records persisted as one NDJSON file per (UTC) day.
+#[derive(Debug)]
+pub struct UsageStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl Default for UsageStore {
+    fn default() -> Self {
+        Self::new(PathBuf::from(usage_path().as_os_str()))
+    }
+}
+
+impl UsageStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn append(&self, record: &UsageRecord, retention_days: i64) -> Result<()> {
+        let mut line = serde_json::to_string(record).map_err(|e| serializer_err(e, None))?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        fs::create_dir_all(&self.path)
+            .await
+            .map_err(|e| io_err(e, None))?;
+
+        let file_path = self.day_path(date_of(record.timestamp)?);
+        if fs::metadata(&file_path).await.is_err() {
+            // First record of the day, drop anything past retention.
+            self.prune(retention_days).await?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file_path)
+            .await
+            .map_err(|e| io_err(e, Some(format!("failed to open {:?}", file_path))))?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| io_err(e, Some(format!("failed to write {:?}", file_path))))?;
+
+        Ok(())
+    }
+
+    /// All records with `from <= timestamp < to` (unix ms), oldest first.
+    pub async fn query(&self, from: u64, to: u64) -> Result<Vec<UsageRecord>> {
+        if from >= to {
+            return Err(validation_err("usage window is empty (from >= to)", None));
+        }
+
+        let (from_date, to_date) = (date_of(from)?, date_of(to)?);
+        let mut records = Vec::new();
+        for (date, file_path) in self.day_files().await? {
+            if date < from_date || date > to_date {
+                continue;
+            }
+
+            let data = fs::read_to_string(&file_path)
+                .await
+                .map_err(|e| io_err(e, Some(format!("failed to read {:?}", file_path))))?;
+            for line in data.lines().filter(|l| !l.trim().is_empty()) {
+                let record: UsageRecord =
+                    serde_json::from_str(line).map_err(|e| serializer_err(e, None))?;
+                if record.timestamp >= from && record.timestamp < to {
+                    records.push(record);
+                }
+            }
+        }
+
+        records.sort_by_key(|r| r.timestamp);
+        Ok(records)
+    }
+
+    async fn prune(&self, retention_days: i64) -> Result<()> {
+        let cutoff = Utc::now().date_naive() - ChronoDuration::days(retention_days.max(1));
+        for (date, file_path) in self.day_files().await? {
+            if date < cutoff {
+                trace!("Removing expired usage records: {:?}", file_path);
+                fs::remove_file(&file_path)
+                    .await
+                    .map_err(|e| io_err(e, None))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn day_files(&self) -> Result<Vec<(NaiveDate, PathBuf)>> {
+        let mut files = Vec::new();
+        let mut dir = match fs::read_dir(&self.path).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+            Err(e) => return Err(io_err(e, None)),
+        };
+
+        while let Some(entry) = dir.next_entry().await.map_err(|e| io_err(e, None))? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let date = file_name
+                .strip_prefix(USAGE_FILE_PREFIX)
+                .and_then(|n| n.strip_suffix(USAGE_FILE_EXT))
+                .and_then(|d| NaiveDate::parse_from_str(d, USAGE_DATE_FORMAT).ok());
+            if let Some(date) = date {
+                files.push((date, entry.path()));
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    fn day_path(&self, date: NaiveDate) -> PathBuf {
+        self.path.join(format!(
+            "{}{}{}",
+            USAGE_FILE_PREFIX,
+            date.format(USAGE_DATE_FORMAT),
+            USAGE_FILE_EXT
+        ))
+    }
+}
+
+fn date_of(timestamp_ms: u64) -> Result<NaiveDate> {
+    Utc.timestamp_millis_opt(timestamp_ms as i64)
+        .single()
+        .map(|dt| dt.date_naive())
+        .ok_or_else(|| validation_err(format!("invalid timestamp: {}", timestamp_ms), None))
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::{UsageExportFormat, UsageRecord, UsageRecorder, UsageStore};
+    use crate::tests::common::get_test_config;
+
+    const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+    fn record(timestamp: u64, endpoint: &str) -> UsageRecord {
+        UsageRecord {
+            timestamp,
+            endpoint: endpoint.to_string(),
+            request_id: "req".to_string(),
+            rate_limit_nft_id: Some("1".to_string()),
+            signatures: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn append_and_query_test() {
+        let file = temp_file::empty();
+        let dir = file.path().with_extension("d");
+        let store = UsageStore::new(dir.clone());
+        let now = super::unix_now_ms();
+
+        store
+            .append(&record(now - DAY_MS, "/web/pkp/sign"), 90)
+            .await
+            .unwrap();
+        store
+            .append(&record(now, "/web/execute"), 90)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.query(now - 2 * DAY_MS, now + 1).await.unwrap().len(),
+            2
+        );
+        let today = store.query(now - 1000, now + 1).await.unwrap();
+        assert_eq!(today.len(), 1);
+        assert_eq!(today[0].endpoint, "/web/execute");
+        assert!(store.query(now, now).await.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recorder_test() {
+        let cfg = get_test_config();
+        let file = temp_file::empty();
+        let dir = file.path().with_extension("d");
+        let store = Arc::new(UsageStore::new(dir.clone()));
+        let start = super::unix_now_ms();
+
+        let mut usage = UsageRecorder::new(
+            &cfg,
+            store.clone(),
+            "/web/pkp/sign",
+            "ok".into(),
+            Instant::now(),
+        );
+        usage.signatures(1);
+        usage.finish().await;
+
+        // A request that fails after being admitted is still recorded.
+        drop(UsageRecorder::new(
+            &cfg,
+            store.clone(),
+            "/web/pkp/sign",
+            "failed".into(),
+            Instant::now(),
+        ));
+
+        let mut records = Vec::new();
+        for _ in 0..50 {
+            records = store.query(start, super::unix_now_ms() + 1).await.unwrap();
+            if records.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(records.len(), 2);
+        let ok = records.iter().find(|r| r.request_id == "ok").unwrap();
+        assert!(ok.success);
+        assert_eq!(ok.signatures, 1);
+        let failed = records.iter().find(|r| r.request_id == "failed").unwrap();
+        assert!(!failed.success);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn render_csv_test() {
+        let mut r = record(1, "/web/execute");
+        r.action_ipfs_id = Some("Qm,\"x\"".to_string());
+
+        let csv = UsageExportFormat::Csv.render(&[r.clone()]).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            "1,/web/execute,req,,1,,\"Qm,\"\"x\"\"\",0,1,0,false"
+        );
+
+        let ndjson = UsageExportFormat::Ndjson.render(&[r.clone()]).unwrap();
+        assert_eq!(
+            serde_json::from_str::<UsageRecord>(ndjson.trim()).unwrap(),
+            r
+        );
+    }
+}
@@ -1,22 +1,23 @@
 use crate::config::{LitNodeConfig, CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT};
-use crate::error::{unexpected_err, Result};
+use crate::error::{unexpected_err, Result, Unexpected};
+use crate::p2p_comms::web::chatter_stream::PeerStream;
 use crate::p2p_comms::web::grpc_transmissions::chatter::{
     chatter_service_client::ChatterServiceClient, NodeShareRequestProto,
 };
 use crate::peers::peer_reviewer::{Issue, PeerComplaint};
+use crate::peers::peer_state::models::SimplePeer;
 use crate::peers::PeerState;
 use crate::tss::common::models::NodeTransmissionDetails;
 use crate::utils;
 use crate::utils::future::call_with_retry_condition;
 use lit_api_core::config::LitApiConfig;
 use lit_core::config::LitConfig;
-use reqwest::Client;
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 use tokio::fs;
 use tonic;
-use tonic::transport::{Channel, ClientTlsConfig};
+use tonic::transport::{Channel, ClientTlsConfig, Uri};
 use tracing::instrument;
 
 #[allow(clippy::unwrap_used)]
@@ -31,15 +32,10 @@ pub async fn batch_transaction_worker(
     lit_config: Arc<LitConfig>,
     peer_state: Arc<PeerState>,
     rx_node_transmission_details: flume::Receiver<NodeTransmissionDetails>,
-    http_client: Client,
 ) {
-    let prefix = lit_config.http_prefix_when_talking_to_other_nodes();
     info!("Starting: tasks::batch_transaction_worker");
 
-    let mut clients: HashMap<
-        String,
-        (SystemTime, ChatterServiceClient<tonic::transport::Channel>),
-    > = HashMap::new();
+    let mut streams: HashMap<String, PeerStream> = HashMap::new();
     let timeout = lit_config
         .ecdsa_round_timeout()
         .unwrap_or(CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT);
@@ -60,103 +56,108 @@ pub async fn batch_transaction_worker(
                         continue;
                     }
                 };
-                let peers = peer_state.clone();
 
-                        //grpc
-                        // trace!("Sending direct grpc transmission to: {:?}", transmission_details.dest_peer.socket_address);
-                        let peer_addr_full = transmission_details.dest_peer.socket_address.clone();
-                        let client = match clients.get(peer_addr_full.as_str()) {
-                            Some(client) => {
-                                //update timestamp
-                                let c = client.1.clone();
-                                clients.insert(peer_addr_full.to_string(), (SystemTime::now(), client.1.clone()));
-                                c
-                            }
-                            None => {
-                                debug!("Creating new grpc client for peer: {}", peer_addr_full);
-                                let mut peer_addr_parts = peer_addr_full.split(':');
-                                let peer_addr = match peer_addr_parts.next() {
-                                    Some(addr) => addr,
-                                    None => {
-                                        error!("Failed to parse peer address from: {}", peer_addr_full);
-                                        continue;
-                                    }
-                                };
-                                let peer_port: u16 = match peer_addr_parts.next() {
-                                    Some(port) => port.parse().expect("Failed to parse peer port to u16"),
-                                    None => 443,
-                                };
-                                let url = format!("{}{}:{}", prefix, peer_addr, INTERNAL_CHATTER_PORT_OFFSET + peer_port);
-                                let uri = url.clone().parse().expect("Failed to parse URL");
-                                let mut channel_builder = Channel::builder(uri).connect_timeout(Duration::from_secs(CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT as u64));
-                                if lit_config.https_enabled() {
-                                    match lit_config.tls_certs() {
-                                        Some(path) => {
-                                            match fs::read(path).await {
-                                                Ok(cert_bytes) => {
-                                                    let tls_cert = tonic::transport::Certificate::from_pem(cert_bytes);
-                                                    channel_builder = match channel_builder.tls_config(ClientTlsConfig::new().ca_certificate(tls_cert)) {
-                                                        Ok(c) => c,
-                                                        Err(e) => {
-                                                            error!("Problem creating channel builder with tls: {:?}", e);
-                                                            continue;
-                                                        }
-                                                    };
-                                                },
-                                                Err(e) => {
-                                                    error!("Problem reading tls certificate: {:?}", e);
-                                                }
-                                            };
-                                        },
-                                        None => error!("tls_certs not set in LitConfig"),
-                                    }
-                                }
+                let peer_addr_full = transmission_details.dest_peer.socket_address.clone();
+                let stream = streams.entry(peer_addr_full.clone()).or_insert_with(|| {
+                    debug!("Opening chatter stream to peer: {}", peer_addr_full);
+                    PeerStream::spawn(
+                        lit_config.clone(),
+                        peer_state.clone(),
+                        transmission_details.dest_peer.clone(),
+                    )
+                });
+                // Never waits, so a slow peer doesn't hold up the others
+                if stream
+                    .send(transmission_details, Duration::from_millis(timeout as u64))
+                    .is_err()
+                {
+                    error!("Chatter stream task to {} has stopped", peer_addr_full);
+                    streams.remove(&peer_addr_full);
+                }
+            }
+            _ = heartbeat.tick() => prune_idle_streams(&mut streams, Duration::from_millis(timeout as u64)),
+        }
+    }
+}
 
-                                let client = match channel_builder.connect().await {
-                                    Ok(channel) => ChatterServiceClient::new(channel),
-                                    Err(e) => {
-                                        error!(
-                                            "Problem connecting to node #{} ({}): {:?}",
-                                            peer_addr, url, e
-                                        );
-                                        warn!("connecting via grpc to {:?} has failed, attempting to get peer staker address for complaint {:?}", &transmission_details.dest_peer.socket_address, e);
-                                        let complainer = peer_state.addr.clone();
-                                        let complaint_channel = peer_state.complaint_channel.clone();
-                                        if let Err(e) = complaint_channel
-                                            .send_async(PeerComplaint {
-                                                complainer,
-                                                issue: Issue::Unresponsive,
-                                                peer_node_staker_address: transmission_details
-                                                    .dest_peer
-                                                    .staker_address,
-                                            })
-                                            .await
-                                        {
-                                            error!("Failed to send complaint to complaint_channel: {:?}", e);
-                                        }
-                                        continue;
-                                    }
-                                };
-                                clients.insert(peer_addr_full.to_string(), (SystemTime::now(), client.clone()));
-                                client
-                            }
-                        };
+/// The connect and request timeout of chatter calls, the default round timeout.
+/// It is in milliseconds, read as seconds a peer that never answered held up
+/// its entries for over 8 hours.
+fn grpc_timeout() -> Duration {
+    Duration::from_millis(CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT as u64)
+}
 
-                        tokio::spawn(async move {
-                            let r = send_direct_grpc(&transmission_details.clone(), &peers, client).await;
-                            if let Err(e) = r {
-                                let peer_addr_full = transmission_details.dest_peer.socket_address.clone();
-                                error!("Error sending direct grpc transmission to {:?}: {}", peer_addr_full, e);
-                            };
-                        });
-                    }
-            _ = heartbeat.tick() => prune_old_clients(&mut clients, timeout as u128).await,
+/// Connect to the chatter server of the peer at the given socket address.
+pub(crate) async fn connect_chatter_client(
+    lit_config: &LitConfig,
+    peer_addr_full: &str,
+) -> Result<ChatterServiceClient<Channel>> {
+    let prefix = lit_config.http_prefix_when_talking_to_other_nodes();
+    let mut peer_addr_parts = peer_addr_full.split(':');
+    let peer_addr = peer_addr_parts.next().expect_or_err(format!(
+        "Failed to parse peer address from: {}",
+        peer_addr_full
+    ))?;
+    let peer_port: u16 = match peer_addr_parts.next() {
+        Some(port) => port
+            .parse()
+            .map_err(|e| unexpected_err(e, Some("Failed to parse peer port to u16".into())))?,
+        None => 443,
+    };
+    let url = format!(
+        "{}{}:{}",
+        prefix,
+        peer_addr,
+        INTERNAL_CHATTER_PORT_OFFSET + peer_port
+    );
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| unexpected_err(e, Some(format!("Failed to parse URL: {}", url))))?;
+    let mut channel_builder = Channel::builder(uri).connect_timeout(grpc_timeout());
+    if lit_config.https_enabled() {
+        match lit_config.tls_certs() {
+            Some(path) => {
+                let cert_bytes = fs::read(path).await.map_err(|e| {
+                    unexpected_err(e, Some("Problem reading tls certificate".into()))
+                })?;
+                let tls_cert = tonic::transport::Certificate::from_pem(cert_bytes);
+                channel_builder = channel_builder
+                    .tls_config(ClientTlsConfig::new().ca_certificate(tls_cert))
+                    .map_err(|e| {
+                        unexpected_err(e, Some("Problem creating channel builder with tls".into()))
+                    })?;
+            }
+            None => error!("tls_certs not set in LitConfig"),
         }
     }
+
+    let channel = channel_builder
+        .connect()
+        .await
+        .map_err(|e| unexpected_err(e, Some(format!("Problem connecting to {}", url))))?;
+    Ok(ChatterServiceClient::new(channel))
+}
+
+pub(crate) async fn complain_unresponsive(peer_state: &PeerState, peer: &SimplePeer) {
+    warn!(
+        "Sending grpc messages to {:?} has failed, filing a complaint",
+        peer.socket_address
+    );
+    if let Err(e) = peer_state
+        .complaint_channel
+        .send_async(PeerComplaint {
+            complainer: peer_state.addr.clone(),
+            issue: Issue::Unresponsive,
+            peer_node_staker_address: peer.staker_address,
+        })
+        .await
+    {
+        error!("Failed to send complaint to complaint_channel: {:?}", e);
+    }
 }
 
 #[instrument(name = "send_direct", skip_all)]
-async fn send_direct_grpc(
+pub(crate) async fn send_direct_grpc(
     transmission_details: &NodeTransmissionDetails,
     peer_state: &PeerState,
     client: ChatterServiceClient<tonic::transport::Channel>,
@@ -180,9 +181,7 @@ async fn send_direct_grpc(
                 encrypted_entry: encrypted_entry_clone.clone(),
             });
             // set timeout for request
-            request.set_timeout(Duration::from_secs(
-                CFG_KEY_ECDSA_ROUND_TIMEOUT_MS_DEFAULT as u64,
-            ));
+            request.set_timeout(grpc_timeout());
             client_clone.send_direct_grpc(request).await
         }
     };
@@ -200,22 +199,7 @@ async fn send_direct_grpc(
             );
 
             if error.code() == tonic::Code::DeadlineExceeded {
-                warn!(
-                    "sending grpc message to {:?} has failed, attempting to get peer staker address for complaint {:?}",
-                    &transmission_details.dest_peer.socket_address, error
-                );
-                let complainer = peer_state.addr.clone();
-                let complaint_channel = peer_state.complaint_channel.clone();
-                if let Err(e) = complaint_channel
-                    .send_async(PeerComplaint {
-                        complainer,
-                        issue: Issue::Unresponsive,
-                        peer_node_staker_address: transmission_details.dest_peer.staker_address,
-                    })
-                    .await
-                {
-                    error!("Failed to send complaint to complaint_channel: {:?}", e);
-                }
+                complain_unresponsive(peer_state, &transmission_details.dest_peer).await;
             }
             return Err(unexpected_err(
                     error,
@@ -238,27 +222,23 @@ async fn send_direct_grpc(
     Ok(true)
 }
 
-#[instrument(skip_all)]
-async fn prune_old_clients(
-    clients: &mut HashMap<String, (SystemTime, ChatterServiceClient<tonic::transport::Channel>)>,
-    timeout: u128,
-) {
-    let now = SystemTime::now();
-    let mut to_remove: Vec<String> = Vec::new();
-    for (peer, (time, _)) in clients.iter() {
-        let elapsed = match now.duration_since(*time) {
-            Ok(elapsed) => elapsed,
-            Err(e) => {
-                error!("Error getting elapsed time: {:?}", e);
-                return;
-            }
-        };
-        if elapsed.as_millis() > timeout {
-            info!("BG: Removing old client for peer: {}", peer);
-            to_remove.push(peer.clone());
+fn prune_idle_streams(streams: &mut HashMap<String, PeerStream>, timeout: Duration) {
+    streams.retain(|peer, stream| {
+        let idle = stream.is_idle(timeout);
+        if idle {
+            info!("BG: Closing idle chatter stream to peer: {}", peer);
         }
-    }
-    for peer in to_remove {
-        clients.remove(&peer);
+        !idle
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grpc_timeout;
+    use std::time::Duration;
+
+    #[test]
+    fn grpc_timeout_is_the_round_timeout() {
+        assert_eq!(grpc_timeout(), Duration::from_secs(30));
     }
 }
@@ -18,6 +18,7 @@ use crate::siwe_db::rpc::EthBlockhashCache;
 use crate::tasks::fsm::node_fsm_worker;
 #[cfg(feature = "rtmetrics")]
 use crate::tasks::realtime_metrics::real_time_metrics_worker;
+use crate::tss::blsful::beacon::beacon_worker;
 use crate::tss::common::models::RoundData;
 use crate::tss::common::peer_checker::peer_checker_worker;
 use crate::tss::common::restore::RestoreState;
@@ -80,6 +81,8 @@ pub fn launch(
 
     let lit_config = cfg.load_full();
     let lit_config_clone = lit_config.clone();
+    let cfg_for_beacon = cfg.clone();
+    let tss_state_for_beacon = tss_state.clone();
     let peer_state_for_tasks = peer_state.clone();
     let peer_state2 = peer_state.clone();
     let peer_state_for_peer_reviewer = peer_state.clone();
@@ -217,6 +220,10 @@ pub fn launch(
                     peer_checker_worker(q, peer_state_for_peer_checker).await;
                 }));
 
+                tasks.push(spawn(move |q| async move {
+                    beacon_worker(q, cfg_for_beacon, tss_state_for_beacon).await;
+                }));
+
                 let lit_config_for_rounds_queue = lit_config.clone();
                 tasks.push(spawn(|q| async move {
                     rounds_worker(
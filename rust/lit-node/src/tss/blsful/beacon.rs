@@ -0,0 +1,408 @@
+//! A threshold BLS randomness beacon.
+//!
+//! Round `n` starts `n * BEACON_PERIOD_SECS` seconds after `BEACON_GENESIS_SECS`.
+//! Neither is configurable: time locks release rounds by when they start, so a
+//! node operator mustn't be able to bring a round forward.  Every node signs
+//! `H(round)` with its share of the BLS root key and the shares are combined into
+//! the signature of the whole network.  BLS signatures are unique, so any
+//! threshold of shares combines into the same signature, which no set of nodes
+//! below the threshold can predict or bias.  The randomness of a round is the
+//! SHA-256 hash of its signature, and both can be checked against the network
+//! public key returned by `/web/handshake`.
+
+use crate::config::LitNodeConfig;
+use crate::error::{unexpected_err, validation_err_code, Result, Unexpected, EC};
+use crate::p2p_comms::CommsManager;
+use crate::peers::peer_state::models::{NetworkState, SimplePeerExt};
+use crate::tss::common::curve_type::CurveType;
+use crate::tss::common::tss_state::TssState;
+use crate::tss::common::verification_key::{
+    peer_verification_key_shares, verify_bls_signature_share, VerificationKeyShare,
+};
+use blsful::{Bls12381G2Impl, PublicKey, Signature, SignatureShare};
+use elliptic_curve::group::GroupEncoding;
+use lazy_static::lazy_static;
+use lit_core::config::ReloadableLitConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// The Unix time at which round 0 started (2024-01-01T00:00:00Z).
+pub const BEACON_GENESIS_SECS: u64 = 1_704_067_200;
+/// Seconds between rounds.
+pub const BEACON_PERIOD_SECS: u64 = 30;
+
+const BEACON_DOMAIN: &[u8] = b"LIT_RANDOMNESS_BEACON_V1";
+// A day of rounds
+const MAX_CACHED_ROUNDS: usize = (24 * 60 * 60 / BEACON_PERIOD_SECS) as usize;
+
+lazy_static! {
+    static ref ROUNDS: RwLock<BTreeMap<u64, BeaconRound>> = RwLock::new(BTreeMap::new());
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeaconRound {
+    pub round: u64,
+    /// The hex-encoded compressed G2 signature of `beacon_message(round)`.
+    pub signature: String,
+    /// The hex-encoded SHA-256 hash of the signature.
+    pub randomness: String,
+}
+
+impl BeaconRound {
+    fn new(round: u64, signature: &Signature<Bls12381G2Impl>) -> Self {
+        let signature_bytes = signature.as_raw_value().to_bytes();
+        Self {
+            round,
+            signature: hex::encode(signature_bytes.as_ref()),
+            randomness: hex::encode(Sha256::digest(signature_bytes.as_ref())),
+        }
+    }
+}
+
+/// The message signed for a round, `H(round)`.
+pub fn beacon_message(round: u64) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(BEACON_DOMAIN);
+    hasher.update(round.to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// The Unix time at which a round starts, `None` if that is past `u64::MAX`.
+pub fn round_start(round: u64) -> Option<u64> {
+    round
+        .checked_mul(BEACON_PERIOD_SECS)
+        .and_then(|offset| offset.checked_add(BEACON_GENESIS_SECS))
+}
+
+/// The round that started most recently.
+pub fn current_round() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    now.saturating_sub(BEACON_GENESIS_SECS) / BEACON_PERIOD_SECS
+}
+
+/// Verify the signature of a round against the hex-encoded BLS root key.
+pub fn verify_round(
+    bls_root_pubkey: &str,
+    round: u64,
+    signature: &Signature<Bls12381G2Impl>,
+) -> Result<()> {
+    let public_key = hex::decode(bls_root_pubkey)
+        .map_err(|e| unexpected_err(e, Some("Invalid BLS root key".into())))
+        .and_then(|bytes| {
+            PublicKey::<Bls12381G2Impl>::try_from(&bytes)
+                .map_err(|e| unexpected_err(e, Some("Invalid BLS root key".into())))
+        })?;
+    signature
+        .verify(&public_key, beacon_message(round))
+        .map_err(|e| {
+            unexpected_err(
+                e,
+                Some(format!("Invalid beacon signature for round {}", round)),
+            )
+        })
+}
+
+/// A round that has already been produced by this node, or the latest one.
+pub fn cached_round(round: Option<u64>) -> Result<BeaconRound> {
+    let rounds = ROUNDS.read().unwrap_or_else(|e| e.into_inner());
+    let cached = match round {
+        Some(round) => rounds.get(&round),
+        None => rounds.values().next_back(),
+    };
+    cached.cloned().ok_or_else(|| {
+        validation_err_code(
+            match round {
+                Some(round) => format!("Beacon round {} is not available", round),
+                None => "No beacon round is available yet".to_string(),
+            },
+            EC::NodeBeaconRoundUnavailable,
+            None,
+        )
+    })
+}
+
+fn cache_round(beacon_round: BeaconRound) {
+    let mut rounds = ROUNDS.write().unwrap_or_else(|e| e.into_inner());
+    rounds.insert(beacon_round.round, beacon_round);
+    while rounds.len() > MAX_CACHED_ROUNDS {
+        rounds.pop_first();
+    }
+}
+
+/// Sign a round together with the other nodes, which must call this with the
+/// same `txn_prefix`.
+pub async fn produce_round(
+    tss_state: &TssState,
+    txn_prefix: &str,
+    round: u64,
+) -> Result<BeaconRound> {
+    // Signing future rounds would make them predictable
+    if round > current_round() {
+        return Err(validation_err_code(
+            format!("Beacon round {} hasn't started yet", round),
+            EC::NodeBeaconRoundUnavailable,
+            None,
+        ));
+    }
+
+    let cipher_state = tss_state.get_cipher_state(CurveType::BLS)?;
+    let bls_root_pubkey = cipher_state
+        .root_keys()
+        .await
+        .first()
+        .cloned()
+        .expect_or_err_code(EC::NodeBLSRootKeyNotFound, "No BLS root key found")?;
+    let (signature_share, _) = cipher_state.sign(beacon_message(round), None).await?;
+
+    let peers = tss_state.peer_state.peers().await?;
+    let mut remaining_peers = peers.all_peers_except(&tss_state.addr);
+    let threshold = peers.threshold_for_set() as usize;
+
+    let cm = CommsManager::new(tss_state, 0, txn_prefix, "0").await?;
+    cm.broadcast(signature_share).await?;
+
+    let key_shares =
+        match peer_verification_key_shares(tss_state, CurveType::BLS, &bls_root_pubkey).await {
+            Ok(key_shares) => Some(key_shares),
+            Err(e) => {
+                warn!(
+                    "Can't verify the shares of beacon round {} one by one: {:?}",
+                    round, e
+                );
+                None
+            }
+        };
+    let message = beacon_message(round);
+    let mut shares = vec![signature_share];
+    // Keep collecting until there is a threshold of valid shares
+    while shares.len() < threshold {
+        let needed = threshold - shares.len();
+        if remaining_peers.len() < needed {
+            return Err(unexpected_err(
+                format!("Not enough valid shares for beacon round {}", round),
+                None,
+            ));
+        }
+        let received: Vec<(u16, SignatureShare<Bls12381G2Impl>)> = cm
+            .collect_from_earliest(&remaining_peers, needed as u16)
+            .await?;
+        remaining_peers
+            .retain(|peer| !received.iter().any(|(index, _)| *index == peer.share_index));
+        shares.extend(valid_shares(round, &message, received, key_shares.as_ref()));
+    }
+
+    // A bad share makes the combined signature invalid, rather than wrong
+    let signature = Signature::from_shares(&shares).map_err(|e| {
+        unexpected_err(e, Some(format!("Failed to combine beacon round {}", round)))
+    })?;
+    verify_round(&bls_root_pubkey, round, &signature)?;
+
+    let beacon_round = BeaconRound::new(round, &signature);
+    cache_round(beacon_round.clone());
+    Ok(beacon_round)
+}
+
+/// The shares that verify against the verification key share of the node that
+/// sent them.  Without the key shares, e.g. when the DKG transcript of the epoch
+/// is missing, the shares are kept and only the combined signature is checked.
+fn valid_shares(
+    round: u64,
+    message: &[u8],
+    received: Vec<(u16, SignatureShare<Bls12381G2Impl>)>,
+    key_shares: Option<&BTreeMap<u16, VerificationKeyShare>>,
+) -> Vec<SignatureShare<Bls12381G2Impl>> {
+    let Some(key_shares) = key_shares else {
+        return received.into_iter().map(|(_, share)| share).collect();
+    };
+
+    received
+        .into_iter()
+        .filter(|(share_index, share)| {
+            let verified = key_shares
+                .get(share_index)
+                .ok_or_else(|| {
+                    unexpected_err(
+                        format!("No verification key share for node {}", share_index),
+                        None,
+                    )
+                })
+                .and_then(|key_share| verify_bls_signature_share(key_share, message, share));
+            if let Err(e) = &verified {
+                warn!(
+                    "Dropping the share of node {} for beacon round {}: {:?}",
+                    share_index, round, e
+                );
+            }
+            verified.is_ok()
+        })
+        .map(|(_, share)| share)
+        .collect()
+}
+
+/// Produce every round of the beacon as it starts.
+pub async fn beacon_worker(
+    mut quit_rx: mpsc::Receiver<bool>,
+    cfg: ReloadableLitConfig,
+    tss_state: Arc<TssState>,
+) {
+    info!("Starting: tasks::beacon_worker");
+
+    loop {
+        let round = current_round() + 1;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let starts = Duration::from_secs(round_start(round).unwrap_or(u64::MAX));
+        let wait = starts.saturating_sub(now);
+
+        tokio::select! {
+            _ = quit_rx.recv() => {
+                break;
+            }
+            _ = tokio::time::sleep(wait) => {}
+        }
+
+        // Read on every round, so that enabling or disabling applies right away
+        if !cfg.load().enable_beacon().unwrap_or(true) {
+            continue;
+        }
+        match tss_state.peer_state.network_state().await {
+            Ok(NetworkState::Restore | NetworkState::Paused) | Err(_) => continue,
+            _ => {}
+        }
+
+        // A slow round mustn't delay the next one
+        let tss_state = tss_state.clone();
+        tokio::spawn(async move {
+            let txn_prefix = format!("BEACON_{}", round);
+            match produce_round(&tss_state, &txn_prefix, round).await {
+                Ok(beacon_round) => {
+                    debug!(
+                        "Beacon round {}: {}",
+                        beacon_round.round, beacon_round.randomness
+                    )
+                }
+                Err(e) => warn!("Error producing beacon round {}: {:?}", round, e),
+            }
+        });
+    }
+
+    info!("Stopped: tasks::beacon_worker");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tss::common::key_share_helper::KeyHelper;
+    use crate::tss::common::traits::key_persistence::KeyPersistence;
+    use blsful::inner_types::G1Projective;
+    use blsful::vsss_rs::Share;
+    use blsful::{SecretKey, SecretKeyShare, SignatureSchemes};
+    use elliptic_curve::Group;
+
+    #[test]
+    fn verifies_rounds() {
+        let secret_key = SecretKey::<Bls12381G2Impl>::new();
+        let public_key = hex::encode(secret_key.public_key().0.to_bytes().as_ref());
+        let signature = secret_key
+            .sign(SignatureSchemes::ProofOfPossession, &beacon_message(7))
+            .unwrap();
+
+        assert!(verify_round(&public_key, 7, &signature).is_ok());
+        assert!(verify_round(&public_key, 8, &signature).is_err());
+
+        let beacon_round = BeaconRound::new(7, &signature);
+        assert_eq!(beacon_round, BeaconRound::new(7, &signature));
+        assert_eq!(hex::decode(&beacon_round.randomness).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn drops_invalid_shares() {
+        let secret_key = SecretKey::<Bls12381G2Impl>::new();
+        let helper = KeyHelper::<G1Projective>::default();
+        let public_key = helper.pk_to_hex(&secret_key.public_key().0);
+        let shares: Vec<SecretKeyShare<Bls12381G2Impl>> = secret_key.split(2, 3).unwrap();
+        let key_shares = shares
+            .iter()
+            .map(|share| {
+                let share_index = share.0.identifier() as u16 - 1;
+                let secret = share
+                    .0
+                    .as_field_element::<blsful::inner_types::Scalar>()
+                    .unwrap();
+                let key_share = VerificationKeyShare {
+                    curve_type: CurveType::BLS,
+                    public_key: public_key.clone(),
+                    share_index,
+                    verification_key: helper.pk_to_hex(&(G1Projective::generator() * secret)),
+                };
+                (share_index, key_share)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let message = beacon_message(5);
+        let sign = |share: &SecretKeyShare<Bls12381G2Impl>, message: &[u8]| {
+            share
+                .sign(SignatureSchemes::ProofOfPossession, message)
+                .unwrap()
+        };
+        let received = vec![
+            (0, sign(&shares[0], &beacon_message(6))),
+            (1, sign(&shares[1], &message)),
+            (2, sign(&shares[1], &message)),
+            (3, sign(&shares[2], &message)),
+        ];
+
+        let valid = valid_shares(5, &message, received.clone(), Some(&key_shares));
+        assert_eq!(valid, vec![received[1].1]);
+
+        // The valid shares combine into the round's signature
+        let signature = Signature::from_shares(&[valid[0], sign(&shares[2], &message)]).unwrap();
+        assert!(verify_round(&public_key, 5, &signature).is_ok());
+
+        assert_eq!(valid_shares(5, &message, received, None).len(), 4);
+    }
+
+    #[test]
+    fn schedules_rounds_from_genesis() {
+        assert_eq!(round_start(0), Some(BEACON_GENESIS_SECS));
+        assert_eq!(
+            round_start(2),
+            Some(BEACON_GENESIS_SECS + 2 * BEACON_PERIOD_SECS)
+        );
+        assert_eq!(round_start(u64::MAX), None);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let round = current_round();
+        assert!(round_start(round).unwrap() <= now);
+        assert!(round_start(round + 1).unwrap() > now);
+    }
+
+    #[test]
+    fn caches_rounds() {
+        assert!(cached_round(Some(u64::MAX)).is_err());
+
+        let secret_key = SecretKey::<Bls12381G2Impl>::new();
+        let signature = secret_key
+            .sign(SignatureSchemes::ProofOfPossession, &beacon_message(3))
+            .unwrap();
+        cache_round(BeaconRound::new(3, &signature));
+
+        assert_eq!(
+            cached_round(Some(3)).unwrap(),
+            BeaconRound::new(3, &signature)
+        );
+        assert!(cached_round(None).is_ok());
+    }
+}
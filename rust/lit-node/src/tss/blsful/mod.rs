@@ -1,5 +1,7 @@
 pub mod backup;
+pub mod beacon;
 pub mod models;
+pub mod timelock;
 use super::common::traits::dkg::BasicDkg;
 use crate::error::{unexpected_err, Result};
 use crate::peers::peer_state::models::SimplePeerExt;
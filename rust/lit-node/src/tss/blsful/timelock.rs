@@ -0,0 +1,167 @@
+//! Time-lock encryption.
+//!
+//! Data can be encrypted offline to the BLS network public key, using the
+//! identity of a beacon round or of a Unix time instead of access control
+//! conditions.  Once that time has passed, every node hands out its signature
+//! share of the identity to anyone who asks, without an auth sig or any
+//! condition checks.  The identity of a round is the message signed by the
+//! randomness beacon, so the published beacon signature of a round (see
+//! `beacon`) decrypts everything locked to it as well.  Rounds are released by
+//! the fixed beacon schedule, so no node config can bring a release forward.
+
+use super::beacon::{beacon_message, round_start};
+use crate::error::{validation_err_code, Result, EC};
+use lazy_static::lazy_static;
+use moka::future::Cache;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const TIMESTAMP_DOMAIN: &[u8] = b"LIT_TIMELOCK_TIMESTAMP_V1";
+/// Shares handed out to a client per window, as there is no auth sig to charge.
+const MAX_SIGN_REQUESTS_PER_WINDOW: u32 = 60;
+const SIGN_REQUEST_WINDOW: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref SIGN_REQUESTS: Cache<IpAddr, Arc<AtomicU32>> = Cache::builder()
+        .max_capacity(100_000)
+        .time_to_live(SIGN_REQUEST_WINDOW)
+        .build();
+}
+
+/// Fails once a client has asked for too many shares in the current window.
+pub async fn check_sign_rate_limit(client: IpAddr) -> Result<()> {
+    let requests = SIGN_REQUESTS
+        .get_with(client, async { Arc::new(AtomicU32::new(0)) })
+        .await;
+    match requests.fetch_add(1, Ordering::Relaxed) < MAX_SIGN_REQUESTS_PER_WINDOW {
+        true => Ok(()),
+        false => Err(validation_err_code(
+            format!(
+                "Too many time lock requests, try again in {} seconds",
+                SIGN_REQUEST_WINDOW.as_secs()
+            ),
+            EC::NodeRateLimitExceeded,
+            None,
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLock {
+    /// Released when the beacon round starts.
+    Round(u64),
+    /// Released at the Unix time, in seconds.
+    Timestamp(u64),
+}
+
+impl TimeLock {
+    pub fn from_request(round: Option<u64>, timestamp: Option<u64>) -> Result<Self> {
+        match (round, timestamp) {
+            (Some(round), None) => Ok(Self::Round(round)),
+            (None, Some(timestamp)) => Ok(Self::Timestamp(timestamp)),
+            _ => Err(validation_err_code(
+                "Exactly one of round and timestamp must be given",
+                EC::NodeBadInput,
+                None,
+            )),
+        }
+    }
+
+    /// The identity to encrypt to: `beacon_message(round)` for rounds, and
+    /// `SHA-256("LIT_TIMELOCK_TIMESTAMP_V1" || timestamp)` with the timestamp as
+    /// big-endian u64 for Unix times.
+    pub fn identity(&self) -> Vec<u8> {
+        match self {
+            Self::Round(round) => beacon_message(*round),
+            Self::Timestamp(timestamp) => {
+                let mut hasher = Sha256::new();
+                hasher.update(TIMESTAMP_DOMAIN);
+                hasher.update(timestamp.to_be_bytes());
+                hasher.finalize().to_vec()
+            }
+        }
+    }
+
+    /// The Unix time at which the lock is released, `None` if never.
+    pub fn release_time(&self) -> Option<u64> {
+        match self {
+            Self::Round(round) => round_start(*round),
+            Self::Timestamp(timestamp) => Some(*timestamp),
+        }
+    }
+
+    /// Fails unless the time has passed.
+    pub fn check_released(&self) -> Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let released = self.release_time().is_some_and(|time| time <= now);
+
+        match released {
+            true => Ok(()),
+            false => Err(validation_err_code(
+                format!("{:?} has not been released yet", self),
+                EC::NodeTimeLockNotReleased,
+                None,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests() {
+        assert_eq!(
+            TimeLock::from_request(Some(1), None).unwrap(),
+            TimeLock::Round(1)
+        );
+        assert_eq!(
+            TimeLock::from_request(None, Some(2)).unwrap(),
+            TimeLock::Timestamp(2)
+        );
+        assert!(TimeLock::from_request(Some(1), Some(2)).is_err());
+        assert!(TimeLock::from_request(None, None).is_err());
+    }
+
+    #[test]
+    fn identities_are_distinct() {
+        assert_eq!(TimeLock::Round(5).identity(), beacon_message(5));
+        assert_ne!(
+            TimeLock::Round(5).identity(),
+            TimeLock::Timestamp(5).identity()
+        );
+        assert_ne!(
+            TimeLock::Timestamp(5).identity(),
+            TimeLock::Timestamp(6).identity()
+        );
+    }
+
+    #[test]
+    fn releases_past_times_only() {
+        assert!(TimeLock::Timestamp(0).check_released().is_ok());
+        assert!(TimeLock::Timestamp(u64::MAX).check_released().is_err());
+
+        assert!(TimeLock::Round(1).check_released().is_ok());
+        assert!(TimeLock::Round(u64::MAX).check_released().is_err());
+        assert_eq!(TimeLock::Round(2).release_time(), round_start(2));
+    }
+
+    #[tokio::test]
+    async fn rate_limits_sign_requests() {
+        let client = IpAddr::from([192, 0, 2, 1]);
+        for _ in 0..MAX_SIGN_REQUESTS_PER_WINDOW {
+            assert!(check_sign_rate_limit(client).await.is_ok());
+        }
+        assert!(check_sign_rate_limit(client).await.is_err());
+        assert!(check_sign_rate_limit(IpAddr::from([192, 0, 2, 2]))
+            .await
+            .is_ok());
+    }
+}
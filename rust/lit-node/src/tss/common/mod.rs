@@ -13,3 +13,4 @@ pub mod storage;
 pub mod traits;
 pub mod tss_state;
 pub mod utils;
+pub mod verification_key;
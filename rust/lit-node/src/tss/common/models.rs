@@ -35,6 +35,25 @@ impl SerdeEncryptPublicKey for NodeTransmissionBatchEntries {
     type S = BincodeSerializer<Self>;
 }
 
+/// An entry with its sequence number within the round it belongs to.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SequencedTransmissionEntry {
+    pub seq: u64,
+    pub entry: NodeTransmissionEntry,
+}
+
+/// The entries of a single chatter stream frame.  If `compressed` is set, the
+/// entry values are deflate compressed.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct NodeTransmissionFrame {
+    pub entries: Vec<SequencedTransmissionEntry>,
+    pub compressed: bool,
+}
+
+impl SerdeEncryptPublicKey for NodeTransmissionFrame {
+    type S = BincodeSerializer<Self>;
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct NodeShareSetRequest {
     pub sender_id: XorName,
@@ -1,10 +1,12 @@
 use super::curve_type::CurveType;
-use crate::config::{backup_key_path, beaver_triple_path, segmented_paths, typed_key_path};
+use crate::config::{
+    backup_key_path, beaver_triple_path, dkg_transcript_path, segmented_paths, typed_key_path,
+};
 use crate::error::{io_err, io_err_code, unexpected_err, unexpected_err_code, Result, EC};
 use crate::peers::peer_state::models::SimplePeer;
 use async_std::path::{Path, PathBuf};
 use async_std::stream::StreamExt;
-use glob::glob;
+use glob::{glob, Pattern};
 use lit_core::error::Unexpected;
 use std::io::{Error, ErrorKind};
 use tokio::fs;
@@ -17,6 +19,7 @@ enum StorageType {
     KeyShare(CurveType),
     BeaverTriple,
     Backup(CurveType),
+    Transcript(CurveType),
 }
 
 impl StorageType {
@@ -52,6 +55,9 @@ impl StorageType {
 
             StorageType::BeaverTriple => Ok(beaver_triple_path(staker_address)),
             StorageType::Backup(_) => Ok(backup_key_path(staker_address)),
+            StorageType::Transcript(curve_type) => {
+                Ok(dkg_transcript_path(curve_type.as_str(), staker_address))
+            }
         }
     }
 
@@ -60,6 +66,7 @@ impl StorageType {
             StorageType::KeyShare(_) => "Key",
             StorageType::BeaverTriple => "BeaverTriple",
             StorageType::Backup(_) => "Backup",
+            StorageType::Transcript(_) => "Transcript",
         }
     }
 }
@@ -70,6 +77,7 @@ impl From<StorageType> for CurveType {
             StorageType::KeyShare(key_type) => key_type,
             StorageType::BeaverTriple => CurveType::K256,
             StorageType::Backup(key_type) => key_type,
+            StorageType::Transcript(key_type) => key_type,
         }
     }
 }
@@ -268,6 +276,85 @@ pub(crate) async fn delete_keyshares_older_than_epoch(
     Ok(true)
 }
 
+/**************** DKG TRANSCRIPT ****************/
+
+// Transcripts belong to the whole network, rather than to a share index
+const TRANSCRIPT_SHARE_INDEX: u16 = 0;
+
+#[doc = "Writes the transcript of the DKG that produced the key shares of an epoch to disk"]
+#[instrument(name = "write_transcript_to_disk", skip(transcript))]
+pub async fn write_transcript_to_disk<T>(
+    pubkey: &str,
+    epoch: u64,
+    curve_type: CurveType,
+    staker_address: &str,
+    transcript: &T,
+) -> Result<bool>
+where
+    T: serde::Serialize + std::marker::Sync,
+{
+    let path = get_full_path(
+        StorageType::Transcript(curve_type),
+        pubkey,
+        TRANSCRIPT_SHARE_INDEX,
+        None,
+        Some(epoch),
+        staker_address,
+    )
+    .await?;
+    do_write_to_disk(&path, transcript).await
+}
+
+#[doc = "Reads the transcript of the DKG that produced the key shares of an epoch from disk"]
+#[instrument(name = "read_transcript_from_disk")]
+pub async fn read_transcript_from_disk<T>(
+    pubkey: &str,
+    epoch: u64,
+    curve_type: CurveType,
+    staker_address: &str,
+) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let path = file_path(
+        StorageType::Transcript(curve_type),
+        pubkey,
+        TRANSCRIPT_SHARE_INDEX,
+        None,
+        Some(epoch),
+        staker_address,
+    )?;
+    do_read_from_disk(&path).await
+}
+
+#[doc = "Returns the epochs of all transcripts of a key on disk, in ascending order"]
+#[instrument(name = "transcript_epochs_on_disk")]
+pub async fn transcript_epochs_on_disk(
+    pubkey: &str,
+    curve_type: CurveType,
+    staker_address: &str,
+) -> Result<Vec<u64>> {
+    let file_names = fetch_file_names(
+        StorageType::Transcript(curve_type),
+        pubkey,
+        "H-*",
+        staker_address,
+    )
+    .await?;
+    let mut epochs = file_names
+        .iter()
+        .filter_map(|file_name| {
+            file_name
+                .trim_end_matches(".cbor")
+                .rsplit('-')
+                .next()
+                .and_then(|epoch| epoch.parse::<u64>().ok())
+        })
+        .collect::<Vec<_>>();
+    epochs.sort_unstable();
+    Ok(epochs)
+}
+
 /**************** BEAVER TRIPLE PAIR ****************/
 
 #[doc = "Reads a beaver triple pair from disk"]
@@ -612,7 +699,27 @@ async fn get_full_path(
     epoch: Option<u64>,
     staker_address: &str,
 ) -> Result<PathBuf> {
-    let mut path = get_directory(storage_type, pubkey, staker_address).await?;
+    get_directory(storage_type, pubkey, staker_address).await?;
+    file_path(
+        storage_type,
+        pubkey,
+        share_index,
+        node_set_hash,
+        epoch,
+        staker_address,
+    )
+}
+
+// The path of a file, without creating its directory (for reads)
+fn file_path(
+    storage_type: StorageType,
+    pubkey: &str,
+    share_index: u16,
+    node_set_hash: Option<u64>,
+    epoch: Option<u64>,
+    staker_address: &str,
+) -> Result<PathBuf> {
+    let mut path = storage_directory(storage_type, pubkey, staker_address)?;
     let file_name = get_file_name(storage_type, pubkey, share_index, node_set_hash, epoch);
     path.push(file_name);
     Ok(path)
@@ -623,15 +730,23 @@ async fn get_directory(
     pubkey: &str,
     staker_address: &str,
 ) -> Result<PathBuf> {
-    let root_dir = storage_type.get_root_dir(staker_address)?;
-    let path = match storage_type {
-        StorageType::Backup(_) => root_dir,
-        _ => segmented_paths(root_dir, pubkey, 3, true)?,
-    };
+    let path = storage_directory(storage_type, pubkey, staker_address)?;
     create_storage_dir(path.as_path()).await?;
     Ok(path)
 }
 
+fn storage_directory(
+    storage_type: StorageType,
+    pubkey: &str,
+    staker_address: &str,
+) -> Result<PathBuf> {
+    let root_dir = storage_type.get_root_dir(staker_address)?;
+    match storage_type {
+        StorageType::Backup(_) => Ok(root_dir),
+        _ => segmented_paths(root_dir, pubkey, 3, true),
+    }
+}
+
 fn get_file_name(
     storage_type: StorageType,
     pubkey: &str,
@@ -701,7 +816,7 @@ async fn fetch_file_names(
     node_set_hash: &str,
     staker_address: &str,
 ) -> Result<Vec<String>> {
-    let path = get_directory(storage_type, pubkey, staker_address).await?;
+    let path = storage_directory(storage_type, pubkey, staker_address)?;
     fetch_file_names_in_path(storage_type, pubkey, node_set_hash, path).await
 }
 
@@ -710,18 +825,21 @@ async fn fetch_file_names_in_path(
     storage_type: StorageType,
     pubkey: &str,
     node_set_hash: &str,
-    mut path: PathBuf,
+    path: PathBuf,
 ) -> Result<Vec<String>> {
     let key_type = CurveType::from(storage_type) as u8;
-    path.push(format!(
+    // Only `node_set_hash` is a pattern, the directory and key are matched literally
+    let dir = path
+        .to_str()
+        .expect_or_err("Could not convert path to string")?;
+    let pattern = PathBuf::from(Pattern::escape(dir)).join(format!(
         "{}-H-{}-{}-*-{}.cbor",
         storage_type.file_name_prefix(),
         key_type,
-        pubkey,
+        Pattern::escape(pubkey),
         node_set_hash
     ));
-
-    let pattern = path
+    let pattern = pattern
         .to_str()
         .expect_or_err("Could not convert path to string")?;
     info!("Checking for data existence: {}", pattern);
@@ -921,4 +1039,25 @@ mod test {
         let extracted_pub_key = fetch_public_key_from_file_name(&file_name).unwrap();
         assert_eq!(&extracted_pub_key, pub_key);
     }
+
+    #[tokio::test]
+    async fn test_fetch_file_names_matches_literally() {
+        use crate::tss::common::storage::{
+            fetch_file_names_in_path, get_file_name, CurveType, StorageType,
+        };
+        let storage_type = StorageType::Transcript(CurveType::BLS);
+        // Glob metacharacters in the directory must not be taken as a pattern
+        let dir = std::env::temp_dir().join(format!("lit-storage-[{}]", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_name = get_file_name(storage_type, "abcdef", 0, None, Some(3));
+        std::fs::write(dir.join(&file_name), b"").unwrap();
+
+        let found =
+            fetch_file_names_in_path(storage_type, "abcdef", "H-*", dir.clone().into()).await;
+        let wildcard = fetch_file_names_in_path(storage_type, "*", "H-*", dir.clone().into()).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found.unwrap(), vec![file_name]);
+        assert!(wildcard.unwrap().is_empty());
+    }
 }
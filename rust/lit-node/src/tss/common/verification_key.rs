@@ -0,0 +1,567 @@
+//! Per-node verification key shares.
+//!
+//! The verification key share of a node is its secret key share times the
+//! generator, so clients can check each BLS signature share on its own, drop
+//! the bad ones and report the node that sent them, rather than failing to
+//! combine the whole set.  ECDSA shares can only be checked once combined (see
+//! `verify_combined_ecdsa_signature`).  The shares of a root key are points on
+//! the same polynomial as the root key itself, which `verify_key_shares`
+//! checks.  Keys derived from the ECDSA root keys are linear combinations of
+//! them, so their verification key shares follow from the root ones (see
+//! `derived_verification_key_share`).
+
+use crate::error::{unexpected_err, validation_err_code, Result, EC};
+use crate::peers::peer_state::models::SimplePeerExt;
+use crate::tss::common::curve_type::CurveType;
+use crate::tss::common::key_share_helper::KeyHelper;
+use crate::tss::common::storage::read_transcript_from_disk;
+use crate::tss::common::traits::key_persistence::KeyPersistence;
+use crate::tss::common::tss_state::TssState;
+use crate::tss::dkg::transcript::DkgTranscript;
+use crate::tss::ecdsa_cait_sith::protocols256k1::ID_SIGN_CTX;
+use crate::tss::hd_key_ecdsa::HdKeyDeriver;
+use blsful::inner_types::G1Projective;
+use blsful::{vsss_rs::Share, Bls12381G2Impl, Pairing, PublicKeyShare, SignatureShare};
+use elliptic_curve::group::{Curve, Group, GroupEncoding};
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::Field;
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::{Signature, VerifyingKey};
+use k256::Secp256k1;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+lazy_static! {
+    // By root key and epoch
+    static ref VERIFICATION_KEYS: RwLock<HashMap<(String, u64), VerificationKeyShare>> =
+        RwLock::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationKeyShare {
+    pub curve_type: CurveType,
+    /// The root key the share belongs to.
+    pub public_key: String,
+    /// The share index returned with signature shares.  The participant
+    /// identifier of the share is `share_index + 1`.
+    pub share_index: u16,
+    pub verification_key: String,
+}
+
+/// The verification key shares of this node for the root keys of the current
+/// epoch.  Keys whose share can't be read are left out.
+pub async fn verification_key_shares(
+    tss_state: &TssState,
+    curve_type: CurveType,
+    root_keys: &[String],
+) -> Vec<VerificationKeyShare> {
+    let epoch = tss_state.peer_state.epoch().await;
+    let share_index = match tss_state.peer_state.peers().await {
+        Ok(peers) => match peers.share_index(&tss_state.addr) {
+            Ok(share_index) => share_index,
+            Err(_) => return vec![],
+        },
+        Err(e) => {
+            warn!("Failed to get peers for verification key shares: {:?}", e);
+            return vec![];
+        }
+    };
+
+    let mut shares = Vec::with_capacity(root_keys.len());
+    for root_key in root_keys {
+        match verification_key_share(tss_state, curve_type, root_key, share_index, epoch).await {
+            Ok(share) => shares.push(share),
+            Err(e) => warn!(
+                "Failed to get the verification key share of {}: {:?}",
+                root_key, e
+            ),
+        }
+    }
+    shares
+}
+
+/// The verification key shares of every node for a root key, by share index,
+/// taken from the DKG transcript of the current epoch.  Nodes whose public key
+/// share isn't in the transcript are left out.
+pub async fn peer_verification_key_shares(
+    tss_state: &TssState,
+    curve_type: CurveType,
+    root_key: &str,
+) -> Result<BTreeMap<u16, VerificationKeyShare>> {
+    let epoch = tss_state.peer_state.epoch().await;
+    let staker_address = &tss_state.peer_state.hex_staker_address();
+    let transcript: DkgTranscript =
+        read_transcript_from_disk(root_key, epoch, curve_type, staker_address).await?;
+
+    Ok(transcript
+        .participants
+        .iter()
+        .filter_map(|participant| {
+            let verification_key = transcript
+                .public_key_shares
+                .get(&participant.protocol_index)?;
+            Some((
+                participant.share_index,
+                VerificationKeyShare {
+                    curve_type,
+                    public_key: root_key.to_string(),
+                    share_index: participant.share_index,
+                    verification_key: verification_key.clone(),
+                },
+            ))
+        })
+        .collect())
+}
+
+async fn verification_key_share(
+    tss_state: &TssState,
+    curve_type: CurveType,
+    root_key: &str,
+    share_index: u16,
+    epoch: u64,
+) -> Result<VerificationKeyShare> {
+    let cache_key = (root_key.to_string(), epoch);
+    if let Some(share) = VERIFICATION_KEYS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&cache_key)
+        .filter(|share| share.share_index == share_index)
+    {
+        return Ok(share.clone());
+    }
+
+    let staker_address = &tss_state.peer_state.hex_staker_address();
+    let verification_key = match curve_type {
+        CurveType::BLS => {
+            public_share::<G1Projective>(root_key, share_index, epoch, staker_address).await?
+        }
+        CurveType::K256 => {
+            public_share::<k256::ProjectivePoint>(root_key, share_index, epoch, staker_address)
+                .await?
+        }
+        _ => {
+            return Err(unexpected_err(
+                format!("No verification key shares for {} keys", curve_type),
+                None,
+            ))
+        }
+    };
+
+    let share = VerificationKeyShare {
+        curve_type,
+        public_key: root_key.to_string(),
+        share_index,
+        verification_key,
+    };
+    let mut cache = VERIFICATION_KEYS.write().unwrap_or_else(|e| e.into_inner());
+    // Only the current and the prior epoch are still signed with
+    cache.retain(|(_, cached_epoch), _| *cached_epoch + 1 >= epoch);
+    cache.insert(cache_key, share.clone());
+    Ok(share)
+}
+
+async fn public_share<G>(
+    root_key: &str,
+    share_index: u16,
+    epoch: u64,
+    staker_address: &str,
+) -> Result<String>
+where
+    G: Group + GroupEncoding + Default,
+    KeyHelper<G>: KeyPersistence<G>,
+{
+    let helper = KeyHelper::<G>::default();
+    let (secret, _) = helper
+        .read_key(root_key, share_index, epoch, staker_address)
+        .await?
+        .ok_or_else(|| unexpected_err(format!("No key share for {}", root_key), None))?;
+    Ok(helper.pk_to_hex(&(G::generator() * secret)))
+}
+
+/// Verify a BLS signature share, e.g. from `EncryptionSignResponse`, against
+/// the verification key share of the node that sent it.
+pub fn verify_bls_signature_share(
+    share: &VerificationKeyShare,
+    message: &[u8],
+    signature_share: &SignatureShare<Bls12381G2Impl>,
+) -> Result<()> {
+    if share.curve_type != CurveType::BLS {
+        return Err(invalid_share(share, "is not a BLS key share"));
+    }
+    let verification_key = KeyHelper::<G1Projective>::default()
+        .pk_from_hex(&share.verification_key)?
+        .to_compressed();
+    let identifier = share
+        .share_index
+        .checked_add(1)
+        .and_then(|identifier| u8::try_from(identifier).ok())
+        .ok_or_else(|| invalid_share(share, "has an invalid share index"))?;
+    let public_key_share = PublicKeyShare(
+        <Bls12381G2Impl as Pairing>::PublicKeyShare::with_identifier_and_value(
+            identifier,
+            &verification_key,
+        ),
+    );
+
+    signature_share
+        .verify(&public_key_share, message)
+        .map_err(|_| invalid_share(share, "doesn't match the signature share"))
+}
+
+/// Verify the signature combined from the ECDSA signature shares of a signing
+/// set, e.g. from PKP signing responses, against the (root or derived) public
+/// key they were signed with.
+///
+/// This does not verify the shares one by one and can't tell which node sent a
+/// bad share.  Unlike BLS, an ECDSA share `s_i = h * k_i + r * sigma_i` is
+/// masked by the node's presignature shares, which nothing public commits to,
+/// so it can't be checked against the node's verification key share.  A failed
+/// combination has to be retried with another signing set.
+pub fn verify_combined_ecdsa_signature(
+    public_key: &str,
+    message_hash: &k256::Scalar,
+    big_r: &k256::AffinePoint,
+    signature_shares: &[k256::Scalar],
+) -> Result<()> {
+    let public_key = KeyHelper::<k256::ProjectivePoint>::default().pk_from_hex(public_key)?;
+    let r = <k256::Scalar as Reduce<k256::U256>>::reduce_bytes(&big_r.x());
+    let s = signature_shares
+        .iter()
+        .fold(k256::Scalar::ZERO, |sum, share| sum + share);
+    let signature = Signature::from_scalars(r.to_bytes(), s.to_bytes()).map_err(|e| {
+        validation_err_code(e, EC::NodeBadInput, Some("Invalid signature shares".into()))
+    })?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    VerifyingKey::from_affine(public_key.to_affine())
+        .and_then(|key| key.verify_prehash(&message_hash.to_bytes(), &signature))
+        .map_err(|e| {
+            validation_err_code(
+                e,
+                EC::NodeBadInput,
+                Some("The signature shares don't combine into a valid signature".into()),
+            )
+        })
+}
+
+/// The verification key share of a node for a PKP derived (with `key_id`) from
+/// the ECDSA root keys, given its shares of the root keys in the order the key
+/// is derived from them.
+pub fn derived_verification_key_share(
+    key_id: &[u8],
+    root_shares: &[VerificationKeyShare],
+) -> Result<VerificationKeyShare> {
+    let share_index = match root_shares.first() {
+        Some(share) if root_shares.len() > 1 => share.share_index,
+        _ => {
+            return Err(validation_err_code(
+                "At least two root key shares are needed",
+                EC::NodeBadInput,
+                None,
+            ))
+        }
+    };
+
+    let helper = KeyHelper::<k256::ProjectivePoint>::default();
+    let mut public_keys = Vec::with_capacity(root_shares.len());
+    let mut verification_keys = Vec::with_capacity(root_shares.len());
+    for share in root_shares {
+        if share.curve_type != CurveType::K256 {
+            return Err(invalid_share(share, "is not an ECDSA key share"));
+        }
+        if share.share_index != share_index {
+            return Err(invalid_share(share, "belongs to another node"));
+        }
+        public_keys.push(helper.pk_from_hex(&share.public_key)?);
+        verification_keys.push(helper.pk_from_hex(&share.verification_key)?);
+    }
+
+    let deriver = HdKeyDeriver::<Secp256k1>::new(key_id, ID_SIGN_CTX)
+        .map_err(|e| validation_err_code(e, EC::NodeBadInput, None))?;
+    Ok(VerificationKeyShare {
+        curve_type: CurveType::K256,
+        public_key: helper.pk_to_hex(&deriver.compute_public_key(&public_keys)),
+        share_index,
+        verification_key: helper.pk_to_hex(&deriver.compute_public_key(&verification_keys)),
+    })
+}
+
+/// Check that the verification key shares of a root key, one per node, all lie
+/// on the polynomial of the given threshold whose constant term is the root key.
+pub fn verify_key_shares(
+    curve_type: CurveType,
+    public_key: &str,
+    shares: &[VerificationKeyShare],
+    threshold: usize,
+) -> Result<()> {
+    match curve_type {
+        CurveType::BLS => verify_key_shares_for::<G1Projective>(public_key, shares, threshold),
+        CurveType::K256 => {
+            verify_key_shares_for::<k256::ProjectivePoint>(public_key, shares, threshold)
+        }
+        _ => Err(validation_err_code(
+            format!("No verification key shares for {} keys", curve_type),
+            EC::NodeInvalidCurveType,
+            None,
+        )),
+    }
+}
+
+fn verify_key_shares_for<G>(
+    public_key: &str,
+    shares: &[VerificationKeyShare],
+    threshold: usize,
+) -> Result<()>
+where
+    G: Group + GroupEncoding + Default,
+    KeyHelper<G>: KeyPersistence<G>,
+{
+    let helper = KeyHelper::<G>::default();
+    let points = shares
+        .iter()
+        .map(|share| {
+            if share.public_key != public_key {
+                return Err(invalid_share(share, "belongs to another key"));
+            }
+            let id = G::Scalar::from(share.share_index as u64 + 1);
+            Ok((id, helper.pk_from_hex(&share.verification_key)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if threshold == 0 || points.len() < threshold {
+        return Err(validation_err_code(
+            format!("{} shares given, {} needed", points.len(), threshold),
+            EC::NodeBadInput,
+            None,
+        ));
+    }
+
+    let (basis, rest) = points.split_at(threshold);
+    if interpolate(basis, G::Scalar::ZERO)? != helper.pk_from_hex(public_key)? {
+        return Err(validation_err_code(
+            "The verification key shares don't add up to the public key",
+            EC::NodeBadInput,
+            None,
+        ));
+    }
+    for ((id, point), share) in rest.iter().zip(&shares[threshold..]) {
+        if interpolate(basis, *id)? != *point {
+            return Err(invalid_share(
+                share,
+                "is inconsistent with the other shares",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Evaluate the polynomial through the points at `x`, in the exponent
+fn interpolate<G: Group>(points: &[(G::Scalar, G)], x: G::Scalar) -> Result<G> {
+    let mut result = G::identity();
+    for (i, (x_i, y_i)) in points.iter().enumerate() {
+        let mut numerator = G::Scalar::ONE;
+        let mut denominator = G::Scalar::ONE;
+        for (j, (x_j, _)) in points.iter().enumerate() {
+            if i != j {
+                numerator *= x - x_j;
+                denominator *= *x_i - x_j;
+            }
+        }
+        let denominator = Option::<G::Scalar>::from(denominator.invert()).ok_or_else(|| {
+            validation_err_code("Duplicate share indices", EC::NodeBadInput, None)
+        })?;
+        result += *y_i * (numerator * denominator);
+    }
+    Ok(result)
+}
+
+fn invalid_share(share: &VerificationKeyShare, msg: &str) -> crate::error::Error {
+    validation_err_code(
+        format!(
+            "The verification key share of node {} for {} {}",
+            share.share_index, share.public_key, msg
+        ),
+        EC::NodeBadInput,
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blsful::{SecretKey, SecretKeyShare, SignatureSchemes};
+
+    fn bls_shares() -> (String, Vec<SecretKeyShare<Bls12381G2Impl>>) {
+        let secret_key = SecretKey::<Bls12381G2Impl>::new();
+        let shares = secret_key.split(2, 3).unwrap();
+        let public_key = KeyHelper::<G1Projective>::default().pk_to_hex(&secret_key.public_key().0);
+        (public_key, shares)
+    }
+
+    fn verification_key(
+        public_key: &str,
+        share: &SecretKeyShare<Bls12381G2Impl>,
+    ) -> VerificationKeyShare {
+        let secret = share
+            .0
+            .as_field_element::<blsful::inner_types::Scalar>()
+            .unwrap();
+        VerificationKeyShare {
+            curve_type: CurveType::BLS,
+            public_key: public_key.to_string(),
+            share_index: share.0.identifier() as u16 - 1,
+            verification_key: KeyHelper::<G1Projective>::default()
+                .pk_to_hex(&(G1Projective::generator() * secret)),
+        }
+    }
+
+    #[test]
+    fn verifies_bls_signature_shares() {
+        let (public_key, shares) = bls_shares();
+        let message = b"verification key shares";
+
+        let first = verification_key(&public_key, &shares[0]);
+        let signature_share = shares[0]
+            .sign(SignatureSchemes::ProofOfPossession, message)
+            .unwrap();
+        assert!(verify_bls_signature_share(&first, message, &signature_share).is_ok());
+        assert!(verify_bls_signature_share(&first, b"other", &signature_share).is_err());
+
+        let second = verification_key(&public_key, &shares[1]);
+        assert!(verify_bls_signature_share(&second, message, &signature_share).is_err());
+    }
+
+    #[test]
+    fn verifies_key_share_sets() {
+        let (public_key, shares) = bls_shares();
+        let mut verification_keys = shares
+            .iter()
+            .map(|share| verification_key(&public_key, share))
+            .collect::<Vec<_>>();
+        assert!(verify_key_shares(CurveType::BLS, &public_key, &verification_keys, 2).is_ok());
+        assert!(
+            verify_key_shares(CurveType::BLS, &public_key, &verification_keys[..1], 2).is_err()
+        );
+
+        verification_keys[2].verification_key =
+            KeyHelper::<G1Projective>::default().pk_to_hex(&G1Projective::generator());
+        assert!(verify_key_shares(CurveType::BLS, &public_key, &verification_keys, 2).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_share_indices() {
+        let (public_key, shares) = bls_shares();
+        let signature_share = shares[0]
+            .sign(SignatureSchemes::ProofOfPossession, b"message")
+            .unwrap();
+
+        let mut share = verification_key(&public_key, &shares[0]);
+        share.share_index = u16::MAX;
+        assert!(verify_bls_signature_share(&share, b"message", &signature_share).is_err());
+    }
+
+    #[test]
+    fn verifies_combined_ecdsa_signatures() {
+        let mut rng = rand::rngs::OsRng;
+        let helper = KeyHelper::<k256::ProjectivePoint>::default();
+        let secret_key = k256::Scalar::random(&mut rng);
+        let public_key = helper.pk_to_hex(&(k256::ProjectivePoint::GENERATOR * secret_key));
+
+        // As out of a presignature: R = k^-1 * G and additive shares of k and k * x
+        let k = k256::Scalar::random(&mut rng);
+        let big_r = (k256::ProjectivePoint::GENERATOR * k.invert().unwrap()).to_affine();
+        let r = <k256::Scalar as Reduce<k256::U256>>::reduce_bytes(&big_r.x());
+        let k_shares = [
+            k256::Scalar::random(&mut rng),
+            k256::Scalar::random(&mut rng),
+        ];
+        let sigma_shares = [
+            k256::Scalar::random(&mut rng),
+            k256::Scalar::random(&mut rng),
+        ];
+        let k_shares = [k_shares[0], k_shares[1], k - k_shares[0] - k_shares[1]];
+        let sigma_shares = [
+            sigma_shares[0],
+            sigma_shares[1],
+            k * secret_key - sigma_shares[0] - sigma_shares[1],
+        ];
+
+        let message_hash = k256::Scalar::random(&mut rng);
+        let mut signature_shares = k_shares
+            .iter()
+            .zip(&sigma_shares)
+            .map(|(k_i, sigma_i)| message_hash * k_i + r * sigma_i)
+            .collect::<Vec<_>>();
+        assert!(verify_combined_ecdsa_signature(
+            &public_key,
+            &message_hash,
+            &big_r,
+            &signature_shares
+        )
+        .is_ok());
+        assert!(verify_combined_ecdsa_signature(
+            &public_key,
+            &k256::Scalar::random(&mut rng),
+            &big_r,
+            &signature_shares
+        )
+        .is_err());
+
+        signature_shares[1] += k256::Scalar::ONE;
+        assert!(verify_combined_ecdsa_signature(
+            &public_key,
+            &message_hash,
+            &big_r,
+            &signature_shares
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn derives_pkp_verification_key_shares() {
+        let mut rng = rand::rngs::OsRng;
+        let helper = KeyHelper::<k256::ProjectivePoint>::default();
+        let key_id = b"pkp key id";
+        let root_secrets = (0..3)
+            .map(|_| k256::Scalar::random(&mut rng))
+            .collect::<Vec<_>>();
+        let secret_shares = (0..3)
+            .map(|_| k256::Scalar::random(&mut rng))
+            .collect::<Vec<_>>();
+        let root_shares = root_secrets
+            .iter()
+            .zip(&secret_shares)
+            .map(|(root_secret, secret_share)| VerificationKeyShare {
+                curve_type: CurveType::K256,
+                public_key: helper.pk_to_hex(&(k256::ProjectivePoint::GENERATOR * root_secret)),
+                share_index: 4,
+                verification_key: helper
+                    .pk_to_hex(&(k256::ProjectivePoint::GENERATOR * secret_share)),
+            })
+            .collect::<Vec<_>>();
+
+        // The same derivation the node applies to its secret shares when signing
+        let deriver = HdKeyDeriver::<Secp256k1>::new(key_id, ID_SIGN_CTX).unwrap();
+        let derived_secret = deriver.compute_secret_key(&root_secrets).unwrap();
+        let derived_share = deriver.compute_secret_key(&secret_shares).unwrap();
+
+        let share = derived_verification_key_share(key_id, &root_shares).unwrap();
+        assert_eq!(share.share_index, 4);
+        assert_eq!(
+            share.public_key,
+            helper.pk_to_hex(&(k256::ProjectivePoint::GENERATOR * derived_secret))
+        );
+        assert_eq!(
+            share.verification_key,
+            helper.pk_to_hex(&(k256::ProjectivePoint::GENERATOR * derived_share))
+        );
+
+        assert!(derived_verification_key_share(key_id, &root_shares[..1]).is_err());
+        let mut other_node = root_shares.clone();
+        other_node[2].share_index = 5;
+        assert!(derived_verification_key_share(key_id, &other_node).is_err());
+    }
+}
@@ -6,7 +6,9 @@ use crate::tss::common::{curve_type::CurveType, dkg_type::DkgType};
 use elliptic_curve::group::{Group, GroupEncoding};
 
 use super::super::dkg::gennaro::{GennaroDkg, Mode, RoundResult};
+use super::super::dkg::transcript::{DkgKind, DkgTranscript, TranscriptRecorder};
 use crate::error::{unexpected_err_code, Result, EC};
+use crate::tss::common::storage::{read_transcript_from_disk, write_transcript_to_disk};
 use crate::utils::consensus::get_threshold_count;
 use elliptic_curve::Field;
 use gennaro_dkg::Parameters;
@@ -24,7 +26,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
         curve_type: CurveType,
         key_state: impl KeyPersistence<G>,
     ) -> Result<String> {
-        let (pk, share, index) = self
+        let (pk, share, index, transcript) = self
             .execute(Mode::Initial, dkg_id, peers, peers, None)
             .await?;
 
@@ -46,6 +48,20 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
                 staker_address,
             )
             .await?;
+
+        if dkg_type == DkgType::Standard {
+            self.write_transcript(
+                transcript,
+                DkgKind::Keygen,
+                dkg_id,
+                &pubkey,
+                epoch,
+                peers,
+                curve_type,
+                None,
+            )
+            .await;
+        }
         Ok(pubkey)
     }
 
@@ -78,7 +94,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
             }
         };
 
-        let (result_pk, secret_share, index) = self
+        let (result_pk, secret_share, index, transcript) = self
             .execute(Mode::RefreshPeer, dkg_id, peers, peers, Some(private_share))
             .await?;
 
@@ -89,7 +105,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
         let peers = &peers.active_peers();
         let dkg_type = DkgType::Standard;
 
-        let _pubkey = key_state
+        let pubkey = key_state
             .write_key(
                 pubkey,
                 pk,
@@ -104,6 +120,23 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
             )
             .await?;
 
+        // Refreshed public key shares build on those of the previous epoch
+        let previous =
+            read_transcript_from_disk::<DkgTranscript>(&pubkey, epoch, curve_type, staker_address)
+                .await
+                .ok();
+        self.write_transcript(
+            transcript,
+            DkgKind::Refresh,
+            dkg_id,
+            &pubkey,
+            next_epoch,
+            peers,
+            curve_type,
+            previous.as_ref(),
+        )
+        .await;
+
         Ok(true)
     }
 
@@ -138,7 +171,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
             Err(_) => (None, None, Mode::NewPeer),
         };
 
-        let (pk, share, index) = self
+        let (pk, share, index, transcript) = self
             .execute(mode, dkg_id, peers, next_peers, private_share)
             .await?;
 
@@ -153,7 +186,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
         let peers = &next_peers.active_peers();
         let dkg_type = DkgType::Standard;
 
-        let _pubkey = key_state
+        let pubkey = key_state
             .write_key(
                 pubkey,
                 pk,
@@ -168,9 +201,61 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
             )
             .await?;
 
+        self.write_transcript(
+            transcript,
+            DkgKind::Reshare,
+            dkg_id,
+            &pubkey,
+            next_epoch,
+            peers,
+            curve_type,
+            None,
+        )
+        .await;
+
         Ok(true)
     }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn write_transcript(
+        &self,
+        transcript: TranscriptRecorder<G>,
+        kind: DkgKind,
+        dkg_id: &str,
+        pubkey: &str,
+        epoch: u64,
+        peers: &[SimplePeer],
+        curve_type: CurveType,
+        previous: Option<&DkgTranscript>,
+    ) {
+        let mut peers = peers.to_vec();
+        peers.set_all_protocol_indices(1);
+        let staker_address = &self.state.peer_state.hex_staker_address();
+
+        // The key shares are in place, so a missing transcript is only reported
+        let result = match transcript.finish(
+            dkg_id,
+            kind,
+            curve_type,
+            epoch,
+            &peers,
+            pubkey.to_string(),
+            previous,
+        ) {
+            Ok(transcript) => {
+                write_transcript_to_disk(pubkey, epoch, curve_type, staker_address, &transcript)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!(
+                "Writing the DKG transcript for {} pubkey: {} epoch: {} failed: {}",
+                curve_type, pubkey, epoch, e
+            );
+        }
+    }
+
     #[instrument(skip_all, fields(txn_prefix = txn_prefix))]
     pub async fn execute(
         &self,
@@ -179,7 +264,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
         current_peers: &Vec<SimplePeer>,
         next_peers: &Vec<SimplePeer>,
         share: Option<<G as Group>::Scalar>,
-    ) -> Result<(G, G::Scalar, u16)> {
+    ) -> Result<(G, G::Scalar, u16, TranscriptRecorder<G>)> {
         // setup Gennaro DKG
 
         // Set the protocol identifier; for Generao, this is 1 based, so we just offset it by 1
@@ -207,6 +292,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
         };
 
         let mut dkg = GennaroDkg::<G>::default();
+        let mut transcript = TranscriptRecorder::<G>::default();
         dkg.init_participant(
             &mode,
             peer_id,
@@ -231,6 +317,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
                     break;
                 }
             };
+            transcript.record_output(peer_id, &round_output);
 
             let round = dkg.get_round().expect_or_err("Empty round")?.to_string();
             let cm =
@@ -245,6 +332,19 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
 
             let received = cm.collect::<RoundResult<G>>().await?;
 
+            for dest_peer in next_peers.all_peers_except(&self.state.addr).iter() {
+                if !received
+                    .iter()
+                    .any(|(share_index, _)| *share_index == dest_peer.share_index)
+                {
+                    transcript.complain(
+                        dest_peer.get_protocol_index()? as usize,
+                        &round,
+                        "No data received",
+                    );
+                }
+            }
+
             for (share_index, data) in received.iter() {
                 let peer_id = next_peers
                     .peer_at_share_index(*share_index)
@@ -252,7 +352,10 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
                     .get_protocol_index()?;
                 if let Err(e) = dkg.add_peer_data(peer_id as usize, data.clone()) {
                     error!("Error while adding peer data: {}", e);
+                    transcript.complain(peer_id as usize, &round, "Invalid data received");
+                    continue;
                 }
+                transcript.record_result(peer_id as usize, data);
             }
         }
 
@@ -269,6 +372,7 @@ impl<G: Group + GroupEncoding + Default> GennaroMpcDkg<G> {
             dkg.get_public_key().expect_or_err("Empty public key")?,
             dkg.get_secret_share().expect_or_err("Empty secret share")?,
             share_index,
+            transcript,
         ))
     }
 }
@@ -2,3 +2,4 @@ pub mod curves;
 pub mod do_dkg;
 pub mod gennaro;
 pub mod traits;
+pub mod transcript;
@@ -0,0 +1,540 @@
+//! Publicly verifiable DKG transcripts.
+//!
+//! Every keygen, refresh and reshare leaves a transcript behind: the participant
+//! set, the Feldman commitments of every participant whose shares were accepted,
+//! the complaints raised along the way, and the resulting public key and public
+//! key shares.  Anyone holding a transcript can check it with
+//! `verify_transcript`, without trusting the node that served it:
+//!
+//! * the commitments of a keygen or reshare sum to the public key, while those
+//!   of a refresh sum to the identity, so a refresh can't change the key;
+//! * the public key share of participant `j` is the sum of all commitment
+//!   polynomials evaluated at `j`, plus its previous share for a refresh.
+//!
+//! `verify_transcript_chain` checks a run of consecutive epochs, which ties the
+//! key in `/web/handshake` back to the keygen that created it.
+
+use super::gennaro::{RoundOutputs, RoundResult};
+use crate::error::{validation_err_code, Result, EC};
+use crate::peers::peer_state::models::SimplePeer;
+use crate::tss::common::curve_type::CurveType;
+use crate::tss::common::key_share_helper::KeyHelper;
+use crate::tss::common::traits::key_persistence::KeyPersistence;
+use crate::utils::consensus::get_threshold_count;
+use blsful::inner_types::G1Projective;
+use elliptic_curve::group::{Group, GroupEncoding};
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DkgKind {
+    Keygen,
+    Refresh,
+    Reshare,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptParticipant {
+    pub protocol_index: u16,
+    pub share_index: u16,
+    pub staker_address: H160,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DkgComplaint {
+    /// The protocol index of the participant complained about.
+    pub participant: u16,
+    pub round: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DkgTranscript {
+    pub dkg_id: String,
+    pub kind: DkgKind,
+    pub curve_type: CurveType,
+    /// The epoch of the resulting key shares.
+    pub epoch: u64,
+    pub threshold: u16,
+    pub participants: Vec<TranscriptParticipant>,
+    /// The hex-encoded Feldman commitments by protocol index, constant term first.
+    pub commitments: BTreeMap<u16, Vec<String>>,
+    pub complaints: Vec<DkgComplaint>,
+    /// The public key, encoded as it is everywhere else for the curve.
+    pub public_key: String,
+    /// The hex-encoded public key shares by protocol index.  Empty for a refresh
+    /// whose previous transcript isn't known.
+    pub public_key_shares: BTreeMap<u16, String>,
+}
+
+/// Collects the public parts of a DKG while it runs.
+#[derive(Debug)]
+pub struct TranscriptRecorder<G: Group + GroupEncoding + Default> {
+    accepted: Option<BTreeSet<usize>>,
+    commitments: BTreeMap<usize, Vec<G>>,
+    complaints: Vec<DkgComplaint>,
+}
+
+impl<G: Group + GroupEncoding + Default> Default for TranscriptRecorder<G> {
+    fn default() -> Self {
+        Self {
+            accepted: None,
+            commitments: BTreeMap::new(),
+            complaints: Vec::new(),
+        }
+    }
+}
+
+impl<G: Group + GroupEncoding + Default> TranscriptRecorder<G> {
+    /// Record the output of this node for a round.
+    pub fn record_output(&mut self, peer_id: usize, output: &RoundOutputs<G>) {
+        match output {
+            RoundOutputs::Round2(data) => {
+                self.accepted = Some(data.0.valid_participant_ids.clone());
+            }
+            RoundOutputs::Round3(data) => {
+                self.commitments.insert(peer_id, data.0.commitments.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Record the data received from another participant.
+    pub fn record_result(&mut self, peer_id: usize, result: &RoundResult<G>) {
+        if let RoundResult::Round3BroadcastData(data) = result {
+            self.commitments.insert(peer_id, data.commitments.clone());
+        }
+    }
+
+    pub fn complain(&mut self, peer_id: usize, round: &str, reason: impl Into<String>) {
+        self.complaints.push(DkgComplaint {
+            participant: peer_id as u16,
+            round: round.to_string(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Build the transcript.  `peers` must have their protocol indices set, and
+    /// `previous` is the transcript of the epoch a refresh started from.
+    #[allow(clippy::too_many_arguments)]
+    pub fn finish(
+        mut self,
+        dkg_id: &str,
+        kind: DkgKind,
+        curve_type: CurveType,
+        epoch: u64,
+        peers: &[SimplePeer],
+        public_key: String,
+        previous: Option<&DkgTranscript>,
+    ) -> Result<DkgTranscript> {
+        let participants = peers
+            .iter()
+            .map(|peer| {
+                Ok(TranscriptParticipant {
+                    protocol_index: peer.get_protocol_index()?,
+                    share_index: peer.share_index,
+                    staker_address: peer.staker_address,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Participants that failed verification in round 2 don't contribute
+        if let Some(accepted) = &self.accepted {
+            let rejected = self
+                .commitments
+                .keys()
+                .filter(|id| !accepted.contains(id))
+                .copied()
+                .collect::<Vec<_>>();
+            for id in rejected {
+                self.commitments.remove(&id);
+                self.complain(id, "2", "Shares failed verification");
+            }
+        }
+
+        let aggregate = aggregate_commitments(self.commitments.values());
+        let public_key_shares = match (kind, previous) {
+            (DkgKind::Refresh, None) => BTreeMap::new(),
+            (DkgKind::Refresh, Some(previous)) => participants
+                .iter()
+                .map(|p| {
+                    let previous_share = previous
+                        .public_key_shares
+                        .get(&p.protocol_index)
+                        .ok_or_else(|| missing_share(p.protocol_index))
+                        .and_then(|share| point_from_hex::<G>(share))?;
+                    let share = previous_share + evaluate(&aggregate, p.protocol_index);
+                    Ok((p.protocol_index, point_to_hex(&share)))
+                })
+                .collect::<Result<_>>()?,
+            _ => participants
+                .iter()
+                .map(|p| {
+                    let share = evaluate(&aggregate, p.protocol_index);
+                    (p.protocol_index, point_to_hex(&share))
+                })
+                .collect(),
+        };
+
+        Ok(DkgTranscript {
+            dkg_id: dkg_id.to_string(),
+            kind,
+            curve_type,
+            epoch,
+            threshold: get_threshold_count(participants.len()) as u16,
+            participants,
+            commitments: self
+                .commitments
+                .iter()
+                .map(|(id, commitments)| {
+                    (*id as u16, commitments.iter().map(point_to_hex).collect())
+                })
+                .collect(),
+            complaints: self.complaints,
+            public_key,
+            public_key_shares,
+        })
+    }
+}
+
+/// Verify a transcript against its commitments.  A refresh can only be checked
+/// together with the transcript of the epoch before it.
+pub fn verify_transcript(
+    transcript: &DkgTranscript,
+    previous: Option<&DkgTranscript>,
+) -> Result<()> {
+    match transcript.curve_type {
+        CurveType::BLS => {
+            verify_for_curve(KeyHelper::<G1Projective>::default(), transcript, previous)
+        }
+        CurveType::K256 => verify_for_curve(
+            KeyHelper::<k256::ProjectivePoint>::default(),
+            transcript,
+            previous,
+        ),
+        CurveType::P256 => verify_for_curve(
+            KeyHelper::<p256::ProjectivePoint>::default(),
+            transcript,
+            previous,
+        ),
+        CurveType::P384 => verify_for_curve(
+            KeyHelper::<p384::ProjectivePoint>::default(),
+            transcript,
+            previous,
+        ),
+        CurveType::Ed25519 => verify_for_curve(
+            KeyHelper::<curve25519_dalek::edwards::SubgroupPoint>::default(),
+            transcript,
+            previous,
+        ),
+        CurveType::Ristretto25519 => verify_for_curve(
+            KeyHelper::<curve25519_dalek::RistrettoPoint>::default(),
+            transcript,
+            previous,
+        ),
+        CurveType::Ed448 => verify_for_curve(
+            KeyHelper::<ed448_goldilocks::EdwardsPoint>::default(),
+            transcript,
+            previous,
+        ),
+        CurveType::RedJubjub => verify_for_curve(
+            KeyHelper::<jubjub::SubgroupPoint>::default(),
+            transcript,
+            previous,
+        ),
+    }
+}
+
+/// Verify the transcripts of consecutive epochs, starting with a keygen.
+pub fn verify_transcript_chain(transcripts: &[DkgTranscript]) -> Result<()> {
+    let first = transcripts
+        .first()
+        .ok_or_else(|| invalid("The transcript chain is empty"))?;
+    if first.kind != DkgKind::Keygen {
+        return Err(invalid("The transcript chain doesn't start with a keygen"));
+    }
+    verify_transcript(first, None)?;
+    for pair in transcripts.windows(2) {
+        verify_transcript(&pair[1], Some(&pair[0]))?;
+    }
+    Ok(())
+}
+
+fn verify_for_curve<G, K>(
+    helper: K,
+    transcript: &DkgTranscript,
+    previous: Option<&DkgTranscript>,
+) -> Result<()>
+where
+    G: Group + GroupEncoding + Default,
+    K: KeyPersistence<G>,
+{
+    let threshold = get_threshold_count(transcript.participants.len());
+    if transcript.threshold as usize != threshold {
+        return Err(invalid(format!(
+            "Threshold {} doesn't match {} participants",
+            transcript.threshold,
+            transcript.participants.len()
+        )));
+    }
+
+    let ids = transcript
+        .participants
+        .iter()
+        .map(|p| p.protocol_index)
+        .collect::<BTreeSet<_>>();
+    if ids.len() != transcript.participants.len() || ids.contains(&0) {
+        return Err(invalid(
+            "Participant protocol indices must be unique and non-zero",
+        ));
+    }
+    if transcript.commitments.len() < threshold {
+        return Err(invalid(format!(
+            "Only {} participants contributed, {} needed",
+            transcript.commitments.len(),
+            threshold
+        )));
+    }
+
+    let mut commitments = Vec::with_capacity(transcript.commitments.len());
+    for (id, points) in transcript.commitments.iter() {
+        if !ids.contains(id) {
+            return Err(invalid(format!("Participant {} isn't in the set", id)));
+        }
+        if points.len() != threshold {
+            return Err(invalid(format!(
+                "Participant {} committed to {} coefficients, {} expected",
+                id,
+                points.len(),
+                threshold
+            )));
+        }
+        commitments.push(
+            points
+                .iter()
+                .map(|point| point_from_hex::<G>(point))
+                .collect::<Result<Vec<G>>>()?,
+        );
+    }
+    let aggregate = aggregate_commitments(commitments.iter());
+    let constant_term = aggregate.first().copied().unwrap_or_else(G::identity);
+
+    if let Some(previous) = previous {
+        if previous.curve_type != transcript.curve_type
+            || previous.public_key != transcript.public_key
+            || previous.epoch + 1 != transcript.epoch
+        {
+            return Err(invalid(format!(
+                "Epoch {} doesn't follow on from epoch {} of the same key",
+                transcript.epoch, previous.epoch
+            )));
+        }
+    }
+
+    let previous_shares = match transcript.kind {
+        DkgKind::Keygen | DkgKind::Reshare => {
+            if helper.pk_from_hex(&transcript.public_key)? != constant_term {
+                return Err(invalid("The commitments don't add up to the public key"));
+            }
+            None
+        }
+        DkgKind::Refresh => {
+            if !bool::from(constant_term.is_identity()) {
+                return Err(invalid("The refresh commitments change the public key"));
+            }
+            let previous = previous.ok_or_else(|| {
+                invalid("A refresh can only be verified with the previous transcript")
+            })?;
+            Some(&previous.public_key_shares)
+        }
+    };
+
+    if transcript.public_key_shares.len() != ids.len() {
+        return Err(invalid("A public key share is missing"));
+    }
+    for id in ids {
+        let share = transcript
+            .public_key_shares
+            .get(&id)
+            .ok_or_else(|| missing_share(id))
+            .and_then(|share| point_from_hex::<G>(share))?;
+        let mut expected = evaluate(&aggregate, id);
+        if let Some(previous_shares) = previous_shares {
+            expected += previous_shares
+                .get(&id)
+                .ok_or_else(|| missing_share(id))
+                .and_then(|share| point_from_hex::<G>(share))?;
+        }
+        if share != expected {
+            return Err(invalid(format!(
+                "The public key share of participant {} doesn't match the commitments",
+                id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn aggregate_commitments<'a, G: Group + GroupEncoding + Default + 'a>(
+    commitments: impl Iterator<Item = &'a Vec<G>>,
+) -> Vec<G> {
+    commitments.fold(Vec::new(), |mut aggregate, points| {
+        if aggregate.len() < points.len() {
+            aggregate.resize(points.len(), G::identity());
+        }
+        for (sum, point) in aggregate.iter_mut().zip(points.iter()) {
+            *sum += point;
+        }
+        aggregate
+    })
+}
+
+/// Evaluate the commitment polynomial at `id` in the exponent.
+fn evaluate<G: Group>(commitments: &[G], id: u16) -> G {
+    let x = G::Scalar::from(id as u64);
+    commitments
+        .iter()
+        .rev()
+        .fold(G::identity(), |acc, commitment| acc * x + commitment)
+}
+
+fn point_to_hex<G: GroupEncoding>(point: &G) -> String {
+    hex::encode(point.to_bytes().as_ref())
+}
+
+fn point_from_hex<G: GroupEncoding>(point: &str) -> Result<G> {
+    let bytes = hex::decode(point).map_err(|_| invalid(format!("Invalid point {}", point)))?;
+    let mut repr = G::Repr::default();
+    if repr.as_ref().len() != bytes.len() {
+        return Err(invalid(format!("Invalid point length {}", point)));
+    }
+    repr.as_mut().copy_from_slice(&bytes);
+    Option::from(G::from_bytes(&repr)).ok_or_else(|| invalid(format!("Invalid point {}", point)))
+}
+
+fn missing_share(id: u16) -> crate::error::Error {
+    invalid(format!(
+        "The public key share of participant {} is missing",
+        id
+    ))
+}
+
+fn invalid(msg: impl Into<String>) -> crate::error::Error {
+    validation_err_code(msg.into(), EC::NodeDKGTranscriptInvalid, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elliptic_curve::Field;
+    use k256::{ProjectivePoint, Scalar};
+
+    // Participants 1..=n each share a random polynomial of degree t - 1
+    fn polynomials(n: usize, t: usize, constant: Option<Scalar>) -> Vec<Vec<Scalar>> {
+        let mut rng = rand::thread_rng();
+        (0..n)
+            .map(|_| {
+                let mut coefficients = (0..t).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+                if let Some(constant) = constant {
+                    coefficients[0] = constant;
+                }
+                coefficients
+            })
+            .collect()
+    }
+
+    fn transcript(
+        kind: DkgKind,
+        epoch: u64,
+        public_key: &ProjectivePoint,
+        polynomials: &[Vec<Scalar>],
+        previous: Option<&DkgTranscript>,
+    ) -> DkgTranscript {
+        let mut recorder = TranscriptRecorder::<ProjectivePoint>::default();
+        for (i, coefficients) in polynomials.iter().enumerate() {
+            recorder.commitments.insert(
+                i + 1,
+                coefficients
+                    .iter()
+                    .map(|c| ProjectivePoint::GENERATOR * c)
+                    .collect(),
+            );
+        }
+        let peers = (0..polynomials.len() as u16)
+            .map(|share_index| SimplePeer {
+                socket_address: format!("127.0.0.1:{}", 7470 + share_index),
+                share_index,
+                protocol_index: Some(share_index + 1),
+                staker_address: H160::random(),
+                key_hash: share_index as u64,
+                kicked: false,
+                version: semver::Version::new(0, 0, 0),
+            })
+            .collect::<Vec<_>>();
+        let public_key = KeyHelper::<ProjectivePoint>::default().pk_to_hex(public_key);
+        recorder
+            .finish(
+                "test",
+                kind,
+                CurveType::K256,
+                epoch,
+                &peers,
+                public_key,
+                previous,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn verifies_keygen_and_refresh() {
+        let keygen = polynomials(5, 3, None);
+        let public_key = keygen.iter().fold(ProjectivePoint::IDENTITY, |acc, c| {
+            acc + ProjectivePoint::GENERATOR * c[0]
+        });
+        let first = transcript(DkgKind::Keygen, 1, &public_key, &keygen, None);
+        assert!(verify_transcript(&first, None).is_ok());
+
+        let refresh = polynomials(5, 3, Some(Scalar::ZERO));
+        let second = transcript(DkgKind::Refresh, 2, &public_key, &refresh, Some(&first));
+        assert!(verify_transcript(&second, Some(&first)).is_ok());
+        assert!(verify_transcript(&second, None).is_err());
+        assert!(verify_transcript_chain(&[first.clone(), second.clone()]).is_ok());
+        assert!(verify_transcript_chain(&[second]).is_err());
+
+        // A refresh mustn't move the key
+        let bad_refresh = polynomials(5, 3, None);
+        let third = transcript(DkgKind::Refresh, 2, &public_key, &bad_refresh, Some(&first));
+        assert!(verify_transcript(&third, Some(&first)).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_transcripts() {
+        let keygen = polynomials(4, 2, None);
+        let public_key = keygen.iter().fold(ProjectivePoint::IDENTITY, |acc, c| {
+            acc + ProjectivePoint::GENERATOR * c[0]
+        });
+        let honest = transcript(DkgKind::Keygen, 1, &public_key, &keygen, None);
+
+        let mut wrong_key = honest.clone();
+        wrong_key.public_key =
+            KeyHelper::<ProjectivePoint>::default().pk_to_hex(&ProjectivePoint::GENERATOR);
+        assert!(verify_transcript(&wrong_key, None).is_err());
+
+        let mut wrong_share = honest.clone();
+        wrong_share
+            .public_key_shares
+            .insert(2, point_to_hex(&ProjectivePoint::GENERATOR));
+        assert!(verify_transcript(&wrong_share, None).is_err());
+
+        let mut dropped = honest.clone();
+        dropped.commitments.remove(&1);
+        dropped.commitments.remove(&2);
+        assert!(verify_transcript(&dropped, None).is_err());
+    }
+}
@@ -38,7 +38,7 @@ pub struct CsSigshare {
     pub msg_hash: Scalar,
 }
 
-const ID_SIGN_CTX: &[u8] = b"LIT_HD_KEY_ID_K256_XMD:SHA-256_SSWU_RO_NUL_";
+pub(crate) const ID_SIGN_CTX: &[u8] = b"LIT_HD_KEY_ID_K256_XMD:SHA-256_SSWU_RO_NUL_";
 
 impl CsEcdsaState {
     // keygen is now using Gennaro DKG - see that implementation in gennaro_dkg.rs
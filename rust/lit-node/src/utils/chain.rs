@@ -17,5 +17,7 @@ pub fn is_evm_compatible_chain(chain: &Chain) -> bool {
             | Chain::Solana
             | Chain::EvmosCosmos
             | Chain::EvmosCosmosTestnet
+            | Chain::Bitcoin
+            | Chain::BitcoinTestnet
     )
 }
@@ -1 +1,2 @@
 pub mod guards;
+pub mod sealed_response;
@@ -0,0 +1,454 @@
+//! End-to-end encrypted response shares.
+//!
+//! The handshake registers the X25519 `clientPublicKey` of the client and
+//! returns a `sealingKeyId` for it.  A client opts in by sending that id in
+//! the `X-Lit-Sealing-Key-Id` header, together with an `X-Request-Id`.  The
+//! secret-bearing fields of the response, such as signature and decryption
+//! shares, are then sealed to its handshake key with `crypto_box` before they
+//! leave the node, so TLS-terminating proxies in front of it only see
+//! ciphertext.  Every field is sealed with a fresh ephemeral key, and the
+//! plaintext binds the request id and the field path, so a sealed value can't
+//! be replayed in another response or moved to another field.
+//!
+//! A sealed response lists the sealed field paths under `sealedFields`, and
+//! each of them holds a `SealedValue`, which `open_sealed_response` reverses
+//! (`openSealedResponse` in `tests/sdk/sealed_response.mjs` for JS clients).
+//! The empty path seals the whole response, for endpoints that return a list;
+//! the response is then a `SealedValue` listing `sealedFields` itself.
+
+use crate::error::{conversion_err, validation_err_code, Result, EC};
+use crypto_box::aead::{Aead, AeadCore, OsRng};
+use crypto_box::{Nonce, PublicKey, SalsaBox, SecretKey};
+use lit_api_core::context::{HEADER_KEY_X_CORRELATION_ID, HEADER_KEY_X_REQUEST_ID};
+use lit_api_core::error::ApiError;
+use moka::future::Cache;
+use rocket::http::{ContentType, StatusClass};
+use rocket::serde::json::{serde_json, Value};
+use rocket::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const HEADER_KEY_X_LIT_SEALING_KEY_ID: &str = "X-Lit-Sealing-Key-Id";
+pub const SEALED_FIELDS_KEY: &str = "sealedFields";
+
+// How long a handshake key can be used to seal responses
+const HANDSHAKE_KEY_TTL: Duration = Duration::from_secs(60 * 60);
+const HANDSHAKE_KEY_MAX_CAPACITY: u64 = 100_000;
+
+// The secret-bearing fields of each endpoint, as dot separated paths (the
+// empty path being the whole response, on its own)
+const SEALED_ROUTES: &[(&str, &[&str])] = &[
+    ("/web/encryption/sign", &["signatureShare"]),
+    ("/web/encryption/sign/v1", &["signatureShare"]),
+    ("/web/encryption/timelock/sign", &["signatureShare"]),
+    ("/web/signing/access_control_condition", &["signatureShare"]),
+    (
+        "/web/signing/access_control_condition/v1",
+        &["signatureShare"],
+    ),
+    (
+        "/web/sign_session_key",
+        &["signedData.sessionSig.signatureShare"],
+    ),
+    ("/web/sign_session_key/v1", &["signatureShare"]),
+    ("/web/pkp/sign", &["signatureShare"]),
+    ("/web/pkp/sign/v1", &["signatureShare"]),
+    ("/web/execute", &["signedData", "decryptedData", "response"]),
+    (
+        "/web/execute/v1",
+        &["signedData", "decryptedData", "response"],
+    ),
+    ("/web/recovery/get_dec_share", &[""]),
+];
+
+/// The client keys of recent handshakes, by sealing key id.
+pub struct HandshakeKeys {
+    keys: Cache<String, [u8; 32]>,
+}
+
+impl Default for HandshakeKeys {
+    fn default() -> Self {
+        Self {
+            keys: Cache::builder()
+                .time_to_live(HANDSHAKE_KEY_TTL)
+                .max_capacity(HANDSHAKE_KEY_MAX_CAPACITY)
+                .build(),
+        }
+    }
+}
+
+impl HandshakeKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the public key of a handshake, returning its sealing key id,
+    /// or `None` if it isn't an X25519 public key.
+    pub async fn register(&self, client_public_key: &str) -> Option<String> {
+        let key = public_key_from_hex(client_public_key).ok()?;
+        let id = hex::encode(rand::random::<[u8; 16]>());
+        self.keys.insert(id.clone(), *key.as_bytes()).await;
+        Some(id)
+    }
+
+    pub async fn get(&self, id: &str) -> Option<PublicKey> {
+        self.keys.get(id).await.map(PublicKey::from)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedValue {
+    pub ephemeral_public_key: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SealedPlaintext {
+    request_id: String,
+    field: String,
+    value: Value,
+}
+
+/// Seal the secret-bearing fields of successful responses for clients that
+/// asked for it.  Attached as a response fairing.
+pub async fn seal_response<'r>(req: &'r Request<'_>, resp: &mut Response<'r>) {
+    let Some(key_id) = req.headers().get_one(HEADER_KEY_X_LIT_SEALING_KEY_ID) else {
+        return;
+    };
+    let Some((_, fields)) = SEALED_ROUTES
+        .iter()
+        .find(|(path, _)| req.uri().path() == *path)
+    else {
+        return;
+    };
+    if resp.status().class() != StatusClass::Success {
+        return;
+    }
+
+    let client_key = match req.rocket().state::<Arc<HandshakeKeys>>() {
+        Some(handshake_keys) => handshake_keys.get(key_id).await,
+        None => None,
+    };
+    let request_id = req
+        .headers()
+        .get_one(HEADER_KEY_X_REQUEST_ID)
+        .or_else(|| req.headers().get_one(HEADER_KEY_X_CORRELATION_ID));
+    let sealed = match (client_key, request_id) {
+        (Some(client_key), Some(request_id)) => match resp.body_mut().to_bytes().await {
+            Ok(body) => seal_body(&body, &client_key, request_id, fields),
+            Err(e) => Err(conversion_err(
+                e,
+                Some("Unable to read the response".into()),
+            )),
+        },
+        (None, _) => Err(validation_err_code(
+            format!(
+                "Unknown or expired {}, handshake again",
+                HEADER_KEY_X_LIT_SEALING_KEY_ID
+            ),
+            EC::NodeBadInput,
+            None,
+        )),
+        (_, None) => Err(validation_err_code(
+            format!(
+                "Sealed responses need an {} header",
+                HEADER_KEY_X_REQUEST_ID
+            ),
+            EC::NodeBadInput,
+            None,
+        )),
+    };
+
+    // Never fall back to sending the shares in the clear
+    let (status, body) = match sealed {
+        Ok(body) => (resp.status(), body),
+        Err(e) => {
+            let handled = e.handle();
+            (handled.0, handled.1.to_string().into_bytes())
+        }
+    };
+    resp.set_status(status);
+    resp.set_header(ContentType::JSON);
+    resp.set_sized_body(body.len(), Cursor::new(body));
+}
+
+fn seal_body(
+    body: &[u8],
+    client_key: &PublicKey,
+    request_id: &str,
+    fields: &[&str],
+) -> Result<Vec<u8>> {
+    let mut response: Value = serde_json::from_slice(body)
+        .map_err(|e| conversion_err(e, Some("The response isn't JSON".into())))?;
+    seal_fields(&mut response, client_key, request_id, fields)?;
+    serde_json::to_vec(&response)
+        .map_err(|e| conversion_err(e, Some("Unable to serialize the response".into())))
+}
+
+/// Seal the fields at the given dot separated paths of a JSON response in
+/// place.  Fails if any of them is missing, so nothing is left in the clear.
+pub fn seal_fields(
+    response: &mut Value,
+    client_key: &PublicKey,
+    request_id: &str,
+    fields: &[&str],
+) -> Result<()> {
+    // Only a sealed whole response is an object whatever it held
+    if !response.is_object() && fields != [""] {
+        return Err(conversion_err("The response isn't a JSON object", None));
+    }
+
+    let mut sealed_fields = Vec::new();
+    for field in fields {
+        let slot = response
+            .pointer_mut(&json_pointer(field))
+            .ok_or_else(|| conversion_err(format!("Field {} to seal is missing", field), None))?;
+        let plaintext = serde_json::to_vec(&SealedPlaintext {
+            request_id: request_id.to_string(),
+            field: field.to_string(),
+            value: slot.take(),
+        })
+        .map_err(|e| conversion_err(e, None))?;
+
+        let ephemeral_key = SecretKey::generate(&mut OsRng);
+        let nonce = SalsaBox::generate_nonce(&mut OsRng);
+        let ciphertext = SalsaBox::new(client_key, &ephemeral_key)
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|e| conversion_err(format!("{:?}", e), Some("Unable to seal".into())))?;
+
+        let sealed = SealedValue {
+            ephemeral_public_key: hex::encode(ephemeral_key.public_key().as_bytes()),
+            nonce: hex::encode(nonce.as_slice()),
+            ciphertext: hex::encode(ciphertext),
+        };
+        *slot = serde_json::to_value(sealed).map_err(|e| conversion_err(e, None))?;
+        sealed_fields.push(Value::String(field.to_string()));
+    }
+    if let Some(object) = response.as_object_mut() {
+        object.insert(SEALED_FIELDS_KEY.to_string(), Value::Array(sealed_fields));
+    }
+
+    Ok(())
+}
+
+/// Open the sealed fields of a response in place, for the client holding the
+/// secret key of its handshake.
+pub fn open_sealed_response(
+    response: &mut Value,
+    client_secret: &SecretKey,
+    request_id: &str,
+) -> Result<()> {
+    let Some(object) = response.as_object_mut() else {
+        return Err(conversion_err("The response isn't a JSON object", None));
+    };
+    let Some(Value::Array(fields)) = object.remove(SEALED_FIELDS_KEY) else {
+        return Ok(());
+    };
+
+    for field in fields {
+        let field = field
+            .as_str()
+            .ok_or_else(|| conversion_err("Invalid sealed field name", None))?;
+        let slot = response
+            .pointer_mut(&json_pointer(field))
+            .ok_or_else(|| conversion_err(format!("Sealed field {} is missing", field), None))?;
+        let sealed: SealedValue =
+            serde_json::from_value(slot.take()).map_err(|e| conversion_err(e, None))?;
+        *slot = open_value(&sealed, client_secret, request_id, field)?;
+    }
+
+    Ok(())
+}
+
+// `signedData.sessionSig` -> `/signedData/sessionSig`, `` -> `` (the root)
+fn json_pointer(path: &str) -> String {
+    if path.is_empty() {
+        return String::new();
+    }
+    path.split('.')
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+fn open_value(
+    sealed: &SealedValue,
+    client_secret: &SecretKey,
+    request_id: &str,
+    field: &str,
+) -> Result<Value> {
+    let ephemeral_key = public_key_from_hex(&sealed.ephemeral_public_key)?;
+    let nonce = hex::decode(&sealed.nonce).map_err(|e| conversion_err(e, None))?;
+    if nonce.len() != 24 {
+        return Err(conversion_err("Invalid nonce length", None));
+    }
+    let ciphertext = hex::decode(&sealed.ciphertext).map_err(|e| conversion_err(e, None))?;
+
+    let plaintext = SalsaBox::new(&ephemeral_key, client_secret)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| conversion_err(format!("Unable to open sealed field {}", field), None))?;
+    let plaintext: SealedPlaintext =
+        serde_json::from_slice(&plaintext).map_err(|e| conversion_err(e, None))?;
+
+    if plaintext.request_id != request_id || plaintext.field != field {
+        return Err(conversion_err(
+            format!(
+                "Sealed field {} belongs to another response or field",
+                field
+            ),
+            None,
+        ));
+    }
+    Ok(plaintext.value)
+}
+
+fn public_key_from_hex(key: &str) -> Result<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(key.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            validation_err_code(
+                "The client public key must be a hex X25519 public key",
+                EC::NodeBadInput,
+                None,
+            )
+        })?;
+    Ok(PublicKey::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::json;
+
+    fn response() -> Value {
+        json!({"result": "success", "signatureShare": {"ProofOfPossession": "abcd"}, "shareIndex": 1})
+    }
+
+    #[test]
+    fn seals_and_opens_fields() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let mut sealed = response();
+        seal_fields(
+            &mut sealed,
+            &client_secret.public_key(),
+            "request-1",
+            &["signatureShare"],
+        )
+        .unwrap();
+
+        assert_eq!(sealed["shareIndex"], 1);
+        assert_eq!(sealed[SEALED_FIELDS_KEY], json!(["signatureShare"]));
+        assert!(serde_json::from_value::<SealedValue>(sealed["signatureShare"].clone()).is_ok());
+
+        open_sealed_response(&mut sealed, &client_secret, "request-1").unwrap();
+        assert_eq!(sealed, response());
+    }
+
+    #[test]
+    fn rejects_other_keys_and_requests() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let mut sealed = response();
+        seal_fields(
+            &mut sealed,
+            &client_secret.public_key(),
+            "request-1",
+            &["signatureShare"],
+        )
+        .unwrap();
+
+        let other_secret = SecretKey::generate(&mut OsRng);
+        assert!(open_sealed_response(&mut sealed.clone(), &other_secret, "request-1").is_err());
+        assert!(open_sealed_response(&mut sealed.clone(), &client_secret, "request-2").is_err());
+
+        // A sealed value moved to another field doesn't open either
+        let object = sealed.as_object_mut().unwrap();
+        let value = object.remove("signatureShare").unwrap();
+        object.insert("result".to_string(), value);
+        object.insert(SEALED_FIELDS_KEY.to_string(), json!(["result"]));
+        assert!(open_sealed_response(&mut sealed, &client_secret, "request-1").is_err());
+    }
+
+    #[test]
+    fn seals_nested_fields() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let response = json!({"success": true, "signedData": {"sessionSig": {"signatureShare": "abcd", "shareIndex": 1}}});
+        let mut sealed = response.clone();
+        seal_fields(
+            &mut sealed,
+            &client_secret.public_key(),
+            "request-1",
+            &["signedData.sessionSig.signatureShare"],
+        )
+        .unwrap();
+
+        assert_eq!(sealed["signedData"]["sessionSig"]["shareIndex"], 1);
+        assert!(serde_json::from_value::<SealedValue>(
+            sealed["signedData"]["sessionSig"]["signatureShare"].clone()
+        )
+        .is_ok());
+
+        open_sealed_response(&mut sealed, &client_secret, "request-1").unwrap();
+        assert_eq!(sealed, response);
+    }
+
+    #[test]
+    fn seals_whole_responses() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let response = json!([{"participantId": 1, "decryptionShare": "abcd"}]);
+        let mut sealed = response.clone();
+        seal_fields(&mut sealed, &client_secret.public_key(), "request-1", &[""]).unwrap();
+
+        assert_eq!(sealed[SEALED_FIELDS_KEY], json!([""]));
+        assert!(sealed.get("decryptionShare").is_none());
+
+        let mut other = sealed.clone();
+        assert!(open_sealed_response(&mut other, &client_secret, "request-2").is_err());
+
+        open_sealed_response(&mut sealed, &client_secret, "request-1").unwrap();
+        assert_eq!(sealed, response);
+    }
+
+    #[test]
+    fn fails_closed_on_missing_fields() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let mut sealed = response();
+        assert!(seal_fields(
+            &mut sealed,
+            &client_secret.public_key(),
+            "request-1",
+            &["signedData.sessionSig.signatureShare"],
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn registers_handshake_keys() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let handshake_keys = HandshakeKeys::new();
+        let id = handshake_keys
+            .register(&hex::encode(client_secret.public_key().as_bytes()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            handshake_keys.get(&id).await,
+            Some(client_secret.public_key())
+        );
+        assert_eq!(handshake_keys.get("unknown").await, None);
+        assert_eq!(handshake_keys.register("test").await, None);
+    }
+
+    #[test]
+    fn parses_client_keys() {
+        let client_secret = SecretKey::generate(&mut OsRng);
+        let key = hex::encode(client_secret.public_key().as_bytes());
+        assert!(public_key_from_hex(&key).is_ok());
+        assert!(public_key_from_hex(&format!("0x{}", key)).is_ok());
+        assert!(public_key_from_hex("abcd").is_err());
+    }
+}
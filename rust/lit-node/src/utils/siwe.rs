@@ -7,7 +7,18 @@ use crate::{
 };
 
 pub fn validate_siwe(siwe_message: &siwe::Message) -> Result<()> {
-    let issued_at = siwe_message.issued_at.clone();
+    validate_siwe_timestamps(
+        &siwe_message.issued_at,
+        siwe_message.expiration_time.as_ref(),
+    )
+}
+
+/// The time checks of `validate_siwe`, for SIWE style messages signed by other chains.
+pub fn validate_siwe_timestamps(
+    issued_at: &siwe::TimeStamp,
+    expiration_time: Option<&siwe::TimeStamp>,
+) -> Result<()> {
+    let issued_at = issued_at.clone();
     let now = siwe::TimeStamp::from_str(&Utc::now().to_rfc3339()).map_err(|e| {
         parser_err_code(
             e,
@@ -55,7 +66,7 @@ pub fn validate_siwe(siwe_message: &siwe::Message) -> Result<()> {
     })?;
 
     // Validate expiration if it is provided.
-    if let Some(expiration) = siwe_message.expiration_time.clone() {
+    if let Some(expiration) = expiration_time.cloned() {
         // Validate that expires_at is in the future of issue_at irrespective of the user's time drift
         if expiration.as_ref() < issued_at.as_ref() {
             return Err(validation_err_code(
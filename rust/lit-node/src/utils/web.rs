@@ -636,6 +636,17 @@ pub async fn get_ipfs_file(
 }
 
 async fn retrieve_from_ipfs(ipfs_id: &String, cfg: &LitConfig) -> Result<String> {
+    let bytes = get_ipfs_bytes(ipfs_id, cfg).await?;
+    String::from_utf8(bytes).map_err(|e| {
+        conversion_err(
+            e,
+            Some("Failed to get text from response during IPFS fetch".into()),
+        )
+    })
+}
+
+/// Retrieve a file from IPFS (uncached), verifying that its CID matches.
+pub async fn get_ipfs_bytes(ipfs_id: &String, cfg: &LitConfig) -> Result<Vec<u8>> {
     let gateway = cfg.ipfs_gateway();
 
     let start_time = SystemTime::now();
@@ -667,18 +678,18 @@ async fn retrieve_from_ipfs(ipfs_id: &String, cfg: &LitConfig) -> Result<String>
         )
         .add_detail(format!("Error getting ipfs file: {}", ipfs_id)));
     }
-    let text_result = req.text().await.map_err(|e| {
+    let bytes_result = req.bytes().await.map_err(|e| {
         conversion_err(
             e,
-            Some("Failed to get text from response during IPFS fetch".into()),
+            Some("Failed to get bytes from response during IPFS fetch".into()),
         )
     })?;
 
-    if text_result.len() > 30000000 {
+    if bytes_result.len() > 30000000 {
         return Err(ipfs_err(
             format!(
                 "Error getting code from ipfs url. File too large: {}",
-                text_result.len()
+                bytes_result.len()
             ),
             None,
         ));
@@ -686,7 +697,7 @@ async fn retrieve_from_ipfs(ipfs_id: &String, cfg: &LitConfig) -> Result<String>
 
     // verify the hash
     let ipfs_hasher = IpfsHasher::default();
-    let cid = ipfs_hasher.compute(text_result.as_bytes());
+    let cid = ipfs_hasher.compute(&bytes_result);
     if cid != ipfs_id.clone() {
         return Err(ipfs_err(
             format!(
@@ -703,7 +714,7 @@ async fn retrieve_from_ipfs(ipfs_id: &String, cfg: &LitConfig) -> Result<String>
         .map_err(|e| unexpected_err(e, Some("Unable to get duration".into())))?;
     debug!("Retrieved from IPFS in {}ms", elapsed.as_millis());
 
-    Ok(text_result)
+    Ok(bytes_result.to_vec())
 }
 
 #[instrument]
@@ -27,6 +27,7 @@ pub async fn test_sdk() {
         .arg("@lit-protocol/lit-node-client-nodejs@3.0.32")
         .arg("ethers")
         .arg("siwe")
+        .arg("tweetnacl")
         .status()
         .await
         .expect("Failed to install package with yarn");
@@ -1,5 +1,10 @@
 import * as LitNodeJsSdk from '@lit-protocol/lit-node-client-nodejs';
 import { getAuthSig } from './utils.mjs';
+import {
+  HEADER_KEY_X_LIT_SEALING_KEY_ID,
+  generateSealingKeyPair,
+  openSealedResponse,
+} from './sealed_response.mjs';
 import assert from 'assert';
 
 const litNodeClient = new LitNodeJsSdk.LitNodeClientNodeJs({
@@ -126,6 +131,39 @@ const testLitActionIpfs = async () => {
   assert(litAuth.actionIpfsIds.includes(ipfsId));
 };
 
+const testSealedResponse = async () => {
+  const nodeUrl = 'http://127.0.0.1:7470';
+  const { publicKey, secretKey } = generateSealingKeyPair();
+
+  const handshake = await fetch(`${nodeUrl}/web/handshake`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ clientPublicKey: publicKey, challenge: null }),
+  }).then((res) => res.json());
+  assert(handshake.sealingKeyId);
+
+  const requestId = crypto.randomUUID();
+  const sealed = await fetch(`${nodeUrl}/web/execute`, {
+    method: 'POST',
+    headers: {
+      'Content-Type': 'application/json',
+      'X-Request-Id': requestId,
+      [HEADER_KEY_X_LIT_SEALING_KEY_ID]: handshake.sealingKeyId,
+    },
+    body: JSON.stringify({
+      code: `(async () => { Lit.Actions.setResponse({ response: 'sealed 🔥' }); })();`,
+      authSig,
+      jsParams: {},
+    }),
+  }).then((res) => res.json());
+  assert(sealed.sealedFields.includes('response'));
+  assert(sealed.response.ciphertext);
+
+  const opened = openSealedResponse(sealed, secretKey, requestId);
+  assert(opened.response === 'sealed 🔥');
+  assert.throws(() => openSealedResponse(sealed, secretKey, 'another-request'));
+};
+
 const testSessionKey = async () => {
   const sessionKey = litNodeClient.getSessionKey();
   assert(sessionKey.publicKey && sessionKey.secretKey);
@@ -137,6 +175,7 @@ Promise.all([
   testEncryptingAndDecryptingZip(),
   testLitActionCode(),
   testLitActionIpfs(),
+  testSealedResponse(),
   testSessionKey(),
 ])
   .then(() => {
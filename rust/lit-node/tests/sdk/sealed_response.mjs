@@ -0,0 +1,71 @@
+import nacl from 'tweetnacl';
+
+// Mirrors src/utils/rocket/sealed_response.rs: a client that sends the
+// `sealingKeyId` of its handshake in the `X-Lit-Sealing-Key-Id` header gets
+// the secret-bearing fields of the response sealed to its handshake key.
+export const HEADER_KEY_X_LIT_SEALING_KEY_ID = 'X-Lit-Sealing-Key-Id';
+export const SEALED_FIELDS_KEY = 'sealedFields';
+
+// The X25519 key pair to send as the `clientPublicKey` of the handshake.
+export const generateSealingKeyPair = () => {
+  const keyPair = nacl.box.keyPair();
+  return {
+    publicKey: Buffer.from(keyPair.publicKey).toString('hex'),
+    secretKey: keyPair.secretKey,
+  };
+};
+
+// `signedData.sessionSig` -> ['signedData', 'sessionSig'], '' -> [] (the root)
+const fieldPath = (field) => (field === '' ? [] : field.split('.'));
+
+const openValue = (sealed, secretKey, requestId, field) => {
+  const plaintext = nacl.box.open(
+    Buffer.from(sealed.ciphertext, 'hex'),
+    Buffer.from(sealed.nonce, 'hex'),
+    Buffer.from(sealed.ephemeralPublicKey, 'hex'),
+    secretKey
+  );
+  if (!plaintext) {
+    throw new Error(`Unable to open sealed field ${field}`);
+  }
+
+  const opened = JSON.parse(Buffer.from(plaintext).toString('utf8'));
+  if (opened.requestId !== requestId || opened.field !== field) {
+    throw new Error(
+      `Sealed field ${field} belongs to another response or field`
+    );
+  }
+  return opened.value;
+};
+
+// Opens the sealed fields of a (parsed) response, for the client holding the
+// secret key of its handshake.  Returns the response as if it had never been
+// sealed; responses without `sealedFields` are returned as they are.
+export const openSealedResponse = (response, secretKey, requestId) => {
+  if (!response || typeof response !== 'object' || Array.isArray(response)) {
+    throw new Error("The response isn't a JSON object");
+  }
+  const { [SEALED_FIELDS_KEY]: fields, ...rest } = response;
+  if (!Array.isArray(fields)) {
+    return response;
+  }
+
+  let opened = structuredClone(rest);
+  for (const field of fields) {
+    const path = fieldPath(field);
+    if (path.length === 0) {
+      opened = openValue(opened, secretKey, requestId, field);
+      continue;
+    }
+
+    const parent = path
+      .slice(0, -1)
+      .reduce((value, segment) => value?.[segment], opened);
+    const key = path[path.length - 1];
+    if (!parent || !(key in parent)) {
+      throw new Error(`Sealed field ${field} is missing`);
+    }
+    parent[key] = openValue(parent[key], secretKey, requestId, field);
+  }
+  return opened;
+};
@@ -1,6 +1,7 @@
 use lit_api_core::server::hyper::handler::types::Context;
 use std::{fs, sync::Arc};
 
+use lit_attestation::kdf::Kdf;
 use lit_core::error::Unexpected;
 use lit_core::{
     config::LitConfig,
@@ -20,6 +21,7 @@ pub struct ServiceContext {
     pub build_id: String,
     pub release_id: Option<String>,
     pub subnet_id: Option<String>,
+    pub kdf: Kdf,
 }
 
 impl ServiceContext {
@@ -61,7 +63,24 @@ impl ServiceContext {
         let release_id = lit_config.litos_guest_release_id().ok();
         let subnet_id = lit_config.subnet_id().ok();
 
-        Ok(Self { cfg: lit_config, build_priv_key, build_id, instance_id, release_id, subnet_id })
+        // Key derivation provider.
+        let kdf = Kdf::try_new_for_cfg(lit_config.as_ref(), None)?;
+
+        Ok(Self {
+            cfg: lit_config,
+            build_priv_key,
+            build_id,
+            instance_id,
+            release_id,
+            subnet_id,
+            kdf,
+        })
+    }
+
+    /// Replace the KDF (i.e. to plug in an alternative provider).
+    pub fn with_kdf(mut self, kdf: Kdf) -> Self {
+        self.kdf = kdf;
+        self
     }
 }
 
@@ -3,13 +3,12 @@ use log::{as_serde, trace};
 use serde_bytes_base64::Bytes;
 
 use crate::context::ContextHelper;
-use lit_attestation::kdf::Kdf;
+use lit_attestation::config::LitAttestationConfig;
+use lit_attestation::kdf::service_context;
 use lit_attestation::service::types::{KdfReq, KdfResp};
 
 use crate::error::Result;
 
-const KDF_NS_PREFIX: &str = "AS";
-
 pub(crate) async fn handle_req(req: Request) -> Result<Response> {
     let ctx = req.ctx().service_ctx()?;
     let req: KdfReq = req.deserialize().await?;
@@ -17,16 +16,22 @@ pub(crate) async fn handle_req(req: Request) -> Result<Response> {
     trace!(req = as_serde!(req); "kdf::handle_req");
 
     // Full context
-    let context = format!("{}::{}", KDF_NS_PREFIX, &req.context);
+    let context = service_context(&req.context)?;
 
-    // Derive key
-    let key = Kdf::try_derive(ctx.cfg.as_ref(), context).await?;
+    // Derive key (the provider enforces the namespace and version rules), defaulting to the
+    // configured version.
+    let version = match req.version {
+        Some(version) => version,
+        None => ctx.cfg.kdf_version()?,
+    };
+    let key = ctx.kdf.derive_version(context, version).await?;
 
     // Return response.
-    Response::try_from(KdfResp { key: Bytes::from(key.to_vec()) })
+    Response::try_from(KdfResp { key: Bytes::from(key.to_vec()), version })
 }
 
-/// Note that this test is only intended to be run with AMD SEV SNP firmware support.
+/// Note that test_handle_req_success is only intended to be run with AMD SEV SNP firmware
+/// support, the remaining tests use the local (key file) provider.
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -34,11 +39,13 @@ mod tests {
 
     use lit_api_core::server::hyper::handler::types::{Context, Request};
 
-    use lit_attestation::config::ENV_ATTESTATION_SERVICE_SOCK_PATH;
-    use lit_attestation::kdf::ENV_KDF_TYPE_OVERRIDE;
+    use lit_attestation::config::{LitAttestationConfig, ENV_ATTESTATION_SERVICE_SOCK_PATH};
+    use lit_attestation::kdf::local::LocalKdfProvider;
+    use lit_attestation::kdf::{Kdf, ENV_KDF_TYPE_OVERRIDE};
     use lit_attestation::service::types::{KdfReq, KdfResp};
     use lit_core::config::LitConfig;
     use lit_core::config::ENV_LIT_CONFIG_FILE;
+    use lit_os_core::config::ENV_BUILD_PRIV_KEY_PATH;
 
     use crate::config::LitAttestationServiceConfig;
     use crate::context::{ServiceContext, CTX_KEY_SERVICE_CTX};
@@ -71,7 +78,54 @@ mod tests {
         assert!(!kdf_platform_resp.key.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_handle_req_local_provider_versions() {
+        // Init
+        setup_test_env();
+        let cfg = Arc::new(<LitConfig as LitAttestationServiceConfig>::must_new());
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let provider = LocalKdfProvider::from_key_file(dir.path().join("kdf.key"))
+            .expect("failed to create local provider");
+        let kdf = Kdf::try_new_with_provider(cfg.as_ref(), Arc::new(provider), None)
+            .expect("failed to create kdf")
+            .with_version(1);
+        let service_ctx = Arc::new(
+            ServiceContext::from_lit_config(cfg.clone())
+                .expect("failed to create service context")
+                .with_kdf(kdf),
+        );
+
+        // Handle requests
+        let v0 = request_kdf(service_ctx.clone(), KdfReq::new("test-123")).await.unwrap();
+        let v1 = request_kdf(service_ctx.clone(), KdfReq::new("test-123").with_version(1))
+            .await
+            .unwrap();
+        let v0_again = request_kdf(service_ctx.clone(), KdfReq::new("test-123")).await.unwrap();
+
+        // Assertions
+        assert_eq!(v0.version, cfg.kdf_version().unwrap());
+        assert_eq!(v0.version, 0);
+        assert_eq!(v1.version, 1);
+        assert_eq!(v0.key.val.len(), 32);
+        assert_ne!(v0.key.val, v1.key.val);
+        assert_eq!(v0.key.val, v0_again.key.val);
+
+        // Future versions are rejected.
+        assert!(request_kdf(service_ctx, KdfReq::new("test-123").with_version(2)).await.is_err());
+    }
+
     // Util
+    async fn request_kdf(
+        service_ctx: Arc<ServiceContext>, req_body: KdfReq,
+    ) -> crate::error::Result<KdfResp> {
+        let mut ctx = Context::new();
+        ctx.insert_raw(CTX_KEY_SERVICE_CTX, service_ctx);
+
+        let req = Request::try_from(&req_body, Arc::new(ctx)).expect("failed to extract Request");
+
+        Ok(handle_req(req).await?.deserialize().await.expect("failed to deserialize"))
+    }
+
     fn get_test_path(path: &str) -> PathBuf {
         let mut test_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         test_path.push(RESOURCES_TEST_DIR);
@@ -81,6 +135,7 @@ mod tests {
 
     fn setup_test_env() {
         env::set_var(ENV_LIT_CONFIG_FILE, get_test_path("config/config.toml"));
+        env::set_var(ENV_BUILD_PRIV_KEY_PATH, get_test_path("config/build.pem"));
         env::set_var(ENV_ATTESTATION_SERVICE_SOCK_PATH, "/tmp/lit-attestation-service.sock");
         env::set_var(ENV_KDF_TYPE_OVERRIDE, "AMD_SEV_SNP");
 
@@ -0,0 +1,95 @@
+#!/usr/bin/env bash
+#
+# Runs the oneshot in <path>/oneshot.input on a (stopped) guest instance and copies the
+# oneshot volume back to <path>/oneshot.output. Installed as
+# {litos.install_dir}/guest/instance/oneshot.sh and called by 'lit os guest instance oneshot'.
+#
+# The oneshot volume is a GPT disk image (<path>/oneshot.img) with a single 'one-shot'
+# partition, which the instance service attaches while the image exists (as with
+# 'create.sh -oneshot'). The guest runs the oneshot actions and powers off.
+
+set -euo pipefail
+
+ONESHOT_IMG_SIZE_MB=66 # 64M partition plus the GPT
+ONESHOT_PART_LABEL="one-shot"
+TIMEOUT_SECS=1800
+
+usage() {
+  echo "Usage: $0 -path <instance dir> [-timeout <secs>]" >&2
+  exit 2
+}
+
+while [ $# -gt 0 ]; do
+  case "$1" in
+    -path) INSTANCE_DIR="${2:-}"; shift 2 ;;
+    -timeout) TIMEOUT_SECS="${2:-}"; shift 2 ;;
+    *) usage ;;
+  esac
+done
+[ -n "${INSTANCE_DIR:-}" ] || usage
+
+INPUT_DIR="${INSTANCE_DIR}/oneshot.input"
+OUTPUT_DIR="${INSTANCE_DIR}/oneshot.output"
+ONESHOT_IMG="${INSTANCE_DIR}/oneshot.img"
+INSTANCE_SERVICE="$(sed -n 's/^INSTANCE_SERVICE="\{0,1\}\([^"]*\)"\{0,1\}$/\1/p' "${INSTANCE_DIR}/instance.env")"
+
+[ -d "${INPUT_DIR}" ] || { echo "Missing oneshot input: ${INPUT_DIR}" >&2; exit 1; }
+[ -n "${INSTANCE_SERVICE}" ] || { echo "Missing INSTANCE_SERVICE in instance.env" >&2; exit 1; }
+if systemctl is-active --quiet "${INSTANCE_SERVICE}"; then
+  echo "The instance must be stopped: ${INSTANCE_SERVICE}" >&2
+  exit 1
+fi
+
+MNT_DIR="$(mktemp -d)"
+LOOP_DEV=""
+cleanup() {
+  if mountpoint -q "${MNT_DIR}"; then umount "${MNT_DIR}"; fi
+  if [ -n "${LOOP_DEV}" ]; then losetup -d "${LOOP_DEV}"; fi
+  rmdir "${MNT_DIR}"
+  # Never leave the volume attached to later boots.
+  rm -f "${ONESHOT_IMG}"
+}
+trap cleanup EXIT
+
+attach() {
+  LOOP_DEV="$(losetup --find --show --partscan "${ONESHOT_IMG}")"
+  udevadm settle
+}
+
+detach() {
+  losetup -d "${LOOP_DEV}"
+  LOOP_DEV=""
+}
+
+# Build the volume.
+rm -f "${ONESHOT_IMG}"
+truncate -s "${ONESHOT_IMG_SIZE_MB}M" "${ONESHOT_IMG}"
+chmod 600 "${ONESHOT_IMG}"
+sgdisk --clear --new=1:0:0 --change-name=1:"${ONESHOT_PART_LABEL}" "${ONESHOT_IMG}" >/dev/null
+attach
+mkfs.ext4 -q -L "${ONESHOT_PART_LABEL}" "${LOOP_DEV}p1"
+mount "${LOOP_DEV}p1" "${MNT_DIR}"
+cp -a "${INPUT_DIR}/." "${MNT_DIR}/"
+umount "${MNT_DIR}"
+detach
+
+# Boot the guest, it powers off once the oneshot has run.
+systemctl start "${INSTANCE_SERVICE}"
+waited=0
+while systemctl is-active --quiet "${INSTANCE_SERVICE}"; do
+  if [ "${waited}" -ge "${TIMEOUT_SECS}" ]; then
+    echo "Timed out waiting for the oneshot to complete, stopping ${INSTANCE_SERVICE}" >&2
+    systemctl stop "${INSTANCE_SERVICE}"
+    break
+  fi
+  sleep 5
+  waited=$((waited + 5))
+done
+
+# Copy the volume back (status report, published keys, bundles e.t.c.).
+rm -rf "${OUTPUT_DIR}"
+mkdir -p "${OUTPUT_DIR}"
+chmod 700 "${OUTPUT_DIR}"
+attach
+mount -o ro "${LOOP_DEV}p1" "${MNT_DIR}"
+cp -a "${MNT_DIR}/." "${OUTPUT_DIR}/"
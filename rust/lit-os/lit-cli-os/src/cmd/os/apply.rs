@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use lit_cli_core::cmd::CliGlobalOpts;
+use lit_cli_core::utils::prompt::confirm;
+use lit_cli_core::utils::system::require_root;
+use lit_core::config::LitConfig;
+
+use crate::cmd::os::guest::instance::create::{
+    do_os_guest_instance_create, GuestInstanceCreateArgsApi, GuestInstanceCreateArgsCommon,
+    GuestInstanceCreateArgsCustom, GuestInstanceCreateArgsNode, GuestInstanceCreateArgsProv,
+};
+use crate::cmd::os::guest::instance::delete::delete_instance;
+use crate::guest::instance::find_one_guest_instance;
+use crate::guest::instance::fleet::{
+    current_fleet_state, plan_fleet, print_fleet_plan, FleetAction, FleetInstanceSpec, FleetPlan,
+    FleetSpec,
+};
+use crate::guest::instance::helper::GuestInstanceHelper;
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct Plan {
+    /// Fleet spec (i.e. fleet.toml).
+    #[arg(long, short, value_name = "PATH")]
+    file: PathBuf,
+    /// Delete instances of the fleet which are no longer declared.
+    #[arg(long)]
+    prune: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct Apply {
+    /// Fleet spec (i.e. fleet.toml).
+    #[arg(long, short, value_name = "PATH")]
+    file: PathBuf,
+    /// Delete instances of the fleet which are no longer declared.
+    #[arg(long)]
+    prune: bool,
+    /// Accept all confirmations
+    #[arg(long, short)]
+    yes: bool,
+}
+
+pub(crate) fn handle_cmd_os_plan(cfg: LitConfig, _opts: CliGlobalOpts, args: Plan) -> bool {
+    let Some((_, plan)) = load_fleet_plan(&cfg, &args.file, args.prune) else {
+        return true;
+    };
+
+    print_fleet_plan(&cfg, &plan);
+
+    true
+}
+
+pub(crate) async fn handle_cmd_os_apply(cfg: LitConfig, opts: CliGlobalOpts, args: Apply) -> bool {
+    if !require_root() {
+        return true;
+    }
+
+    let Some((spec, plan)) = load_fleet_plan(&cfg, &args.file, args.prune) else {
+        return true;
+    };
+
+    print_fleet_plan(&cfg, &plan);
+    if plan.actions.is_empty() {
+        return true;
+    }
+
+    if !args.yes && !confirm("Apply these changes?", false).expect("failed to capture confirmation")
+    {
+        return true;
+    }
+
+    for action in plan.actions.iter() {
+        if !opts.quiet() {
+            println!();
+        }
+
+        if !apply_fleet_action(&cfg, &opts, &spec, action).await {
+            eprintln!();
+            eprintln!("Failed to apply fleet spec, run plan to review the remaining changes");
+
+            return true;
+        }
+    }
+
+    true
+}
+
+fn load_fleet_plan(cfg: &LitConfig, file: &Path, prune: bool) -> Option<(FleetSpec, FleetPlan)> {
+    let spec = match FleetSpec::load(file) {
+        Ok(spec) => spec,
+        Err(e) => {
+            eprintln!("Invalid fleet spec: {e:?}");
+
+            return None;
+        }
+    };
+
+    let current = match current_fleet_state(cfg) {
+        Ok(current) => current,
+        Err(e) => {
+            eprintln!("Failed to read the current guest instances: {e:?}");
+
+            return None;
+        }
+    };
+
+    let plan = plan_fleet(&spec, &current, prune);
+
+    Some((spec, plan))
+}
+
+async fn apply_fleet_action(
+    cfg: &LitConfig, opts: &CliGlobalOpts, spec: &FleetSpec, action: &FleetAction,
+) -> bool {
+    match action {
+        FleetAction::Delete { id, .. } => {
+            let Some(item) = find_one_guest_instance(cfg, None, None, Some(id)) else {
+                return false;
+            };
+
+            delete_instance(cfg, &item, opts);
+        }
+        FleetAction::Stop { id, .. } => {
+            let Some(item) = find_one_guest_instance(cfg, None, None, Some(id)) else {
+                return false;
+            };
+
+            if !opts.quiet() {
+                println!("Stopping: {:?}", &item.path);
+            }
+
+            if let Err(e) = item.stop().and_then(|_| item.disable()) {
+                eprintln!("Failed to stop instance ({id}): {e:?}");
+
+                return false;
+            }
+        }
+        FleetAction::Start { id, .. } => {
+            let Some(item) = find_one_guest_instance(cfg, None, None, Some(id)) else {
+                return false;
+            };
+
+            if !opts.quiet() {
+                println!("Starting: {:?}", &item.path);
+            }
+
+            if let Err(e) = item.enable(cfg).and_then(|_| item.start()) {
+                eprintln!("Failed to start instance ({id}): {e:?}");
+
+                return false;
+            }
+        }
+        FleetAction::Create { name } => {
+            let instance = fleet_instance(spec, name);
+            if !opts.quiet() {
+                println!("Creating: {} ({})", name, instance.guest_type);
+            }
+
+            return create_fleet_instance(cfg, opts, spec, instance, None).await.is_some();
+        }
+        FleetAction::Recreate { name, id, .. } => {
+            let Some(item) = find_one_guest_instance(cfg, None, None, Some(id)) else {
+                return false;
+            };
+
+            let instance = fleet_instance(spec, name);
+            if !opts.quiet() {
+                println!("Recreating: {} ({})", name, id);
+            }
+
+            if create_fleet_instance(cfg, opts, spec, instance, Some(id.clone())).await.is_none() {
+                return false;
+            }
+
+            delete_instance(cfg, &item, opts);
+        }
+    }
+
+    true
+}
+
+fn fleet_instance<'a>(spec: &'a FleetSpec, name: &str) -> &'a FleetInstanceSpec {
+    spec.instances
+        .iter()
+        .find(|instance| instance.name.eq(name))
+        .expect("planned instance missing from fleet spec")
+}
+
+async fn create_fleet_instance(
+    cfg: &LitConfig, opts: &CliGlobalOpts, spec: &FleetSpec, instance: &FleetInstanceSpec,
+    from_id: Option<String>,
+) -> Option<String> {
+    let common_args = GuestInstanceCreateArgsCommon {
+        name: Some(instance.name.clone()),
+        label: spec.instance_labels(instance),
+        env: instance.env,
+        subnet_id: instance.subnet_id.clone(),
+        template_id: instance.template_id.clone(),
+        from_id,
+        vcpus: instance.vcpus,
+        mem: instance.mem.clone(),
+        img_size: instance.img_size.clone(),
+        net4_ip: instance.net4_ip.clone(),
+        net4_gw: instance.net4_gw.clone(),
+        net6_ip: instance.net6_ip.clone(),
+        net6_gw: instance.net6_gw.clone(),
+        net6_dhcp: instance.net6_dhcp,
+        no_start: !instance.running,
+        ..Default::default()
+    };
+
+    let (created, instance_id) = do_os_guest_instance_create(
+        cfg,
+        opts,
+        instance.guest_type,
+        common_args,
+        Some(GuestInstanceCreateArgsProv::default()),
+        Some(GuestInstanceCreateArgsNode::default()),
+        Some(GuestInstanceCreateArgsApi::default()),
+        Some(GuestInstanceCreateArgsCustom::default()),
+    )
+    .await;
+
+    if !created {
+        eprintln!("Invalid fleet instance: {}", instance.name);
+    }
+
+    instance_id
+}
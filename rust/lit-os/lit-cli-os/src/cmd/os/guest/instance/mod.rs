@@ -14,6 +14,10 @@ use crate::cmd::os::guest::instance::describe::{
 };
 use crate::cmd::os::guest::instance::logs::{handle_cmd_os_guest_instance_logs, GuestInstanceLogs};
 use crate::cmd::os::guest::instance::ls::handle_cmd_os_guest_instance_ls;
+#[cfg(feature = "guest-build")]
+use crate::cmd::os::guest::instance::oneshot::{
+    handle_cmd_os_guest_instance_oneshot, GuestInstanceOneShot,
+};
 use crate::cmd::os::guest::instance::ps::handle_cmd_os_guest_instance_ps;
 use crate::cmd::os::guest::instance::recreate::{
     handle_cmd_os_guest_instance_recreate, GuestInstanceRecreate,
@@ -38,6 +42,8 @@ pub(crate) mod delete;
 pub(crate) mod describe;
 pub(crate) mod logs;
 pub(crate) mod ls;
+#[cfg(feature = "guest-build")]
+pub(crate) mod oneshot;
 pub(crate) mod ps;
 pub(crate) mod recreate;
 pub(crate) mod repair;
@@ -87,6 +93,10 @@ pub(crate) enum GuestInstanceCommands {
     /// Resize the image of a guest instance
     #[command(arg_required_else_help = true)]
     Resize(GuestInstanceResize),
+    #[cfg(feature = "guest-build")]
+    /// Run signed oneshot maintenance actions on a guest instance
+    #[command(arg_required_else_help = true)]
+    Oneshot(GuestInstanceOneShot),
     /// List all guest instances
     Ls {},
     /// Show running guest instances
@@ -113,6 +123,10 @@ pub(crate) async fn handle_cmd_os_guest_instance(
         }
         GuestInstanceCommands::Repair(args) => handle_cmd_os_guest_instance_repair(cfg, opts, args),
         GuestInstanceCommands::Resize(args) => handle_cmd_os_guest_instance_resize(cfg, opts, args),
+        #[cfg(feature = "guest-build")]
+        GuestInstanceCommands::Oneshot(args) => {
+            handle_cmd_os_guest_instance_oneshot(cfg, opts, args).await
+        }
         GuestInstanceCommands::Ls {} => handle_cmd_os_guest_instance_ls(&cfg, &opts),
         GuestInstanceCommands::Ps {} => handle_cmd_os_guest_instance_ps(&cfg, &opts),
         GuestInstanceCommands::Status(args) => handle_cmd_os_guest_instance_status(cfg, opts, args),
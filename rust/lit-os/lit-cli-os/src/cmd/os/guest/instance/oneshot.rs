@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use clap::Args;
+
+use lit_cli_core::cmd::CliGlobalOpts;
+use lit_cli_core::utils::system::require_root;
+use lit_core::config::LitConfig;
+use lit_os_core::guest::oneshot::config::{
+    ActionEntry, OneShotConfig, ACTION_SETTINGS_KEY_PATH, ACTION_SETTINGS_KEY_PATHS,
+    ACTION_SETTINGS_KEY_RECIPIENT, ACTION_SETTINGS_KEY_VOLUMES, ACTION_TYPE_CONFIG_RESET,
+    ACTION_TYPE_DIAGNOSTICS, ACTION_TYPE_DISK_REKEY, ACTION_TYPE_KEY_SHARE_EXPORT,
+    ACTION_TYPE_KEY_SHARE_IMPORT, ACTION_TYPE_LUKS_ROTATE,
+};
+use lit_os_core::guest::oneshot::status::OneShotStatusReport;
+use lit_os_core::guest::oneshot::{ONESHOT_FILE_STATUS_REPORT, SIGNATURE_UNRECORDED_MAX_TTL_SECS};
+
+use crate::config::LitCliOsConfig;
+use crate::guest::instance::find_one_guest_instance;
+use crate::guest::instance::oneshot::write_signed_oneshot;
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct GuestInstanceOneShot {
+    #[arg(value_name = "ID", value_enum)]
+    id: String,
+    /// Collect a diagnostic bundle.
+    #[arg(long)]
+    diagnostics: bool,
+    /// Path (under /var) of the key shares to export or import.
+    #[arg(long, value_name = "PATH")]
+    key_share_path: Option<String>,
+    /// Export the key shares, sealed to the handoff key of the receiving guest.
+    #[arg(long, value_name = "PUBLIC_KEY", requires = "key_share_path")]
+    key_share_export: Option<String>,
+    /// Import the key shares (publishes the handoff key when no bundle is present).
+    #[arg(long, requires = "key_share_path", conflicts_with = "key_share_export")]
+    key_share_import: bool,
+    /// Reset a path (under /var) to the cloud-init config (may be repeated).
+    #[arg(long, value_name = "PATH")]
+    config_reset: Vec<String>,
+    /// Re-key the LUKS volumes (i.e. root,var).
+    #[arg(long, value_name = "VOLUMES", num_args = 0..=1, default_missing_value = "root,var")]
+    disk_rekey: Option<String>,
+    /// Rotate the LUKS boot passphrases to the next key version (i.e. root,var).
+    #[arg(long, value_name = "VOLUMES", num_args = 0..=1, default_missing_value = "root,var")]
+    luks_rotate: Option<String>,
+    /// Hours the signature remains valid (capped to minutes for --diagnostics / --disk-rekey).
+    #[arg(long, default_value_t = 24)]
+    ttl: u64,
+    /// Automatically stop the guest prior to running.
+    #[arg(long)]
+    restart: bool,
+}
+
+pub(crate) async fn handle_cmd_os_guest_instance_oneshot(
+    cfg: LitConfig, opts: CliGlobalOpts, args: GuestInstanceOneShot,
+) -> bool {
+    if args.id.is_empty() {
+        return false;
+    }
+    if !require_root() {
+        return true;
+    }
+
+    let oneshot_cfg = oneshot_config_from_args(&args);
+    if oneshot_cfg.is_empty() {
+        eprintln!("Invalid: no oneshot actions requested");
+        return false;
+    }
+    if oneshot_cfg.actions().contains_key(ACTION_TYPE_DISK_REKEY) && oneshot_cfg.len() > 1 {
+        eprintln!("Invalid: --disk-rekey must be run on its own");
+        return false;
+    }
+
+    let admin_key = match cfg.admin_key() {
+        Ok(key) => key,
+        Err(_e) => {
+            eprintln!("Invalid: admin key is required");
+            return false;
+        }
+    };
+
+    if let Some(item) = find_one_guest_instance(&cfg, None, None, Some(&args.id)) {
+        let instance_env = &item.instance_env;
+        let instance_id =
+            instance_env.instance_id.as_ref().expect("missing INSTANCE_ID key in instance.env");
+        let instance_service = instance_env
+            .instance_service
+            .as_ref()
+            .expect("missing INSTANCE_SERVICE key in instance.env");
+
+        if systemctl::is_active(instance_service.as_str())
+            .unwrap_or_else(|_| panic!("failed to run systemctl is-active on {instance_service}"))
+        {
+            if args.restart {
+                systemctl::stop(instance_service.as_str()).unwrap_or_else(|_| {
+                    panic!("failed to run systemctl stop on {instance_service}")
+                });
+            } else {
+                eprintln!("Invalid: --restart is required if the guest is still running.");
+                return false;
+            }
+        }
+
+        // Diagnostics and disk_rekey run without /var so the guest can't record them as run,
+        // it only accepts them while the signature is about to expire.
+        let mut ttl = Duration::from_secs(args.ttl * 60 * 60);
+        let unrecorded_ttl = Duration::from_secs(SIGNATURE_UNRECORDED_MAX_TTL_SECS);
+        if ttl > unrecorded_ttl
+            && oneshot_cfg
+                .actions()
+                .keys()
+                .all(|action| action == ACTION_TYPE_DIAGNOSTICS || action == ACTION_TYPE_DISK_REKEY)
+        {
+            if !opts.quiet() {
+                println!(
+                    "Signature valid for {} minutes (runs without /var)",
+                    unrecorded_ttl.as_secs() / 60
+                );
+            }
+            ttl = unrecorded_ttl;
+        }
+
+        // Write signed config (anything else in the dir, i.e. a key share bundle, is kept).
+        let mut oneshot_dir = PathBuf::from(&item.path);
+        oneshot_dir.push("oneshot.input");
+
+        write_signed_oneshot(
+            oneshot_dir.as_path(),
+            &oneshot_cfg,
+            instance_id,
+            ttl,
+            admin_key.as_str(),
+        )
+        .await;
+
+        // Execute oneshot.
+        let oneshot_cmd = cfg
+            .litos_guest_instance_oneshot_cmd()
+            .expect("failed to determine the lit os install location");
+
+        let mut cmd = Command::new(oneshot_cmd);
+        cmd.arg("-path").arg(&item.path);
+
+        if opts.quiet() {
+            cmd.stderr(Stdio::inherit()).stdout(Stdio::null());
+        } else {
+            cmd.stderr(Stdio::inherit()).stdout(Stdio::inherit());
+        }
+
+        match cmd.spawn() {
+            Ok(mut child) => {
+                let status = child.wait().expect("failed waiting for child process");
+
+                if !status.success() {
+                    if !opts.quiet() {
+                        eprintln!();
+                    }
+
+                    eprintln!("Failed to run oneshot on instance");
+                }
+            }
+            Err(err) => {
+                eprintln!("failed to spawn instance oneshot script: {cmd:?} - {err:?}");
+            }
+        }
+
+        // Report (copied back from the oneshot volume by the script).
+        let mut report_file = PathBuf::from(&item.path);
+        report_file.push("oneshot.output");
+        report_file.push(ONESHOT_FILE_STATUS_REPORT);
+
+        match OneShotStatusReport::try_from(report_file.as_path()) {
+            Ok(report) => {
+                for (action, status) in report.actions().iter() {
+                    match status.message.as_ref() {
+                        Some(msg) => println!("{action}: {} ({msg})", status.state),
+                        None => println!("{action}: {}", status.state),
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("No oneshot status report found ({report_file:?}): {e:?}");
+            }
+        }
+
+        if args.restart {
+            systemctl::start(instance_service.as_str())
+                .unwrap_or_else(|_| panic!("failed to run systemctl start on {instance_service}"));
+        }
+    }
+
+    true
+}
+
+fn oneshot_config_from_args(args: &GuestInstanceOneShot) -> OneShotConfig {
+    let mut oneshot_cfg = OneShotConfig::new();
+
+    if args.diagnostics {
+        oneshot_cfg.insert_action(ACTION_TYPE_DIAGNOSTICS.into(), ActionEntry::new());
+    }
+
+    if let Some(path) = args.key_share_path.as_ref() {
+        if let Some(recipient) = args.key_share_export.as_ref() {
+            let mut entry = ActionEntry::new();
+            entry
+                .insert_setting(ACTION_SETTINGS_KEY_PATH.into(), path.as_bytes().to_vec())
+                .insert_setting(
+                    ACTION_SETTINGS_KEY_RECIPIENT.into(),
+                    recipient.as_bytes().to_vec(),
+                );
+            oneshot_cfg.insert_action(ACTION_TYPE_KEY_SHARE_EXPORT.into(), entry);
+        }
+
+        if args.key_share_import {
+            let mut entry = ActionEntry::new();
+            entry.insert_setting(ACTION_SETTINGS_KEY_PATH.into(), path.as_bytes().to_vec());
+            oneshot_cfg.insert_action(ACTION_TYPE_KEY_SHARE_IMPORT.into(), entry);
+        }
+    }
+
+    if !args.config_reset.is_empty() {
+        let mut entry = ActionEntry::new();
+        entry.insert_setting(
+            ACTION_SETTINGS_KEY_PATHS.into(),
+            args.config_reset.join("\n").into_bytes(),
+        );
+        oneshot_cfg.insert_action(ACTION_TYPE_CONFIG_RESET.into(), entry);
+    }
+
+    if let Some(volumes) = args.disk_rekey.as_ref() {
+        let mut entry = ActionEntry::new();
+        entry.insert_setting(ACTION_SETTINGS_KEY_VOLUMES.into(), volumes.as_bytes().to_vec());
+        oneshot_cfg.insert_action(ACTION_TYPE_DISK_REKEY.into(), entry);
+    }
+
+    if let Some(volumes) = args.luks_rotate.as_ref() {
+        let mut entry = ActionEntry::new();
+        entry.insert_setting(ACTION_SETTINGS_KEY_VOLUMES.into(), volumes.as_bytes().to_vec());
+        oneshot_cfg.insert_action(ACTION_TYPE_LUKS_ROTATE.into(), entry);
+    }
+
+    oneshot_cfg
+}
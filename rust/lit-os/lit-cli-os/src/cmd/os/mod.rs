@@ -3,6 +3,8 @@ use clap::{Args, Subcommand};
 use lit_cli_core::cmd::CliGlobalOpts;
 use lit_core::config::{LitConfig, LitConfigBuilder};
 
+#[cfg(feature = "guest-instance")]
+use crate::cmd::os::apply::{handle_cmd_os_apply, handle_cmd_os_plan, Apply, Plan};
 #[cfg(any(feature = "guest-instance", feature = "guest-build"))]
 use crate::cmd::os::cleanup::{handle_cmd_os_cleanup, CleanUp};
 #[cfg(any(feature = "guest-instance", feature = "guest-build"))]
@@ -20,6 +22,8 @@ use crate::cmd::os::update::{handle_cmd_os_update, Update};
 use crate::cmd::os::util::{handle_cmd_os_util, Util};
 use crate::config::LitCliOsConfig;
 
+#[cfg(feature = "guest-instance")]
+mod apply;
 #[cfg(any(feature = "guest-instance", feature = "guest-build"))]
 mod cleanup;
 #[cfg(any(feature = "guest-instance", feature = "guest-build"))]
@@ -68,6 +72,12 @@ enum OsCommands {
     #[cfg(any(feature = "guest-instance", feature = "guest-build"))]
     /// Delete all resources
     DeleteAll(DeleteAll),
+    #[cfg(feature = "guest-instance")]
+    /// Show the changes required to converge guest instances to a fleet spec
+    Plan(Plan),
+    #[cfg(feature = "guest-instance")]
+    /// Converge guest instances to a fleet spec
+    Apply(Apply),
     /// Utility commands
     #[command(arg_required_else_help = true)]
     Util(Util),
@@ -93,6 +103,10 @@ pub async fn handle_cmd_os(
         OsCommands::CleanUp(args) => handle_cmd_os_cleanup(cfg, opts, args).await,
         #[cfg(any(feature = "guest-instance", feature = "guest-build"))]
         OsCommands::DeleteAll(args) => handle_cmd_os_delete_all(cfg, opts, args).await,
+        #[cfg(feature = "guest-instance")]
+        OsCommands::Plan(args) => handle_cmd_os_plan(cfg, opts, args),
+        #[cfg(feature = "guest-instance")]
+        OsCommands::Apply(args) => handle_cmd_os_apply(cfg, opts, args).await,
         OsCommands::Util(args) => handle_cmd_os_util(cfg, opts, args).await,
     }
 }
@@ -29,6 +29,7 @@ pub trait LitCliOsConfig {
     fn litos_guest_instance_destroy_cmd(&self) -> Result<String>;
     fn litos_guest_instance_repair_cmd(&self) -> Result<String>;
     fn litos_guest_instance_resize_cmd(&self) -> Result<String>;
+    fn litos_guest_instance_oneshot_cmd(&self) -> Result<String>;
     fn litos_guest_default_vcpus(&self) -> Result<i64>;
     fn litos_guest_default_mem(&self) -> Result<String>;
     fn litos_guest_default_img_size(&self) -> Result<String>;
@@ -125,6 +126,11 @@ impl LitCliOsConfig for LitConfig {
         Ok(format!("{}/guest/instance/resize.sh", self.litos_install_dir()?))
     }
 
+    // Installed from lit-cli-os/install/guest/instance/oneshot.sh
+    fn litos_guest_instance_oneshot_cmd(&self) -> Result<String> {
+        Ok(format!("{}/guest/instance/oneshot.sh", self.litos_install_dir()?))
+    }
+
     // Guest
     // Defaults
     fn litos_guest_default_vcpus(&self) -> Result<i64> {
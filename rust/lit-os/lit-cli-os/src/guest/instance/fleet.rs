@@ -0,0 +1,662 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use term_table::row::Row;
+use term_table::table_cell::TableCell;
+
+use lit_cli_core::config::LitCliConfig;
+use lit_cli_core::utils::system::parse_human_as_bytes;
+use lit_core::config::envs::LitEnv;
+use lit_core::config::LitConfig;
+use lit_core::error::Result;
+use lit_os_core::error::{io_err, parser_err, validation_err};
+use lit_os_core::guest::cloud_init::network_config::NET_IF_EXTERNAL;
+use lit_os_core::guest::types::GuestType;
+use lit_os_core::utils::ip::IpKind;
+use lit_os_core::utils::validate::{validate_host_name_part, validate_image_size, validate_label};
+
+use crate::guest::instance::helper::{GuestInstanceHelper, GuestInstanceItemHelper};
+use crate::guest::instance::{find_guest_instances, GuestInstanceItem};
+
+pub(crate) const FLEET_LABEL_PREFIX: &str = "fleet:";
+const FLEET_NAME_DEFAULT: &str = "default";
+
+/// The desired guest instances of a host (i.e. fleet.toml).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FleetSpec {
+    /// Name of the fleet, instances created by it are labelled 'fleet:<name>'.
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    /// Delete instances of this fleet which are no longer declared.
+    #[serde(default)]
+    pub(crate) prune: bool,
+    #[serde(default, rename = "instance")]
+    pub(crate) instances: Vec<FleetInstanceSpec>,
+}
+
+/// A desired guest instance, identified by its type and (unique) name.  Unset options are
+/// left as they are (or take the configured defaults on creation).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FleetInstanceSpec {
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) guest_type: GuestType,
+    pub(crate) env: Option<LitEnv>,
+    pub(crate) subnet_id: Option<String>,
+    pub(crate) template_id: Option<String>,
+    /// Expected release id, instances on any other release are reported as drift (recreating
+    /// them would install the current release of the subnet, which may not be this one).
+    pub(crate) release_id: Option<String>,
+    pub(crate) vcpus: Option<i64>,
+    pub(crate) mem: Option<String>,
+    pub(crate) img_size: Option<String>,
+    #[serde(default)]
+    pub(crate) labels: Vec<String>,
+    pub(crate) net4_ip: Option<String>,
+    pub(crate) net4_gw: Option<String>,
+    pub(crate) net6_ip: Option<String>,
+    pub(crate) net6_gw: Option<String>,
+    #[serde(default)]
+    pub(crate) net6_dhcp: bool,
+    #[serde(default = "default_running")]
+    pub(crate) running: bool,
+}
+
+fn default_running() -> bool {
+    true
+}
+
+impl FleetSpec {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| io_err(e, Some(format!("failed to read fleet spec: {path:?}"))))?;
+        let spec: FleetSpec = toml::from_str(&content)
+            .map_err(|e| parser_err(e, Some(format!("failed to parse fleet spec: {path:?}"))))?;
+        spec.verify()?;
+
+        Ok(spec)
+    }
+
+    pub(crate) fn verify(&self) -> Result<()> {
+        validate_label(&self.fleet_label())?;
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        for instance in self.instances.iter() {
+            validate_host_name_part(&instance.name, Some(15))?;
+            if matches!(instance.guest_type, GuestType::Custom) {
+                return Err(validation_err(
+                    format!("instance '{}': custom instances are not supported", instance.name),
+                    None,
+                ));
+            }
+            if !seen.insert(instance.name.as_str()) {
+                return Err(validation_err(
+                    format!("instance '{}' is declared more than once", instance.name),
+                    None,
+                ));
+            }
+            for label in instance.labels.iter() {
+                validate_label(label)?;
+            }
+            if let Some(img_size) = instance.img_size.as_ref() {
+                validate_image_size(img_size)?;
+            }
+            if instance.net4_ip.is_some() != instance.net4_gw.is_some() {
+                return Err(validation_err(
+                    format!(
+                        "instance '{}': net4_ip and net4_gw must be provided as a pair",
+                        instance.name
+                    ),
+                    None,
+                ));
+            }
+            if instance.net6_dhcp && (instance.net6_ip.is_some() || instance.net6_gw.is_some()) {
+                return Err(validation_err(
+                    format!(
+                        "instance '{}': net6_dhcp may not be provided with net6_ip or net6_gw",
+                        instance.name
+                    ),
+                    None,
+                ));
+            }
+            if instance.net6_ip.is_some() != instance.net6_gw.is_some() {
+                return Err(validation_err(
+                    format!(
+                        "instance '{}': net6_ip and net6_gw must be provided as a pair",
+                        instance.name
+                    ),
+                    None,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn fleet_label(&self) -> String {
+        format!("{FLEET_LABEL_PREFIX}{}", self.name.as_deref().unwrap_or(FLEET_NAME_DEFAULT))
+    }
+
+    /// The labels an instance is created with.
+    pub(crate) fn instance_labels(&self, instance: &FleetInstanceSpec) -> Vec<String> {
+        let mut labels = instance.labels.clone();
+        labels.push(self.fleet_label());
+
+        labels
+    }
+}
+
+/// The current state of a guest instance, as far as a fleet spec is concerned.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FleetInstanceState {
+    pub(crate) id: String,
+    pub(crate) name: Option<String>,
+    pub(crate) guest_type: Option<String>,
+    pub(crate) env: Option<String>,
+    pub(crate) subnet_id: Option<String>,
+    pub(crate) release_id: Option<String>,
+    pub(crate) vcpus: Option<i64>,
+    pub(crate) mem: Option<String>,
+    pub(crate) img_size: Option<String>,
+    pub(crate) labels: Vec<String>,
+    pub(crate) net4_ips: Vec<String>,
+    pub(crate) net4_gw: Option<String>,
+    pub(crate) net6_ips: Vec<String>,
+    pub(crate) net6_gw: Option<String>,
+    pub(crate) net6_dhcp: bool,
+    pub(crate) running: bool,
+}
+
+impl FleetInstanceState {
+    pub(crate) fn from_item(item: &GuestInstanceItem) -> Result<Self> {
+        let instance_env = &item.instance_env;
+        let mut state = FleetInstanceState {
+            id: instance_env.instance_id.clone().unwrap_or_default(),
+            name: instance_env.instance_name_suffix.clone(),
+            guest_type: item.build_env.build_type.clone(),
+            env: item.build_env.build_release.clone(),
+            subnet_id: instance_env.subnet_id.clone(),
+            release_id: item.release_env.as_ref().and_then(|r| r.release_id.clone()),
+            vcpus: instance_env.instance_vcpus_i64(),
+            mem: instance_env.instance_mem.clone(),
+            img_size: instance_env.instance_img_size.clone(),
+            labels: instance_env.labels(),
+            ..Default::default()
+        };
+
+        if item.service_exists()? {
+            let instance_service = item.service_name()?;
+            state.running = systemctl::is_active(instance_service.as_str()).unwrap_or_else(|_| {
+                panic!("failed to run systemctl is-active on {instance_service}")
+            });
+        }
+
+        if let Some(network_cfg) = item.network_cfg()? {
+            if let Some(iface) = network_cfg.ethernets().get(NET_IF_EXTERNAL) {
+                state.net4_ips = iface.addresses_of(IpKind::V4)?;
+                state.net4_gw = iface.gateway4().cloned();
+                state.net6_ips = iface.addresses_of(IpKind::V6)?;
+                state.net6_gw = iface.gateway6().cloned();
+                state.net6_dhcp = iface.dhcp6().cloned().unwrap_or(false);
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+/// Read the current state of all valid guest instances.
+pub(crate) fn current_fleet_state(cfg: &LitConfig) -> Result<Vec<FleetInstanceState>> {
+    find_guest_instances(cfg, None, None, None)
+        .unwrap_or_default()
+        .iter()
+        .filter(|item| item.is_valid())
+        .map(FleetInstanceState::from_item)
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum FleetAction {
+    Delete { name: Option<String>, id: String, reason: String },
+    Stop { name: String, id: String },
+    Recreate { name: String, id: String, changes: Vec<String> },
+    Create { name: String },
+    Start { name: String, id: String },
+}
+
+impl FleetAction {
+    fn order(&self) -> u8 {
+        // Free up resources before allocating them.
+        match self {
+            FleetAction::Delete { .. } => 0,
+            FleetAction::Stop { .. } => 1,
+            FleetAction::Recreate { .. } => 2,
+            FleetAction::Create { .. } => 3,
+            FleetAction::Start { .. } => 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FleetPlan {
+    pub(crate) actions: Vec<FleetAction>,
+    /// Differences which can not be converged (reported only).
+    pub(crate) drift: Vec<(String, String)>,
+}
+
+impl FleetPlan {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.actions.is_empty() && self.drift.is_empty()
+    }
+}
+
+/// Compute the actions required to converge the current state to the spec.
+pub(crate) fn plan_fleet(
+    spec: &FleetSpec, current: &[FleetInstanceState], prune: bool,
+) -> FleetPlan {
+    let fleet_label = spec.fleet_label();
+    let prune = prune || spec.prune;
+    let mut plan = FleetPlan::default();
+
+    // Newest first (as returned by find_guest_instances).
+    let mut by_key: HashMap<(String, String), Vec<&FleetInstanceState>> = HashMap::new();
+    for state in current.iter() {
+        if let (Some(guest_type), Some(name)) = (state.guest_type.as_ref(), state.name.as_ref()) {
+            by_key.entry((guest_type.clone(), name.clone())).or_default().push(state);
+        }
+    }
+
+    let mut matched: HashSet<&str> = HashSet::new();
+    for instance in spec.instances.iter() {
+        let key = (instance.guest_type.to_string(), instance.name.clone());
+        let Some(states) = by_key.get(&key) else {
+            plan.actions.push(FleetAction::Create { name: instance.name.clone() });
+            continue;
+        };
+
+        let state = states[0];
+        matched.insert(state.id.as_str());
+        for duplicate in states[1..].iter() {
+            matched.insert(duplicate.id.as_str());
+            if prune {
+                plan.actions.push(FleetAction::Delete {
+                    name: duplicate.name.clone(),
+                    id: duplicate.id.clone(),
+                    reason: format!("duplicate of {}", state.id),
+                });
+            } else {
+                plan.drift.push((
+                    duplicate.id.clone(),
+                    format!("duplicate of {} (use prune to delete)", state.id),
+                ));
+            }
+        }
+
+        let (changes, drift) = instance_changes(instance, state, &spec.instance_labels(instance));
+        for msg in drift {
+            plan.drift.push((state.id.clone(), msg));
+        }
+
+        if !changes.is_empty() {
+            plan.actions.push(FleetAction::Recreate {
+                name: instance.name.clone(),
+                id: state.id.clone(),
+                changes,
+            });
+        } else if instance.running && !state.running {
+            plan.actions
+                .push(FleetAction::Start { name: instance.name.clone(), id: state.id.clone() });
+        } else if !instance.running && state.running {
+            plan.actions
+                .push(FleetAction::Stop { name: instance.name.clone(), id: state.id.clone() });
+        }
+    }
+
+    // Instances of this fleet which are no longer declared.
+    for state in current.iter() {
+        if matched.contains(state.id.as_str()) || !state.labels.contains(&fleet_label) {
+            continue;
+        }
+
+        if prune {
+            plan.actions.push(FleetAction::Delete {
+                name: state.name.clone(),
+                id: state.id.clone(),
+                reason: "not declared".to_string(),
+            });
+        } else {
+            plan.drift.push((state.id.clone(), "not declared (use prune to delete)".to_string()));
+        }
+    }
+
+    plan.actions.sort_by_key(|action| action.order());
+
+    plan
+}
+
+/// Returns the changes which require the instance to be recreated and any drift which
+/// can not be converged.
+fn instance_changes(
+    instance: &FleetInstanceSpec, state: &FleetInstanceState, labels: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let mut changes: Vec<String> = Vec::new();
+    let mut drift: Vec<String> = Vec::new();
+
+    let mut diff = |field: &str, current: Option<&String>, desired: Option<String>| {
+        if let Some(desired) = desired {
+            if current != Some(&desired) {
+                changes.push(format!(
+                    "{field}: {} -> {desired}",
+                    current.map(|v| v.as_str()).unwrap_or("NULL")
+                ));
+            }
+        }
+    };
+
+    diff("env", state.env.as_ref(), instance.env.map(|env| env.to_string()));
+    diff(
+        "subnet_id",
+        state.subnet_id.as_ref(),
+        instance.subnet_id.as_ref().map(|id| id.trim_start_matches("0x").to_lowercase()),
+    );
+    diff(
+        "vcpus",
+        state.vcpus.map(|v| v.to_string()).as_ref(),
+        instance.vcpus.map(|v| v.to_string()),
+    );
+    diff("net4_gw", state.net4_gw.as_ref(), instance.net4_gw.clone());
+    diff("net6_gw", state.net6_gw.as_ref(), instance.net6_gw.clone());
+
+    if let Some(net4_ip) = instance.net4_ip.as_ref() {
+        if !state.net4_ips.contains(net4_ip) {
+            changes.push(format!("net4_ip: [{}] -> {net4_ip}", state.net4_ips.join(", ")));
+        }
+    }
+    if let Some(net6_ip) = instance.net6_ip.as_ref() {
+        if !state.net6_ips.contains(net6_ip) {
+            changes.push(format!("net6_ip: [{}] -> {net6_ip}", state.net6_ips.join(", ")));
+        }
+    }
+    if instance.net6_dhcp && !state.net6_dhcp {
+        changes.push("net6_dhcp: false -> true".to_string());
+    }
+
+    if let Some(release_id) = instance.release_id.as_ref() {
+        if state.release_id.as_ref() != Some(release_id) {
+            drift.push(format!(
+                "release_id: {} -> {release_id} (releases are not pinned on creation)",
+                state.release_id.as_deref().unwrap_or("NULL")
+            ));
+        }
+    }
+
+    if let Some(mem) = instance.mem.as_ref() {
+        if !same_size(state.mem.as_deref(), mem) {
+            changes.push(format!("mem: {} -> {mem}", state.mem.as_deref().unwrap_or("NULL")));
+        }
+    }
+    if let Some(img_size) = instance.img_size.as_ref() {
+        let current = state.img_size.as_deref().and_then(|v| parse_human_as_bytes(v).ok());
+        let desired = parse_human_as_bytes(img_size).ok();
+        match (current, desired) {
+            (Some(current), Some(desired)) if desired < current => drift.push(format!(
+                "img_size: {} -> {img_size} (images can not be shrunk)",
+                state.img_size.as_deref().unwrap_or("NULL")
+            )),
+            (current, desired) if current != desired => changes.push(format!(
+                "img_size: {} -> {img_size}",
+                state.img_size.as_deref().unwrap_or("NULL")
+            )),
+            _ => {}
+        }
+    }
+
+    let mut current_labels = state.labels.clone();
+    let mut desired_labels = labels.to_vec();
+    current_labels.sort();
+    desired_labels.sort();
+    if current_labels != desired_labels {
+        changes.push(format!(
+            "labels: [{}] -> [{}]",
+            current_labels.join(", "),
+            desired_labels.join(", ")
+        ));
+    }
+
+    (changes, drift)
+}
+
+fn same_size(current: Option<&str>, desired: &str) -> bool {
+    match (current.map(parse_human_as_bytes), parse_human_as_bytes(desired)) {
+        (Some(Ok(current)), Ok(desired)) => current == desired,
+        _ => current == Some(desired),
+    }
+}
+
+pub(crate) fn print_fleet_plan(cfg: &LitConfig, plan: &FleetPlan) {
+    if plan.is_empty() {
+        println!("No changes, the fleet is up to date.");
+        return;
+    }
+
+    let null_val = "NULL".to_string();
+    if !plan.actions.is_empty() {
+        let mut table = cfg.default_ascii_table();
+        table.add_row(Row::new(vec![
+            TableCell::new("action"),
+            TableCell::new("name"),
+            TableCell::new("id"),
+            TableCell::new("details"),
+        ]));
+
+        for (i, action) in plan.actions.iter().enumerate() {
+            let (kind, name, id, details) = match action {
+                FleetAction::Delete { name, id, reason } => {
+                    ("delete", name.as_ref().unwrap_or(&null_val), id, reason.clone())
+                }
+                FleetAction::Stop { name, id } => ("stop", name, id, String::new()),
+                FleetAction::Recreate { name, id, changes } => {
+                    ("recreate", name, id, changes.join("\n"))
+                }
+                FleetAction::Create { name } => ("create", name, &null_val, String::new()),
+                FleetAction::Start { name, id } => ("start", name, id, String::new()),
+            };
+
+            let mut row = Row::new(vec![
+                TableCell::new(kind),
+                TableCell::new(name),
+                TableCell::new(id),
+                TableCell::new(details),
+            ]);
+            if i != 0 {
+                row.has_separator = false;
+            }
+            table.add_row(row);
+        }
+
+        println!("{}", table.render());
+    }
+
+    if !plan.drift.is_empty() {
+        let mut table = cfg.default_ascii_table();
+        table.add_row(Row::new(vec![TableCell::new("id"), TableCell::new("drift")]));
+
+        for (i, (id, msg)) in plan.drift.iter().enumerate() {
+            let mut row = Row::new(vec![TableCell::new(id), TableCell::new(msg)]);
+            if i != 0 {
+                row.has_separator = false;
+            }
+            table.add_row(row);
+        }
+
+        println!("{}", table.render());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(content: &str) -> FleetSpec {
+        let spec: FleetSpec = toml::from_str(content).expect("failed to parse spec");
+        spec.verify().expect("invalid spec");
+        spec
+    }
+
+    fn node_spec() -> FleetSpec {
+        spec(
+            r#"
+            name = "test"
+
+            [[instance]]
+            name = "node1"
+            type = "node"
+            vcpus = 8
+            mem = "32G"
+            img_size = "50G"
+            "#,
+        )
+    }
+
+    fn node_state() -> FleetInstanceState {
+        FleetInstanceState {
+            id: "abc123".to_string(),
+            name: Some("node1".to_string()),
+            guest_type: Some("node".to_string()),
+            vcpus: Some(8),
+            mem: Some("32G".to_string()),
+            img_size: Some("50G".to_string()),
+            labels: vec!["fleet:test".to_string()],
+            running: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plan_creates_missing_instances() {
+        let plan = plan_fleet(&node_spec(), &[], false);
+        assert_eq!(plan.actions, vec![FleetAction::Create { name: "node1".to_string() }]);
+    }
+
+    #[test]
+    fn plan_is_empty_when_converged() {
+        let plan = plan_fleet(&node_spec(), &[node_state()], false);
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn plan_recreates_changed_instances() {
+        let mut state = node_state();
+        state.mem = Some("16G".to_string());
+        let plan = plan_fleet(&node_spec(), &[state], false);
+        assert_eq!(
+            plan.actions,
+            vec![FleetAction::Recreate {
+                name: "node1".to_string(),
+                id: "abc123".to_string(),
+                changes: vec!["mem: 16G -> 32G".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn plan_reports_image_shrink_as_drift() {
+        let mut state = node_state();
+        state.img_size = Some("100G".to_string());
+        let plan = plan_fleet(&node_spec(), &[state], false);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.drift.len(), 1);
+    }
+
+    #[test]
+    fn plan_reports_release_mismatch_as_drift() {
+        let spec = spec(
+            r#"
+            name = "test"
+
+            [[instance]]
+            name = "node1"
+            type = "node"
+            release_id = "release2"
+            "#,
+        );
+        let mut state = node_state();
+        state.release_id = Some("release1".to_string());
+
+        let plan = plan_fleet(&spec, &[state.clone()], false);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.drift.len(), 1);
+
+        state.release_id = Some("release2".to_string());
+        assert!(plan_fleet(&spec, &[state], false).is_empty());
+    }
+
+    #[test]
+    fn plan_starts_and_stops_instances() {
+        let mut state = node_state();
+        state.running = false;
+        let plan = plan_fleet(&node_spec(), &[state], false);
+        assert_eq!(
+            plan.actions,
+            vec![FleetAction::Start { name: "node1".to_string(), id: "abc123".to_string() }]
+        );
+    }
+
+    #[test]
+    fn plan_prunes_undeclared_fleet_instances_only() {
+        let mut undeclared = node_state();
+        undeclared.id = "def456".to_string();
+        undeclared.name = Some("node2".to_string());
+        let mut unmanaged = node_state();
+        unmanaged.id = "ghi789".to_string();
+        unmanaged.name = Some("other".to_string());
+        unmanaged.labels = vec![];
+        let current = vec![node_state(), undeclared, unmanaged];
+
+        let plan = plan_fleet(&node_spec(), &current, false);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.drift.len(), 1);
+
+        let plan = plan_fleet(&node_spec(), &current, true);
+        assert_eq!(
+            plan.actions,
+            vec![FleetAction::Delete {
+                name: Some("node2".to_string()),
+                id: "def456".to_string(),
+                reason: "not declared".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn verify_rejects_invalid_specs() {
+        let res: FleetSpec = toml::from_str(
+            r#"
+            [[instance]]
+            name = "node1"
+            type = "node"
+
+            [[instance]]
+            name = "node1"
+            type = "node"
+            "#,
+        )
+        .unwrap();
+        assert!(res.verify().is_err());
+
+        let res: FleetSpec = toml::from_str(
+            r#"
+            [[instance]]
+            name = "node1"
+            type = "node"
+            net4_ip = "10.0.0.2/24"
+            "#,
+        )
+        .unwrap();
+        assert!(res.verify().is_err());
+    }
+}
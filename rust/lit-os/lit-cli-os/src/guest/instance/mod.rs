@@ -24,6 +24,8 @@ use crate::guest::common::print_describe_string;
 use crate::guest::instance::helper::{GuestInstanceHelper, GuestInstanceItemHelper};
 
 pub(crate) mod common;
+#[cfg(feature = "guest-instance")]
+pub(crate) mod fleet;
 pub(crate) mod helper;
 pub(crate) mod oneshot;
 pub(crate) mod release;
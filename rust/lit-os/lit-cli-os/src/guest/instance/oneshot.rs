@@ -5,20 +5,33 @@ use crate::cmd::os::guest::template::release::{do_release_template, GuestTemplat
 use crate::guest::instance::common::write_password;
 use crate::guest::template::GuestTemplateItem;
 #[cfg(feature = "guest-build")]
-use lit_attestation::attestation::TryGenerate;
+use lit_attestation::attestation::{TryGenerate, DATA_KEY_INSTANCE_ID, DATA_KEY_UNIX_TIME};
+#[cfg(feature = "guest-build")]
+use lit_attestation::{Attestation, AttestationType};
 use lit_cli_core::cmd::CliGlobalOpts;
+#[cfg(feature = "guest-build")]
+use lit_cli_core::utils::system::chmod;
 use lit_core::config::LitConfig;
+#[cfg(feature = "guest-build")]
+use lit_os_core::guest::oneshot::config::oneshot_config_hash;
 #[allow(unused_imports)]
 use lit_os_core::guest::oneshot::config::{
     ActionEntry, OneShotConfig, ACTION_SETTINGS_KEY_GUEST_VCPUS, ACTION_SETTINGS_KEY_REQUEST,
     ACTION_TYPE_BOOTSTRAP,
 };
+#[cfg(feature = "guest-build")]
+use lit_os_core::guest::oneshot::{
+    ONESHOT_FILE_CONFIG, ONESHOT_FILE_SIGNATURE, SIGNATURE_DATA_KEY_CONFIG_HASH,
+    SIGNATURE_DATA_KEY_EXPIRES,
+};
 use lit_os_core::guest::types::GuestType;
 #[allow(unused_imports)]
 use lit_os_prov_core::release::create::types::CreateReleaseRequest;
+#[cfg(feature = "guest-build")]
+use std::fs;
 use std::path::Path;
 #[allow(unused_imports)]
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[allow(unused_variables, clippy::too_many_arguments)]
 pub(crate) async fn create_oneshot_actions(
@@ -86,3 +99,46 @@ pub(crate) async fn create_oneshot_actions(
 
     oneshot_cfg
 }
+
+/// Write the config to the oneshot dir along with its signature (required by the maintenance
+/// actions), which binds it to the instance until it expires.
+#[cfg(feature = "guest-build")]
+pub(crate) async fn write_signed_oneshot(
+    oneshot_dir: &Path, oneshot_cfg: &OneShotConfig, instance_id: &str, ttl: Duration,
+    admin_key: &str,
+) {
+    let mut config_file = oneshot_dir.to_path_buf();
+    config_file.push(ONESHOT_FILE_CONFIG);
+    let mut signature_file = oneshot_dir.to_path_buf();
+    signature_file.push(ONESHOT_FILE_SIGNATURE);
+
+    fs::create_dir_all(oneshot_dir)
+        .unwrap_or_else(|_| panic!("failed to create dir: {oneshot_dir:?}"));
+    oneshot_cfg
+        .write_file(config_file.as_path())
+        .unwrap_or_else(|_| panic!("failed to write oneshot config file: {config_file:?}"));
+    chmod(config_file.as_path(), &"600".to_string());
+
+    // Sign what was written (the guest hashes the file as is).
+    let config = fs::read(&config_file)
+        .unwrap_or_else(|_| panic!("failed to read oneshot config file: {config_file:?}"));
+
+    let unix_time = SystemTime::now().duration_since(UNIX_EPOCH).expect("failed to get unix time");
+    let expires = (unix_time + ttl).as_secs();
+    let noonce = unix_time.as_millis().to_le_bytes().to_vec();
+
+    let mut signature = Attestation::new(AttestationType::AdminSigned, noonce)
+        .await
+        .expect("failed to request Attestation");
+    signature
+        .insert_data(SIGNATURE_DATA_KEY_CONFIG_HASH, oneshot_config_hash(&config[..]))
+        .insert_data(SIGNATURE_DATA_KEY_EXPIRES, expires.to_le_bytes().to_vec())
+        .insert_data(DATA_KEY_INSTANCE_ID, instance_id.as_bytes().to_vec())
+        .insert_data(DATA_KEY_UNIX_TIME, unix_time.as_secs().to_le_bytes().to_vec());
+    signature.sign(admin_key).expect("failed to sign oneshot config");
+
+    let signature = signature.generate().await.expect("failed to generate oneshot signature");
+    signature
+        .to_file(signature_file.as_path())
+        .unwrap_or_else(|_| panic!("failed to write oneshot signature: {signature_file:?}"));
+}
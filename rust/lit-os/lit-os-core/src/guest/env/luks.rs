@@ -0,0 +1,137 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::result::Result as StdResult;
+
+use lit_core::utils::env::{parse_env_to_map, Error as EnvError};
+
+use crate::error::{io_err, Result};
+
+/// Records the LUKS boot passphrase key versions (relative to the var volume, so the running
+/// guest sees it as '/var/local/etc/lit-os-luks').
+pub const GUEST_LUKS_ENV_VAR_PATH: &str = "local/etc/lit-os-luks";
+
+pub const LUKS_KEY_VERSION_INITIAL: u32 = 0;
+
+#[derive(Default, Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+#[allow(unused)]
+pub struct GuestLuksEnv {
+    pub root_key_version: Option<String>,
+    pub var_key_version: Option<String>,
+}
+
+impl GuestLuksEnv {
+    pub fn root_key_version_u32(&self) -> Option<u32> {
+        self.root_key_version.as_ref()?.parse().ok()
+    }
+
+    pub fn var_key_version_u32(&self) -> Option<u32> {
+        self.var_key_version.as_ref()?.parse().ok()
+    }
+
+    pub fn set_root_key_version(&mut self, version: u32) {
+        self.root_key_version = Some(version.to_string());
+    }
+
+    pub fn set_var_key_version(&mut self, version: u32) {
+        self.var_key_version = Some(version.to_string());
+    }
+
+    pub fn write_file(&self, path: &Path) -> Result<()> {
+        let mut contents = String::new();
+        if let Some(version) = self.root_key_version.as_ref() {
+            contents.push_str(format!("LUKS_ROOT_KEY_VERSION={version}\n").as_str());
+        }
+        if let Some(version) = self.var_key_version.as_ref() {
+            contents.push_str(format!("LUKS_VAR_KEY_VERSION={version}\n").as_str());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| io_err(e, Some(format!("failed to make dir: {parent:?}"))))?;
+        }
+
+        fs::write(path, contents).map_err(|e| io_err(e, Some(format!("failed to write: {path:?}"))))
+    }
+}
+
+/// The KDF context of a boot passphrase (version 0 is the context used prior to rotation).
+pub fn luks_boot_passphrase_context(label: &str, version: u32) -> String {
+    if version == LUKS_KEY_VERSION_INITIAL {
+        format!("{label}:boot-passphrase")
+    } else {
+        format!("{label}:boot-passphrase:v{version}")
+    }
+}
+
+pub fn read_guest_luks_env<P: AsRef<Path>>(path: P) -> StdResult<Option<GuestLuksEnv>, EnvError> {
+    if !path.as_ref().exists() {
+        return Ok(None);
+    }
+
+    let file = File::open(path.as_ref()).unwrap();
+    let mut reader = BufReader::new(file);
+
+    load_guest_luks_env(&mut reader)
+}
+
+pub fn load_guest_luks_env<R: Read>(
+    reader: &mut BufReader<R>,
+) -> StdResult<Option<GuestLuksEnv>, EnvError> {
+    let mut env = GuestLuksEnv::default();
+
+    let env_data = parse_env_to_map(reader, true)?;
+
+    for (key, value) in env_data {
+        match key.as_str() {
+            "LUKS_ROOT_KEY_VERSION" => env.root_key_version = Some(value),
+            "LUKS_VAR_KEY_VERSION" => env.var_key_version = Some(value),
+            _ => {}
+        }
+    }
+
+    Ok(Some(env))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::guest::env::luks::{
+        luks_boot_passphrase_context, read_guest_luks_env, GuestLuksEnv,
+    };
+
+    #[test]
+    fn luks_boot_passphrase_context_test() {
+        // Must never change (existing volumes are keyed with it).
+        assert_eq!(
+            luks_boot_passphrase_context("root:\"/dev/vda2\"", 0),
+            "root:\"/dev/vda2\":boot-passphrase"
+        );
+        assert_eq!(
+            luks_boot_passphrase_context("root:\"/dev/vda2\"", 2),
+            "root:\"/dev/vda2\":boot-passphrase:v2"
+        );
+    }
+
+    #[test]
+    fn guest_luks_env_test() {
+        let mut env = GuestLuksEnv::default();
+        env.set_root_key_version(3);
+
+        let file = temp_file::empty();
+        env.write_file(file.path()).expect("failed to write luks env");
+
+        let loaded = read_guest_luks_env(file.path())
+            .expect("failed to read luks env")
+            .expect("expected luks env");
+        assert_eq!(loaded, env);
+        assert_eq!(loaded.root_key_version_u32(), Some(3));
+        assert_eq!(loaded.var_key_version_u32(), None);
+
+        env.set_var_key_version(1);
+        env.write_file(file.path()).expect("failed to write luks env");
+
+        let loaded = read_guest_luks_env(file.path()).unwrap().unwrap();
+        assert_eq!(loaded.var_key_version_u32(), Some(1));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod build;
 pub mod cmdline;
 pub mod instance;
+pub mod luks;
 pub mod release;
@@ -5,15 +5,30 @@ use std::result::Result as StdResult;
 
 use serde::{Deserialize, Serialize};
 use serde_bytes_base64::Bytes;
+use sha2::{Digest, Sha512};
 
 use crate::error::{io_err, serializer_err, validation_err, Result};
 
 pub const ONESHOT_VERSION_V1: u32 = 1;
 
 pub const ACTION_TYPE_BOOTSTRAP: &str = "bootstrap";
+pub const ACTION_TYPE_KEY_SHARE_EXPORT: &str = "key_share_export";
+pub const ACTION_TYPE_KEY_SHARE_IMPORT: &str = "key_share_import";
+pub const ACTION_TYPE_DISK_REKEY: &str = "disk_rekey";
+pub const ACTION_TYPE_LUKS_ROTATE: &str = "luks_rotate";
+pub const ACTION_TYPE_CONFIG_RESET: &str = "config_reset";
+pub const ACTION_TYPE_DIAGNOSTICS: &str = "diagnostics";
 
 pub const ACTION_SETTINGS_KEY_REQUEST: &str = "request";
 pub const ACTION_SETTINGS_KEY_GUEST_VCPUS: &str = "guest_vcpus";
+/// Hex X25519 public key of the guest receiving exported key shares.
+pub const ACTION_SETTINGS_KEY_RECIPIENT: &str = "recipient";
+/// Path (under /var) of the key shares to export or import.
+pub const ACTION_SETTINGS_KEY_PATH: &str = "path";
+/// Comma separated volumes to re-key or rotate (root, var).
+pub const ACTION_SETTINGS_KEY_VOLUMES: &str = "volumes";
+/// Newline separated paths (under /var) to reset.
+pub const ACTION_SETTINGS_KEY_PATHS: &str = "paths";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -87,6 +102,13 @@ impl OneShotConfig {
     }
 }
 
+/// Hash of the config file as written, signed by the oneshot signature.
+pub fn oneshot_config_hash(bytes: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    hasher.update(bytes);
+    hasher.finalize().to_vec()
+}
+
 impl Default for OneShotConfig {
     fn default() -> Self {
         Self::new()
@@ -137,6 +159,15 @@ impl ActionEntry {
         self
     }
 
+    pub fn setting_string(&self, key: &str) -> Result<Option<String>> {
+        match self.settings.get(key) {
+            Some(val) => Ok(Some(String::from_utf8(val.to_vec()).map_err(|e| {
+                validation_err(e, Some(format!("setting '{key}' is not valid utf-8")))
+            })?)),
+            None => Ok(None),
+        }
+    }
+
     // Verify
     pub fn verify(&self) -> Result<()> {
         Ok(())
@@ -147,7 +178,10 @@ impl ActionEntry {
 mod tests {
     use std::path::PathBuf;
 
-    use crate::guest::oneshot::config::OneShotConfig;
+    use crate::guest::oneshot::config::{
+        oneshot_config_hash, ActionEntry, OneShotConfig, ACTION_SETTINGS_KEY_PATH,
+        ACTION_TYPE_KEY_SHARE_EXPORT,
+    };
 
     const RESOURCES_TEST_DIR: &str = "resources/test/guest/oneshot";
 
@@ -214,6 +248,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn config_hash_test() {
+        let file_path = get_test_path("config-ok.yaml");
+        let bytes = std::fs::read(file_path.as_path()).expect("failed to read oneshot config");
+
+        assert_eq!(oneshot_config_hash(&bytes).len(), 64);
+        assert_eq!(oneshot_config_hash(&bytes), oneshot_config_hash(&bytes));
+
+        let mut other = bytes.clone();
+        other.push(b'\n');
+        assert_ne!(oneshot_config_hash(&bytes), oneshot_config_hash(&other));
+    }
+
+    #[test]
+    fn setting_string_test() {
+        let mut entry = ActionEntry::new();
+        entry.insert_setting(ACTION_SETTINGS_KEY_PATH.into(), b"lit/node".to_vec());
+
+        let mut config = OneShotConfig::new();
+        config.insert_action(ACTION_TYPE_KEY_SHARE_EXPORT.into(), entry.clone());
+        config.verify().expect("failed to verify oneshot config");
+
+        assert_eq!(entry.setting_string(ACTION_SETTINGS_KEY_PATH).unwrap().unwrap(), "lit/node");
+        assert!(entry.setting_string("missing").unwrap().is_none());
+
+        entry.insert_setting(ACTION_SETTINGS_KEY_PATH.into(), vec![0xff, 0xfe]);
+        assert!(entry.setting_string(ACTION_SETTINGS_KEY_PATH).is_err());
+    }
+
     // Util
     fn get_test_path(path: &str) -> PathBuf {
         let mut test_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -3,6 +3,10 @@ use std::path::PathBuf;
 use crate::guest::oneshot::config::OneShotConfig;
 
 pub const REQUIRE_SYNC: &str = "sync";
+/// Mount /var, even once the guest has been initialised.
+pub const REQUIRE_VAR: &str = "var";
+/// The config must carry a valid admin or operator signature.
+pub const REQUIRE_SIGNED: &str = "signed";
 
 #[derive(Debug, Clone)]
 pub struct OneShotContext {
@@ -1,6 +1,32 @@
+use crate::guest::oneshot::config::{
+    ACTION_TYPE_BOOTSTRAP, ACTION_TYPE_DIAGNOSTICS, ACTION_TYPE_KEY_SHARE_EXPORT,
+    ACTION_TYPE_KEY_SHARE_IMPORT,
+};
+
 pub const ONESHOT_FILE_CONFIG: &str = "config.yaml";
+/// AdminSigned attestation over the config (required by actions with the 'signed' require).
+pub const ONESHOT_FILE_SIGNATURE: &str = "config.sig";
+pub const ONESHOT_FILE_STATUS: &str = "status";
+pub const ONESHOT_FILE_STATUS_REPORT: &str = "status.json";
+
+pub const ALLOWED_ONESHOT_FILES: [&str; 9] = [
+    ONESHOT_FILE_CONFIG, ONESHOT_FILE_SIGNATURE, ONESHOT_FILE_STATUS, ONESHOT_FILE_STATUS_REPORT,
+    ACTION_TYPE_BOOTSTRAP, ACTION_TYPE_KEY_SHARE_EXPORT, ACTION_TYPE_KEY_SHARE_IMPORT,
+    ACTION_TYPE_DIAGNOSTICS, "lost+found",
+];
+
+// State (under /var, as the oneshot volume is writable by the host)
+pub const ONESHOT_VAR_DIR: &str = "lit/os/oneshot";
+pub const ONESHOT_DIR_MARKERS: &str = "markers";
+pub const ONESHOT_FILE_CONSUMED_NONCES: &str = "consumed";
 
-pub const ALLOWED_ONESHOT_FILES: [&str; 2] = [ONESHOT_FILE_CONFIG, "lost+found"];
+// Signature (attestation) data
+pub const SIGNATURE_DATA_KEY_CONFIG_HASH: &str = "ONESHOT_CONFIG_HASH";
+pub const SIGNATURE_DATA_KEY_EXPIRES: &str = "ONESHOT_EXPIRES";
+/// The longest a signed oneshot run without /var (i.e. diagnostics, disk_rekey) may still be
+/// valid for when it runs, as its nonce can't be recorded to stop it being replayed.
+pub const SIGNATURE_UNRECORDED_MAX_TTL_SECS: u64 = 10 * 60;
 
 pub mod config;
 pub mod context;
+pub mod status;
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{generic_err, io_err, serializer_err, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionState {
+    /// Executed during this run.
+    Done,
+    /// Already executed for this config (marker present).
+    Skipped,
+    /// Waiting on input from the operator (i.e. a key share bundle).
+    Pending,
+    Failed,
+}
+
+impl fmt::Display for ActionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionState::Done => write!(f, "done"),
+            ActionState::Skipped => write!(f, "skipped"),
+            ActionState::Pending => write!(f, "pending"),
+            ActionState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionStatus {
+    pub state: ActionState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub unix_time: u64,
+}
+
+/// Written to the oneshot volume after every action so the host can tell what ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OneShotStatusReport {
+    config_hash: String,
+    actions: BTreeMap<String, ActionStatus>,
+}
+
+impl OneShotStatusReport {
+    pub fn new(config_hash: String) -> Self {
+        Self { config_hash, actions: BTreeMap::new() }
+    }
+
+    // Accessors
+    pub fn config_hash(&self) -> &String {
+        &self.config_hash
+    }
+
+    pub fn actions(&self) -> &BTreeMap<String, ActionStatus> {
+        &self.actions
+    }
+
+    pub fn set_action(
+        &mut self, action: &str, state: ActionState, message: Option<String>,
+    ) -> Result<&mut Self> {
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| generic_err(e, None))?
+            .as_secs();
+
+        self.actions.insert(action.to_string(), ActionStatus { state, message, unix_time });
+
+        Ok(self)
+    }
+
+    /// True when every action either ran or was already done.
+    pub fn is_complete(&self) -> bool {
+        self.actions.values().all(|s| matches!(s.state, ActionState::Done | ActionState::Skipped))
+    }
+
+    // Read / Write
+    pub fn write_file(&self, file: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self).map_err(|e| serializer_err(e, None))?;
+        fs::write(file, json).map_err(|e| io_err(e, None))?;
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&Path> for OneShotStatusReport {
+    type Error = crate::error::Error;
+
+    fn try_from(value: &Path) -> std::result::Result<Self, Self::Error> {
+        let bytes = fs::read(value).map_err(|e| io_err(e, None))?;
+
+        serde_json::from_slice(&bytes[..]).map_err(|e| serializer_err(e, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::guest::oneshot::status::{ActionState, OneShotStatusReport};
+
+    #[test]
+    fn write_and_load_test() {
+        let mut report = OneShotStatusReport::new("abcd".into());
+        report
+            .set_action("diagnostics", ActionState::Done, None)
+            .unwrap()
+            .set_action("key_share_import", ActionState::Pending, Some("awaiting bundle".into()))
+            .unwrap();
+
+        assert!(!report.is_complete());
+
+        let file = temp_file::empty();
+        report.write_file(file.path()).expect("failed to write status report");
+
+        let loaded =
+            OneShotStatusReport::try_from(file.path()).expect("failed to load status report");
+        assert_eq!(loaded, report);
+        assert_eq!(loaded.actions()["key_share_import"].state, ActionState::Pending);
+    }
+}
@@ -36,6 +36,8 @@ pub const CTX_KEY_NO_RESIZE: &str = "NO_RESIZE";
 pub const CTX_KEY_PASSPHRASE_INIT: &str = "PASSPHRASE_INIT";
 pub const CTX_KEY_ROOT_PASSPHRASE_BOOT: &str = "ROOT_PASSPHRASE_BOOT";
 pub const CTX_KEY_VAR_PASSPHRASE_BOOT: &str = "VAR_PASSPHRASE_BOOT";
+pub const CTX_KEY_ROOT_PASSPHRASE_BOOT_PREVIOUS: &str = "ROOT_PASSPHRASE_BOOT_PREVIOUS";
+pub const CTX_KEY_VAR_PASSPHRASE_BOOT_PREVIOUS: &str = "VAR_PASSPHRASE_BOOT_PREVIOUS";
 pub const CTX_KEY_CONTEXT_HASH: &str = "CONTEXT_HASH";
 pub const CTX_KEY_CLOUD_INIT_CTX: &str = "CLOUD_INIT_CTX";
 pub const CTX_KEY_ONESHOT_CTX: &str = "ONESHOT_CTX";
@@ -3,22 +3,25 @@ use lit_core::error::Unexpected;
 use lit_os_core::error::unexpected_err;
 #[allow(unused_imports)]
 use lit_os_core::error::{generic_err, Result};
+use lit_os_core::guest::env::luks::{luks_boot_passphrase_context, LUKS_KEY_VERSION_INITIAL};
 use lit_os_prov_api_client::api::release::ProvApiClientRelease;
 use lit_os_prov_api_client::client::ProvApiClient;
 use lit_os_prov_core::release::init::types::{InitRelease, InitReleaseRequest};
 #[allow(unused_imports)]
 use log::info;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::init::context::{
-    InitContext, CTX_KEY_PASSPHRASE_INIT, CTX_KEY_ROOT_PASSPHRASE_BOOT, CTX_KEY_VAR_PASSPHRASE_BOOT,
+    InitContext, CTX_KEY_PASSPHRASE_INIT, CTX_KEY_ROOT_PASSPHRASE_BOOT,
+    CTX_KEY_ROOT_PASSPHRASE_BOOT_PREVIOUS, CTX_KEY_VAR_PASSPHRASE_BOOT,
+    CTX_KEY_VAR_PASSPHRASE_BOOT_PREVIOUS,
 };
 use crate::init::stage::Outcome;
 #[cfg(not(target_os = "macos"))]
 use crate::utils::cryptsetup_sys::cryptsetup_reencrypt_dev;
 #[cfg(not(target_os = "macos"))]
 use crate::utils::libcryptsetup::{
-    cryptsetup_add_keyslot, cryptsetup_has_slot_active, cryptsetup_init_dev,
+    cryptsetup_add_keyslot, cryptsetup_has_slot_active, cryptsetup_init_dev, cryptsetup_key_version,
 };
 #[cfg(not(target_os = "macos"))]
 use libcryptsetup_rs::CryptDevice;
@@ -37,8 +40,14 @@ pub(crate) async fn run(ctx: &mut InitContext) -> Result<Outcome> {
 
     // Load / generate keys
     let init_passphrase = load_init_passphrases(ctx).await?;
-    let (root_passphrase, var_passphrase) =
-        load_boot_passphrases(ctx, &root_dev_label, &var_dev_label).await?;
+    let (root_passphrase, var_passphrase) = load_boot_passphrases(
+        ctx,
+        root_dev_path.as_path(),
+        &root_dev_label,
+        var_dev_path.as_path(),
+        &var_dev_label,
+    )
+    .await?;
 
     #[cfg(not(target_os = "macos"))]
     if cryptsetup_has_slot_active(&mut root_dev, root_dev_label.as_str(), 0)? {
@@ -112,29 +121,77 @@ pub(crate) fn volume_paths_and_labels(
 }
 
 pub(crate) async fn prepare_boot_passphrases(ctx: &mut InitContext) -> Result<()> {
-    let (_root_dev_path, root_dev_label, _var_dev_path, var_dev_label) =
+    let (root_dev_path, root_dev_label, var_dev_path, var_dev_label) =
         volume_paths_and_labels(ctx)?;
 
-    let _ = load_boot_passphrases(ctx, &root_dev_label, &var_dev_label).await?;
+    let _ = load_boot_passphrases(
+        ctx,
+        root_dev_path.as_path(),
+        &root_dev_label,
+        var_dev_path.as_path(),
+        &var_dev_label,
+    )
+    .await?;
 
     Ok(())
 }
 
 #[allow(dead_code)]
 async fn load_boot_passphrases(
-    ctx: &mut InitContext, root_dev_label: &String, var_dev_label: &String,
+    ctx: &mut InitContext, root_dev_path: &Path, root_dev_label: &String, var_dev_path: &Path,
+    var_dev_label: &String,
 ) -> Result<(Vec<u8>, Vec<u8>)> {
-    let root_passphrase =
-        ctx.passphrase_of_length(format!("{root_dev_label}:boot-passphrase").as_str(), 64).await?;
+    let root_passphrase = load_boot_passphrase(
+        ctx, root_dev_path, root_dev_label, CTX_KEY_ROOT_PASSPHRASE_BOOT,
+        CTX_KEY_ROOT_PASSPHRASE_BOOT_PREVIOUS,
+    )
+    .await?;
+
+    let var_passphrase = load_boot_passphrase(
+        ctx, var_dev_path, var_dev_label, CTX_KEY_VAR_PASSPHRASE_BOOT,
+        CTX_KEY_VAR_PASSPHRASE_BOOT_PREVIOUS,
+    )
+    .await?;
+
+    Ok((root_passphrase, var_passphrase))
+}
 
-    ctx.insert(CTX_KEY_ROOT_PASSPHRASE_BOOT.to_string(), Box::new(root_passphrase.clone()));
+// Loads the passphrase of the current key version, along with the previous one (if any) so
+// unlock can still proceed should a rotation not have completed.
+async fn load_boot_passphrase(
+    ctx: &mut InitContext, dev_path: &Path, dev_label: &String, key: &str, previous_key: &str,
+) -> Result<Vec<u8>> {
+    let version = luks_key_version(dev_path, dev_label)?;
 
-    let var_passphrase =
-        ctx.passphrase_of_length(format!("{var_dev_label}:boot-passphrase").as_str(), 64).await?;
+    let passphrase = ctx
+        .passphrase_of_length(luks_boot_passphrase_context(dev_label, version).as_str(), 64)
+        .await?;
 
-    ctx.insert(CTX_KEY_VAR_PASSPHRASE_BOOT.to_string(), Box::new(var_passphrase.clone()));
+    ctx.insert(key.to_string(), Box::new(passphrase.clone()));
 
-    Ok((root_passphrase, var_passphrase))
+    if version > LUKS_KEY_VERSION_INITIAL {
+        let previous = ctx
+            .passphrase_of_length(luks_boot_passphrase_context(dev_label, version - 1).as_str(), 64)
+            .await?;
+
+        ctx.insert(previous_key.to_string(), Box::new(previous));
+    }
+
+    Ok(passphrase)
+}
+
+#[allow(unused_variables)]
+pub(crate) fn luks_key_version(dev_path: &Path, dev_label: &String) -> Result<u32> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        let mut dev = cryptsetup_init_dev(dev_path, dev_label.as_str())?;
+
+        Ok(cryptsetup_key_version(&mut dev, dev_label.as_str())?
+            .unwrap_or(LUKS_KEY_VERSION_INITIAL))
+    }
+
+    #[cfg(target_os = "macos")]
+    Ok(LUKS_KEY_VERSION_INITIAL)
 }
 
 #[cfg(not(target_os = "macos"))]
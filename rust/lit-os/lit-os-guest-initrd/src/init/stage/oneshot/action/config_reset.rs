@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use log::info;
+
+use lit_os_core::error::{config_err, Result};
+use lit_os_core::guest::oneshot::config::{ActionEntry, ACTION_SETTINGS_KEY_PATHS};
+use lit_os_core::guest::oneshot::context::OneShotContext;
+
+use crate::init::context::InitContext;
+use crate::init::stage::oneshot::action::{var_relative_path, Outcome};
+
+// Purges the given paths from /var, the sync (required) then restores them from the cloud-init.
+pub(crate) async fn run(
+    ctx: &mut InitContext, _oneshot_ctx: &OneShotContext, entry: &ActionEntry,
+) -> Result<Outcome> {
+    let paths = entry.setting_string(ACTION_SETTINGS_KEY_PATHS)?.ok_or_else(|| {
+        config_err(
+            format!("config reset action requires setting: {ACTION_SETTINGS_KEY_PATHS}"),
+            None,
+        )
+    })?;
+
+    // Verify all paths before purging anything.
+    let mut purged: Vec<PathBuf> = Vec::new();
+    for path in paths.lines().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let mut full_path = PathBuf::from("/var");
+        full_path.push(var_relative_path(path)?);
+
+        purged.push(full_path);
+    }
+    if purged.is_empty() {
+        return Err(config_err(format!("setting '{ACTION_SETTINGS_KEY_PATHS}' is empty"), None));
+    }
+
+    for path in purged {
+        info!("Resetting {:?}", &path);
+
+        ctx.push_purged(path);
+    }
+
+    Ok(Outcome::Continue)
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::process::Command;
+
+use log::{info, warn};
+
+use lit_os_core::config::LitOsGuestConfig;
+use lit_os_core::error::{io_err, Result};
+use lit_os_core::guest::oneshot::config::{ActionEntry, ACTION_TYPE_DIAGNOSTICS};
+use lit_os_core::guest::oneshot::context::OneShotContext;
+
+use crate::init::context::InitContext;
+use crate::init::stage::oneshot::action::Outcome;
+
+// Output file, command and args (nothing here may read key material).
+const DIAGNOSTIC_COMMANDS: [(&str, &str, &[&str]); 8] = [
+    ("uname.txt", "uname", &["-a"]),
+    ("dmesg.txt", "dmesg", &[]),
+    ("ip-addr.txt", "ip", &["addr"]),
+    ("ip-route.txt", "ip", &["route"]),
+    ("lsblk.txt", "lsblk", &["-f"]),
+    ("blkid.txt", "blkid", &[]),
+    ("df.txt", "df", &["-h"]),
+    ("meminfo.txt", "cat", &["/proc/meminfo"]),
+];
+
+// Collects a diagnostic bundle into the oneshot volume.
+pub(crate) async fn run(
+    ctx: &mut InitContext, _oneshot_ctx: &OneShotContext, _entry: &ActionEntry,
+) -> Result<Outcome> {
+    let mut dir = ctx.cfg().litos_oneshot_mnt();
+    dir.push(ACTION_TYPE_DIAGNOSTICS);
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| io_err(e, Some(format!("failed to make dir: {dir:?}"))))?;
+    }
+
+    for (file, cmd, args) in DIAGNOSTIC_COMMANDS {
+        let mut path = dir.clone();
+        path.push(file);
+
+        // Collect as much as we can, a missing tool shouldn't fail the bundle.
+        let contents = match Command::new(cmd).args(args).output() {
+            Ok(out) => {
+                let mut contents = out.stdout;
+                contents.extend_from_slice(&out.stderr);
+                contents
+            }
+            Err(e) => {
+                warn!("Failed to run diagnostic: {} {:?} - {:?}", cmd, args, e);
+
+                format!("failed to run: {e:?}").into_bytes()
+            }
+        };
+
+        fs::write(&path, contents)
+            .map_err(|e| io_err(e, Some(format!("failed to write: {path:?}"))))?;
+    }
+
+    // Build env (no secrets, helps match the report with a build).
+    let mut path = dir.clone();
+    path.push("build.env.txt");
+    fs::write(&path, format!("{:#?}", ctx.build_env()))
+        .map_err(|e| io_err(e, Some(format!("failed to write: {path:?}"))))?;
+
+    info!("Collected diagnostics into '{}'", ACTION_TYPE_DIAGNOSTICS);
+
+    Ok(Outcome::Continue)
+}
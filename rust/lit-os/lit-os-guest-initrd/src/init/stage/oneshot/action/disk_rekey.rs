@@ -0,0 +1,134 @@
+#[allow(unused_imports)]
+use std::path::Path;
+
+#[allow(unused_imports)]
+use log::info;
+
+#[allow(unused_imports)]
+use lit_os_core::error::{config_err, generic_err, validation_err, Result};
+use lit_os_core::guest::oneshot::config::{ActionEntry, ACTION_SETTINGS_KEY_VOLUMES};
+use lit_os_core::guest::oneshot::context::OneShotContext;
+
+use crate::init::context::{
+    InitContext, CTX_KEY_ROOT_PASSPHRASE_BOOT, CTX_KEY_VAR_PASSPHRASE_BOOT,
+};
+use crate::init::stage::init::{prepare_boot_passphrases, volume_paths_and_labels};
+use crate::init::stage::oneshot::action::Outcome;
+#[cfg(not(target_os = "macos"))]
+use crate::utils::cryptsetup_sys::cryptsetup_reencrypt_dev;
+#[cfg(not(target_os = "macos"))]
+use crate::utils::libcryptsetup::{
+    cryptsetup_add_keyslot_at, cryptsetup_destroy_slot, cryptsetup_find_keyslot,
+    cryptsetup_has_slot_active, cryptsetup_init_dev,
+};
+
+const VOLUME_ROOT: &str = "root";
+const VOLUME_VAR: &str = "var";
+
+// Re-key the LUKS volumes (new master key), the boot passphrases are kept.
+#[allow(unused_variables, unreachable_code)]
+pub(crate) async fn run(
+    ctx: &mut InitContext, _oneshot_ctx: &OneShotContext, entry: &ActionEntry,
+) -> Result<Outcome> {
+    #[cfg(target_os = "macos")]
+    unimplemented!("cryptsetup not supported on MacOs");
+
+    let volumes = entry
+        .setting_string(ACTION_SETTINGS_KEY_VOLUMES)?
+        .unwrap_or_else(|| format!("{VOLUME_ROOT},{VOLUME_VAR}"));
+    let volumes: Vec<&str> =
+        volumes.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect();
+    if volumes.is_empty() {
+        return Err(config_err(format!("setting '{ACTION_SETTINGS_KEY_VOLUMES}' is empty"), None));
+    }
+
+    let (root_dev_path, root_dev_label, var_dev_path, var_dev_label) =
+        volume_paths_and_labels(ctx)?;
+
+    prepare_boot_passphrases(ctx).await?;
+
+    for volume in volumes {
+        let (dev_path, dev_label, passphrase) = match volume {
+            VOLUME_ROOT => (&root_dev_path, &root_dev_label, CTX_KEY_ROOT_PASSPHRASE_BOOT),
+            VOLUME_VAR => (&var_dev_path, &var_dev_label, CTX_KEY_VAR_PASSPHRASE_BOOT),
+            _ => {
+                return Err(config_err(
+                    format!(
+                        "setting '{ACTION_SETTINGS_KEY_VOLUMES}' has an invalid volume: {volume}"
+                    ),
+                    None,
+                ))
+            }
+        };
+        let passphrase = ctx
+            .get_bin(passphrase)
+            .cloned()
+            .ok_or_else(|| config_err(format!("missing context: {passphrase}"), None))?;
+
+        #[cfg(not(target_os = "macos"))]
+        rekey_volume(ctx, dev_path.as_path(), dev_label, &passphrase[..]).await?;
+    }
+
+    Ok(Outcome::Continue)
+}
+
+// Re-key the luks volume:
+// 1. Occupy slot 0 with a throwaway passphrase, so the new keyslot can't land in it (we rely on
+//    slot 0 being empty to determine if init has been done).
+// 2. Call reencrypt with the boot passphrase keyslot, which wipes every other keyslot (including
+//    slot 0) and stores the boot passphrase in a new keyslot.
+#[cfg(not(target_os = "macos"))]
+async fn rekey_volume(
+    ctx: &mut InitContext, dev_path: &Path, label: &String, passphrase: &[u8],
+) -> Result<()> {
+    info!("Re-keying volume '{}'", label);
+
+    let mut dev = cryptsetup_init_dev(dev_path, label)?;
+    if cryptsetup_has_slot_active(&mut dev, label, 0)? {
+        return Err(validation_err(
+            format!("luks re-key refused for '{label}', the volume has not been initialised"),
+            None,
+        ));
+    }
+
+    let slot = cryptsetup_find_keyslot(&mut dev, label, passphrase)?;
+
+    let placeholder =
+        ctx.random_key_of_length(format!("{label}:rekey-placeholder").as_str(), 64).await?;
+    let master_key = ctx.random_key_of_length(format!("{label}:master-key").as_str(), 64).await?;
+
+    cryptsetup_add_keyslot_at(&mut dev, label, 0, passphrase, &placeholder[..])?;
+
+    if let Err(e) = cryptsetup_reencrypt_dev(
+        dev_path,
+        label,
+        passphrase,
+        slot as u8,
+        &master_key[..],
+        Some(512),
+        Some("aes-xts-plain64"),
+        Some("sha512"),
+    ) {
+        // Never leave slot 0 behind (the next boot would treat the volume as uninitialised).
+        let mut dev = cryptsetup_init_dev(dev_path, label)?;
+        if cryptsetup_has_slot_active(&mut dev, label, 0)? {
+            cryptsetup_destroy_slot(&mut dev, label, 0)?;
+        }
+
+        return Err(e);
+    }
+
+    // Reload device
+    let mut dev = cryptsetup_init_dev(dev_path, label)?;
+    if cryptsetup_has_slot_active(&mut dev, label, 0)? {
+        return Err(generic_err(
+            format!("luks re-key failed for '{label}', slot '0' is still defined"),
+            None,
+        ));
+    }
+    let new_slot = cryptsetup_find_keyslot(&mut dev, label, passphrase)?;
+
+    info!("Re-keyed volume '{}' (keyslot: {} -> {})", label, slot, new_slot);
+
+    Ok(())
+}
@@ -0,0 +1,363 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crypto_box::aead::{Aead, AeadCore, OsRng};
+use crypto_box::{Nonce, PublicKey, SalsaBox, SecretKey};
+use lit_attestation::attestation::DATA_KEY_REQ_BODY_HASH;
+use lit_attestation::verification::Policy;
+use lit_attestation::Attestation;
+use lit_core::utils::binary::{bytes_to_hex, hex_to_bytes};
+use lit_core::utils::hash::sha256;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_bytes_base64::Bytes;
+use walkdir::WalkDir;
+
+use lit_os_core::config::LitOsGuestConfig;
+use lit_os_core::error::{
+    config_err, conversion_err, io_err, serializer_err, validation_err, Result,
+};
+use lit_os_core::guest::oneshot::config::{
+    ActionEntry, ACTION_SETTINGS_KEY_PATH, ACTION_SETTINGS_KEY_RECIPIENT,
+    ACTION_TYPE_KEY_SHARE_EXPORT, ACTION_TYPE_KEY_SHARE_IMPORT,
+};
+use lit_os_core::guest::oneshot::context::OneShotContext;
+
+use crate::init::context::InitContext;
+use crate::init::stage::oneshot::action::{var_relative_path, Outcome};
+
+// Handoff:
+// 1. Run 'key_share_import' on the receiving guest, it publishes its handoff public key along
+//    with an attestation binding it to the guest (and reports pending).
+// 2. Run 'key_share_export' on the sending guest with the public key as the 'recipient' and the
+//    attestation copied next to the config, it seals the key shares to the recipient.
+// 3. Copy the bundle to the receiving guest and run 'key_share_import' again.
+const HANDOFF_KEY_CONTEXT: &str = "oneshot:key-share-handoff";
+const FILE_BUNDLE: &str = "bundle.json";
+const FILE_HANDOFF_PUBLIC_KEY: &str = "handoff.pub";
+const FILE_HANDOFF_ATTESTATION: &str = "handoff.attestation";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyShareBundle {
+    ephemeral_public_key: String,
+    nonce: String,
+    ciphertext: Bytes,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyShareFiles {
+    files: Vec<KeyShareFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyShareFile {
+    path: String,
+    data: Bytes,
+}
+
+pub(crate) async fn export(
+    ctx: &mut InitContext, _oneshot_ctx: &OneShotContext, entry: &ActionEntry,
+) -> Result<Outcome> {
+    let recipient = entry.setting_string(ACTION_SETTINGS_KEY_RECIPIENT)?.ok_or_else(|| {
+        config_err(
+            format!("key share export action requires setting: {ACTION_SETTINGS_KEY_RECIPIENT}"),
+            None,
+        )
+    })?;
+    let recipient = public_key_from_hex(recipient.as_str())?;
+    let (src, rel_path) = key_share_path(ctx, entry)?;
+
+    let dir = action_dir(ctx, ACTION_TYPE_KEY_SHARE_EXPORT)?;
+
+    // The recipient must be an attested guest.
+    verify_recipient(ctx, &dir, &recipient).await?;
+
+    // Collect the key shares
+    let mut files = KeyShareFiles::default();
+    for item in WalkDir::new(&src).follow_links(false) {
+        let item = item.map_err(|e| io_err(e, Some(format!("failed to walk: {src:?}"))))?;
+        if !item.file_type().is_file() {
+            continue;
+        }
+
+        let rel = item
+            .path()
+            .strip_prefix(&src)
+            .map_err(|e| io_err(e, Some(format!("unable to strip prefix {src:?}"))))?;
+        let data = fs::read(item.path())
+            .map_err(|e| io_err(e, Some(format!("failed to read: {:?}", item.path()))))?;
+
+        files.files.push(KeyShareFile {
+            path: rel.to_string_lossy().to_string(),
+            data: Bytes::from(data),
+        });
+    }
+    if files.files.is_empty() {
+        return Err(validation_err(format!("no key shares found in /var/{rel_path:?}"), None));
+    }
+
+    // Seal to the recipient
+    let bundle = seal_key_shares(&files, &recipient)?;
+
+    let mut bundle_path = dir;
+    bundle_path.push(FILE_BUNDLE);
+    write_json(&bundle_path, &bundle)?;
+
+    info!("Exported {} key share file(s) from /var/{:?}", files.files.len(), rel_path);
+
+    Ok(Outcome::Continue)
+}
+
+pub(crate) async fn import(
+    ctx: &mut InitContext, _oneshot_ctx: &OneShotContext, entry: &ActionEntry,
+) -> Result<Outcome> {
+    let (dest, rel_path) = key_share_path(ctx, entry)?;
+    let secret = SecretKey::from(ctx.derive_key(HANDOFF_KEY_CONTEXT).await?);
+
+    let dir = action_dir(ctx, ACTION_TYPE_KEY_SHARE_IMPORT)?;
+    let mut bundle_path = dir.clone();
+    bundle_path.push(FILE_BUNDLE);
+
+    if !bundle_path.exists() {
+        publish_handoff_key(ctx, &dir, &secret.public_key()).await?;
+
+        return Ok(Outcome::Pending(format!(
+            "no key share bundle, handoff key published to '{ACTION_TYPE_KEY_SHARE_IMPORT}/{FILE_HANDOFF_PUBLIC_KEY}'"
+        )));
+    }
+
+    // Open the bundle
+    let bundle: KeyShareBundle = serde_json::from_slice(
+        &fs::read(&bundle_path).map_err(|e| io_err(e, Some(format!("{bundle_path:?}"))))?[..],
+    )
+    .map_err(|e| serializer_err(e, Some("invalid key share bundle".into())))?;
+
+    let files = open_key_shares(&bundle, &secret)?;
+
+    // Verify all paths before writing anything.
+    let mut writes: Vec<(PathBuf, &KeyShareFile)> = Vec::new();
+    for file in files.files.iter() {
+        let mut path = dest.clone();
+        path.push(var_relative_path(file.path.as_str())?);
+
+        if path.exists() {
+            let existing = fs::read(&path).map_err(|e| io_err(e, Some(format!("{path:?}"))))?;
+            if existing.eq(&file.data[..]) {
+                continue;
+            }
+
+            return Err(validation_err(
+                format!("refusing to overwrite existing key share: {path:?}"),
+                None,
+            ));
+        }
+
+        writes.push((path, file));
+    }
+
+    for (path, file) in writes.iter() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| io_err(e, Some(format!("failed to make dir: {parent:?}"))))?;
+        }
+
+        fs::write(path, &file.data[..])
+            .map_err(|e| io_err(e, Some(format!("failed to write: {path:?}"))))?;
+    }
+
+    info!("Imported {} key share file(s) into /var/{:?}", writes.len(), rel_path);
+
+    Ok(Outcome::Continue)
+}
+
+async fn publish_handoff_key(
+    ctx: &mut InitContext, dir: &Path, public_key: &PublicKey,
+) -> Result<()> {
+    let mut public_key_path = dir.to_path_buf();
+    public_key_path.push(FILE_HANDOFF_PUBLIC_KEY);
+
+    fs::write(&public_key_path, bytes_to_hex(public_key.as_bytes()))
+        .map_err(|e| io_err(e, Some(format!("failed to write: {public_key_path:?}"))))?;
+
+    let attestation =
+        ctx.request_attestation(None, Some(sha256(public_key.as_bytes()).to_vec())).await?;
+
+    let mut attestation_path = dir.to_path_buf();
+    attestation_path.push(FILE_HANDOFF_ATTESTATION);
+    attestation.to_file(attestation_path.as_path())?;
+
+    info!("Published key share handoff key: {}", bytes_to_hex(public_key.as_bytes()));
+
+    Ok(())
+}
+
+async fn verify_recipient(ctx: &mut InitContext, dir: &Path, recipient: &PublicKey) -> Result<()> {
+    let mut attestation_path = dir.to_path_buf();
+    attestation_path.push(FILE_HANDOFF_ATTESTATION);
+
+    if !attestation_path.exists() {
+        return Err(config_err(
+            format!("key share export requires the recipient's '{FILE_HANDOFF_ATTESTATION}' in '{ACTION_TYPE_KEY_SHARE_EXPORT}'"),
+            None,
+        ));
+    }
+
+    let attestation = Attestation::try_from(attestation_path.as_path())?;
+
+    if ctx.is_release() {
+        attestation
+            .verify_full(ctx.cfg(), None, Some(Policy::Default))
+            .await
+            .map_err(|e| validation_err(e, Some("failed to verify the recipient".into())))?;
+    } else {
+        attestation
+            .verify()
+            .await
+            .map_err(|e| validation_err(e, Some("failed to verify the recipient".into())))?;
+    }
+
+    let expected = sha256(recipient.as_bytes()).to_vec();
+    match attestation.get_data(DATA_KEY_REQ_BODY_HASH) {
+        Some(hash) if hash.to_vec().eq(&expected) => Ok(()),
+        _ => Err(validation_err(
+            "recipient attestation does not attest to the recipient handoff key",
+            None,
+        )),
+    }
+}
+
+fn key_share_path(ctx: &InitContext, entry: &ActionEntry) -> Result<(PathBuf, PathBuf)> {
+    let path = entry.setting_string(ACTION_SETTINGS_KEY_PATH)?.ok_or_else(|| {
+        config_err(format!("key share action requires setting: {ACTION_SETTINGS_KEY_PATH}"), None)
+    })?;
+    let rel_path = var_relative_path(path.as_str())?;
+
+    let mut full_path = ctx.cfg().litos_initrd_var_mnt();
+    full_path.push(&rel_path);
+
+    Ok((full_path, rel_path))
+}
+
+fn action_dir(ctx: &InitContext, action: &str) -> Result<PathBuf> {
+    let mut dir = ctx.cfg().litos_oneshot_mnt();
+    dir.push(action);
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| io_err(e, Some(format!("failed to make dir: {dir:?}"))))?;
+    }
+
+    Ok(dir)
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let json = serde_json::to_vec(value).map_err(|e| serializer_err(e, None))?;
+
+    fs::write(path, json).map_err(|e| io_err(e, Some(format!("failed to write: {path:?}"))))
+}
+
+fn seal_key_shares(files: &KeyShareFiles, recipient: &PublicKey) -> Result<KeyShareBundle> {
+    let plaintext = serde_json::to_vec(files).map_err(|e| serializer_err(e, None))?;
+    let ephemeral_key = SecretKey::generate(&mut OsRng);
+    let nonce = SalsaBox::generate_nonce(&mut OsRng);
+    let ciphertext = SalsaBox::new(recipient, &ephemeral_key)
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|e| conversion_err(format!("{e:?}"), Some("failed to seal key shares".into())))?;
+
+    Ok(KeyShareBundle {
+        ephemeral_public_key: bytes_to_hex(ephemeral_key.public_key().as_bytes()),
+        nonce: bytes_to_hex(nonce.as_slice()),
+        ciphertext: Bytes::from(ciphertext),
+    })
+}
+
+fn open_key_shares(bundle: &KeyShareBundle, secret: &SecretKey) -> Result<KeyShareFiles> {
+    let ephemeral_key = public_key_from_hex(bundle.ephemeral_public_key.as_str())?;
+    let nonce = hex_to_bytes(bundle.nonce.as_str())?;
+    if nonce.len() != 24 {
+        return Err(conversion_err("invalid key share bundle nonce length", None));
+    }
+
+    let plaintext = SalsaBox::new(&ephemeral_key, secret)
+        .decrypt(Nonce::from_slice(&nonce), &bundle.ciphertext[..])
+        .map_err(|_| validation_err("key share bundle was not sealed to this guest", None))?;
+
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| serializer_err(e, Some("invalid key share bundle contents".into())))
+}
+
+fn public_key_from_hex(key: &str) -> Result<PublicKey> {
+    let bytes: [u8; 32] = hex_to_bytes(key)?
+        .try_into()
+        .map_err(|_| conversion_err(format!("invalid X25519 public key: {key}"), None))?;
+
+    Ok(PublicKey::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_box::aead::OsRng;
+    use crypto_box::SecretKey;
+    use lit_core::utils::binary::bytes_to_hex;
+    use serde_bytes_base64::Bytes;
+
+    use super::{
+        open_key_shares, public_key_from_hex, seal_key_shares, KeyShareFile, KeyShareFiles,
+    };
+
+    fn files() -> KeyShareFiles {
+        KeyShareFiles {
+            files: vec![
+                KeyShareFile { path: "K256/share-1.cbor".into(), data: Bytes::from(vec![1, 2, 3]) },
+                KeyShareFile { path: "BLS/share-1.cbor".into(), data: Bytes::from(vec![4, 5]) },
+            ],
+        }
+    }
+
+    #[test]
+    fn seal_open_round_trip_test() {
+        let secret = SecretKey::generate(&mut OsRng);
+        let recipient = public_key_from_hex(&bytes_to_hex(secret.public_key().as_bytes())).unwrap();
+
+        let bundle = seal_key_shares(&files(), &recipient).unwrap();
+        let opened = open_key_shares(&bundle, &secret).unwrap();
+
+        assert_eq!(opened.files.len(), 2);
+        for (opened, file) in opened.files.iter().zip(files().files.iter()) {
+            assert_eq!(opened.path, file.path);
+            assert_eq!(opened.data[..], file.data[..]);
+        }
+    }
+
+    #[test]
+    fn open_other_recipient_test() {
+        let secret = SecretKey::generate(&mut OsRng);
+        let other = SecretKey::generate(&mut OsRng);
+
+        let bundle = seal_key_shares(&files(), &secret.public_key()).unwrap();
+
+        assert!(open_key_shares(&bundle, &other).is_err());
+    }
+
+    #[test]
+    fn open_tampered_test() {
+        let secret = SecretKey::generate(&mut OsRng);
+
+        let mut bundle = seal_key_shares(&files(), &secret.public_key()).unwrap();
+        let mut ciphertext = bundle.ciphertext[..].to_vec();
+        ciphertext[0] ^= 1;
+        bundle.ciphertext = Bytes::from(ciphertext);
+        assert!(open_key_shares(&bundle, &secret).is_err());
+
+        let mut bundle = seal_key_shares(&files(), &secret.public_key()).unwrap();
+        bundle.nonce = bytes_to_hex([0u8; 12]);
+        assert!(open_key_shares(&bundle, &secret).is_err());
+    }
+
+    #[test]
+    fn public_key_from_hex_test() {
+        assert!(public_key_from_hex(&bytes_to_hex([7u8; 32])).is_ok());
+        assert!(public_key_from_hex(&bytes_to_hex([7u8; 31])).is_err());
+        assert!(public_key_from_hex("not hex").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+#[allow(unused_imports)]
+use log::info;
+
+use lit_os_core::config::LitOsGuestConfig;
+#[allow(unused_imports)]
+use lit_os_core::error::{config_err, generic_err, validation_err, Result};
+use lit_os_core::guest::env::luks::{
+    luks_boot_passphrase_context, read_guest_luks_env, GUEST_LUKS_ENV_VAR_PATH,
+};
+use lit_os_core::guest::oneshot::config::{ActionEntry, ACTION_SETTINGS_KEY_VOLUMES};
+use lit_os_core::guest::oneshot::context::OneShotContext;
+
+use crate::init::context::{
+    InitContext, CTX_KEY_ROOT_PASSPHRASE_BOOT, CTX_KEY_VAR_PASSPHRASE_BOOT,
+};
+use crate::init::stage::init::{luks_key_version, volume_paths_and_labels};
+use crate::init::stage::oneshot::action::Outcome;
+#[cfg(not(target_os = "macos"))]
+use crate::utils::libcryptsetup::{
+    cryptsetup_has_slot_active, cryptsetup_init_dev, cryptsetup_rotate_passphrase,
+};
+
+const VOLUME_ROOT: &str = "root";
+const VOLUME_VAR: &str = "var";
+
+// Rotate the boot passphrases to the next key version (the master key is kept). The volumes
+// are active (var is mounted to record the versions), adding / removing keyslots is fine.
+#[allow(unused_variables, unreachable_code)]
+pub(crate) async fn run(
+    ctx: &mut InitContext, _oneshot_ctx: &OneShotContext, entry: &ActionEntry,
+) -> Result<Outcome> {
+    #[cfg(target_os = "macos")]
+    unimplemented!("cryptsetup not supported on MacOs");
+
+    let volumes = entry
+        .setting_string(ACTION_SETTINGS_KEY_VOLUMES)?
+        .unwrap_or_else(|| format!("{VOLUME_ROOT},{VOLUME_VAR}"));
+    let volumes: Vec<&str> =
+        volumes.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect();
+    if volumes.is_empty() {
+        return Err(config_err(format!("setting '{ACTION_SETTINGS_KEY_VOLUMES}' is empty"), None));
+    }
+
+    if ctx.is_first_boot() {
+        return Err(validation_err(
+            "luks rotation refused, the volumes have not been initialised",
+            None,
+        ));
+    }
+
+    let (root_dev_path, root_dev_label, var_dev_path, var_dev_label) =
+        volume_paths_and_labels(ctx)?;
+
+    let mut env_path = ctx.cfg().litos_initrd_var_mnt();
+    env_path.push(GUEST_LUKS_ENV_VAR_PATH);
+    let mut env = read_guest_luks_env(&env_path)
+        .map_err(|e| config_err(e, Some(format!("failed to read: {env_path:?}"))))?
+        .unwrap_or_default();
+
+    for volume in volumes {
+        let (dev_path, dev_label, passphrase_key) = match volume {
+            VOLUME_ROOT => (&root_dev_path, &root_dev_label, CTX_KEY_ROOT_PASSPHRASE_BOOT),
+            VOLUME_VAR => (&var_dev_path, &var_dev_label, CTX_KEY_VAR_PASSPHRASE_BOOT),
+            _ => {
+                return Err(config_err(
+                    format!(
+                        "setting '{ACTION_SETTINGS_KEY_VOLUMES}' has an invalid volume: {volume}"
+                    ),
+                    None,
+                ))
+            }
+        };
+        // Set by unlock, i.e. the passphrase that activated the volume.
+        let passphrase = ctx
+            .get_bin(passphrase_key)
+            .cloned()
+            .ok_or_else(|| config_err(format!("missing context: {passphrase_key}"), None))?;
+
+        let version = luks_key_version(dev_path.as_path(), dev_label)? + 1;
+        let new_passphrase = ctx
+            .passphrase_of_length(luks_boot_passphrase_context(dev_label, version).as_str(), 64)
+            .await?;
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            let mut dev = cryptsetup_init_dev(dev_path.as_path(), dev_label)?;
+            if cryptsetup_has_slot_active(&mut dev, dev_label, 0)? {
+                return Err(validation_err(
+                    format!("luks rotation refused for '{dev_label}', the volume has not been initialised"),
+                    None,
+                ));
+            }
+
+            let (old_slot, new_slot) = cryptsetup_rotate_passphrase(
+                dev_path.as_path(),
+                dev_label,
+                &passphrase[..],
+                &new_passphrase[..],
+                version,
+            )?;
+
+            info!(
+                "Rotated volume '{}' to key version {} (keyslot: {} -> {})",
+                dev_label, version, old_slot, new_slot
+            );
+        }
+
+        ctx.insert(passphrase_key.to_string(), Box::new(new_passphrase));
+
+        match volume {
+            VOLUME_ROOT => env.set_root_key_version(version),
+            _ => env.set_var_key_version(version),
+        }
+
+        // Record after each volume (a later failure must not lose an earlier rotation).
+        env.write_file(env_path.as_path())?;
+    }
+
+    Ok(Outcome::Continue)
+}
@@ -1,12 +1,18 @@
 pub(crate) mod bootstrap;
+pub(crate) mod config_reset;
+pub(crate) mod diagnostics;
+pub(crate) mod disk_rekey;
+pub(crate) mod key_share;
+pub(crate) mod luks_rotate;
 
 use crate::init::context::InitContext;
 use futures::future::LocalBoxFuture;
-use lit_os_core::error::{config_err, Result};
+use lit_os_core::error::{config_err, validation_err, Result};
 use lit_os_core::guest::oneshot::config::ActionEntry;
 use lit_os_core::guest::oneshot::context::OneShotContext;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use tokio::sync::RwLock;
 
 pub(crate) static ACTIONS: Lazy<RwLock<Vec<OneShotAction>>> = Lazy::new(|| RwLock::new(Vec::new()));
@@ -22,6 +28,8 @@ pub(crate) type ExecutorFn = for<'a> fn(
 pub(crate) enum Outcome {
     Continue,
     Break,
+    /// Not done, waiting on the operator (no marker is written).
+    Pending(String),
 }
 
 #[derive(Clone)]
@@ -91,3 +99,58 @@ pub(crate) async fn actions_map() -> Result<HashMap<String, OneShotAction>> {
 
     Ok(map)
 }
+
+/// Resolve a path setting (relative to /var, i.e. 'lit/node' or '/var/lit/node') safely.
+pub(crate) fn var_relative_path(path: &str) -> Result<PathBuf> {
+    let path = Path::new(path);
+    let path = path.strip_prefix("/var").unwrap_or(path);
+
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::RootDir | Component::CurDir => {}
+            _ => return Err(validation_err(format!("path must fall under /var: {path:?}"), None)),
+        }
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Err(validation_err("path must not be /var itself", None));
+    }
+
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::var_relative_path;
+
+    #[test]
+    fn var_relative_path_test() {
+        assert_eq!(var_relative_path("lit/node").unwrap(), PathBuf::from("lit/node"));
+        assert_eq!(var_relative_path("/var/lit/node").unwrap(), PathBuf::from("lit/node"));
+        assert_eq!(var_relative_path("./lit/node/").unwrap(), PathBuf::from("lit/node"));
+        // Absolute paths are taken as under /var.
+        assert_eq!(var_relative_path("/etc/passwd").unwrap(), PathBuf::from("etc/passwd"));
+        assert_eq!(var_relative_path("/var2/lit").unwrap(), PathBuf::from("var2/lit"));
+    }
+
+    #[test]
+    fn var_relative_path_traversal_test() {
+        assert!(var_relative_path("../etc/passwd").is_err());
+        assert!(var_relative_path("lit/../../etc").is_err());
+        assert!(var_relative_path("/var/../etc/shadow").is_err());
+        assert!(var_relative_path("/var/lit/node/..").is_err());
+    }
+
+    #[test]
+    fn var_relative_path_var_itself_test() {
+        assert!(var_relative_path("").is_err());
+        assert!(var_relative_path("/var").is_err());
+        assert!(var_relative_path("/var/").is_err());
+        assert!(var_relative_path("/").is_err());
+        assert!(var_relative_path(".").is_err());
+    }
+}
@@ -5,7 +5,12 @@ use log::{as_error, error, info};
 use lit_os_core::config::LitOsGuestConfig;
 use lit_os_core::error::{config_err, io_err, validation_err, Result};
 use lit_os_core::guest::cloud_init::CLOUD_INIT_FILE_INIT_PW;
-use lit_os_core::guest::oneshot::context::{OneShotContext, REQUIRE_SYNC};
+use lit_os_core::guest::oneshot::config::ACTION_TYPE_DISK_REKEY;
+use lit_os_core::guest::oneshot::context::{
+    OneShotContext, REQUIRE_SIGNED, REQUIRE_SYNC, REQUIRE_VAR,
+};
+use lit_os_core::guest::oneshot::status::ActionState;
+use lit_os_core::guest::oneshot::ONESHOT_FILE_STATUS;
 
 use crate::init::context::{
     InitContext, CTX_KEY_PASSPHRASE_INIT, CTX_KEY_ROOT_PASSPHRASE_BOOT, CTX_KEY_VAR_PASSPHRASE_BOOT,
@@ -13,6 +18,8 @@ use crate::init::context::{
 use crate::init::stage::init::{prepare_boot_passphrases, volume_paths_and_labels};
 use crate::init::stage::oneshot::action::Outcome as ActionOutcome;
 use crate::init::stage::oneshot::action::{actions_map, ACTIONS};
+use crate::init::stage::oneshot::signature::verify_oneshot_signature;
+use crate::init::stage::oneshot::status::OneShotStatus;
 use crate::init::stage::unlock::{
     activate_luks_root_volume, activate_luks_var_volume, mount_root_volume, mount_var_volume,
 };
@@ -24,6 +31,8 @@ use crate::utils::libcryptsetup::{cryptsetup_has_slot_active, cryptsetup_init_de
 pub(crate) mod action;
 pub(crate) mod prepare;
 pub(crate) mod setup;
+pub(crate) mod signature;
+pub(crate) mod status;
 
 pub(crate) async fn run(ctx: &mut InitContext) -> Result<Outcome> {
     let oneshot_ctx = ctx.get_oneshot_ctx();
@@ -42,10 +51,31 @@ pub(crate) async fn run(ctx: &mut InitContext) -> Result<Outcome> {
             return Err(config_err(format!("one shot action '{key}' is invalid"), None));
         }
     }
+    if config.actions().contains_key(ACTION_TYPE_DISK_REKEY)
+        && (oneshot_ctx.has_require(&REQUIRE_SYNC.into())
+            || oneshot_ctx.has_require(&REQUIRE_VAR.into()))
+    {
+        return Err(config_err(
+            format!("one shot action '{ACTION_TYPE_DISK_REKEY}' must run without actions that mount the volumes"),
+            None,
+        ));
+    }
     info!("Oneshot dry run verified");
 
+    let mut status = OneShotStatus::load(&oneshot_ctx)?;
+
     // Before
-    match before_execute(ctx, &oneshot_ctx).await? {
+    let before = match before_execute(ctx, &oneshot_ctx, &mut status).await {
+        Ok(before) => before,
+        Err(e) => {
+            // i.e. invalid signature, report it against every action.
+            for key in config.actions().keys() {
+                status.record(key, ActionState::Failed, Some(e.to_string()))?;
+            }
+            return Err(e);
+        }
+    };
+    match before {
         Outcome::Break => unreachable!("invalid outcome for before_execute (Break)"),
         Outcome::PowerOff => return Ok(Outcome::PowerOff),
         _ => {}
@@ -56,13 +86,29 @@ pub(crate) async fn run(ctx: &mut InitContext) -> Result<Outcome> {
     let actions = ACTIONS.read().await.clone();
     for action in actions.iter() {
         if let Some(entry) = config.actions().get(action.action()) {
+            if status.is_done(action.action()) {
+                status.record(action.action(), ActionState::Skipped, None)?;
+                continue;
+            }
+
             env::set_var(ENV_LOG_INIT_SUB_STAGE, action.action());
             info!("Running oneshot action '{:?}'", entry);
             match action.execute(ctx, &oneshot_ctx, entry).await {
-                Ok(ActionOutcome::Continue) => continue,
-                Ok(ActionOutcome::Break) => break,
+                Ok(ActionOutcome::Continue) => {
+                    status.record(action.action(), ActionState::Done, None)?;
+                    continue;
+                }
+                Ok(ActionOutcome::Break) => {
+                    status.record(action.action(), ActionState::Done, None)?;
+                    break;
+                }
+                Ok(ActionOutcome::Pending(msg)) => {
+                    status.record(action.action(), ActionState::Pending, Some(msg))?;
+                    continue;
+                }
                 Err(e) => {
                     error!(error = as_error!(e); "one shot action '{}' failed", action.action());
+                    status.record(action.action(), ActionState::Failed, Some(e.to_string()))?;
                     env::remove_var(ENV_LOG_INIT_SUB_STAGE);
                     return Ok(Outcome::Diagnose);
                 }
@@ -84,11 +130,19 @@ pub(crate) async fn run(ctx: &mut InitContext) -> Result<Outcome> {
 }
 
 pub(crate) async fn before_execute(
-    ctx: &mut InitContext, oneshot_ctx: &OneShotContext,
+    ctx: &mut InitContext, oneshot_ctx: &OneShotContext, status: &mut OneShotStatus,
 ) -> Result<Outcome> {
     // Process requires
-    if oneshot_ctx.has_require(&REQUIRE_SYNC.into()) {
-        info!("Oneshot requires '{}': Mounting volumes", REQUIRE_SYNC);
+    let mut signature = None;
+    if oneshot_ctx.has_require(&REQUIRE_SIGNED.into()) {
+        info!("Oneshot requires '{}': Verifying signature", REQUIRE_SIGNED);
+
+        signature = Some(verify_oneshot_signature(ctx, oneshot_ctx).await?);
+    }
+
+    if oneshot_ctx.has_require(&REQUIRE_SYNC.into()) || oneshot_ctx.has_require(&REQUIRE_VAR.into())
+    {
+        info!("Oneshot requires '{}' or '{}': Mounting volumes", REQUIRE_SYNC, REQUIRE_VAR);
 
         // Prepare for mount
         prepare_for_mount(ctx).await?;
@@ -96,6 +150,13 @@ pub(crate) async fn before_execute(
         // Mount
         mount_root(ctx, oneshot_ctx)?; // root is required for var
         mount_var(ctx, oneshot_ctx)?;
+
+        // Markers and consumed nonces are kept in the encrypted /var (see status).
+        status.use_var(ctx.cfg().litos_initrd_var_mnt().as_path());
+    }
+
+    if let Some(signature) = signature.as_ref() {
+        status.consume_signature(signature)?;
     }
 
     Ok(Outcome::Continue)
@@ -113,7 +174,7 @@ pub(crate) async fn after_execute(
 
     // Write status
     let mut status_path = ctx.cfg().litos_oneshot_mnt();
-    status_path.push("status");
+    status_path.push(ONESHOT_FILE_STATUS);
 
     fs::write(status_path.as_path(), "1").map_err(|e| io_err(e, None))?;
 
@@ -171,10 +232,9 @@ fn mount_root(ctx: &mut InitContext, _oneshot_ctx: &OneShotContext) -> Result<()
 }
 
 #[allow(dead_code)]
-fn mount_var(ctx: &mut InitContext, _oneshot_ctx: &OneShotContext) -> Result<()> {
-    if !ctx.is_first_boot() {
-        // TODO: We could support it, but will we need to?
-        return Err(validation_err("this oneshot requires mounting /var however this is only supported before init has occurred", None));
+fn mount_var(ctx: &mut InitContext, oneshot_ctx: &OneShotContext) -> Result<()> {
+    if !ctx.is_first_boot() && !oneshot_ctx.has_require(&REQUIRE_VAR.into()) {
+        return Err(validation_err(format!("this oneshot requires mounting /var however this is only supported before init has occurred (or with the '{REQUIRE_VAR}' require)"), None));
     }
 
     activate_luks_var_volume(ctx)?;
@@ -1,11 +1,19 @@
 #[allow(unused_imports)]
-use lit_os_core::guest::oneshot::config::{ActionEntry, ACTION_TYPE_BOOTSTRAP};
+use lit_os_core::guest::oneshot::config::{
+    ActionEntry, ACTION_TYPE_BOOTSTRAP, ACTION_TYPE_CONFIG_RESET, ACTION_TYPE_DIAGNOSTICS,
+    ACTION_TYPE_DISK_REKEY, ACTION_TYPE_KEY_SHARE_EXPORT, ACTION_TYPE_KEY_SHARE_IMPORT,
+    ACTION_TYPE_LUKS_ROTATE,
+};
 #[allow(unused_imports)]
-use lit_os_core::guest::oneshot::context::{OneShotContext, REQUIRE_SYNC};
+use lit_os_core::guest::oneshot::context::{
+    OneShotContext, REQUIRE_SIGNED, REQUIRE_SYNC, REQUIRE_VAR,
+};
 
 use crate::init::context::InitContext;
 #[allow(unused_imports)]
-use crate::init::stage::oneshot::action::bootstrap;
+use crate::init::stage::oneshot::action::{
+    bootstrap, config_reset, diagnostics, disk_rekey, key_share, luks_rotate,
+};
 #[allow(unused_imports)]
 use crate::init::stage::oneshot::action::{OneShotAction, ACTIONS};
 
@@ -14,6 +22,9 @@ pub(crate) async fn prepare_oneshot_actions(_ctx: &mut InitContext) {
     #[allow(unused_mut)]
     let mut actions = ACTIONS.write().await;
 
+    // NB: Not signed, the request it carries is an attestation verified against the Admin
+    //     policy by the prov bootstrap itself, and /var can only be mounted (REQUIRE_SYNC
+    //     without REQUIRE_VAR) before init has occurred, i.e. on the first boot.
     #[cfg(feature = "type-prov")]
     actions.push(
         OneShotAction::new(
@@ -24,4 +35,68 @@ pub(crate) async fn prepare_oneshot_actions(_ctx: &mut InitContext) {
         )
         .with_require(REQUIRE_SYNC.into()),
     );
+
+    // Maintenance (signed).
+    // NB: Order matters, diagnostics first (captures the state before any change) and
+    //     export before import (a guest may hand its shares on after receiving them).
+    actions.push(
+        OneShotAction::new(
+            ACTION_TYPE_DIAGNOSTICS.into(),
+            |ctx: &mut InitContext, oneshot_ctx: &OneShotContext, entry: &ActionEntry| {
+                Box::pin(diagnostics::run(ctx, oneshot_ctx, entry))
+            },
+        )
+        .with_require(REQUIRE_SIGNED.into()),
+    );
+    actions.push(
+        OneShotAction::new(
+            ACTION_TYPE_KEY_SHARE_EXPORT.into(),
+            |ctx: &mut InitContext, oneshot_ctx: &OneShotContext, entry: &ActionEntry| {
+                Box::pin(key_share::export(ctx, oneshot_ctx, entry))
+            },
+        )
+        .with_require(REQUIRE_SIGNED.into())
+        .with_require(REQUIRE_VAR.into()),
+    );
+    actions.push(
+        OneShotAction::new(
+            ACTION_TYPE_KEY_SHARE_IMPORT.into(),
+            |ctx: &mut InitContext, oneshot_ctx: &OneShotContext, entry: &ActionEntry| {
+                Box::pin(key_share::import(ctx, oneshot_ctx, entry))
+            },
+        )
+        .with_require(REQUIRE_SIGNED.into())
+        .with_require(REQUIRE_VAR.into()),
+    );
+    actions.push(
+        OneShotAction::new(
+            ACTION_TYPE_CONFIG_RESET.into(),
+            |ctx: &mut InitContext, oneshot_ctx: &OneShotContext, entry: &ActionEntry| {
+                Box::pin(config_reset::run(ctx, oneshot_ctx, entry))
+            },
+        )
+        .with_require(REQUIRE_SIGNED.into())
+        .with_require(REQUIRE_VAR.into())
+        .with_require(REQUIRE_SYNC.into()),
+    );
+    actions.push(
+        OneShotAction::new(
+            ACTION_TYPE_LUKS_ROTATE.into(),
+            |ctx: &mut InitContext, oneshot_ctx: &OneShotContext, entry: &ActionEntry| {
+                Box::pin(luks_rotate::run(ctx, oneshot_ctx, entry))
+            },
+        )
+        .with_require(REQUIRE_SIGNED.into())
+        .with_require(REQUIRE_VAR.into()),
+    );
+    // NB: Must run with the volumes closed (can't be combined with actions that mount them).
+    actions.push(
+        OneShotAction::new(
+            ACTION_TYPE_DISK_REKEY.into(),
+            |ctx: &mut InitContext, oneshot_ctx: &OneShotContext, entry: &ActionEntry| {
+                Box::pin(disk_rekey::run(ctx, oneshot_ctx, entry))
+            },
+        )
+        .with_require(REQUIRE_SIGNED.into()),
+    );
 }
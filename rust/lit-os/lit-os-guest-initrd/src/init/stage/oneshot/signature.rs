@@ -0,0 +1,227 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lit_attestation::attestation::DATA_KEY_INSTANCE_ID;
+use lit_attestation::verification::Policy;
+use lit_attestation::{Attestation, AttestationType};
+use lit_blockchain::resolver::contract::ContractResolver;
+use lit_core::utils::binary::bytes_to_hex;
+use log::info;
+
+use lit_os_core::config::LitOsGuestConfig;
+use lit_os_core::error::{config_err, conversion_err, generic_err, io_err, validation_err, Result};
+use lit_os_core::guest::oneshot::config::oneshot_config_hash;
+use lit_os_core::guest::oneshot::context::OneShotContext;
+use lit_os_core::guest::oneshot::{
+    ONESHOT_FILE_CONFIG, ONESHOT_FILE_SIGNATURE, SIGNATURE_DATA_KEY_CONFIG_HASH,
+    SIGNATURE_DATA_KEY_EXPIRES, SIGNATURE_UNRECORDED_MAX_TTL_SECS,
+};
+
+use crate::init::context::InitContext;
+
+/// A verified oneshot signature.
+pub(crate) struct OneShotSignature {
+    /// Recorded once run (see status).
+    pub(crate) nonce: Vec<u8>,
+    pub(crate) expires: u64,
+}
+
+// The signature is an AdminSigned attestation over the config file, bound to this instance
+// and valid until it expires.
+pub(crate) async fn verify_oneshot_signature(
+    ctx: &mut InitContext, oneshot_ctx: &OneShotContext,
+) -> Result<OneShotSignature> {
+    let mut config_path = oneshot_ctx.path().clone();
+    config_path.push(ONESHOT_FILE_CONFIG);
+    let mut signature_path = oneshot_ctx.path().clone();
+    signature_path.push(ONESHOT_FILE_SIGNATURE);
+
+    if !signature_path.exists() {
+        return Err(validation_err(
+            format!(
+                "this oneshot requires a signed config ('{ONESHOT_FILE_SIGNATURE}' is missing)"
+            ),
+            None,
+        ));
+    }
+
+    let config = fs::read(&config_path)
+        .map_err(|e| io_err(e, Some(format!("failed to read: {config_path:?}"))))?;
+    let signature = Attestation::try_from(signature_path.as_path())?;
+
+    let instance_id = ctx.cfg().litos_guest_instance_id().map_err(|e| config_err(e, None))?;
+    let unix_time =
+        SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| generic_err(e, None))?.as_secs();
+    let expires = check_oneshot_signature(&signature, &config[..], &instance_id, unix_time)?;
+
+    // Signed by an admin or operator on-chain.
+    let resolver = ContractResolver::try_from(ctx.cfg())?;
+    signature
+        .verify_full(ctx.cfg(), Some(&resolver), Some(Policy::AdminOrOperator))
+        .await
+        .map_err(|e| validation_err(e, Some("failed to verify oneshot signature".into())))?;
+
+    info!("Oneshot signature verified");
+
+    Ok(OneShotSignature { nonce: signature.noonce().to_vec(), expires })
+}
+
+// Checks what the signature was issued for (the signer is verified on-chain by the caller).
+// Returns when it expires.
+pub(crate) fn check_oneshot_signature(
+    signature: &Attestation, config: &[u8], instance_id: &str, unix_time: u64,
+) -> Result<u64> {
+    if !signature.typ().eq(&AttestationType::AdminSigned) {
+        return Err(validation_err(
+            format!("oneshot signature must be '{}'", AttestationType::AdminSigned),
+            None,
+        ));
+    }
+
+    // Signed this config.
+    let config_hash = oneshot_config_hash(config);
+    match signature.get_data(SIGNATURE_DATA_KEY_CONFIG_HASH) {
+        Some(hash) if hash.to_vec().eq(&config_hash) => {}
+        _ => {
+            return Err(validation_err(
+                format!(
+                    "oneshot signature does not match the config (hash: {})",
+                    bytes_to_hex(&config_hash)
+                ),
+                None,
+            ))
+        }
+    }
+
+    // Signed for this instance.
+    match signature.get_data_string(DATA_KEY_INSTANCE_ID) {
+        Some(signed_id) if signed_id.eq(instance_id) => {}
+        signed_id => {
+            return Err(validation_err(
+                format!("oneshot signature is for another instance ({signed_id:?})"),
+                None,
+            ))
+        }
+    }
+
+    // Not expired.
+    let expires = signature.get_data(SIGNATURE_DATA_KEY_EXPIRES).ok_or_else(|| {
+        validation_err(
+            format!("oneshot signature missing data: {SIGNATURE_DATA_KEY_EXPIRES}"),
+            None,
+        )
+    })?;
+    let expires = u64::from_le_bytes(expires.to_vec().try_into().map_err(|_e| {
+        conversion_err(format!("unable to convert '{SIGNATURE_DATA_KEY_EXPIRES}' to u64"), None)
+    })?);
+    if unix_time > expires {
+        return Err(validation_err(format!("oneshot signature expired at {expires}"), None));
+    }
+
+    Ok(expires)
+}
+
+// Without /var the nonce can't be recorded, so the signature may be replayed until it expires,
+// which must therefore be soon.
+pub(crate) fn check_unrecorded_signature(expires: u64, unix_time: u64) -> Result<()> {
+    if expires > unix_time.saturating_add(SIGNATURE_UNRECORDED_MAX_TTL_SECS) {
+        return Err(validation_err(
+            format!(
+                "oneshot signature expires at {expires}, it must expire within {} seconds as this oneshot runs without /var",
+                SIGNATURE_UNRECORDED_MAX_TTL_SECS
+            ),
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lit_attestation::attestation::DATA_KEY_INSTANCE_ID;
+    use lit_attestation::{Attestation, AttestationType};
+    use lit_os_core::guest::oneshot::config::oneshot_config_hash;
+    use lit_os_core::guest::oneshot::{SIGNATURE_DATA_KEY_CONFIG_HASH, SIGNATURE_DATA_KEY_EXPIRES};
+
+    use super::{check_oneshot_signature, check_unrecorded_signature};
+    use lit_os_core::guest::oneshot::SIGNATURE_UNRECORDED_MAX_TTL_SECS;
+
+    const CONFIG: &[u8] = b"[actions.diagnostics]\n";
+    const INSTANCE_ID: &str = "a1b2c3d4";
+    const NOW: u64 = 1_700_000_000;
+
+    async fn signature(
+        typ: AttestationType, config: &[u8], instance_id: Option<&str>, expires: Option<u64>,
+    ) -> Attestation {
+        let mut signature = Attestation::new(typ, NOW.to_le_bytes().to_vec()).await.unwrap();
+        signature.insert_data(SIGNATURE_DATA_KEY_CONFIG_HASH, oneshot_config_hash(config));
+        if let Some(instance_id) = instance_id {
+            signature.insert_data(DATA_KEY_INSTANCE_ID, instance_id.as_bytes().to_vec());
+        }
+        if let Some(expires) = expires {
+            signature.insert_data(SIGNATURE_DATA_KEY_EXPIRES, expires.to_le_bytes().to_vec());
+        }
+        signature
+    }
+
+    #[tokio::test]
+    async fn check_oneshot_signature_valid_test() {
+        let signature =
+            signature(AttestationType::AdminSigned, CONFIG, Some(INSTANCE_ID), Some(NOW + 60))
+                .await;
+
+        assert!(check_oneshot_signature(&signature, CONFIG, INSTANCE_ID, NOW).is_ok());
+        assert!(check_oneshot_signature(&signature, CONFIG, INSTANCE_ID, NOW + 60).is_ok());
+    }
+
+    #[tokio::test]
+    async fn check_oneshot_signature_other_config_test() {
+        let signature =
+            signature(AttestationType::AdminSigned, CONFIG, Some(INSTANCE_ID), Some(NOW + 60))
+                .await;
+
+        assert!(check_oneshot_signature(&signature, b"[actions.disk_rekey]\n", INSTANCE_ID, NOW)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn check_oneshot_signature_other_instance_test() {
+        let signature =
+            signature(AttestationType::AdminSigned, CONFIG, Some(INSTANCE_ID), Some(NOW + 60))
+                .await;
+        assert!(check_oneshot_signature(&signature, CONFIG, "e5f6a7b8", NOW).is_err());
+
+        let signature = signature(AttestationType::AdminSigned, CONFIG, None, Some(NOW + 60)).await;
+        assert!(check_oneshot_signature(&signature, CONFIG, INSTANCE_ID, NOW).is_err());
+    }
+
+    #[tokio::test]
+    async fn check_oneshot_signature_expired_test() {
+        let signature =
+            signature(AttestationType::AdminSigned, CONFIG, Some(INSTANCE_ID), Some(NOW - 1)).await;
+        assert!(check_oneshot_signature(&signature, CONFIG, INSTANCE_ID, NOW).is_err());
+
+        let signature =
+            signature(AttestationType::AdminSigned, CONFIG, Some(INSTANCE_ID), None).await;
+        assert!(check_oneshot_signature(&signature, CONFIG, INSTANCE_ID, NOW).is_err());
+    }
+
+    #[test]
+    fn check_unrecorded_signature_test() {
+        assert!(check_unrecorded_signature(NOW + 60, NOW).is_ok());
+        assert!(check_unrecorded_signature(NOW + SIGNATURE_UNRECORDED_MAX_TTL_SECS, NOW).is_ok());
+        assert!(
+            check_unrecorded_signature(NOW + SIGNATURE_UNRECORDED_MAX_TTL_SECS + 1, NOW).is_err()
+        );
+        assert!(check_unrecorded_signature(NOW + 24 * 60 * 60, NOW).is_err());
+    }
+
+    #[tokio::test]
+    async fn check_oneshot_signature_wrong_type_test() {
+        let signature =
+            signature(AttestationType::AmdSevSnp, CONFIG, Some(INSTANCE_ID), Some(NOW + 60)).await;
+
+        assert!(check_oneshot_signature(&signature, CONFIG, INSTANCE_ID, NOW).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lit_core::utils::binary::bytes_to_hex;
+use log::info;
+
+use lit_os_core::error::{generic_err, io_err, validation_err, Result};
+use lit_os_core::guest::oneshot::config::oneshot_config_hash;
+use lit_os_core::guest::oneshot::context::OneShotContext;
+use lit_os_core::guest::oneshot::status::{ActionState, OneShotStatusReport};
+use lit_os_core::guest::oneshot::{
+    ONESHOT_DIR_MARKERS, ONESHOT_FILE_CONFIG, ONESHOT_FILE_CONSUMED_NONCES,
+    ONESHOT_FILE_STATUS_REPORT, ONESHOT_VAR_DIR,
+};
+
+use crate::init::stage::oneshot::signature::{check_unrecorded_signature, OneShotSignature};
+
+// Tracks the actions of a run: the report is rewritten (on the oneshot volume) after each
+// action, so it survives a failure part way through.
+//
+// Once /var is mounted, a marker (holding the config hash) is written there when an action is
+// done, so running the same config again skips it, and the nonce of the signature is recorded,
+// so the same signed config can't be run again. Both live in the guest's encrypted /var as the
+// oneshot volume is writable by the host. Oneshots which run with the volumes closed (i.e.
+// diagnostics, disk_rekey) keep neither, so their signature (bound to the instance) must expire
+// within minutes, which bounds how long they may be replayed.
+pub(crate) struct OneShotStatus {
+    path: PathBuf,
+    var_path: Option<PathBuf>,
+    report: OneShotStatusReport,
+}
+
+impl OneShotStatus {
+    pub(crate) fn load(oneshot_ctx: &OneShotContext) -> Result<Self> {
+        let mut config_path = oneshot_ctx.path().clone();
+        config_path.push(ONESHOT_FILE_CONFIG);
+
+        let config = fs::read(&config_path)
+            .map_err(|e| io_err(e, Some(format!("failed to read: {config_path:?}"))))?;
+
+        Ok(Self {
+            path: oneshot_ctx.path().clone(),
+            var_path: None,
+            report: OneShotStatusReport::new(bytes_to_hex(oneshot_config_hash(&config[..]))),
+        })
+    }
+
+    /// Keep the markers and consumed nonces under the (mounted) /var.
+    pub(crate) fn use_var(&mut self, var_mnt: &Path) {
+        let mut var_path = var_mnt.to_path_buf();
+        var_path.push(ONESHOT_VAR_DIR);
+
+        self.var_path = Some(var_path);
+    }
+
+    /// Record the nonce of the signature, failing if it has already been run (or, without /var,
+    /// if the signature doesn't expire soon).
+    pub(crate) fn consume_signature(&mut self, signature: &OneShotSignature) -> Result<()> {
+        let consumed_path = match self.var_path.as_ref() {
+            Some(var_path) => var_path.join(ONESHOT_FILE_CONSUMED_NONCES),
+            None => {
+                let unix_time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|e| generic_err(e, None))?
+                    .as_secs();
+                return check_unrecorded_signature(signature.expires, unix_time);
+            }
+        };
+        let nonce = bytes_to_hex(&signature.nonce);
+
+        if let Ok(consumed) = fs::read_to_string(&consumed_path) {
+            if consumed.lines().any(|line| line.trim().eq(&nonce)) {
+                return Err(validation_err(
+                    format!("oneshot signature has already been run (nonce: {nonce})"),
+                    None,
+                ));
+            }
+        }
+
+        if let Some(parent) = consumed_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| io_err(e, Some(format!("failed to make dir: {parent:?}"))))?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&consumed_path)
+            .map_err(|e| io_err(e, Some(format!("failed to open: {consumed_path:?}"))))?;
+        writeln!(file, "{nonce}")
+            .map_err(|e| io_err(e, Some(format!("failed to write: {consumed_path:?}"))))?;
+
+        nix::unistd::sync();
+
+        Ok(())
+    }
+
+    pub(crate) fn is_done(&self, action: &str) -> bool {
+        match self.marker_path(action) {
+            Some(marker_path) => match fs::read_to_string(marker_path) {
+                Ok(hash) => hash.trim().eq(self.report.config_hash()),
+                Err(_) => false,
+            },
+            None => false,
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self, action: &str, state: ActionState, message: Option<String>,
+    ) -> Result<()> {
+        info!("Oneshot action '{}': {}", action, state);
+
+        if state == ActionState::Done {
+            if let Some(marker_path) = self.marker_path(action) {
+                if let Some(parent) = marker_path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| io_err(e, Some(format!("failed to make dir: {parent:?}"))))?;
+                }
+
+                fs::write(&marker_path, self.report.config_hash())
+                    .map_err(|e| io_err(e, Some(format!("failed to write: {marker_path:?}"))))?;
+            }
+        }
+
+        self.report.set_action(action, state, message)?;
+
+        let mut report_path = self.path.clone();
+        report_path.push(ONESHOT_FILE_STATUS_REPORT);
+        self.report.write_file(report_path.as_path())?;
+
+        // We may halt or power off at any point after this.
+        nix::unistd::sync();
+
+        Ok(())
+    }
+
+    fn marker_path(&self, action: &str) -> Option<PathBuf> {
+        self.var_path.as_ref().map(|var_path| {
+            let mut path = var_path.clone();
+            path.push(ONESHOT_DIR_MARKERS);
+            path.push(action);
+            path
+        })
+    }
+}
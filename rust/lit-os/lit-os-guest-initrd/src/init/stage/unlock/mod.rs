@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{fs, thread};
 
-use log::{as_error, error, info};
+use log::{as_error, error, info, warn};
 
 use lit_core::error::Unexpected;
 use lit_core::utils::binary::bytes_to_hex;
@@ -14,7 +14,8 @@ use lit_os_core::guest::types::GuestType;
 use lit_os_core::utils::mount::mount;
 
 use crate::init::context::{
-    InitContext, CTX_KEY_ROOT_PASSPHRASE_BOOT, CTX_KEY_VAR_PASSPHRASE_BOOT,
+    InitContext, CTX_KEY_ROOT_PASSPHRASE_BOOT, CTX_KEY_ROOT_PASSPHRASE_BOOT_PREVIOUS,
+    CTX_KEY_VAR_PASSPHRASE_BOOT, CTX_KEY_VAR_PASSPHRASE_BOOT_PREVIOUS,
 };
 use crate::init::stage::Outcome;
 #[cfg(not(target_os = "macos"))]
@@ -102,13 +103,6 @@ fn activate_luks_volumes(ctx: &mut InitContext) -> Result<()> {
 pub(crate) fn activate_luks_root_volume(ctx: &mut InitContext) -> Result<()> {
     let (root_dev, root_dev_label) = root_volume_dev_and_label(ctx)?;
 
-    let root_passphrase = ctx
-        .get_bin(CTX_KEY_ROOT_PASSPHRASE_BOOT)
-        .ok_or_else(|| {
-            generic_err(format!("missing init context key: {CTX_KEY_ROOT_PASSPHRASE_BOOT}"), None)
-        })?
-        .clone();
-
     let read_only =
         !ctx.cfg().is_dev() || bool_option_to_bool(ctx.cmdline_env().build_opt_ro.as_ref());
 
@@ -117,7 +111,8 @@ pub(crate) fn activate_luks_root_volume(ctx: &mut InitContext) -> Result<()> {
         root_dev.as_path(),
         &root_dev_label,
         LUKS_NAME_ROOT,
-        &root_passphrase[..],
+        CTX_KEY_ROOT_PASSPHRASE_BOOT,
+        CTX_KEY_ROOT_PASSPHRASE_BOOT_PREVIOUS,
         read_only,
     )
 }
@@ -125,32 +120,45 @@ pub(crate) fn activate_luks_root_volume(ctx: &mut InitContext) -> Result<()> {
 pub(crate) fn activate_luks_var_volume(ctx: &mut InitContext) -> Result<()> {
     let (var_dev, var_dev_label) = var_volume_dev_and_label(ctx)?;
 
-    let var_passphrase = ctx
-        .get_bin(CTX_KEY_VAR_PASSPHRASE_BOOT)
-        .ok_or_else(|| {
-            generic_err(format!("missing init context key: {CTX_KEY_VAR_PASSPHRASE_BOOT}"), None)
-        })?
-        .clone();
-
     activate_luks_volume(
         ctx,
         var_dev.as_path(),
         &var_dev_label,
         LUKS_NAME_VAR,
-        &var_passphrase[..],
+        CTX_KEY_VAR_PASSPHRASE_BOOT,
+        CTX_KEY_VAR_PASSPHRASE_BOOT_PREVIOUS,
         false,
     )
 }
 
 #[allow(unused_variables)]
 fn activate_luks_volume(
-    ctx: &mut InitContext, dev_path: &Path, label: &String, name: &str, boot_passphrase: &[u8],
-    read_only: bool,
+    ctx: &mut InitContext, dev_path: &Path, label: &String, name: &str, passphrase_key: &str,
+    previous_passphrase_key: &str, read_only: bool,
 ) -> Result<()> {
+    let boot_passphrase = ctx
+        .get_bin(passphrase_key)
+        .ok_or_else(|| generic_err(format!("missing init context key: {passphrase_key}"), None))?
+        .clone();
+
     info!("Activating volume '{}' (read-only: {})", label, read_only);
 
     #[cfg(not(target_os = "macos"))]
-    cryptsetup_activate_dev(dev_path, label, name, boot_passphrase, read_only)?;
+    if let Err(e) = cryptsetup_activate_dev(dev_path, label, name, &boot_passphrase[..], read_only)
+    {
+        // A rotation may not have completed, try the previous key version.
+        let previous_passphrase = match ctx.get_bin(previous_passphrase_key).cloned() {
+            Some(passphrase) => passphrase,
+            None => return Err(e),
+        };
+
+        warn!("Activating volume '{}' with the previous key version", label);
+
+        cryptsetup_activate_dev(dev_path, label, name, &previous_passphrase[..], read_only)?;
+
+        // Use the one that works from here on (i.e. re-activation, re-key and rotation).
+        ctx.insert(passphrase_key.to_string(), Box::new(previous_passphrase));
+    }
 
     ctx.set_activated(true);
 
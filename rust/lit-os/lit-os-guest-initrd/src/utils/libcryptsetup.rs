@@ -1,8 +1,13 @@
 use libcryptsetup_rs::consts::flags::{CryptActivate, CryptDeactivate};
-use libcryptsetup_rs::{CryptDevice, CryptInit};
-use lit_os_core::error::{generic_err, Result};
+use libcryptsetup_rs::{CryptDevice, CryptInit, TokenInput};
+use lit_os_core::error::{conversion_err, generic_err, Result};
+use serde_json::json;
 use std::path::Path;
 
+pub const LUKS2_KEYSLOTS_MAX: u32 = 32;
+
+const TOKEN_TYPE_KEY_VERSION: &str = "litos-key-version";
+
 pub fn cryptsetup_init_dev(path: &Path, label: &str) -> Result<CryptDevice> {
     log::info!("cryptsetup_init_dev:{}:{}", std::file!(), std::line!());
     let mut dev = CryptInit::init(path)
@@ -80,6 +85,34 @@ pub fn cryptsetup_add_keyslot(
     Ok(slot)
 }
 
+pub fn cryptsetup_add_keyslot_at(
+    dev: &mut CryptDevice, label: &str, slot: u32, passphrase: &[u8], new_passphrase: &[u8],
+) -> Result<u32> {
+    log::info!("cryptsetup_add_keyslot_at:{}:{}", std::file!(), std::line!());
+    dev.keyslot_handle().add_by_passphrase(Some(slot), passphrase, new_passphrase).map_err(|e| {
+        generic_err(e, Some(format!("failed to add keyslot '{slot}' on '{label}' luks device")))
+    })
+}
+
+// Checks the passphrase without activating anything, returning the keyslot it opens.
+pub fn cryptsetup_find_keyslot(
+    dev: &mut CryptDevice, label: &str, passphrase: &[u8],
+) -> Result<u32> {
+    log::info!("cryptsetup_find_keyslot:{}:{}", std::file!(), std::line!());
+    dev.activate_handle()
+        .activate_by_passphrase(None, None, passphrase, CryptActivate::empty())
+        .map_err(|e| {
+            generic_err(
+                format!(
+                    "passphrase does not open any keyslot on '{}' luks device: status: {:?}",
+                    label,
+                    e.to_string()
+                ),
+                None,
+            )
+        })
+}
+
 // RAD: Why is this dead code
 pub fn cryptsetup_destroy_slot(dev: &mut CryptDevice, label: &str, slot: u32) -> Result<()> {
     log::info!("cryptsetup_destroy_slot:{}:{}", std::file!(), std::line!());
@@ -87,3 +120,101 @@ pub fn cryptsetup_destroy_slot(dev: &mut CryptDevice, label: &str, slot: u32) ->
         generic_err(e, Some(format!("failed to destroy keyslot '{slot}' on '{label}' luks device")))
     })
 }
+
+pub fn cryptsetup_free_keyslot(dev: &mut CryptDevice, label: &str, from: u32) -> Result<u32> {
+    log::info!("cryptsetup_free_keyslot:{}:{}", std::file!(), std::line!());
+    for slot in from..LUKS2_KEYSLOTS_MAX {
+        if !cryptsetup_has_slot_active(dev, label, slot)? {
+            return Ok(slot);
+        }
+    }
+
+    Err(generic_err(format!("no free keyslot (from '{from}') on '{label}' luks device"), None))
+}
+
+// The key version lives in a LUKS2 token so it can be read before anything is decrypted.
+pub fn cryptsetup_key_version(dev: &mut CryptDevice, label: &str) -> Result<Option<u32>> {
+    log::info!("cryptsetup_key_version:{}:{}", std::file!(), std::line!());
+    match find_key_version_token(dev) {
+        Some((_token, value)) => {
+            let version =
+                value["version"].as_str().and_then(|v| v.parse::<u32>().ok()).ok_or_else(|| {
+                    conversion_err(format!("invalid key version token on '{label}': {value}"), None)
+                })?;
+
+            Ok(Some(version))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn cryptsetup_set_key_version(dev: &mut CryptDevice, label: &str, version: u32) -> Result<()> {
+    log::info!("cryptsetup_set_key_version:{}:{}", std::file!(), std::line!());
+    let value = json!({
+        "type": TOKEN_TYPE_KEY_VERSION,
+        "keyslots": [],
+        "version": version.to_string(),
+    });
+
+    let input = match find_key_version_token(dev) {
+        Some((token, _)) => TokenInput::ReplaceToken(token, &value),
+        None => TokenInput::AddToken(&value),
+    };
+
+    dev.token_handle().json_set(input).map_err(|e| {
+        generic_err(e, Some(format!("failed to set key version token on '{label}' luks device")))
+    })?;
+
+    Ok(())
+}
+
+fn find_key_version_token(dev: &mut CryptDevice) -> Option<(u32, serde_json::Value)> {
+    for token in 0..LUKS2_KEYSLOTS_MAX {
+        if let Ok(value) = dev.token_handle().json_get(token) {
+            if value["type"].as_str() == Some(TOKEN_TYPE_KEY_VERSION) {
+                return Some((token, value));
+            }
+        }
+    }
+
+    None
+}
+
+// Rotate a passphrase:
+// 1. Add the new passphrase to a free keyslot (never slot 0, we rely on it being empty to
+//    determine if init has been done).
+// 2. Verify the new passphrase opens that keyslot.
+// 3. Record the new key version.
+// 4. Destroy the old keyslot.
+pub fn cryptsetup_rotate_passphrase(
+    dev_path: &Path, label: &str, passphrase: &[u8], new_passphrase: &[u8], new_version: u32,
+) -> Result<(u32, u32)> {
+    log::info!("cryptsetup_rotate_passphrase:{}:{}", std::file!(), std::line!());
+    let mut dev = cryptsetup_init_dev(dev_path, label)?;
+
+    let old_slot = cryptsetup_find_keyslot(&mut dev, label, passphrase)?;
+    let new_slot = cryptsetup_free_keyslot(&mut dev, label, 1)?;
+
+    cryptsetup_add_keyslot_at(&mut dev, label, new_slot, passphrase, new_passphrase)?;
+
+    // Reload device
+    let mut dev = cryptsetup_init_dev(dev_path, label)?;
+    match cryptsetup_find_keyslot(&mut dev, label, new_passphrase) {
+        Ok(slot) if slot == new_slot => {}
+        res => {
+            cryptsetup_destroy_slot(&mut dev, label, new_slot)?;
+
+            return Err(generic_err(
+                format!(
+                    "passphrase rotation failed for '{label}', new keyslot '{new_slot}' did not verify ({res:?})"
+                ),
+                None,
+            ));
+        }
+    }
+
+    cryptsetup_set_key_version(&mut dev, label, new_version)?;
+    cryptsetup_destroy_slot(&mut dev, label, old_slot)?;
+
+    Ok((old_slot, new_slot))
+}
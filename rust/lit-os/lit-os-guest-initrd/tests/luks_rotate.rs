@@ -0,0 +1,127 @@
+// Loopback tests for the LUKS passphrase rotation, these require root, losetup and cryptsetup:
+//   sudo -E cargo test -p lit-os-guest-initrd --test luks_rotate -- --ignored
+#![cfg(not(target_os = "macos"))]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use temp_file::TempFile;
+
+use lit_os_guest_initrd::utils::libcryptsetup::{
+    cryptsetup_find_keyslot, cryptsetup_has_slot_active, cryptsetup_init_dev,
+    cryptsetup_key_version, cryptsetup_rotate_passphrase,
+};
+
+const LABEL: &str = "test:loop";
+const PASSPHRASE_V0: &[u8] = b"boot-passphrase-v0";
+const PASSPHRASE_V1: &[u8] = b"boot-passphrase-v1";
+const PASSPHRASE_V2: &[u8] = b"boot-passphrase-v2";
+
+struct LoopDevice {
+    _img: TempFile,
+    path: PathBuf,
+}
+
+impl LoopDevice {
+    // A LUKS2 volume laid out as init leaves it (slot 0 empty, boot passphrase in slot 2).
+    fn new() -> Self {
+        let img = temp_file::empty();
+        run("truncate", &["-s", "32M", img.path().to_str().unwrap()]);
+
+        let out = Command::new("losetup")
+            .args(["--find", "--show", img.path().to_str().unwrap()])
+            .output()
+            .expect("failed to run losetup");
+        assert!(out.status.success(), "losetup failed: {out:?}");
+        let path = PathBuf::from(String::from_utf8(out.stdout).unwrap().trim());
+
+        let key_file = temp_file::with_contents(PASSPHRASE_V0);
+        run(
+            "cryptsetup",
+            &[
+                "luksFormat",
+                "-q",
+                "--type",
+                "luks2",
+                "--pbkdf",
+                "pbkdf2",
+                "--pbkdf-force-iterations",
+                "1000",
+                "--key-slot",
+                "2",
+                "--key-file",
+                key_file.path().to_str().unwrap(),
+                path.to_str().unwrap(),
+            ],
+        );
+
+        Self { _img: img, path }
+    }
+
+    fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        let _ = Command::new("losetup").args(["-d", self.path.to_str().unwrap()]).status();
+    }
+}
+
+fn run(cmd: &str, args: &[&str]) {
+    let status = Command::new(cmd).args(args).status().expect("failed to run command");
+    assert!(status.success(), "{cmd} {args:?} failed: {status:?}");
+}
+
+#[test]
+#[ignore]
+fn rotate_passphrase_test() {
+    let loop_dev = LoopDevice::new();
+
+    let mut dev = cryptsetup_init_dev(loop_dev.path(), LABEL).unwrap();
+    assert_eq!(cryptsetup_key_version(&mut dev, LABEL).unwrap(), None);
+
+    // v0 -> v1
+    let (old_slot, new_slot) =
+        cryptsetup_rotate_passphrase(loop_dev.path(), LABEL, PASSPHRASE_V0, PASSPHRASE_V1, 1)
+            .expect("failed to rotate to v1");
+    assert_eq!(old_slot, 2);
+    assert_ne!(new_slot, 0);
+
+    let mut dev = cryptsetup_init_dev(loop_dev.path(), LABEL).unwrap();
+    assert_eq!(cryptsetup_key_version(&mut dev, LABEL).unwrap(), Some(1));
+    assert!(!cryptsetup_has_slot_active(&mut dev, LABEL, 0).unwrap());
+    assert!(!cryptsetup_has_slot_active(&mut dev, LABEL, old_slot).unwrap());
+    assert_eq!(cryptsetup_find_keyslot(&mut dev, LABEL, PASSPHRASE_V1).unwrap(), new_slot);
+    assert!(cryptsetup_find_keyslot(&mut dev, LABEL, PASSPHRASE_V0).is_err());
+
+    // v1 -> v2 (the token is replaced, not duplicated)
+    let (old_slot, new_slot) =
+        cryptsetup_rotate_passphrase(loop_dev.path(), LABEL, PASSPHRASE_V1, PASSPHRASE_V2, 2)
+            .expect("failed to rotate to v2");
+    assert_ne!(new_slot, 0);
+
+    let mut dev = cryptsetup_init_dev(loop_dev.path(), LABEL).unwrap();
+    assert_eq!(cryptsetup_key_version(&mut dev, LABEL).unwrap(), Some(2));
+    assert!(!cryptsetup_has_slot_active(&mut dev, LABEL, 0).unwrap());
+    assert!(!cryptsetup_has_slot_active(&mut dev, LABEL, old_slot).unwrap());
+    assert_eq!(cryptsetup_find_keyslot(&mut dev, LABEL, PASSPHRASE_V2).unwrap(), new_slot);
+    assert!(cryptsetup_find_keyslot(&mut dev, LABEL, PASSPHRASE_V1).is_err());
+}
+
+#[test]
+#[ignore]
+fn rotate_passphrase_wrong_passphrase_test() {
+    let loop_dev = LoopDevice::new();
+
+    assert!(cryptsetup_rotate_passphrase(loop_dev.path(), LABEL, PASSPHRASE_V1, PASSPHRASE_V2, 1)
+        .is_err());
+
+    // Nothing changed.
+    let mut dev = cryptsetup_init_dev(loop_dev.path(), LABEL).unwrap();
+    assert_eq!(cryptsetup_key_version(&mut dev, LABEL).unwrap(), None);
+    assert_eq!(cryptsetup_find_keyslot(&mut dev, LABEL, PASSPHRASE_V0).unwrap(), 2);
+    assert!(!cryptsetup_has_slot_active(&mut dev, LABEL, 0).unwrap());
+    assert!(!cryptsetup_has_slot_active(&mut dev, LABEL, 1).unwrap());
+}
@@ -19,14 +19,25 @@ use lit_os_prov_core::release::common::load::load_release;
 use lit_os_prov_core::release::create::types::{CreateReleaseRequest, CreateReleaseResponse};
 use lit_os_prov_core::release::init::types::{InitReleaseRequest, InitReleaseResponse};
 use lit_os_prov_core::release::issue::types::{IssueReleaseRequest, IssueReleaseResponse};
+use lit_os_prov_core::release::log::types::{
+    ReleaseLogConsistencyResponse, ReleaseLogEntriesResponse, ReleaseLogHeadResponse,
+    ReleaseLogProofResponse,
+};
 use lit_os_prov_core::release::query::types::{QueryReleaseRequest, QueryReleaseResponse};
 
 use crate::error::attestation_err_code;
 use crate::release::create::create_release;
 use crate::release::init::init_release;
 use crate::release::issue::issue_release;
+use crate::release::log::{
+    release_log_consistency_proof, release_log_entries, release_log_head,
+    release_log_inclusion_proof,
+};
 use crate::release::query::query_releases;
 
+// Entries returned per request (at most).
+const RELEASE_LOG_ENTRIES_MAX: u64 = 1000;
+
 #[post("/", format = "json", data = "<request>")]
 pub(crate) async fn ep_create(
     cfg: &State<ReloadableLitConfig>, tracing: Tracing, resolver: &State<Arc<ContractResolver>>,
@@ -166,6 +177,76 @@ pub(crate) async fn ep_query(
     .await
 }
 
+#[get("/log/head")]
+pub(crate) async fn ep_log_head(
+    cfg: &State<ReloadableLitConfig>, tracing: Tracing,
+) -> status::Custom<Value> {
+    trace!("ep_log_head");
+    let cfg = cfg.load_full();
+    with_context(tracing, async move {
+        match release_log_head(&cfg) {
+            Ok(head) => status::Custom(Status::Ok, json!(ReleaseLogHeadResponse::new(true, head))),
+            Err(e) => e.handle(),
+        }
+    })
+    .await
+}
+
+#[get("/log/entries/<start>/<count>")]
+pub(crate) async fn ep_log_entries(
+    cfg: &State<ReloadableLitConfig>, tracing: Tracing, start: u64, count: u64,
+) -> status::Custom<Value> {
+    trace!("ep_log_entries");
+    let cfg = cfg.load_full();
+    with_context(tracing, async move {
+        match release_log_entries(&cfg, start, count.min(RELEASE_LOG_ENTRIES_MAX)) {
+            Ok(entries) => status::Custom(
+                Status::Ok,
+                json!(ReleaseLogEntriesResponse::new(true, start, entries)),
+            ),
+            Err(e) => e.handle(),
+        }
+    })
+    .await
+}
+
+#[get("/log/proof/<release_id>")]
+pub(crate) async fn ep_log_proof(
+    cfg: &State<ReloadableLitConfig>, tracing: Tracing, release_id: &str,
+) -> status::Custom<Value> {
+    trace!("ep_log_proof");
+    let cfg = cfg.load_full();
+    with_context(tracing, async move {
+        match release_log_inclusion_proof(&cfg, release_id) {
+            Ok(proof) => {
+                status::Custom(Status::Ok, json!(ReleaseLogProofResponse::new(true, proof)))
+            }
+            Err(e) => e.handle(),
+        }
+    })
+    .await
+}
+
+#[get("/log/consistency/<old_size>/<new_size>")]
+pub(crate) async fn ep_log_consistency(
+    cfg: &State<ReloadableLitConfig>, tracing: Tracing, old_size: u64, new_size: u64,
+) -> status::Custom<Value> {
+    trace!("ep_log_consistency");
+    let cfg = cfg.load_full();
+    with_context(tracing, async move {
+        match release_log_consistency_proof(&cfg, old_size, new_size) {
+            Ok(proof) => {
+                status::Custom(Status::Ok, json!(ReleaseLogConsistencyResponse::new(true, proof)))
+            }
+            Err(e) => e.handle(),
+        }
+    })
+    .await
+}
+
 pub(crate) fn routes() -> Vec<Route> {
-    routes![ep_create, ep_issue, ep_init, ep_query]
+    routes![
+        ep_create, ep_issue, ep_init, ep_query, ep_log_head, ep_log_entries, ep_log_proof,
+        ep_log_consistency
+    ]
 }
@@ -0,0 +1,54 @@
+use lit_blockchain::config::LitBlockchainConfig;
+use lit_core::config::LitConfig;
+use lit_os_prov_core::config::{LitOsProvConfig, CFG_KEY_LITOS_PROV_RELEASE_LOG_PRIVATE_KEY};
+use lit_os_prov_core::error::{config_err, Result};
+use lit_os_prov_core::release::log::types::{
+    ReleaseConsistencyProof, ReleaseInclusionProof, ReleaseLogEntry, ReleaseLogHead,
+};
+use lit_os_prov_core::release::log::ReleaseLog;
+
+pub(crate) fn release_log_head(cfg: &LitConfig) -> Result<ReleaseLogHead> {
+    let mut head = ReleaseLog::from_cfg(cfg).head()?;
+    head.sign(&release_log_private_key(cfg)?)?;
+
+    Ok(head)
+}
+
+pub(crate) fn release_log_entries(
+    cfg: &LitConfig, start: u64, count: u64,
+) -> Result<Vec<ReleaseLogEntry>> {
+    ReleaseLog::from_cfg(cfg).entries_range(start, count)
+}
+
+pub(crate) fn release_log_inclusion_proof(
+    cfg: &LitConfig, release_id: &str,
+) -> Result<ReleaseInclusionProof> {
+    let mut proof = ReleaseLog::from_cfg(cfg).inclusion_proof(release_id)?;
+    proof.head.sign(&release_log_private_key(cfg)?)?;
+
+    Ok(proof)
+}
+
+pub(crate) fn release_log_consistency_proof(
+    cfg: &LitConfig, old_size: u64, new_size: u64,
+) -> Result<ReleaseConsistencyProof> {
+    ReleaseLog::from_cfg(cfg).consistency_proof(old_size, new_size)
+}
+
+// The log has its own key, so the heads vouch for more than the wallet issuing the releases.
+fn release_log_private_key(cfg: &LitConfig) -> Result<String> {
+    let private_key = cfg.litos_prov_release_log_private_key()?;
+    if let Ok(wallet_key) = cfg.blockchain_wallet_private_key(None) {
+        if private_key
+            .trim_start_matches("0x")
+            .eq_ignore_ascii_case(wallet_key.trim_start_matches("0x"))
+        {
+            return Err(config_err(
+                format!("{CFG_KEY_LITOS_PROV_RELEASE_LOG_PRIVATE_KEY} must not be the wallet key"),
+                None,
+            ));
+        }
+    }
+
+    Ok(private_key)
+}
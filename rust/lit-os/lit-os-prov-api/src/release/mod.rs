@@ -1,4 +1,5 @@
 pub(crate) mod create;
 pub(crate) mod init;
 pub(crate) mod issue;
+pub(crate) mod log;
 pub(crate) mod query;
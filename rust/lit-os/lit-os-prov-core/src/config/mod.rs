@@ -8,11 +8,15 @@ use crate::release::common::keys::AUTHOR_KEY_FILE_NAME;
 
 pub const PROV_SHARED_PATH: &str = "/var/lit/os/prov/shared";
 
+pub const CFG_KEY_LITOS_PROV_RELEASE_LOG_PRIVATE_KEY: &str = "litos.prov.release_log.private_key";
+
 pub trait LitOsProvConfig {
     fn apply_defaults(builder: LitConfigBuilder) -> Result<LitConfigBuilder>;
     fn litos_prov_shared_release_path(&self) -> PathBuf;
     fn litos_prov_shared_keys_path(&self) -> PathBuf;
+    fn litos_prov_shared_log_path(&self) -> PathBuf;
     fn litos_prov_shared_author_key_path(&self) -> PathBuf;
+    fn litos_prov_release_log_private_key(&self) -> Result<String>;
 }
 
 impl LitOsProvConfig for LitConfig {
@@ -31,9 +35,19 @@ impl LitOsProvConfig for LitConfig {
         PathBuf::from(format!("{}/{}", PROV_SHARED_PATH, "keys"))
     }
 
+    fn litos_prov_shared_log_path(&self) -> PathBuf {
+        PathBuf::from(format!("{}/{}", PROV_SHARED_PATH, "log"))
+    }
+
     fn litos_prov_shared_author_key_path(&self) -> PathBuf {
         let mut path = self.litos_prov_shared_keys_path();
         path.push(AUTHOR_KEY_FILE_NAME);
         path
     }
+
+    /// The private key (hex) the release log heads are signed with. It must not be the wallet
+    /// key releases are issued with, or a stolen wallet key could also vouch for the log.
+    fn litos_prov_release_log_private_key(&self) -> Result<String> {
+        self.get_checked_string(CFG_KEY_LITOS_PROV_RELEASE_LOG_PRIVATE_KEY)
+    }
 }
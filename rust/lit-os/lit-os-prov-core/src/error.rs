@@ -38,6 +38,12 @@ pub enum EC {
     /// The attempt to issue an IdBlock met wth invalidity
     #[code(kind = Validation, http_status = 400)]
     ProvReleaseIdBlockIssueInvalid,
+    /// Failed to read or append to the release log
+    #[code(kind = Io, http_status = 500)]
+    ProvReleaseLogFailed,
+    /// The release log request is invalid (i.e. beyond the tree size)
+    #[code(kind = Validation, http_status = 400)]
+    ProvReleaseLogInvalid,
 }
 
 generate_pkg_constructors!(PKG_NAME);
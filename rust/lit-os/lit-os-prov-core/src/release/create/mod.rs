@@ -29,6 +29,7 @@ use crate::release::common::manifest::{
     load_release_manifest, ReleaseManifest, RELEASE_FILE_INITIAL_PASSWORD,
 };
 use crate::release::create::types::CreateRelease;
+use crate::release::log::{release_log_entry, ReleaseLog};
 
 pub mod types;
 
@@ -103,6 +104,13 @@ pub async fn create_release(
         )
         .await?;
 
+    // Record in the release log
+    let release_log = ReleaseLog::from_cfg(cfg);
+    release_log.append(&release_log_entry(req.release_id(), req.manifest_cid(), &manifest))?;
+    if let Some(updates) = updates {
+        updates.push(release_log.path().to_path_buf());
+    }
+
     Ok((manifest, build_env, release_dir))
 }
 
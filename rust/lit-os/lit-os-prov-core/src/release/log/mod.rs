@@ -0,0 +1,318 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs4::FileExt;
+use log::{as_serde, info};
+
+use lit_attestation::transparency::merkle::{consistency_path, inclusion_path, root_hash, Hash};
+use lit_attestation::transparency::types::hashes_to_hex;
+use lit_core::config::LitConfig;
+use lit_core::utils::binary::bytes_to_hex;
+
+use crate::config::LitOsProvConfig;
+use crate::error::{
+    io_err_code, lock_err_code, serializer_err_code, validation_err_code, Result, EC,
+};
+use crate::release::common::manifest::ReleaseManifest;
+use crate::release::log::types::{
+    ReleaseConsistencyProof, ReleaseInclusionProof, ReleaseLogEntry, ReleaseLogHead,
+};
+
+pub mod types;
+
+pub const RELEASE_LOG_FILE_NAME: &str = "releases.log";
+pub const RELEASE_LOG_LOCK_FILE_NAME: &str = "releases.log.lock";
+
+/// An append-only Merkle log of release manifests (one JSON entry per line, the line number
+/// is the leaf index).
+pub struct ReleaseLog {
+    path: PathBuf,
+}
+
+impl ReleaseLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn from_cfg(cfg: &LitConfig) -> Self {
+        let mut path = cfg.litos_prov_shared_log_path();
+        path.push(RELEASE_LOG_FILE_NAME);
+
+        Self::new(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    pub fn append(&self, entry: &ReleaseLogEntry) -> Result<ReleaseLogHead> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| serializer_err_code(e, EC::ProvReleaseLogFailed, None))?;
+        line.push('\n');
+
+        // Appends must not interleave (the log is an ordered list of leaves), not even between
+        // prov instances sharing the log.
+        let _lock = self.lock(true)?;
+
+        let mut file =
+            OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| {
+                io_err_code(
+                    e,
+                    EC::ProvReleaseLogFailed,
+                    Some(format!("failed to open: {:?}", self.path)),
+                )
+            })?;
+        file.write_all(line.as_bytes()).and_then(|_| file.sync_all()).map_err(|e| {
+            io_err_code(
+                e,
+                EC::ProvReleaseLogFailed,
+                Some(format!("failed to append: {:?}", self.path)),
+            )
+        })?;
+
+        let leaves = leaf_hashes(&self.read_entries()?)?;
+        let head = ReleaseLogHead::new(leaves.len() as u64, &root_hash(&leaves));
+
+        info!(release_id = as_serde!(entry.release_id),
+            tree_size = as_serde!(head.tree_size),
+            root_hash = as_serde!(head.root_hash);
+            "Appended release to release log");
+
+        Ok(head)
+    }
+
+    pub fn entries(&self) -> Result<Vec<ReleaseLogEntry>> {
+        // Not while an entry is half written.
+        let _lock = self.lock(false)?;
+
+        self.read_entries()
+    }
+
+    fn read_entries(&self) -> Result<Vec<ReleaseLogEntry>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(io_err_code(
+                    e,
+                    EC::ProvReleaseLogFailed,
+                    Some(format!("failed to open: {:?}", self.path)),
+                ))
+            }
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| {
+                io_err_code(
+                    e,
+                    EC::ProvReleaseLogFailed,
+                    Some(format!("failed to read: {:?}", self.path)),
+                )
+            })?;
+            if line.is_empty() {
+                continue;
+            }
+
+            entries.push(serde_json::from_str(&line).map_err(|e| {
+                serializer_err_code(
+                    e,
+                    EC::ProvReleaseLogFailed,
+                    Some(format!("failed to parse entry {} of: {:?}", entries.len(), self.path)),
+                )
+            })?);
+        }
+
+        Ok(entries)
+    }
+
+    pub fn entries_range(&self, start: u64, count: u64) -> Result<Vec<ReleaseLogEntry>> {
+        Ok(self.entries()?.into_iter().skip(start as usize).take(count as usize).collect())
+    }
+
+    pub fn head(&self) -> Result<ReleaseLogHead> {
+        let leaves = self.leaves()?;
+
+        Ok(ReleaseLogHead::new(leaves.len() as u64, &root_hash(&leaves)))
+    }
+
+    /// The inclusion proof of the latest entry for the release (against the current head).
+    pub fn inclusion_proof(&self, release_id: &str) -> Result<ReleaseInclusionProof> {
+        let entries = self.entries()?;
+        let leaf_index =
+            entries.iter().rposition(|e| e.release_id.eq(release_id)).ok_or_else(|| {
+                validation_err_code(
+                    format!("release id ({release_id}) not found in release log"),
+                    EC::ProvReleaseNotFound,
+                    None,
+                )
+            })?;
+
+        let leaves = leaf_hashes(&entries)?;
+
+        Ok(ReleaseInclusionProof {
+            entry: entries[leaf_index].clone(),
+            leaf_index: leaf_index as u64,
+            head: ReleaseLogHead::new(leaves.len() as u64, &root_hash(&leaves)),
+            audit_path: hashes_to_hex(&inclusion_path(&leaves, leaf_index)),
+        })
+    }
+
+    pub fn consistency_proof(
+        &self, old_size: u64, new_size: u64,
+    ) -> Result<ReleaseConsistencyProof> {
+        let leaves = self.leaves()?;
+        if old_size > new_size || new_size > leaves.len() as u64 {
+            return Err(validation_err_code(
+                format!(
+                    "invalid consistency range: {} -> {} (tree size: {})",
+                    old_size,
+                    new_size,
+                    leaves.len()
+                ),
+                EC::ProvReleaseLogInvalid,
+                None,
+            ));
+        }
+
+        let (old_size, new_size) = (old_size as usize, new_size as usize);
+
+        Ok(ReleaseConsistencyProof {
+            old: ReleaseLogHead::new(old_size as u64, &root_hash(&leaves[..old_size])),
+            new: ReleaseLogHead::new(new_size as u64, &root_hash(&leaves[..new_size])),
+            proof: hashes_to_hex(&consistency_path(&leaves[..new_size], old_size)),
+        })
+    }
+
+    fn leaves(&self) -> Result<Vec<Hash>> {
+        leaf_hashes(&self.entries()?)
+    }
+
+    // A lock file next to the log (rather than the log itself, which is only opened to append),
+    // released when the returned file is dropped.
+    fn lock(&self, exclusive: bool) -> Result<fs::File> {
+        let path = self.path.with_file_name(RELEASE_LOG_LOCK_FILE_NAME);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                io_err_code(
+                    e,
+                    EC::ProvReleaseLogFailed,
+                    Some(format!("failed to make dir: {parent:?}")),
+                )
+            })?;
+        }
+
+        let file = OpenOptions::new().create(true).write(true).open(&path).map_err(|e| {
+            io_err_code(e, EC::ProvReleaseLogFailed, Some(format!("failed to open: {path:?}")))
+        })?;
+        if exclusive { file.lock_exclusive() } else { file.lock_shared() }.map_err(|e| {
+            lock_err_code(e, EC::ProvReleaseLogFailed, Some(format!("failed to lock: {path:?}")))
+        })?;
+
+        Ok(file)
+    }
+}
+
+/// The log entry of a release manifest (and its measurements).
+pub fn release_log_entry(
+    release_id: &str, manifest_cid: &str, manifest: &ReleaseManifest,
+) -> ReleaseLogEntry {
+    ReleaseLogEntry {
+        release_id: release_id.to_string(),
+        manifest_cid: manifest_cid.to_string(),
+        manifest_hash: manifest.hash().map(|hash| bytes_to_hex(hash.to_vec())),
+        measurements: manifest
+            .measurements()
+            .iter()
+            .map(|(measurement, profile)| {
+                (measurement.clone(), format!("{}:{}", profile.vcpu_type, profile.vcpus))
+            })
+            .collect(),
+        unix_time: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    }
+}
+
+fn leaf_hashes(entries: &[ReleaseLogEntry]) -> Result<Vec<Hash>> {
+    entries.iter().map(|e| e.leaf_hash()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::release::log::types::ReleaseLogEntry;
+    use crate::release::log::{ReleaseLog, RELEASE_LOG_FILE_NAME};
+
+    fn entry(release_id: &str) -> ReleaseLogEntry {
+        ReleaseLogEntry {
+            release_id: release_id.to_string(),
+            manifest_cid: format!("Qm{release_id}"),
+            manifest_hash: None,
+            measurements: BTreeMap::new(),
+            unix_time: 0,
+        }
+    }
+
+    #[test]
+    fn release_log_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = ReleaseLog::new(dir.path().join("log").join(RELEASE_LOG_FILE_NAME));
+
+        assert_eq!(log.head().unwrap().tree_size, 0);
+        assert!(log.inclusion_proof("a").is_err());
+
+        let old = log.append(&entry("a")).unwrap();
+        log.append(&entry("b")).unwrap();
+        let head = log.append(&entry("c")).unwrap();
+        assert_eq!(head, log.head().unwrap());
+        assert_eq!(head.tree_size, 3);
+
+        let proof = log.inclusion_proof("b").unwrap();
+        assert_eq!(proof.leaf_index, 1);
+        assert_eq!(proof.head, head);
+        proof.verify().expect("expected inclusion proof to verify");
+
+        // The latest entry for a release wins.
+        log.append(&entry("a")).unwrap();
+        let proof = log.inclusion_proof("a").unwrap();
+        assert_eq!(proof.leaf_index, 3);
+        proof.verify().expect("expected inclusion proof to verify");
+
+        let consistency = log.consistency_proof(old.tree_size, 4).unwrap();
+        assert_eq!(consistency.old, old);
+        consistency.verify().expect("expected consistency proof to verify");
+        assert!(log.consistency_proof(2, 5).is_err());
+        assert!(log.consistency_proof(3, 2).is_err());
+
+        assert_eq!(log.entries_range(1, 2).unwrap(), vec![entry("b"), entry("c")]);
+    }
+
+    #[test]
+    fn release_log_concurrent_append_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(RELEASE_LOG_FILE_NAME);
+
+        // Separate logs (as separate prov instances would have) appending to the same file.
+        let handles = (0..4)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let log = ReleaseLog::new(path);
+                    for i in 0..10 {
+                        log.append(&entry(&format!("{t}-{i}"))).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let log = ReleaseLog::new(path);
+        assert_eq!(log.entries().unwrap().len(), 40);
+        assert_eq!(log.head().unwrap().tree_size, 40);
+        log.inclusion_proof("2-5").unwrap().verify().expect("expected inclusion proof to verify");
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+pub use lit_attestation::transparency::types::{
+    ReleaseConsistencyProof, ReleaseInclusionProof, ReleaseLogEntry, ReleaseLogHead,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseLogHeadResponse {
+    pub success: bool,
+    pub head: ReleaseLogHead,
+}
+
+impl ReleaseLogHeadResponse {
+    pub fn new(success: bool, head: ReleaseLogHead) -> Self {
+        Self { success, head }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseLogEntriesResponse {
+    pub success: bool,
+    pub start: u64,
+    pub entries: Vec<ReleaseLogEntry>,
+}
+
+impl ReleaseLogEntriesResponse {
+    pub fn new(success: bool, start: u64, entries: Vec<ReleaseLogEntry>) -> Self {
+        Self { success, start, entries }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseLogProofResponse {
+    pub success: bool,
+    pub proof: ReleaseInclusionProof,
+}
+
+impl ReleaseLogProofResponse {
+    pub fn new(success: bool, proof: ReleaseInclusionProof) -> Self {
+        Self { success, proof }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseLogConsistencyResponse {
+    pub success: bool,
+    pub proof: ReleaseConsistencyProof,
+}
+
+impl ReleaseLogConsistencyResponse {
+    pub fn new(success: bool, proof: ReleaseConsistencyProof) -> Self {
+        Self { success, proof }
+    }
+}
@@ -2,4 +2,5 @@ pub mod common;
 pub mod create;
 pub mod init;
 pub mod issue;
+pub mod log;
 pub mod query;